@@ -1,6 +1,15 @@
 use std::path::{Path, PathBuf};
 
-use clap::{arg, builder::ArgPredicate, command, value_parser, Parser};
+use clap::{builder::ArgPredicate, value_parser, Parser};
+use gapix_core::{
+    excel::{ColumnPreset, ColumnSpec, Hyperlink, StageColumn, SummaryOptions, TrackPointColumn},
+    gpx_writer::{GpxVersion, OutputOptions},
+    json::JsonOptions,
+    map::{MapColouring, MapOptions, MapProjection},
+    render::ImageFormat,
+    stage::StageDetectionParameters,
+    units::UnitSystem,
+};
 use log::{info, warn};
 
 /// Returns the parsed command line options. Uses the 'wild' crate to do glob
@@ -35,6 +44,25 @@ pub struct Args {
     )]
     pub join: bool,
 
+    #[arg(
+        long,
+        default_value = "false",
+        conflicts_with = "join",
+        help = "Compare the input files, which should be rides of the same route, and produce a \
+                '.comparison.xlsx' file with a row per ride and the timing of each ride at regular distances. \
+                The file is named after the first input file. The control detection options apply."
+    )]
+    pub compare: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Check the input files against the rules of the GPX 1.1 XSD, and for suspect data such as times \
+                that go backwards and duplicate points, and print a report of the errors and warnings. If any \
+                file has errors, stops with an error before producing any other outputs."
+    )]
+    pub validate: bool,
+
     #[arg(
         short,
         long,
@@ -44,6 +72,30 @@ pub struct Args {
     )]
     pub metres: Option<u16>,
 
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Write the '.joined.gpx' and '.simplified.gpx' files as GPX 1.0 rather than 1.1, for older \
+                tools and validators that only accept 1.0"
+    )]
+    pub gpx_1_0: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Write the metadata bounds of the '.joined.gpx' and '.simplified.gpx' files as they were read, \
+                rather than recomputing them from the points that are written"
+    )]
+    pub keep_bounds: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Set the metadata time and name of the '.joined.gpx' and '.simplified.gpx' files to the time of \
+                the first trackpoint and the name of the first track"
+    )]
+    pub recompute_metadata: bool,
+
     #[arg(
         short,
         long,
@@ -52,11 +104,93 @@ pub struct Args {
             ("min_control_time", ArgPredicate::IsPresent, "true"),
             ("control_resumption_distance", ArgPredicate::IsPresent, "true"),
             ("trackpoint_hyperlinks", ArgPredicate::IsPresent, "true"),
+            ("units", ArgPredicate::IsPresent, "true"),
+            ("columns", ArgPredicate::IsPresent, "true"),
+            ("stage_columns", ArgPredicate::IsPresent, "true"),
+            ("trackpoint_columns", ArgPredicate::IsPresent, "true"),
             ]),
         help = "Analyse the GPX and produce a summary spreadsheet in .xlsx format",
     )]
     pub analyse: bool,
 
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Produce a self-contained '.html' report of the ride, with a map, elevation and speed charts \
+                and the stages. The control detection and units options apply."
+    )]
+    pub html: bool,
+
+    #[arg(
+        long,
+        default_value_ifs([
+            ("json_points", ArgPredicate::IsPresent, "true"),
+            ]),
+        help = "Produce a '.json' summary of the ride and its stages, for use by other programs. All values are \
+                metric regardless of 'units'. The control detection options apply."
+    )]
+    pub json: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Include every trackpoint, with its derived data, in the JSON summary. Implies 'json'."
+    )]
+    pub json_points: bool,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Produce an image of the elevation profile of the ride, shaded by gradient and with the \
+                controls and climbs marked, as a '.profile.svg' or '.profile.png' file (see 'image-format'). \
+                The control detection and units options apply."
+    )]
+    pub profile: bool,
+
+    #[arg(
+        long,
+        default_value_ifs([
+            ("map_colour", ArgPredicate::IsPresent, "true"),
+            ("map_projection", ArgPredicate::IsPresent, "true"),
+            ("map_places", ArgPredicate::IsPresent, "true"),
+            ]),
+        help = "Produce a map of the ride, drawn without map tiles, with the start, finish, controls and waypoints \
+                marked, as a '.map.svg' or '.map.png' file (see 'image-format'). The control detection and units \
+                options apply."
+    )]
+    pub map: bool,
+
+    #[arg(
+        long,
+        default_value = "stage",
+        help = "What the colour of the track on the map represents. One of 'plain', 'speed', 'heart-rate' or \
+                'stage'. Implies 'map'."
+    )]
+    pub map_colour: MapColouring,
+
+    #[arg(
+        long,
+        default_value = "equirectangular",
+        help = "The projection used to draw the map. One of 'equirectangular' or 'web-mercator' (the projection \
+                used by most web maps). Implies 'map'."
+    )]
+    pub map_projection: MapProjection,
+
+    #[arg(
+        long,
+        default_value = "false",
+        help = "Label the places near the track on the map, using the places loaded for reverse geocoding \
+                (see 'countries'). Implies 'map'."
+    )]
+    pub map_places: bool,
+
+    #[arg(
+        long,
+        default_value = "svg",
+        help = "The format of any images produced. One of 'svg' or 'png'."
+    )]
+    pub image_format: ImageFormat,
+
     #[arg(
         long,
         default_value = "0.15",
@@ -87,6 +221,43 @@ pub struct Args {
     )]
     pub trackpoint_hyperlinks: bool,
 
+    #[arg(
+        short = 'u',
+        long,
+        default_value = "metric",
+        help = "The units to use for distances, speeds, heights and temperatures in the analysis output. One of \
+                'metric' (km, m, km/h, m, °C), 'imperial' (mi, ft, mph, ft, °F) or 'uk' (mi, yd, mph, m, °C), where \
+                the second unit is for short distances. The control detection options are always metric. Implies 'analyse'."
+    )]
+    pub units: UnitSystem,
+
+    #[arg(
+        long,
+        default_value = "standard",
+        help = "The set of columns to write to the Stages and Track Points sheets of the .xlsx. One of \
+                'minimal', 'standard' or 'debug'. Implies 'analyse'."
+    )]
+    pub columns: ColumnPreset,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "A comma-separated list of the columns to write to the Stages sheet, in order. Overrides \
+                '--columns'. Available columns: number, type, location, start-time, end-time, duration, distance, \
+                avg-speed, ascent, descent, min-elevation, max-elevation, max-speed, heart-rate, temperature, \
+                track-points. Implies 'analyse'."
+    )]
+    pub stage_columns: Option<Vec<StageColumn>>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "A comma-separated list of the columns to write to the Track Points sheet, in order. Overrides \
+                '--columns'. Available columns: index, time, location, elevation, distance, speed, heart-rate, \
+                temperature, cadence, power, stage, extensions. Implies 'analyse'."
+    )]
+    pub trackpoint_columns: Option<Vec<TrackPointColumn>>,
+
     #[arg(
         short = 'c',
         long,
@@ -118,6 +289,11 @@ const JOINED_EXT: &str = "joined.gpx";
 const SIMPLIFIED_EXT: &str = "simplified.gpx";
 const JOINED_SIMPLIFIED_EXT: &str = "joined.simplified.gpx";
 const ANALYSIS_EXT: &str = "xlsx";
+const COMPARISON_EXT: &str = "comparison.xlsx";
+const HTML_EXT: &str = "html";
+const JSON_EXT: &str = "json";
+const PROFILE_EXT: &str = "profile";
+const MAP_EXT: &str = "map";
 
 impl Args {
     /// Returns the list of files to process, in sorted order. This is based on
@@ -144,17 +320,90 @@ impl Args {
         files
     }
 
+    /// Returns the parameters for stage detection, which are used both when
+    /// analysing and when comparing.
+    pub fn stage_detection_parameters(&self) -> StageDetectionParameters {
+        StageDetectionParameters {
+            stopped_speed_kmh: self.control_speed,
+            min_metres_to_resume: self.control_resumption_distance,
+            min_duration_seconds: self.min_control_time * 60.0,
+        }
+    }
+
+    /// Returns the options that control the contents of the summary workbook.
+    pub fn summary_options(&self) -> SummaryOptions {
+        let mut columns = ColumnSpec::preset(self.columns);
+        if let Some(stage_columns) = &self.stage_columns {
+            columns.stages = stage_columns.clone();
+        }
+        if let Some(trackpoint_columns) = &self.trackpoint_columns {
+            columns.track_points = trackpoint_columns.clone();
+        }
+
+        SummaryOptions {
+            trackpoint_hyperlinks: if self.trackpoint_hyperlinks {
+                Hyperlink::Yes
+            } else {
+                Hyperlink::No
+            },
+            units: self.units,
+            columns,
+        }
+    }
+
+    /// Returns `options` with the GPX version that was asked for and the
+    /// metadata recomputed as asked for. The bounds are recomputed unless
+    /// `--keep-bounds` is given.
+    pub fn gpx_output_options(&self, options: OutputOptions) -> OutputOptions {
+        OutputOptions {
+            gpx_version: if self.gpx_1_0 {
+                GpxVersion::V1_0
+            } else {
+                GpxVersion::V1_1
+            },
+            // The files we write have had points joined or removed, so the
+            // bounds that were read are stale.
+            recompute_bounds: !self.keep_bounds,
+            recompute_time_and_name: self.recompute_metadata,
+            ..options
+        }
+    }
+
+    /// Returns the options that control the contents of the JSON summary.
+    pub fn json_options(&self) -> JsonOptions {
+        JsonOptions {
+            include_points: self.json_points,
+        }
+    }
+
+    /// Returns the options that control how the map image is drawn.
+    pub fn map_options(&self) -> MapOptions {
+        MapOptions {
+            units: self.units,
+            projection: self.map_projection,
+            colouring: self.map_colour,
+            show_places: self.map_places,
+            ..Default::default()
+        }
+    }
+
     fn is_input_file(p: &Path) -> bool {
         p.extension()
-            .is_some_and(|ext| ext.to_ascii_lowercase() == "gpx" || ext.to_ascii_lowercase() == "fit")
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gpx") || ext.eq_ignore_ascii_case("fit"))
     }
 
+    /// Returns true if `p` is one of the GPX files that we write. Only the file
+    /// name is checked, so the names of the folders do not matter. The other
+    /// outputs do not end in '.gpx' or '.fit', so they are never inputs.
     fn is_output_file(p: &Path) -> bool {
-        let s = p.to_string_lossy().to_ascii_lowercase();
-        s.ends_with(JOINED_EXT)
-            || s.ends_with(SIMPLIFIED_EXT)
-            || s.ends_with(JOINED_SIMPLIFIED_EXT)  // Redundant, but for reliability under future changes.
-            || s.ends_with(ANALYSIS_EXT)
+        let Some(name) = p.file_name() else {
+            return false;
+        };
+
+        let name = name.to_string_lossy().to_ascii_lowercase();
+        [JOINED_EXT, SIMPLIFIED_EXT, JOINED_SIMPLIFIED_EXT]
+            .iter()
+            .any(|ext| name.ends_with(&format!(".{ext}")))
     }
 }
 
@@ -165,6 +414,10 @@ pub struct RequiredOutputFiles {
     pub joined_file: Option<PathBuf>,
     pub simplified_file: Option<PathBuf>,
     pub analysis_file: Option<PathBuf>,
+    pub html_file: Option<PathBuf>,
+    pub json_file: Option<PathBuf>,
+    pub profile_file: Option<PathBuf>,
+    pub map_file: Option<PathBuf>,
 }
 
 impl RequiredOutputFiles {
@@ -181,6 +434,14 @@ impl RequiredOutputFiles {
 
         let joined_file = args.join.then(|| set_ext(JOINED_EXT));
         let analysis_file = args.analyse.then(|| set_ext(ANALYSIS_EXT));
+        let html_file = args.html.then(|| set_ext(HTML_EXT));
+        let json_file = args.json.then(|| set_ext(JSON_EXT));
+        let profile_file = args
+            .profile
+            .then(|| set_ext(&format!("{PROFILE_EXT}.{}", args.image_format.extension())));
+        let map_file = args
+            .map
+            .then(|| set_ext(&format!("{MAP_EXT}.{}", args.image_format.extension())));
 
         let simplified_file = if args.join && args.metres.is_some() {
             Some(set_ext(JOINED_SIMPLIFIED_EXT))
@@ -194,6 +455,10 @@ impl RequiredOutputFiles {
             joined_file,
             simplified_file,
             analysis_file,
+            html_file,
+            json_file,
+            profile_file,
+            map_file,
         }
     }
 }
@@ -230,7 +495,54 @@ pub fn get_required_outputs<P: AsRef<Path>>(args: &Args, file: P) -> RequiredOut
                 rof.analysis_file = None;
             }
         }
+
+        if let Some(file) = rof.html_file.as_ref() {
+            if file.exists() {
+                info!("File {:?} already exists, skipping", file);
+                rof.html_file = None;
+            }
+        }
+
+        if let Some(file) = rof.json_file.as_ref() {
+            if file.exists() {
+                info!("File {:?} already exists, skipping", file);
+                rof.json_file = None;
+            }
+        }
+
+        if let Some(file) = rof.profile_file.as_ref() {
+            if file.exists() {
+                info!("File {:?} already exists, skipping", file);
+                rof.profile_file = None;
+            }
+        }
+
+        if let Some(file) = rof.map_file.as_ref() {
+            if file.exists() {
+                info!("File {:?} already exists, skipping", file);
+                rof.map_file = None;
+            }
+        }
     }
 
     rof
 }
+
+/// Determines the name of the comparison workbook, which is based on the first
+/// input file. Returns None if we are not comparing, or if the file already
+/// exists and we are not forcing overwrites.
+pub fn get_comparison_output<P: AsRef<Path>>(args: &Args, first_file: P) -> Option<PathBuf> {
+    if !args.compare {
+        return None;
+    }
+
+    let mut file = first_file.as_ref().to_owned();
+    file.set_extension(COMPARISON_EXT);
+
+    if !args.force && file.exists() {
+        info!("File {:?} already exists, skipping", file);
+        return None;
+    }
+
+    Some(file)
+}
@@ -0,0 +1,136 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use gapix_core::{
+    excel::{create_comparison_xlsx, write_summary_to_file},
+    model::EnrichedGpx,
+    read::read_input_file,
+    stage::{detect_stages, StageList},
+};
+use log::{error, info, warn};
+use logging_timer::time;
+use rayon::prelude::*;
+
+use crate::args::Args;
+
+/// Reads all the input files, detects the stages in each one and writes a
+/// workbook comparing them. Files that cannot be read are logged and left out
+/// of the comparison.
+#[time]
+pub fn compare_input_files<P>(files: &[P], args: &Args, comparison_file: &Path) -> Result<()>
+where
+    P: AsRef<Path> + Sync,
+{
+    if files.len() < 2 {
+        warn!("compare: only 1 input file was specified, there is nothing to compare it with");
+    }
+
+    let rides = read_rides(files, args);
+    if rides.is_empty() {
+        bail!("compare: none of the input files could be read");
+    }
+
+    info!("compare: comparing {} rides", rides.len());
+
+    let workbook = create_comparison_xlsx(args.units, &rides)?;
+    write_summary_to_file(comparison_file, workbook)?;
+    Ok(())
+}
+
+/// Reads each of 'files' and detects its stages. Files that cannot be read are
+/// logged and left out, the rides are in the same order as 'files'.
+fn read_rides<P>(files: &[P], args: &Args) -> Vec<(EnrichedGpx, StageList)>
+where
+    P: AsRef<Path> + Sync,
+{
+    files
+        .par_iter()
+        .filter_map(|f| {
+            let f = f.as_ref();
+            let result = read_input_file(f).and_then(|gpx| {
+                let mut enriched_gpx = EnrichedGpx::try_from(&gpx.into_single_track())?;
+                let stages = detect_stages(&mut enriched_gpx, args.stage_detection_parameters());
+                Ok((enriched_gpx, stages))
+            });
+
+            match result {
+                Ok(ride) => Some(ride),
+                Err(err) => {
+                    error!("compare: excluding {:?} due to error: {}", f, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use clap::Parser;
+
+    use super::*;
+
+    /// Makes an empty directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gapix-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes a 5 minute ride north at 40km/h to 'path'.
+    fn write_ride(path: &Path) {
+        let points: String = (0..300)
+            .map(|secs| {
+                format!(
+                    "<trkpt lat=\"{}\" lon=\"-2.5\"><ele>100</ele>\
+                     <time>2024-09-01T05:{:02}:{:02}Z</time></trkpt>",
+                    53.0 + secs as f64 * 0.0001,
+                    secs / 60,
+                    secs % 60
+                )
+            })
+            .collect();
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <gpx creator=\"test\" version=\"1.1\" xmlns=\"http://www.topografix.com/GPX/1/1\">\
+             <trk><name>Event</name><trkseg>{points}</trkseg></trk></gpx>"
+        );
+        fs::write(path, xml).unwrap();
+    }
+
+    #[test]
+    fn files_that_cannot_be_read_are_left_out() {
+        #[cfg(feature = "geocoding")]
+        gapix_core::geocoding::initialise_geocoding(gapix_core::geocoding::GeocodingOptions::new(
+            None,
+            Vec::new(),
+            false,
+        ));
+
+        let dir = test_dir("compare");
+        let good = dir.join("good.gpx");
+        let bad = dir.join("bad.gpx");
+        let missing = dir.join("missing.gpx");
+        write_ride(&good);
+        fs::write(&bad, "<gpx>this is not a GPX file").unwrap();
+        let args = Args::parse_from(["gapix", "--compare"]);
+
+        let rides = read_rides(&[&bad, &good, &missing], &args);
+        assert_eq!(rides.len(), 1);
+        assert_eq!(rides[0].0.filename.as_deref(), Some(good.as_path()));
+        assert_eq!(rides[0].0.points.len(), 300);
+
+        let comparison_file = dir.join("comparison.xlsx");
+        compare_input_files(&[&bad, &good], &args, &comparison_file).unwrap();
+        assert!(comparison_file.exists());
+
+        let err = compare_input_files(&[&bad, &missing], &args, &dir.join("none.xlsx"));
+        assert!(err.is_err());
+        assert!(!dir.join("none.xlsx").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
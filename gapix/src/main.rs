@@ -1,26 +1,39 @@
 use anyhow::{Context, Ok, Result};
-use args::{get_required_outputs, parse_args, Args, RequiredOutputFiles};
+use args::{get_comparison_output, get_required_outputs, parse_args, Args, RequiredOutputFiles};
+use compare::compare_input_files;
 use clap::builder::styling::AnsiColor;
+#[cfg(feature = "geocoding")]
 use directories::ProjectDirs;
 use env_logger::Builder;
+#[cfg(feature = "geocoding")]
+use gapix_core::geocoding::{initialise_geocoding, GeocodingOptions};
+#[cfg(feature = "timezones")]
+use gapix_core::timezones::initialise_timezones;
 use gapix_core::{
-    excel::{create_summary_xlsx, write_summary_to_file, Hyperlink},
-    geocoding::{initialise_geocoding, GeocodingOptions},
+    excel::{create_summary_xlsx, write_summary_to_file},
     gpx_writer::{write_gpx_to_file, OutputOptions},
-    model::Gpx,
+    html::{create_html_report, write_html_report_to_file},
+    json::{create_json_summary, write_json_summary_to_file},
+    map::create_map_svg,
+    model::{EnrichedGpx, Gpx},
+    profile::{create_elevation_profile_svg, ProfileOptions},
     read::read_input_file,
+    render::write_image_to_file,
     simplification::{metres_to_epsilon, reduce_trackpoints_by_rdp},
-    stage::{detect_stages, StageDetectionParameters},
+    stage::detect_stages,
 };
 use join::join_input_files;
 use log::{debug, error, info, logger, warn};
 use logging_timer::time;
 use rayon::prelude::*;
+use validate::validate_input_files;
 
 use std::io::Write;
 
 mod args;
+mod compare;
 mod join;
+mod validate;
 
 pub const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
 pub const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
@@ -51,8 +64,16 @@ fn main2() -> Result<()> {
         return Ok(());
     }
 
-    let geo_opt = get_geocoding_options(&args);
-    initialise_geocoding(geo_opt);
+    #[cfg(feature = "geocoding")]
+    initialise_geocoding(get_geocoding_options(&args));
+    #[cfg(feature = "timezones")]
+    initialise_timezones();
+
+    // Validation looks at the input files as they were read, so it comes
+    // before any joining or other processing.
+    if args.validate {
+        validate_input_files(&input_files)?;
+    }
 
     // In join mode we join all the input files into a single file
     // and then process it. There is nothing to be done after that.
@@ -63,7 +84,11 @@ fn main2() -> Result<()> {
         if let Some(joined_filename) = &rof.joined_file {
             let mut gpx = join_input_files(&input_files)?;
             gpx.filename = Some(joined_filename.clone());
-            write_gpx_to_file(joined_filename, &gpx, OutputOptions::Full)?;
+            write_gpx_to_file(
+                joined_filename,
+                &gpx,
+                args.gpx_output_options(OutputOptions::full()),
+            )?;
             analyse_gpx(&gpx, &args, &rof)?;
             simplify_gpx(gpx, &args, rof)?;
         }
@@ -71,6 +96,12 @@ fn main2() -> Result<()> {
         return Ok(());
     }
 
+    // In compare mode we produce a single workbook comparing all the input
+    // files. Any other outputs that were asked for are then produced as normal.
+    if let Some(comparison_file) = get_comparison_output(&args, &input_files[0]) {
+        compare_input_files(&input_files, &args, &comparison_file)?;
+    }
+
     // The other modes break down to 'process each file separately'.
     debug!("In per-file mode");
 
@@ -92,28 +123,52 @@ fn main2() -> Result<()> {
 fn analyse_gpx(gpx: &Gpx, args: &Args, rof: &RequiredOutputFiles) -> Result<()> {
     assert!(gpx.is_single_track());
 
+    if rof.analysis_file.is_none()
+        && rof.html_file.is_none()
+        && rof.json_file.is_none()
+        && rof.profile_file.is_none()
+        && rof.map_file.is_none()
+    {
+        return Ok(());
+    }
+
+    // Analysis requires us to enrich the GPX data with some
+    // derived data such as speed and running distance.
+    let mut enriched_gpx = EnrichedGpx::try_from(gpx)?;
+    let stages = detect_stages(&mut enriched_gpx, args.stage_detection_parameters());
+
     if let Some(analysis_file) = &rof.analysis_file {
         assert!(args.analyse);
+        let workbook = create_summary_xlsx(&args.summary_options(), &enriched_gpx, &stages)?;
+        write_summary_to_file(analysis_file, workbook)?;
+    }
 
-        // Analysis requires us to enrich the GPX data with some
-        // derived data such as speed and running distance.
-        let mut enriched_gpx = gpx.to_enriched_gpx()?;
-        let params = StageDetectionParameters {
-            stopped_speed_kmh: args.control_speed,
-            min_metres_to_resume: args.control_resumption_distance,
-            min_duration_seconds: args.min_control_time * 60.0,
-        };
+    if let Some(html_file) = &rof.html_file {
+        assert!(args.html);
+        let html = create_html_report(args.units, &enriched_gpx, &stages)?;
+        write_html_report_to_file(html_file, &html)?;
+    }
 
-        let stages = detect_stages(&mut enriched_gpx, params);
+    if let Some(json_file) = &rof.json_file {
+        assert!(args.json);
+        let summary = create_json_summary(&args.json_options(), &enriched_gpx, &stages);
+        write_json_summary_to_file(json_file, &summary)?;
+    }
 
-        let tp_hyper = if args.trackpoint_hyperlinks {
-            Hyperlink::Yes
-        } else {
-            Hyperlink::No
+    if let Some(profile_file) = &rof.profile_file {
+        assert!(args.profile);
+        let options = ProfileOptions {
+            units: args.units,
+            ..Default::default()
         };
+        let svg = create_elevation_profile_svg(&options, &enriched_gpx, &stages)?;
+        write_image_to_file(profile_file, &svg, args.image_format)?;
+    }
 
-        let workbook = create_summary_xlsx(tp_hyper, &enriched_gpx, &stages)?;
-        write_summary_to_file(analysis_file, workbook)?;
+    if let Some(map_file) = &rof.map_file {
+        assert!(args.map);
+        let svg = create_map_svg(&args.map_options(), &enriched_gpx, &stages)?;
+        write_image_to_file(map_file, &svg, args.image_format)?;
     }
 
     Ok(())
@@ -136,12 +191,17 @@ fn simplify_gpx(mut gpx: Gpx, args: &Args, rof: RequiredOutputFiles) -> Result<(
             gpx.filename
             );
 
-        write_gpx_to_file(simplified_file, &gpx, OutputOptions::AudaxUKDIY)?;
+        write_gpx_to_file(
+            simplified_file,
+            &gpx,
+            args.gpx_output_options(OutputOptions::audax_uk_diy()),
+        )?;
     }
 
     Ok(())
 }
 
+#[cfg(feature = "geocoding")]
 fn get_geocoding_options(args: &Args) -> GeocodingOptions {
     let project_dirs = ProjectDirs::from("", "", env!("CARGO_PKG_NAME"));
     GeocodingOptions::new(
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use gapix_core::read::read_input_file;
+use logging_timer::time;
+
+/// Reads each input file, validates it and prints the report to stdout. Files
+/// that cannot be read are reported too. Returns an error if any file could
+/// not be read or has validation errors, so that scripts can check the exit
+/// status; warnings on their own do not count.
+#[time]
+pub fn validate_input_files<P: AsRef<Path>>(files: &[P]) -> Result<()> {
+    let mut num_invalid = 0;
+
+    for f in files {
+        let f = f.as_ref();
+        println!("{}", f.display());
+        match read_input_file(f) {
+            Ok(gpx) => {
+                let report = gpx.validate();
+                for line in report.to_string().lines() {
+                    println!("  {line}");
+                }
+                if !report.is_valid() {
+                    num_invalid += 1;
+                }
+            }
+            Err(err) => {
+                println!("  error: could not be read: {err}");
+                num_invalid += 1;
+            }
+        }
+    }
+
+    if num_invalid > 0 {
+        bail!(
+            "validate: {num_invalid} of {} files failed validation",
+            files.len()
+        );
+    }
+
+    Ok(())
+}
@@ -1,10 +1,11 @@
-use chrono::{DateTime, SecondsFormat, Utc};
+use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
 
-use crate::{
-    error::GapixError,
-    geocoding::{get_timezone, RTreePoint},
-};
+#[cfg(feature = "timezones")]
+use crate::timezones::get_timezone;
+use crate::{error::GapixError, model_impls::RTreePoint};
+
+pub use gapix_model::dates::format_utc_date;
 
 /// Convert `utc_date` to a date in the specified `timezone`.
 pub fn utc_to_timezone(utc_date: DateTime<Utc>, timezone: Tz) -> DateTime<Tz> {
@@ -12,6 +13,7 @@ pub fn utc_to_timezone(utc_date: DateTime<Utc>, timezone: Tz) -> DateTime<Tz> {
 }
 
 /// Convert `utc_date` to a date in the timezone that `point` is in.
+#[cfg(feature = "timezones")]
 pub fn utc_to_appropriate_timezone(
     utc_date: DateTime<Utc>,
     point: RTreePoint,
@@ -25,8 +27,25 @@ pub fn utc_to_appropriate_timezone(
     }
 }
 
-/// Formats 'utc_date' into a string like "2024-09-01T05:10:44Z".
-/// This is the format that GPX files contain.
-pub fn format_utc_date(utc_date: &DateTime<Utc>) -> String {
-    utc_date.to_rfc3339_opts(SecondsFormat::Secs, true)
+/// Without the 'timezones' feature the timezone of `point` cannot be found, so
+/// `utc_date` is returned in UTC.
+#[cfg(not(feature = "timezones"))]
+pub fn utc_to_appropriate_timezone(
+    utc_date: DateTime<Utc>,
+    _point: RTreePoint,
+) -> Result<DateTime<Tz>, GapixError> {
+    Ok(utc_to_timezone(utc_date, Tz::UTC))
+}
+
+#[cfg(all(test, not(feature = "timezones")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_timezones_dates_are_in_utc() {
+        let utc_date = "2024-06-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let local_date = utc_to_appropriate_timezone(utc_date, [53.0, -2.5]).unwrap();
+        assert_eq!(local_date.timezone(), Tz::UTC);
+        assert_eq!(local_date, utc_date);
+    }
 }
@@ -1,44 +1,26 @@
 use std::{num::TryFromIntError, path::PathBuf};
 
-use quick_xml::events::attributes::AttrError;
+use gapix_model::GpxError;
 use thiserror::Error;
-use crate::read::xml_reader_extensions::XmlReaderConversions;
 
 #[derive(Debug, Error)]
 pub enum GapixError {
     #[error(transparent)]
-    XmlError(#[from] quick_xml::Error),
-    #[error(transparent)]
-    XmlEncodingError(#[from] quick_xml::encoding::EncodingError),
+    Gpx(#[from] GpxError),
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    #[cfg(feature = "excel")]
     #[error(transparent)]
     Xlsx(#[from] rust_xlsxwriter::XlsxError),
+    #[cfg(feature = "fit")]
     #[error(transparent)]
     FitParseError(#[from] fitparser::Error),
+    #[cfg(feature = "json")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 
-    #[error("Mandatory attribute {0} was not found on the element")]
-    MandatoryAttributeNotFound(String),
-    #[error("Mandatory element {0} was not found")]
-    MandatoryElementNotFound(String),
-    #[error("Could not parse {from} into type {dest_type}")]
-    ParseFailure { from: String, dest_type: String },
-    #[error("Unexpected Start element {0}")]
-    UnexpectedStartElement(String),
-    #[error("Unexpected End element {0}")]
-    UnexpectedEndElement(String),
-    #[error("Did not find the {0} element")]
-    ElementNotFound(String),
-    #[error("Element {element} has unexpected extra attributes {attributes}")]
-    UnexpectedAttributes { element: String, attributes: String },
-    #[error("Did not find an Event::Text element, buffer position = {0}, event={1}")]
-    MissingText(u64, String),
-    #[error("Date could not be parsed: {0}")]
-    DateParseFailure(String),
     #[error("Multiple tracks were found when the operation requires a single track")]
     MultipleTracksFound,
-    #[error("{0} is not a valid fix type. Valid values are 'none', '2d', '3d', 'dgps', 'pps'")]
-    InvalidFixType(String),
     #[error("Date could not be formatted: {0}")]
     DateFormatFailure(String),
     #[error("Could not create file {path:?}")]
@@ -48,26 +30,19 @@ pub enum GapixError {
     },
     #[error("Could not perform a numeric conversion: {0}")]
     NumericConversionError(String),
-    #[error("Unexpected event received from Xml parser: {0}")]
-    UnexpectedEvent(String),
-    #[error("Unexpected EOF. Check file for corruption")]
-    UnexpectedEof,
-    #[error("Invalid DGPS station Id of {0}. Valid range is 0..=1023")]
-    InvalidDGPSStationId(i64),
-    #[error("Invalid latitude of {0}. Valid range is -90.0..=90.0")]
-    InvalidLatitude(f64),
-    #[error("Invalid longitude of {0}. Valid range is -180.0..=180.0")]
-    InvalidLongitude(f64),
-    #[error("Invalid degrees of {0}. Valid range is 0.0..=360")]
-    InvalidDegrees(f64),
     #[error("Field named '{0}' not found in FIT data record")]
     FieldNotFound(String),
-}
-
-impl From<AttrError> for GapixError {
-    fn from(value: AttrError) -> Self {
-        Self::XmlError(value.into())
-    }
+    #[error("Could not render image: {0}")]
+    ImageRender(String),
+    #[error("The track has no elevation data")]
+    NoElevationData,
+    #[error("The track has no points")]
+    NoTrackPoints,
+    #[error("{what} is not supported by this build. Rebuild with the '{feature}' feature enabled")]
+    FeatureNotEnabled {
+        what: &'static str,
+        feature: &'static str,
+    },
 }
 
 impl From<TryFromIntError> for GapixError {
@@ -75,24 +50,3 @@ impl From<TryFromIntError> for GapixError {
         Self::NumericConversionError(value.to_string())
     }
 }
-
-impl GapixError {
-    pub(crate) fn bad_start<C: XmlReaderConversions>(bytes: &[u8], converter: &C) -> Self {
-        match converter.bytes_to_string(bytes) {
-            Ok(s) => Self::UnexpectedStartElement(s),
-            Err(err) => err,
-        }
-    }
-
-    pub(crate) fn bad_end<C: XmlReaderConversions>(bytes: &[u8], converter: &C) -> Self {
-        match converter.bytes_to_string(bytes) {
-            Ok(s) => Self::UnexpectedEndElement(s),
-            Err(err) => err,
-        }
-    }
-
-    pub(crate) fn bad_event(event: Result<quick_xml::events::Event<'_>, quick_xml::Error>) -> Self {
-        let s = format!("{:?}", event);
-        Self::NumericConversionError(s)
-    }
-}
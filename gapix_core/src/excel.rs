@@ -1,25 +1,28 @@
 use std::{
     collections::HashSet,
+    fmt,
     fs::File,
     io::{BufWriter, Seek, Write},
     path::Path,
+    str::FromStr,
 };
 
 use chrono::{DateTime, Datelike, TimeDelta, TimeZone, Timelike, Utc};
 use log::info;
 use logging_timer::time;
 use rust_xlsxwriter::{
-    Color, ExcelDateTime, Format, FormatAlign, FormatBorder, FormatPattern, Url, Workbook,
-    Worksheet,
+    Chart, ChartFormat, ChartLine, ChartMarker, ChartMarkerType, ChartType, Color, ExcelDateTime,
+    Format, FormatAlign, FormatBorder, FormatPattern, Url, Workbook, Worksheet,
 };
 
 use crate::{
     byte_counter::ByteCounter,
     dates::utc_to_appropriate_timezone,
     error::GapixError,
-    geocoding::RTreePoint,
-    model::{EnrichedGpx, EnrichedTrackPoint},
+    model::{EnrichedGpx, EnrichedTrackPoint, Waypoint},
+    model_impls::RTreePoint,
     stage::{Stage, StageList, StageType},
+    units::UnitSystem,
 };
 
 const DATE_COLUMN_WIDTH: f64 = 18.0;
@@ -37,6 +40,21 @@ const SPEED_COLUMN_WIDTH: f64 = 8.0;
 const HEART_RATE_WIDTH_WITH_UNITS: f64 = 17.5;
 const TEMPERATURE_COLUMN_WIDTH_WITH_UNITS: f64 = 12.0;
 const CADENCE_COLUMN_WIDTH_WITH_UNITS: f64 = 15.5;
+const POWER_COLUMN_WIDTH_WITH_UNITS: f64 = 10.5;
+const SUMMARY_LABEL_COLUMN_WIDTH: f64 = 24.0;
+const EXTENSIONS_COLUMN_WIDTH: f64 = 60.0;
+
+/// The name of the hidden sheet that the charts take their data from.
+const CHART_DATA_SHEET: &str = "Chart Data";
+/// Charts with tens of thousands of points are very slow to render in
+/// LibreOffice and look no better than charts with a couple of thousand, so we
+/// sample the trackpoints down to (at most) this many.
+const MAX_CHART_POINTS: usize = 2000;
+const CHART_WIDTH: u32 = 1200;
+const CHART_HEIGHT: u32 = 400;
+/// Each chart occupies this many rows on the 'Charts' sheet (20 pixels per
+/// row), plus a small gap.
+const CHART_ROW_SPACING: u32 = 22;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 /// Whether to include Google maps hyperlinks
@@ -46,30 +64,332 @@ pub enum Hyperlink {
     No,
 }
 
+/// The columns that can be written to the 'Stages' sheet. Each column is
+/// actually a block of one or more related columns, e.g. 'Duration' writes
+/// both the stage and the running duration.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StageColumn {
+    Number,
+    Type,
+    Location,
+    StartTime,
+    EndTime,
+    Duration,
+    Distance,
+    AverageSpeed,
+    Ascent,
+    Descent,
+    MinElevation,
+    MaxElevation,
+    MaxSpeed,
+    HeartRate,
+    Temperature,
+    TrackPoints,
+}
+
+impl StageColumn {
+    const NAMES: [(StageColumn, &'static str); 16] = [
+        (StageColumn::Number, "number"),
+        (StageColumn::Type, "type"),
+        (StageColumn::Location, "location"),
+        (StageColumn::StartTime, "start-time"),
+        (StageColumn::EndTime, "end-time"),
+        (StageColumn::Duration, "duration"),
+        (StageColumn::Distance, "distance"),
+        (StageColumn::AverageSpeed, "avg-speed"),
+        (StageColumn::Ascent, "ascent"),
+        (StageColumn::Descent, "descent"),
+        (StageColumn::MinElevation, "min-elevation"),
+        (StageColumn::MaxElevation, "max-elevation"),
+        (StageColumn::MaxSpeed, "max-speed"),
+        (StageColumn::HeartRate, "heart-rate"),
+        (StageColumn::Temperature, "temperature"),
+        (StageColumn::TrackPoints, "track-points"),
+    ];
+}
+
+/// The columns that can be written to the 'Track Points' sheet. As for
+/// StageColumn, each one may be a block of several related columns.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TrackPointColumn {
+    Index,
+    Time,
+    Location,
+    Elevation,
+    Distance,
+    Speed,
+    HeartRate,
+    Temperature,
+    Cadence,
+    Power,
+    /// The number of the stage the point is in. Mainly for debugging stage
+    /// detection.
+    Stage,
+    /// The raw extensions XML of the point.
+    Extensions,
+}
+
+impl TrackPointColumn {
+    const NAMES: [(TrackPointColumn, &'static str); 12] = [
+        (TrackPointColumn::Index, "index"),
+        (TrackPointColumn::Time, "time"),
+        (TrackPointColumn::Location, "location"),
+        (TrackPointColumn::Elevation, "elevation"),
+        (TrackPointColumn::Distance, "distance"),
+        (TrackPointColumn::Speed, "speed"),
+        (TrackPointColumn::HeartRate, "heart-rate"),
+        (TrackPointColumn::Temperature, "temperature"),
+        (TrackPointColumn::Cadence, "cadence"),
+        (TrackPointColumn::Power, "power"),
+        (TrackPointColumn::Stage, "stage"),
+        (TrackPointColumn::Extensions, "extensions"),
+    ];
+}
+
+/// Implements Display and FromStr for the column enums using their NAMES
+/// tables, so that the names used on the command line are defined in one place.
+macro_rules! impl_column_names {
+    ($t:ty, $what:literal) => {
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let (_, name) = Self::NAMES
+                    .iter()
+                    .find(|(c, _)| c == self)
+                    .expect("All columns should have a name");
+                write!(f, "{name}")
+            }
+        }
+
+        impl FromStr for $t {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let s = s.trim().to_ascii_lowercase();
+                Self::NAMES
+                    .iter()
+                    .find(|(_, name)| *name == s)
+                    .map(|(c, _)| *c)
+                    .ok_or_else(|| {
+                        let names: Vec<_> = Self::NAMES.iter().map(|(_, n)| *n).collect();
+                        format!(
+                            "unknown {} column '{s}', expected one of {}",
+                            $what,
+                            names.join(", ")
+                        )
+                    })
+            }
+        }
+    };
+}
+
+impl_column_names!(StageColumn, "stage");
+impl_column_names!(TrackPointColumn, "track point");
+
+/// Named sets of columns.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ColumnPreset {
+    /// Just the essentials, for casual use.
+    Minimal,
+    /// Everything useful for analysing a ride.
+    #[default]
+    Standard,
+    /// Everything, including columns that are only useful when investigating
+    /// problems with stage detection or the input file.
+    Debug,
+}
+
+impl fmt::Display for ColumnPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnPreset::Minimal => write!(f, "minimal"),
+            ColumnPreset::Standard => write!(f, "standard"),
+            ColumnPreset::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+impl FromStr for ColumnPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "minimal" => Ok(ColumnPreset::Minimal),
+            "standard" => Ok(ColumnPreset::Standard),
+            "debug" => Ok(ColumnPreset::Debug),
+            _ => Err(format!(
+                "unknown column preset '{s}', expected one of 'minimal', 'standard' or 'debug'"
+            )),
+        }
+    }
+}
+
+/// Specifies which columns are written to the 'Stages' and 'Track Points'
+/// sheets, and in what order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    pub stages: Vec<StageColumn>,
+    pub track_points: Vec<TrackPointColumn>,
+}
+
+impl ColumnSpec {
+    /// Returns the columns for one of the named presets.
+    pub fn preset(preset: ColumnPreset) -> Self {
+        use StageColumn as S;
+        use TrackPointColumn as T;
+
+        match preset {
+            ColumnPreset::Minimal => Self {
+                stages: vec![
+                    S::Number,
+                    S::Type,
+                    S::Location,
+                    S::StartTime,
+                    S::Duration,
+                    S::Distance,
+                    S::AverageSpeed,
+                    S::Ascent,
+                ],
+                track_points: vec![T::Index, T::Time, T::Elevation, T::Distance, T::Speed],
+            },
+            ColumnPreset::Standard => Self {
+                stages: vec![
+                    S::Number,
+                    S::Type,
+                    S::Location,
+                    S::StartTime,
+                    S::EndTime,
+                    S::Duration,
+                    S::Distance,
+                    S::AverageSpeed,
+                    S::Ascent,
+                    S::Descent,
+                    S::MinElevation,
+                    S::MaxElevation,
+                    S::MaxSpeed,
+                    S::HeartRate,
+                    S::Temperature,
+                    S::TrackPoints,
+                ],
+                track_points: vec![
+                    T::Index,
+                    T::Time,
+                    T::Location,
+                    T::Elevation,
+                    T::Distance,
+                    T::Speed,
+                    T::HeartRate,
+                    T::Temperature,
+                    T::Cadence,
+                    T::Power,
+                ],
+            },
+            ColumnPreset::Debug => {
+                let mut spec = Self::preset(ColumnPreset::Standard);
+                spec.track_points.insert(1, T::Stage);
+                spec.track_points.push(T::Extensions);
+                spec
+            }
+        }
+    }
+}
+
+impl Default for ColumnSpec {
+    fn default() -> Self {
+        Self::preset(ColumnPreset::Standard)
+    }
+}
+
+/// Options that control the contents of the summary workbook.
+#[derive(Debug, Clone)]
+pub struct SummaryOptions {
+    /// Whether to write a Google Maps hyperlink for every trackpoint.
+    pub trackpoint_hyperlinks: Hyperlink,
+    /// The units that distances, speeds, heights and temperatures are written in.
+    pub units: UnitSystem,
+    /// The columns to write on the 'Stages' and 'Track Points' sheets.
+    pub columns: ColumnSpec,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        Self {
+            trackpoint_hyperlinks: Hyperlink::No,
+            units: Default::default(),
+            columns: Default::default(),
+        }
+    }
+}
+
 /// Builds the Workbook that is used for the summary.
 #[time]
 pub fn create_summary_xlsx(
-    trackpoint_hyperlinks: Hyperlink,
+    options: &SummaryOptions,
     gpx: &EnrichedGpx,
     stages: &StageList,
 ) -> Result<Workbook, GapixError> {
+    let units = options.units;
     let mut workbook = Workbook::new();
 
     // This will appear as the first sheet in the workbook.
+    let summary_ws = workbook.add_worksheet();
+    summary_ws.set_name("Summary")?;
+    write_summary(summary_ws, units, gpx, stages)?;
+
     let stages_ws = workbook.add_worksheet();
     stages_ws.set_name("Stages")?;
-    write_stages(stages_ws, gpx, stages)?;
+    write_stages(stages_ws, units, &options.columns.stages, gpx, stages)?;
 
-    // This will appear as the second sheet in the workbook.
     let tp_ws = workbook.add_worksheet();
     tp_ws.set_name("Track Points")?;
     write_trackpoints(
         tp_ws,
+        units,
+        &options.columns.track_points,
         &gpx.points,
-        trackpoint_hyperlinks,
-        &stages.highlighted_trackpoints(),
+        stages,
+        options.trackpoint_hyperlinks,
     )?;
 
+    let waypoints_ws = workbook.add_worksheet();
+    waypoints_ws.set_name("Waypoints")?;
+    write_waypoints(waypoints_ws, units, gpx)?;
+
+    // The charts are driven from a hidden sheet of data rather than from the
+    // 'Track Points' sheet, which is too large to chart sensibly. The data
+    // sheet is added last so that it is never the active sheet.
+    let chart_data = ChartData::new(units, gpx, stages);
+    let charts_ws = workbook.add_worksheet();
+    charts_ws.set_name("Charts")?;
+    write_charts(charts_ws, &chart_data)?;
+
+    let chart_data_ws = workbook.add_worksheet();
+    chart_data_ws.set_name(CHART_DATA_SHEET)?;
+    write_chart_data(chart_data_ws, &chart_data)?;
+    chart_data_ws.set_hidden(true);
+
+    Ok(workbook)
+}
+
+/// Builds a Workbook that compares several rides, for example several people
+/// riding the same event or one person riding the same route several times.
+/// Each ride is a GPX and the stages that were detected in it. The first sheet
+/// has a row per ride, the second sheet shows the elapsed and control time of
+/// each ride at regular distances so that they can be compared side by side.
+#[time]
+pub fn create_comparison_xlsx(
+    units: UnitSystem,
+    rides: &[(EnrichedGpx, StageList)],
+) -> Result<Workbook, GapixError> {
+    let mut workbook = Workbook::new();
+
+    let rides_ws = workbook.add_worksheet();
+    rides_ws.set_name("Rides")?;
+    write_comparison_rides(rides_ws, units, rides)?;
+
+    let timing_ws = workbook.add_worksheet();
+    timing_ws.set_name("Timing")?;
+    write_comparison_timing(timing_ws, units, rides)?;
+
     Ok(workbook)
 }
 
@@ -111,6 +431,179 @@ where
     Ok(())
 }
 
+/// Write the "Summary" tab of the summary spreadsheet. This is a vertical list
+/// of the ride totals, one per row, with the value in the second column. Where
+/// a value occurs at a particular point (e.g. max speed) the distance and
+/// trackpoint are written alongside it.
+#[time]
+fn write_summary(
+    ws: &mut Worksheet,
+    units: UnitSystem,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+) -> Result<(), GapixError> {
+    let mut fc = FormatControl::new(units);
+
+    let distance = format!("At ({})", units.distance_unit());
+    write_headers(ws, &fc, "Ride Summary", &["", "Value", &distance, "Point"])?;
+    ws.set_column_width(0, SUMMARY_LABEL_COLUMN_WIDTH)?;
+    ws.set_column_width(1, DATE_COLUMN_WIDTH)?;
+    ws.set_column_width(2, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
+
+    let name = gpx
+        .track_name
+        .as_deref()
+        .or(gpx.metadata.name.as_deref())
+        .unwrap_or_default();
+    write_summary_label(ws, &fc, "Name")?;
+    write_string(ws, &fc.col_offset(1), name)?;
+    fc.increment_row();
+
+    let filename = gpx
+        .filename
+        .as_ref()
+        .and_then(|f| f.file_name())
+        .map(|f| f.to_string_lossy())
+        .unwrap_or_default();
+    write_summary_label(ws, &fc, "File")?;
+    write_string(ws, &fc.col_offset(1), &filename)?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Device")?;
+    write_string(ws, &fc.col_offset(1), &gpx.creator)?;
+    fc.increment_row();
+
+    if stages.is_empty() {
+        write_summary_label(ws, &fc, "Stages")?;
+        write_string(ws, &fc.col_offset(1), "No stages detected")?;
+        return Ok(());
+    }
+
+    let start_point = stages.first_point().as_rtree_point();
+    let end_point = stages.last_point().as_rtree_point();
+
+    write_summary_label(ws, &fc, "Date")?;
+    match stages.start_time() {
+        Some(t) => write_utc_date_as_local_day(ws, &fc.col_offset(1), t, start_point)?,
+        None => write_blank(ws, &fc.col_offset(1))?,
+    }
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Start (local)")?;
+    write_utc_date_as_local_option(ws, &fc.col_offset(1), stages.start_time(), start_point)?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "End (local)")?;
+    write_utc_date_as_local_option(ws, &fc.col_offset(1), stages.end_time(), end_point)?;
+    fc.increment_row();
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Distance ({})", units.distance_unit()))?;
+    write_kilometres(ws, &fc.col_offset(1), stages.distance_km())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Elapsed Time")?;
+    write_duration_option(ws, &fc.col_offset(1), stages.duration())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Moving Time")?;
+    write_duration_option(ws, &fc.col_offset(1), stages.total_moving_time())?;
+    write_percentage_option(ws, &fc.col_offset(2), stages.moving_percent())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Control Time")?;
+    write_duration_option(ws, &fc.col_offset(1), stages.total_control_time())?;
+    write_percentage_option(ws, &fc.col_offset(2), stages.controlling_percent())?;
+    fc.increment_row();
+    fc.increment_row();
+
+    let speed_unit = units.speed_unit();
+    write_summary_label(ws, &fc, &format!("Avg Overall Speed ({speed_unit})"))?;
+    write_speed_option(ws, &fc.col_offset(1), stages.average_overall_speed())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Avg Moving Speed ({speed_unit})"))?;
+    write_speed_option(ws, &fc.col_offset(1), stages.average_moving_speed())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Max Speed ({speed_unit})"))?;
+    write_max_speed_data(ws, &fc.col_offset(1), stages.max_speed())?;
+    fc.increment_row();
+    fc.increment_row();
+
+    let height_unit = units.height_unit();
+    write_summary_label(ws, &fc, &format!("Ascent ({height_unit})"))?;
+    write_metres_option(ws, &fc.col_offset(1), stages.total_ascent_metres())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Descent ({height_unit})"))?;
+    write_metres_option(ws, &fc.col_offset(1), stages.total_descent_metres())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Min Elevation ({height_unit})"))?;
+    write_elevation_data(ws, &fc.col_offset(1), stages.min_elevation())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Max Elevation ({height_unit})"))?;
+    write_elevation_data(ws, &fc.col_offset(1), stages.max_elevation())?;
+    fc.increment_row();
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Avg Heart Rate (bpm)")?;
+    write_f64_option(ws, &fc.col_offset(1), gpx.avg_heart_rate())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Max Heart Rate (bpm)")?;
+    let max_hr = stages
+        .max_heart_rate()
+        .and_then(|p| p.heart_rate().map(|hr| (p, hr)));
+    if let Some((point, hr)) = max_hr {
+        write_integer(ws, &fc.col_offset(1), hr as u32)?;
+        write_kilometres_running_with_map_hyperlink(ws, &fc.col_offset(2), point)?;
+        write_trackpoint_number(ws, &fc.col_offset(3), point.index)?;
+    } else {
+        write_blank(ws, &fc.col_offset(1))?;
+    }
+    fc.increment_row();
+    fc.increment_row();
+
+    let temperature_unit = units.temperature_unit();
+    write_summary_label(ws, &fc, &format!("Avg Temp ({temperature_unit})"))?;
+    write_temperature_option(ws, &fc.col_offset(1), gpx.avg_temperature())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Min Temp ({temperature_unit})"))?;
+    write_temperature_point(ws, &fc.col_offset(1), stages.min_temperature())?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, &format!("Max Temp ({temperature_unit})"))?;
+    write_temperature_point(ws, &fc.col_offset(1), stages.max_temperature())?;
+    fc.increment_row();
+    fc.increment_row();
+
+    let num_controls = stages
+        .iter()
+        .filter(|s| s.stage_type == StageType::Control)
+        .count();
+    let num_moving = stages.len() - num_controls;
+    write_summary_label(ws, &fc, "Stages")?;
+    write_integer(ws, &fc.col_offset(1), stages.len().try_into()?)?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Moving Stages")?;
+    write_integer(ws, &fc.col_offset(1), num_moving.try_into()?)?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Controls")?;
+    write_integer(ws, &fc.col_offset(1), num_controls.try_into()?)?;
+    fc.increment_row();
+
+    write_summary_label(ws, &fc, "Track Points")?;
+    write_integer(ws, &fc.col_offset(1), gpx.points.len().try_into()?)?;
+
+    Ok(())
+}
+
 /// Write the "Stages" tab of the summary spreadsheet.
 ///
 /// We write the data in vertical fashion to keep the code for the headers and
@@ -124,10 +617,12 @@ where
 #[time]
 fn write_stages(
     ws: &mut Worksheet,
+    units: UnitSystem,
+    columns: &[StageColumn],
     gpx: &EnrichedGpx,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    let mut fc = FormatControl::new();
+    let mut fc = FormatControl::new(units);
 
     if stages.is_empty() {
         write_string(ws, &fc, "No stages detected")?;
@@ -137,22 +632,28 @@ fn write_stages(
     ws.set_freeze_panes(0, 2)?;
     ws.set_freeze_panes(2, 0)?;
 
-    output_stage_number(ws, &mut fc, stages)?;
-    output_stage_type(ws, &mut fc, stages)?;
-    output_stage_location(ws, &mut fc, stages)?;
-    output_start_time(ws, &mut fc, stages)?;
-    output_end_time(ws, &mut fc, stages)?;
-    output_duration(ws, &mut fc, stages)?;
-    output_distance(ws, &mut fc, stages)?;
-    output_average_speed(ws, &mut fc, stages)?;
-    output_ascent(ws, &mut fc, stages)?;
-    output_descent(ws, &mut fc, stages)?;
-    output_min_elevation(ws, &mut fc, stages)?;
-    output_max_elevation(ws, &mut fc, stages)?;
-    output_max_speed(ws, &mut fc, stages)?;
-    output_heart_rate(ws, &mut fc, stages, gpx.avg_heart_rate())?;
-    output_temperature(ws, &mut fc, stages, gpx.avg_temperature())?;
-    output_track_points(ws, &mut fc, stages)?;
+    for column in columns {
+        match column {
+            StageColumn::Number => output_stage_number(ws, &mut fc, stages)?,
+            StageColumn::Type => output_stage_type(ws, &mut fc, stages)?,
+            StageColumn::Location => output_stage_location(ws, &mut fc, stages)?,
+            StageColumn::StartTime => output_start_time(ws, &mut fc, stages)?,
+            StageColumn::EndTime => output_end_time(ws, &mut fc, stages)?,
+            StageColumn::Duration => output_duration(ws, &mut fc, stages)?,
+            StageColumn::Distance => output_distance(ws, &mut fc, stages)?,
+            StageColumn::AverageSpeed => output_average_speed(ws, &mut fc, stages)?,
+            StageColumn::Ascent => output_ascent(ws, &mut fc, stages)?,
+            StageColumn::Descent => output_descent(ws, &mut fc, stages)?,
+            StageColumn::MinElevation => output_min_elevation(ws, &mut fc, stages)?,
+            StageColumn::MaxElevation => output_max_elevation(ws, &mut fc, stages)?,
+            StageColumn::MaxSpeed => output_max_speed(ws, &mut fc, stages)?,
+            StageColumn::HeartRate => output_heart_rate(ws, &mut fc, stages, gpx.avg_heart_rate())?,
+            StageColumn::Temperature => {
+                output_temperature(ws, &mut fc, stages, gpx.avg_temperature())?
+            }
+            StageColumn::TrackPoints => output_track_points(ws, &mut fc, stages)?,
+        }
+    }
 
     Ok(())
 }
@@ -341,7 +842,8 @@ fn output_distance(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "Distance (km)", &["Stage", "Running"])?;
+    let heading = format!("Distance ({})", fc.units.distance_unit());
+    write_headers(ws, fc, &heading, &["Stage", "Running"])?;
     ws.set_column_width(fc.col, KILOMETRES_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 1, METRES_COLUMN_WIDTH)?;
 
@@ -370,7 +872,8 @@ fn output_average_speed(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "Avg Speed (km/h)", &["Stage", "Running"])?;
+    let heading = format!("Avg Speed ({})", fc.units.speed_unit());
+    write_headers(ws, fc, &heading, &["Stage", "Running"])?;
     ws.set_column_width(fc.col, SPEED_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 1, SPEED_COLUMN_WIDTH)?;
 
@@ -401,7 +904,9 @@ fn output_ascent(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "Ascent (m)", &["Stage", "Running", "m/km"])?;
+    let heading = format!("Ascent ({})", fc.units.height_unit());
+    let rate = fc.units.climb_rate_unit();
+    write_headers(ws, fc, &heading, &["Stage", "Running", rate])?;
     ws.set_column_width(fc.col, METRES_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 1, METRES_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 2, METRES_COLUMN_WIDTH)?;
@@ -410,7 +915,7 @@ fn output_ascent(
         if stage.stage_type == StageType::Moving {
             write_metres_option(ws, fc, stage.ascent_metres())?;
             write_metres_option(ws, &fc.col_offset(1), stage.running_ascent_metres())?;
-            write_metres_option(ws, &fc.col_offset(2), stage.ascent_rate_per_km())?;
+            write_climb_rate_option(ws, &fc.col_offset(2), stage.ascent_rate_per_km())?;
         } else {
             write_blank(ws, fc)?;
             write_blank(ws, &fc.col_offset(1))?;
@@ -426,7 +931,7 @@ fn output_ascent(
     let rate = stages
         .total_ascent_metres()
         .map(|a| a / stages.distance_km());
-    write_metres_option(ws, &fc.col_offset(2), rate)?;
+    write_climb_rate_option(ws, &fc.col_offset(2), rate)?;
 
     fc.next_colour_block(3);
     Ok(())
@@ -437,7 +942,9 @@ fn output_descent(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "Descent (m)", &["Stage", "Running", "m/km"])?;
+    let heading = format!("Descent ({})", fc.units.height_unit());
+    let rate = fc.units.climb_rate_unit();
+    write_headers(ws, fc, &heading, &["Stage", "Running", rate])?;
     ws.set_column_width(fc.col, METRES_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 1, METRES_COLUMN_WIDTH)?;
     ws.set_column_width(fc.col + 2, METRES_COLUMN_WIDTH)?;
@@ -446,7 +953,7 @@ fn output_descent(
         if stage.stage_type == StageType::Moving {
             write_metres_option(ws, fc, stage.descent_metres())?;
             write_metres_option(ws, &fc.col_offset(1), stage.running_descent_metres())?;
-            write_metres_option(ws, &fc.col_offset(2), stage.descent_rate_per_km())?;
+            write_climb_rate_option(ws, &fc.col_offset(2), stage.descent_rate_per_km())?;
         } else {
             write_blank(ws, fc)?;
             write_blank(ws, &fc.col_offset(1))?;
@@ -462,7 +969,7 @@ fn output_descent(
     let rate = stages
         .total_descent_metres()
         .map(|a| a / stages.distance_km());
-    write_metres_option(ws, &fc.col_offset(2), rate)?;
+    write_climb_rate_option(ws, &fc.col_offset(2), rate)?;
 
     fc.next_colour_block(3);
     Ok(())
@@ -473,12 +980,9 @@ fn output_min_elevation(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(
-        ws,
-        fc,
-        "Min Elevation",
-        &["Elevation (m)", "Distance (km)", "Point"],
-    )?;
+    let elevation = format!("Elevation ({})", fc.units.height_unit());
+    let distance = format!("Distance ({})", fc.units.distance_unit());
+    write_headers(ws, fc, "Min Elevation", &[&elevation, &distance, "Point"])?;
     ws.set_column_width(fc.col, ELEVATION_COLUMN_WIDTH_WITH_UNITS)?;
     ws.set_column_width(fc.col + 1, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
 
@@ -506,12 +1010,9 @@ fn output_max_elevation(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(
-        ws,
-        fc,
-        "Max Elevation",
-        &["Elevation (m)", "Distance (km)", "Point"],
-    )?;
+    let elevation = format!("Elevation ({})", fc.units.height_unit());
+    let distance = format!("Distance ({})", fc.units.distance_unit());
+    write_headers(ws, fc, "Max Elevation", &[&elevation, &distance, "Point"])?;
     ws.set_column_width(fc.col, ELEVATION_COLUMN_WIDTH_WITH_UNITS)?;
     ws.set_column_width(fc.col + 1, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
 
@@ -539,12 +1040,9 @@ fn output_max_speed(
     fc: &mut FormatControl,
     stages: &StageList,
 ) -> Result<(), GapixError> {
-    write_headers(
-        ws,
-        fc,
-        "Max Speed",
-        &["Speed (km/h)", "Distance (km)", "Point"],
-    )?;
+    let speed = format!("Speed ({})", fc.units.speed_unit());
+    let distance = format!("Distance ({})", fc.units.distance_unit());
+    write_headers(ws, fc, "Max Speed", &[&speed, &distance, "Point"])?;
     ws.set_column_width(fc.col, SPEED_COLUMN_WIDTH_WITH_UNITS)?;
     ws.set_column_width(fc.col + 1, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
 
@@ -573,12 +1071,8 @@ fn output_heart_rate(
     stages: &StageList,
     avg_heart_rate: Option<f64>,
 ) -> Result<(), GapixError> {
-    write_headers(
-        ws,
-        fc,
-        "Heart Rate",
-        &["Avg", "Max", "Distance (km)", "Point"],
-    )?;
+    let distance = format!("Distance ({})", fc.units.distance_unit());
+    write_headers(ws, fc, "Heart Rate", &["Avg", "Max", &distance, "Point"])?;
     ws.set_column_width(fc.col + 2, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
 
     for stage in stages {
@@ -599,10 +1093,11 @@ fn output_temperature(
     stages: &StageList,
     avg_temp: Option<f64>,
 ) -> Result<(), GapixError> {
+    let heading = format!("Temp {}", fc.units.temperature_unit());
     write_headers(
         ws,
         fc,
-        "Temp °C",
+        &heading,
         &[
             "Avg",
             "Min",
@@ -673,25 +1168,44 @@ fn output_track_points(
 #[time]
 fn write_trackpoints(
     ws: &mut Worksheet,
+    units: UnitSystem,
+    columns: &[TrackPointColumn],
     points: &[EnrichedTrackPoint],
+    stages: &StageList,
     hyperlink: Hyperlink,
-    mandatory_hyperlinks: &HashSet<usize>,
 ) -> Result<(), GapixError> {
-    let mut fc = FormatControl::new();
+    let mut fc = FormatControl::new(units);
 
     ws.set_freeze_panes(2, 0)?;
 
-    output_tp_index(ws, &mut fc, points)?;
-    output_tp_time(ws, &mut fc, points)?;
-    output_tp_location(ws, &mut fc, points, hyperlink, mandatory_hyperlinks)?;
-    output_tp_elevation(ws, &mut fc, points)?;
-    output_tp_distance(ws, &mut fc, points)?;
-    output_tp_speed(ws, &mut fc, points)?;
-    output_tp_heart_rate(ws, &mut fc, points)?;
-    output_tp_air_temp(ws, &mut fc, points)?;
-    output_tp_cadence(ws, &mut fc, points)?;
+    for column in columns {
+        match column {
+            TrackPointColumn::Index => output_tp_index(ws, &mut fc, points)?,
+            TrackPointColumn::Time => output_tp_time(ws, &mut fc, points)?,
+            TrackPointColumn::Location => output_tp_location(
+                ws,
+                &mut fc,
+                points,
+                hyperlink,
+                &stages.highlighted_trackpoints(),
+            )?,
+            TrackPointColumn::Elevation => output_tp_elevation(ws, &mut fc, points)?,
+            TrackPointColumn::Distance => output_tp_distance(ws, &mut fc, points)?,
+            TrackPointColumn::Speed => output_tp_speed(ws, &mut fc, points)?,
+            TrackPointColumn::HeartRate => output_tp_heart_rate(ws, &mut fc, points)?,
+            TrackPointColumn::Temperature => output_tp_air_temp(ws, &mut fc, points)?,
+            TrackPointColumn::Cadence => output_tp_cadence(ws, &mut fc, points)?,
+            TrackPointColumn::Power => output_tp_power(ws, &mut fc, points)?,
+            TrackPointColumn::Stage => output_tp_stage(ws, &mut fc, points, stages)?,
+            TrackPointColumn::Extensions => output_tp_extensions(ws, &mut fc, points)?,
+        }
+    }
+
+    // Each output function moves 'fc' onto the next free column.
+    if fc.col > 0 {
+        ws.autofilter(1, 0, points.len() as u32 + 1, fc.col - 1)?;
+    }
 
-    ws.autofilter(1, 0, points.len() as u32 + 1, fc.col)?;
     Ok(())
 }
 
@@ -781,10 +1295,11 @@ fn output_tp_elevation(
     fc: &mut FormatControl,
     points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
+    let heading = format!("Elevation ({})", fc.units.height_unit());
     write_headers(
         ws,
         fc,
-        "Elevation (m)",
+        &heading,
         &["Height", "Delta", "Running Ascent", "Running Descent"],
     )?;
     ws.set_column_width(fc.col, METRES_COLUMN_WIDTH_WITH_UNITS)?;
@@ -817,12 +1332,14 @@ fn output_tp_distance(
     fc: &mut FormatControl,
     points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "Distance", &["Delta (m)", "Running (km)"])?;
+    let delta = format!("Delta ({})", fc.units.short_distance_unit());
+    let running = format!("Running ({})", fc.units.distance_unit());
+    write_headers(ws, fc, "Distance", &[&delta, &running])?;
     ws.set_column_width(fc.col, METRES_COLUMN_WIDTH_WITH_UNITS)?;
     ws.set_column_width(fc.col + 1, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
 
     for p in points {
-        write_metres(ws, fc, p.delta_metres)?;
+        write_short_distance(ws, fc, p.delta_metres)?;
         write_kilometres(ws, &fc.col_offset(1), p.running_metres / 1000.0)?;
         fc.increment_row();
     }
@@ -836,7 +1353,8 @@ fn output_tp_speed(
     fc: &mut FormatControl,
     points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "", &["Speed (km/h)"])?;
+    let heading = format!("Speed ({})", fc.units.speed_unit());
+    write_headers(ws, fc, "", &[&heading])?;
     ws.set_column_width(fc.col, SPEED_COLUMN_WIDTH_WITH_UNITS)?;
 
     for p in points {
@@ -875,11 +1393,12 @@ fn output_tp_air_temp(
     fc: &mut FormatControl,
     points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
-    write_headers(ws, fc, "", &["Temp (°C)"])?;
+    let heading = format!("Temp ({})", fc.units.temperature_unit());
+    write_headers(ws, fc, "", &[&heading])?;
     ws.set_column_width(fc.col, TEMPERATURE_COLUMN_WIDTH_WITH_UNITS)?;
 
     for p in points {
-        write_f64_option(ws, fc, p.air_temp())?;
+        write_temperature_option(ws, fc, p.air_temp())?;
         fc.increment_row();
     }
 
@@ -905,164 +1424,908 @@ fn output_tp_cadence(
         fc.increment_row();
     }
 
+    fc.next_colour_block(1);
     Ok(())
 }
 
-// Utility functions.
-
-/// Writes a main heading (which can be blank) and a set of
-/// sub-headings, and automatically merges the columns of
-/// the main heading if necessary.
-fn write_headers(
+fn output_tp_power(
     ws: &mut Worksheet,
-    fc: &FormatControl,
-    main_heading: &str,
-    sub_headings: &[&str],
+    fc: &mut FormatControl,
+    points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
-    if main_heading.is_empty() {
-        ws.write_blank(0, fc.col, &fc.minor_header_format())?;
-    } else {
-        ws.merge_range(
-            0,
-            fc.col,
-            0,
-            fc.col + sub_headings.len() as u16 - 1,
-            main_heading,
-            &fc.minor_header_format(),
-        )?;
-    }
+    write_headers(ws, fc, "", &["Power (W)"])?;
+    ws.set_column_width(fc.col, POWER_COLUMN_WIDTH_WITH_UNITS)?;
 
-    for (idx, &heading) in sub_headings.iter().enumerate() {
-        ws.write_string_with_format(1, fc.col + idx as u16, heading, &fc.minor_header_format())?;
+    for p in points {
+        if let Some(power) = p.power() {
+            write_integer(ws, fc, power.into())?;
+        } else {
+            write_blank(ws, fc)?;
+        }
+
+        fc.increment_row();
     }
 
+    fc.next_colour_block(1);
     Ok(())
 }
 
-/// Writes a lat-lon pair with the lat in the first cell as specified
-/// by 'rc' and the lon in the next column. If 'hyperlink' is yes then
-/// a hyperlink to Google Maps is written into the third column.
-fn write_lat_lon_with_location_description(
+fn output_tp_stage(
     ws: &mut Worksheet,
-    fc: &FormatControl,
-    hyperlink: Hyperlink,
-    stage: &Stage,
+    fc: &mut FormatControl,
+    points: &[EnrichedTrackPoint],
+    stages: &StageList,
 ) -> Result<(), GapixError> {
-    let format = fc.lat_lon_format().set_font_color(Color::Black);
+    write_headers(ws, fc, "", &["Stage"])?;
 
-    let lat = stage.start.lat;
-    let lon = stage.start.lon;
-    ws.write_number_with_format(fc.row, fc.col, lat, &format)?;
-    ws.write_number_with_format(fc.row, fc.col + 1, lon, &format)?;
+    // Stages are contiguous and cover all the points, so we can walk the two
+    // lists together. Stage numbers start at 1, as on the 'Stages' sheet.
+    let mut stage_iter = stages.iter().zip(1_u32..).peekable();
 
-    match hyperlink {
-        Hyperlink::Yes => {
-            let url = make_hyperlink((lat, lon));
-            // TODO: Font still blue.
-            let format = format.set_align(FormatAlign::Right);
-            ws.write_url_with_format(fc.row, fc.col + 2, url, &format)?;
-        }
-        Hyperlink::No => {
-            write_blank(ws, &fc.col_offset(2))?;
+    for p in points {
+        while stage_iter
+            .peek()
+            .is_some_and(|(stage, _)| stage.end.index < p.index)
+        {
+            stage_iter.next();
         }
-    };
-
-    let fc = fc.col_offset(3);
 
-    if let Some(desc) = stage.reverse_geocode() {
-        if !desc.is_empty() {
-            ws.write_string_with_format(fc.row, fc.col, desc, &fc.location_format())?;
-        } else {
-            write_blank(ws, &fc)?;
+        match stage_iter.peek() {
+            Some((stage, number)) if stage.start.index <= p.index => {
+                write_integer(ws, fc, *number)?;
+            }
+            _ => write_blank(ws, fc)?,
         }
-    } else {
-        write_blank(ws, &fc)?;
+
+        fc.increment_row();
     }
 
+    fc.next_colour_block(1);
     Ok(())
 }
 
-fn write_lat_lon_no_location(
+fn output_tp_extensions(
     ws: &mut Worksheet,
-    fc: &FormatControl,
-    (lat, lon): (f64, f64),
-    hyperlink: Hyperlink,
+    fc: &mut FormatControl,
+    points: &[EnrichedTrackPoint],
 ) -> Result<(), GapixError> {
-    let format = fc.lat_lon_format().set_font_color(Color::Black);
-
-    ws.write_number_with_format(fc.row, fc.col, lat, &format)?;
-    ws.write_number_with_format(fc.row, fc.col + 1, lon, &format)?;
+    write_headers(ws, fc, "", &["Extensions"])?;
+    ws.set_column_width(fc.col, EXTENSIONS_COLUMN_WIDTH)?;
 
-    match hyperlink {
-        Hyperlink::Yes => {
-            let url = make_hyperlink((lat, lon));
-            // TODO: Font still blue.
-            let format = format.set_align(FormatAlign::Right);
-            ws.write_url_with_format(fc.row, fc.col + 2, url, &format)?;
-        }
-        Hyperlink::No => {
-            write_blank(ws, &fc.col_offset(2))?;
+    for p in points {
+        match &p.extensions {
+            Some(ext) if !ext.is_empty() => write_text(ws, fc, ext.as_str().trim())?,
+            _ => write_blank(ws, fc)?,
         }
-    };
 
-    Ok(())
-}
-
-/// Writes an integer.
-fn write_integer(ws: &mut Worksheet, fc: &FormatControl, value: u32) -> Result<(), GapixError> {
-    ws.write_number_with_format(fc.row, fc.col, value, &fc.integer_format())?;
-    Ok(())
-}
+        fc.increment_row();
+    }
 
-/// Writes a blank into a cell. We often want to do this when there is no data
-/// so that banding formatting is applied to the cell.
-fn write_blank(ws: &mut Worksheet, fc: &FormatControl) -> Result<(), GapixError> {
-    ws.write_blank(fc.row, fc.col, &fc.string_format())?;
+    fc.next_colour_block(1);
     Ok(())
 }
 
-/// Writes an elevation data block (min or max) as found on the Stages tab.
-fn write_elevation_data(
+/// Write the "Waypoints" tab of the summary spreadsheet. Each waypoint is
+/// matched to the nearest trackpoint so that planned controls and POIs can be
+/// checked against where the ride actually went.
+#[time]
+fn write_waypoints(
     ws: &mut Worksheet,
-    fc: &FormatControl,
-    point: Option<&EnrichedTrackPoint>,
+    units: UnitSystem,
+    gpx: &EnrichedGpx,
 ) -> Result<(), GapixError> {
-    if point.is_none() {
-        write_blank(ws, fc)?;
-        write_blank(ws, &fc.col_offset(1))?;
-        write_blank(ws, &fc.col_offset(2))?;
+    let mut fc = FormatControl::new(units);
+
+    if gpx.waypoints.is_empty() {
+        write_string(ws, &fc, "No waypoints")?;
         return Ok(());
     }
 
-    let point = point.expect("Point should exist, we just checked for is_none() above");
+    ws.set_freeze_panes(2, 1)?;
 
-    match point.ele {
-        Some(ele) => {
-            write_metres(ws, fc, ele)?;
-        }
-        None => {
-            write_blank(ws, fc)?;
-        }
-    }
+    let nearest: Vec<_> = gpx.waypoints.iter().map(|w| gpx.nearest_point(w)).collect();
 
-    write_kilometres_running_with_map_hyperlink(ws, &fc.col_offset(1), point)?;
-    write_trackpoint_number(ws, &fc.col_offset(2), point.index)?;
-    Ok(())
-}
+    output_wp_details(ws, &mut fc, &gpx.waypoints)?;
+    output_wp_location(ws, &mut fc, &gpx.waypoints)?;
+    output_wp_time(ws, &mut fc, &gpx.waypoints)?;
+    output_wp_nearest_point(ws, &mut fc, &nearest)?;
 
-/// Writes a string right aligned.
-fn write_string(ws: &mut Worksheet, fc: &FormatControl, value: &str) -> Result<(), GapixError> {
-    ws.write_string_with_format(fc.row, fc.col, value, &fc.string_format())?;
     Ok(())
 }
 
-/// Writes a string right aligned and bold.
-fn write_string_bold(
+fn output_wp_details(
     ws: &mut Worksheet,
-    fc: &FormatControl,
-    value: &str,
+    fc: &mut FormatControl,
+    waypoints: &[Waypoint],
 ) -> Result<(), GapixError> {
-    let format = fc.string_format().set_bold();
+    write_headers(ws, fc, "Waypoint", &["Name", "Description", "Symbol"])?;
+    ws.set_column_width(fc.col, LOCATION_DESCRIPTION_COLUMN_WIDTH)?;
+    ws.set_column_width(fc.col + 1, LOCATION_DESCRIPTION_COLUMN_WIDTH)?;
+
+    for w in waypoints {
+        write_text(ws, fc, w.name.as_deref().unwrap_or_default())?;
+        write_text(
+            ws,
+            &fc.col_offset(1),
+            w.description.as_deref().unwrap_or_default(),
+        )?;
+        write_text(
+            ws,
+            &fc.col_offset(2),
+            w.symbol.as_deref().unwrap_or_default(),
+        )?;
+        fc.increment_row();
+    }
+
+    fc.next_colour_block(3);
+    Ok(())
+}
+
+fn output_wp_location(
+    ws: &mut Worksheet,
+    fc: &mut FormatControl,
+    waypoints: &[Waypoint],
+) -> Result<(), GapixError> {
+    write_headers(ws, fc, "Location", &["Lat", "Lon", "Map"])?;
+    ws.set_column_width(fc.col, LAT_LON_COLUMN_WIDTH)?;
+    ws.set_column_width(fc.col + 1, LAT_LON_COLUMN_WIDTH)?;
+    ws.set_column_width(fc.col + 2, LINKED_LAT_LON_COLUMN_WIDTH)?;
+
+    for w in waypoints {
+        write_lat_lon_no_location(ws, fc, (w.lat.value(), w.lon.value()), Hyperlink::Yes)?;
+        fc.increment_row();
+    }
+
+    fc.next_colour_block(3);
+    Ok(())
+}
+
+fn output_wp_time(
+    ws: &mut Worksheet,
+    fc: &mut FormatControl,
+    waypoints: &[Waypoint],
+) -> Result<(), GapixError> {
+    write_headers(ws, fc, "Time", &["UTC", "Local"])?;
+    ws.set_column_width(fc.col, DATE_COLUMN_WIDTH)?;
+    ws.set_column_width(fc.col + 1, DATE_COLUMN_WIDTH)?;
+
+    for w in waypoints {
+        write_utc_date_option(ws, fc, w.time)?;
+        write_utc_date_as_local_option(
+            ws,
+            &fc.col_offset(1),
+            w.time,
+            [w.lat.value(), w.lon.value()],
+        )?;
+        fc.increment_row();
+    }
+
+    fc.next_colour_block(2);
+    Ok(())
+}
+
+fn output_wp_nearest_point(
+    ws: &mut Worksheet,
+    fc: &mut FormatControl,
+    nearest: &[Option<(&EnrichedTrackPoint, f64)>],
+) -> Result<(), GapixError> {
+    let distance = format!("Distance ({})", fc.units.distance_unit());
+    let off_track = format!("Off Track ({})", fc.units.short_distance_unit());
+    write_headers(
+        ws,
+        fc,
+        "Nearest Track Point",
+        &["Point", &distance, &off_track, "Passed (local)", "Elapsed"],
+    )?;
+    ws.set_column_width(fc.col + 1, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
+    ws.set_column_width(fc.col + 2, METRES_COLUMN_WIDTH_WITH_UNITS)?;
+    ws.set_column_width(fc.col + 3, DATE_COLUMN_WIDTH)?;
+    ws.set_column_width(fc.col + 4, DURATION_COLUMN_WIDTH)?;
+
+    for n in nearest {
+        match n {
+            Some((point, metres)) => {
+                write_trackpoint_number(ws, fc, point.index)?;
+                write_kilometres_running_with_map_hyperlink(ws, &fc.col_offset(1), point)?;
+                write_short_distance(ws, &fc.col_offset(2), *metres)?;
+                write_utc_date_as_local_option(
+                    ws,
+                    &fc.col_offset(3),
+                    point.time,
+                    point.as_rtree_point(),
+                )?;
+                write_duration_option(ws, &fc.col_offset(4), point.running_delta_time)?;
+            }
+            None => {
+                for col in 0..5 {
+                    write_blank(ws, &fc.col_offset(col))?;
+                }
+            }
+        }
+
+        fc.increment_row();
+    }
+
+    fc.next_colour_block(5);
+    Ok(())
+}
+
+/// The data that drives the charts. This is a sampled-down version of the
+/// trackpoints plus some per-stage data.
+struct ChartData<'a> {
+    units: UnitSystem,
+    /// The sampled trackpoints, in order.
+    points: Vec<&'a EnrichedTrackPoint>,
+    /// The first point of each stage, used to mark the stage boundaries on the
+    /// elevation profile.
+    stage_starts: Vec<&'a EnrichedTrackPoint>,
+    /// Per-stage (label, moving minutes, control minutes). Only one of the two
+    /// times will be set for any stage.
+    stage_times: Vec<(String, Option<f64>, Option<f64>)>,
+}
+
+impl<'a> ChartData<'a> {
+    fn new(units: UnitSystem, gpx: &'a EnrichedGpx, stages: &'a StageList) -> Self {
+        let points = gpx.sample_points(MAX_CHART_POINTS);
+        let stage_starts = stages.iter().map(|stage| &stage.start).collect();
+
+        let stage_times = stages
+            .iter()
+            .enumerate()
+            .map(|(idx, stage)| {
+                let minutes = stage
+                    .duration()
+                    .map(|d| d.num_milliseconds() as f64 / 60_000.0);
+                let label = format!("{} {}", idx + 1, stage.stage_type);
+                match stage.stage_type {
+                    StageType::Moving => (label, minutes, None),
+                    StageType::Control => (label, None, minutes),
+                }
+            })
+            .collect();
+
+        Self {
+            units,
+            points,
+            stage_starts,
+            stage_times,
+        }
+    }
+
+    fn distance_label(&self) -> String {
+        format!("Distance ({})", self.units.distance_unit())
+    }
+
+    fn elevation_label(&self) -> String {
+        format!("Elevation ({})", self.units.height_unit())
+    }
+
+    fn speed_label(&self) -> String {
+        format!("Speed ({})", self.units.speed_unit())
+    }
+
+    fn has_elevation(&self) -> bool {
+        self.points.iter().any(|p| p.ele.is_some())
+    }
+
+    fn has_speed(&self) -> bool {
+        self.points.iter().any(|p| p.speed_kmh.is_some())
+    }
+
+    fn has_heart_rate(&self) -> bool {
+        self.points.iter().any(|p| p.heart_rate().is_some())
+    }
+}
+
+// Column layout of the hidden 'Chart Data' sheet. Row 0 is a heading row.
+const CD_DISTANCE_COL: u16 = 0;
+const CD_ELEVATION_COL: u16 = 1;
+const CD_SPEED_COL: u16 = 2;
+const CD_HEART_RATE_COL: u16 = 3;
+const CD_STAGE_DISTANCE_COL: u16 = 5;
+const CD_STAGE_ELEVATION_COL: u16 = 6;
+const CD_STAGE_LABEL_COL: u16 = 8;
+const CD_STAGE_MOVING_COL: u16 = 9;
+const CD_STAGE_CONTROL_COL: u16 = 10;
+
+/// Writes the hidden sheet that the charts take their data from. Missing
+/// values are left as empty cells, which both Excel and LibreOffice show as
+/// gaps in the chart.
+#[time]
+fn write_chart_data(ws: &mut Worksheet, data: &ChartData) -> Result<(), GapixError> {
+    let units = data.units;
+    let headings = [
+        (CD_DISTANCE_COL, data.distance_label()),
+        (CD_ELEVATION_COL, data.elevation_label()),
+        (CD_SPEED_COL, data.speed_label()),
+        (CD_HEART_RATE_COL, "Heart Rate (bpm)".to_string()),
+        (
+            CD_STAGE_DISTANCE_COL,
+            format!("Stage Start ({})", units.distance_unit()),
+        ),
+        (
+            CD_STAGE_ELEVATION_COL,
+            format!("Stage Start ({})", units.height_unit()),
+        ),
+        (CD_STAGE_LABEL_COL, "Stage".to_string()),
+        (CD_STAGE_MOVING_COL, "Moving (mins)".to_string()),
+        (CD_STAGE_CONTROL_COL, "Control (mins)".to_string()),
+    ];
+
+    for (col, heading) in headings {
+        ws.write_string(0, col, heading)?;
+    }
+
+    for (row, p) in (1..).zip(&data.points) {
+        ws.write_number(
+            row,
+            CD_DISTANCE_COL,
+            units.distance(p.running_metres / 1000.0),
+        )?;
+        if let Some(ele) = p.ele {
+            ws.write_number(row, CD_ELEVATION_COL, units.height(ele))?;
+        }
+        if let Some(speed) = p.speed_kmh {
+            ws.write_number(row, CD_SPEED_COL, units.speed(speed))?;
+        }
+        if let Some(hr) = p.heart_rate() {
+            ws.write_number(row, CD_HEART_RATE_COL, hr)?;
+        }
+    }
+
+    for (row, p) in (1..).zip(&data.stage_starts) {
+        ws.write_number(
+            row,
+            CD_STAGE_DISTANCE_COL,
+            units.distance(p.running_metres / 1000.0),
+        )?;
+        if let Some(ele) = p.ele {
+            ws.write_number(row, CD_STAGE_ELEVATION_COL, units.height(ele))?;
+        }
+    }
+
+    for (row, (label, moving, control)) in (1..).zip(&data.stage_times) {
+        ws.write_string(row, CD_STAGE_LABEL_COL, label)?;
+        if let Some(m) = moving {
+            ws.write_number(row, CD_STAGE_MOVING_COL, *m)?;
+        }
+        if let Some(c) = control {
+            ws.write_number(row, CD_STAGE_CONTROL_COL, *c)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the 'Charts' sheet. Charts for which there is no data (for example,
+/// the heart rate series when the GPX has no heart rate extensions) are
+/// omitted.
+#[time]
+fn write_charts(ws: &mut Worksheet, data: &ChartData) -> Result<(), GapixError> {
+    if data.points.is_empty() {
+        ws.write_string(0, 0, "No data to chart")?;
+        return Ok(());
+    }
+
+    let mut row = 0;
+
+    if data.has_elevation() {
+        ws.insert_chart(row, 0, &make_elevation_chart(data))?;
+        row += CHART_ROW_SPACING;
+    }
+
+    if data.has_speed() || data.has_heart_rate() {
+        ws.insert_chart(row, 0, &make_speed_and_heart_rate_chart(data))?;
+        row += CHART_ROW_SPACING;
+    }
+
+    if !data.stage_times.is_empty() {
+        ws.insert_chart(row, 0, &make_stage_times_chart(data))?;
+    }
+
+    Ok(())
+}
+
+/// Makes an elevation vs distance profile, with the start of each stage
+/// marked on the profile.
+fn make_elevation_chart(data: &ChartData) -> Chart {
+    let last_row = data.points.len() as u32;
+
+    let mut chart = Chart::new(ChartType::ScatterStraight);
+    chart
+        .add_series()
+        .set_name("Elevation")
+        .set_categories(chart_data_range(CD_DISTANCE_COL, last_row))
+        .set_values(chart_data_range(CD_ELEVATION_COL, last_row))
+        .set_marker(ChartMarker::new().set_none());
+
+    if !data.stage_starts.is_empty() {
+        let last_row = data.stage_starts.len() as u32;
+        chart
+            .add_series()
+            .set_name("Stage Start")
+            .set_categories(chart_data_range(CD_STAGE_DISTANCE_COL, last_row))
+            .set_values(chart_data_range(CD_STAGE_ELEVATION_COL, last_row))
+            .set_format(ChartFormat::new().set_no_line())
+            .set_marker(
+                ChartMarker::new()
+                    .set_type(ChartMarkerType::Diamond)
+                    .set_size(8),
+            );
+    }
+
+    chart.title().set_name("Elevation Profile");
+    chart.x_axis().set_name(&data.distance_label()).set_min(0);
+    chart.y_axis().set_name(&data.elevation_label());
+    set_chart_size(&mut chart);
+    chart
+}
+
+/// Makes a chart of speed and heart rate against distance. Heart rate is
+/// plotted on the secondary axis because its scale is so different.
+fn make_speed_and_heart_rate_chart(data: &ChartData) -> Chart {
+    let last_row = data.points.len() as u32;
+
+    let mut chart = Chart::new(ChartType::ScatterStraight);
+
+    if data.has_speed() {
+        chart
+            .add_series()
+            .set_name(&data.speed_label())
+            .set_categories(chart_data_range(CD_DISTANCE_COL, last_row))
+            .set_values(chart_data_range(CD_SPEED_COL, last_row))
+            .set_marker(ChartMarker::new().set_none())
+            .set_format(ChartFormat::new().set_line(ChartLine::new().set_width(0.75)));
+        chart.y_axis().set_name(&data.speed_label());
+    }
+
+    if data.has_heart_rate() {
+        chart
+            .add_series()
+            .set_name("Heart Rate (bpm)")
+            .set_categories(chart_data_range(CD_DISTANCE_COL, last_row))
+            .set_values(chart_data_range(CD_HEART_RATE_COL, last_row))
+            .set_marker(ChartMarker::new().set_none())
+            .set_format(
+                ChartFormat::new().set_line(ChartLine::new().set_width(0.75).set_color(Color::Red)),
+            )
+            .set_secondary_axis(data.has_speed());
+
+        if data.has_speed() {
+            chart.y2_axis().set_name("Heart Rate (bpm)");
+        } else {
+            chart.y_axis().set_name("Heart Rate (bpm)");
+        }
+    }
+
+    chart.title().set_name("Speed and Heart Rate");
+    chart.x_axis().set_name(&data.distance_label()).set_min(0);
+    set_chart_size(&mut chart);
+    chart
+}
+
+/// Makes a bar chart of the time spent in each stage, with moving and control
+/// times shown as different series so they are coloured differently.
+fn make_stage_times_chart(data: &ChartData) -> Chart {
+    let last_row = data.stage_times.len() as u32;
+
+    let mut chart = Chart::new(ChartType::ColumnStacked);
+    chart
+        .add_series()
+        .set_name("Moving")
+        .set_categories(chart_data_range(CD_STAGE_LABEL_COL, last_row))
+        .set_values(chart_data_range(CD_STAGE_MOVING_COL, last_row));
+    chart
+        .add_series()
+        .set_name("Control")
+        .set_categories(chart_data_range(CD_STAGE_LABEL_COL, last_row))
+        .set_values(chart_data_range(CD_STAGE_CONTROL_COL, last_row));
+
+    chart.title().set_name("Moving and Control Time per Stage");
+    chart.x_axis().set_name("Stage");
+    chart.y_axis().set_name("Minutes");
+    set_chart_size(&mut chart);
+    chart
+}
+
+fn set_chart_size(chart: &mut Chart) {
+    chart.set_width(CHART_WIDTH).set_height(CHART_HEIGHT);
+}
+
+/// Returns a range in the 'Chart Data' sheet covering rows 1 to 'last_row' of
+/// the specified column (row 0 is the heading).
+fn chart_data_range(col: u16, last_row: u32) -> (&'static str, u32, u16, u32, u16) {
+    (CHART_DATA_SHEET, 1, col, last_row, col)
+}
+
+/// The interval, in the selected distance units, of the rows on the 'Timing'
+/// sheet of the comparison workbook.
+const COMPARISON_TIMING_INTERVAL: f64 = 10.0;
+
+/// Writes the 'Rides' sheet of the comparison workbook, one row per ride.
+/// Like the Stages sheet, this is written a block of columns at a time.
+#[time]
+fn write_comparison_rides(
+    ws: &mut Worksheet,
+    units: UnitSystem,
+    rides: &[(EnrichedGpx, StageList)],
+) -> Result<(), GapixError> {
+    let mut fc = FormatControl::new(units);
+
+    ws.set_freeze_panes(2, 1)?;
+
+    write_headers(ws, &fc, "", &["Ride"])?;
+    ws.set_column_width(fc.col, SUMMARY_LABEL_COLUMN_WIDTH)?;
+    for name in comparison_names(rides) {
+        write_string(ws, &fc, &name)?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(1);
+
+    write_headers(ws, &fc, "", &["Start (local)"])?;
+    ws.set_column_width(fc.col, DATE_COLUMN_WIDTH)?;
+    for (_, stages) in rides {
+        if stages.is_empty() {
+            write_blank(ws, &fc)?;
+        } else {
+            let point = stages.first_point().as_rtree_point();
+            write_utc_date_as_local_option(ws, &fc, stages.start_time(), point)?;
+        }
+        fc.increment_row();
+    }
+    fc.next_colour_block(1);
+
+    let heading = format!("Distance ({})", units.distance_unit());
+    write_headers(ws, &fc, "", &[&heading])?;
+    ws.set_column_width(fc.col, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
+    for (_, stages) in rides {
+        write_kilometres(ws, &fc, stages.distance_km())?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(1);
+
+    write_headers(ws, &fc, "Time", &["Elapsed", "Moving", "Control"])?;
+    for (_, stages) in rides {
+        write_duration_option(ws, &fc, stages.duration())?;
+        write_duration_option(ws, &fc.col_offset(1), stages.total_moving_time())?;
+        write_duration_option(ws, &fc.col_offset(2), stages.total_control_time())?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(3);
+
+    let heading = format!("Speed ({})", units.speed_unit());
+    write_headers(ws, &fc, &heading, &["Overall", "Moving", "Max"])?;
+    for (_, stages) in rides {
+        write_speed_option(ws, &fc, stages.average_overall_speed())?;
+        write_speed_option(ws, &fc.col_offset(1), stages.average_moving_speed())?;
+        write_speed_option(
+            ws,
+            &fc.col_offset(2),
+            stages.max_speed().and_then(|p| p.speed_kmh),
+        )?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(3);
+
+    let heading = format!("Climbing ({})", units.height_unit());
+    write_headers(ws, &fc, &heading, &["Ascent", "Descent"])?;
+    for (_, stages) in rides {
+        write_metres_option(ws, &fc, stages.total_ascent_metres())?;
+        write_metres_option(ws, &fc.col_offset(1), stages.total_descent_metres())?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(2);
+
+    write_headers(ws, &fc, "", &["Stops"])?;
+    for (_, stages) in rides {
+        let num_controls = stages
+            .iter()
+            .filter(|s| s.stage_type == StageType::Control)
+            .count();
+        write_integer(ws, &fc, num_controls.try_into()?)?;
+        fc.increment_row();
+    }
+    fc.next_colour_block(1);
+
+    write_headers(ws, &fc, "", &["File"])?;
+    for (gpx, _) in rides {
+        let filename = gpx
+            .filename
+            .as_ref()
+            .map(|f| f.to_string_lossy())
+            .unwrap_or_default();
+        write_text(ws, &fc, &filename)?;
+        fc.increment_row();
+    }
+
+    Ok(())
+}
+
+/// Writes the 'Timing' sheet of the comparison workbook. Each row is a
+/// distance along the ride, and each ride has a pair of columns showing the
+/// elapsed time when that distance was reached and how much of that was spent
+/// at controls. The last row is the finish of each ride.
+#[time]
+fn write_comparison_timing(
+    ws: &mut Worksheet,
+    units: UnitSystem,
+    rides: &[(EnrichedGpx, StageList)],
+) -> Result<(), GapixError> {
+    let mut fc = FormatControl::new(units);
+
+    ws.set_freeze_panes(2, 1)?;
+
+    let max_km = rides
+        .iter()
+        .map(|(_, stages)| stages.distance_km())
+        .fold(0.0, f64::max);
+    let interval_km = COMPARISON_TIMING_INTERVAL / units.distance(1.0);
+    let num_checkpoints = (max_km / interval_km).floor() as u32;
+
+    let heading = format!("Distance ({})", units.distance_unit());
+    write_headers(ws, &fc, "", &[&heading])?;
+    ws.set_column_width(fc.col, KILOMETRES_COLUMN_WIDTH_WITH_UNITS)?;
+    for n in 1..=num_checkpoints {
+        write_kilometres(ws, &fc, n as f64 * interval_km)?;
+        fc.increment_row();
+    }
+    write_string_bold(ws, &fc, "Finish")?;
+    fc.next_colour_block(1);
+
+    for ((gpx, stages), name) in rides.iter().zip(comparison_names(rides)) {
+        write_headers(ws, &fc, &name, &["Elapsed", "Control"])?;
+        ws.set_column_width(fc.col, DURATION_COLUMN_WIDTH)?;
+        ws.set_column_width(fc.col + 1, DURATION_COLUMN_WIDTH)?;
+
+        for n in 1..=num_checkpoints {
+            let metres = n as f64 * interval_km * 1000.0;
+            let idx = gpx.points.partition_point(|p| p.running_metres < metres);
+            match gpx.points.get(idx) {
+                Some(point) => {
+                    write_duration_option(ws, &fc, point.running_delta_time)?;
+                    let control = control_time_until(stages, point);
+                    write_duration_option(ws, &fc.col_offset(1), control)?;
+                }
+                None => {
+                    write_blank(ws, &fc)?;
+                    write_blank(ws, &fc.col_offset(1))?;
+                }
+            }
+            fc.increment_row();
+        }
+
+        write_duration_option(ws, &fc, stages.duration())?;
+        write_duration_option(ws, &fc.col_offset(1), stages.total_control_time())?;
+        fc.next_colour_block(2);
+    }
+
+    Ok(())
+}
+
+/// Returns the names that the rides are shown under in the comparison
+/// workbook. Riders on the same event usually have the same track name, so
+/// this is the file name (without its extension) where there is one. Any names
+/// that are still the same get a number added, e.g. "ride (2)".
+fn comparison_names(rides: &[(EnrichedGpx, StageList)]) -> Vec<String> {
+    let names: Vec<_> = rides
+        .iter()
+        .map(|(gpx, _)| {
+            gpx.filename
+                .as_ref()
+                .and_then(|f| f.file_stem())
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_else(|| gpx.ride_name())
+        })
+        .collect();
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let count = names.iter().filter(|n| *n == name).count();
+            if count == 1 {
+                name.clone()
+            } else {
+                let n = names[..idx].iter().filter(|n| *n == name).count() + 1;
+                format!("{name} ({n})")
+            }
+        })
+        .collect()
+}
+
+/// Returns the total time spent in Control stages from the start of the ride
+/// up to 'point'.
+fn control_time_until(stages: &StageList, point: &EnrichedTrackPoint) -> Option<TimeDelta> {
+    let mut total = TimeDelta::zero();
+
+    for stage in stages
+        .iter()
+        .filter(|s| s.stage_type == StageType::Control && s.start.index <= point.index)
+    {
+        let end = if stage.end.index <= point.index {
+            &stage.end
+        } else {
+            point
+        };
+
+        total += end.time? - stage.start.start_time()?;
+    }
+
+    Some(total)
+}
+
+// Utility functions.
+
+/// Writes a main heading (which can be blank) and a set of
+/// sub-headings, and automatically merges the columns of
+/// the main heading if necessary.
+fn write_headers(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    main_heading: &str,
+    sub_headings: &[&str],
+) -> Result<(), GapixError> {
+    if main_heading.is_empty() {
+        ws.write_blank(0, fc.col, &fc.minor_header_format())?;
+    } else {
+        ws.merge_range(
+            0,
+            fc.col,
+            0,
+            fc.col + sub_headings.len() as u16 - 1,
+            main_heading,
+            &fc.minor_header_format(),
+        )?;
+    }
+
+    for (idx, &heading) in sub_headings.iter().enumerate() {
+        ws.write_string_with_format(1, fc.col + idx as u16, heading, &fc.minor_header_format())?;
+    }
+
+    Ok(())
+}
+
+/// Writes a lat-lon pair with the lat in the first cell as specified
+/// by 'rc' and the lon in the next column. If 'hyperlink' is yes then
+/// a hyperlink to Google Maps is written into the third column.
+fn write_lat_lon_with_location_description(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    hyperlink: Hyperlink,
+    stage: &Stage,
+) -> Result<(), GapixError> {
+    let format = fc.lat_lon_format().set_font_color(Color::Black);
+
+    let lat = stage.start.lat;
+    let lon = stage.start.lon;
+    ws.write_number_with_format(fc.row, fc.col, lat, &format)?;
+    ws.write_number_with_format(fc.row, fc.col + 1, lon, &format)?;
+
+    match hyperlink {
+        Hyperlink::Yes => {
+            let url = make_hyperlink((lat, lon));
+            // TODO: Font still blue.
+            let format = format.set_align(FormatAlign::Right);
+            ws.write_url_with_format(fc.row, fc.col + 2, url, &format)?;
+        }
+        Hyperlink::No => {
+            write_blank(ws, &fc.col_offset(2))?;
+        }
+    };
+
+    let fc = fc.col_offset(3);
+
+    if let Some(desc) = stage.reverse_geocode() {
+        if !desc.is_empty() {
+            ws.write_string_with_format(fc.row, fc.col, desc, &fc.location_format())?;
+        } else {
+            write_blank(ws, &fc)?;
+        }
+    } else {
+        write_blank(ws, &fc)?;
+    }
+
+    Ok(())
+}
+
+fn write_lat_lon_no_location(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    (lat, lon): (f64, f64),
+    hyperlink: Hyperlink,
+) -> Result<(), GapixError> {
+    let format = fc.lat_lon_format().set_font_color(Color::Black);
+
+    ws.write_number_with_format(fc.row, fc.col, lat, &format)?;
+    ws.write_number_with_format(fc.row, fc.col + 1, lon, &format)?;
+
+    match hyperlink {
+        Hyperlink::Yes => {
+            let url = make_hyperlink((lat, lon));
+            // TODO: Font still blue.
+            let format = format.set_align(FormatAlign::Right);
+            ws.write_url_with_format(fc.row, fc.col + 2, url, &format)?;
+        }
+        Hyperlink::No => {
+            write_blank(ws, &fc.col_offset(2))?;
+        }
+    };
+
+    Ok(())
+}
+
+/// Writes an integer.
+fn write_integer(ws: &mut Worksheet, fc: &FormatControl, value: u32) -> Result<(), GapixError> {
+    ws.write_number_with_format(fc.row, fc.col, value, &fc.integer_format())?;
+    Ok(())
+}
+
+/// Writes a blank into a cell. We often want to do this when there is no data
+/// so that banding formatting is applied to the cell.
+fn write_blank(ws: &mut Worksheet, fc: &FormatControl) -> Result<(), GapixError> {
+    ws.write_blank(fc.row, fc.col, &fc.string_format())?;
+    Ok(())
+}
+
+/// Writes an elevation data block (min or max) as found on the Stages tab.
+fn write_elevation_data(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    point: Option<&EnrichedTrackPoint>,
+) -> Result<(), GapixError> {
+    if point.is_none() {
+        write_blank(ws, fc)?;
+        write_blank(ws, &fc.col_offset(1))?;
+        write_blank(ws, &fc.col_offset(2))?;
+        return Ok(());
+    }
+
+    let point = point.expect("Point should exist, we just checked for is_none() above");
+
+    match point.ele {
+        Some(ele) => {
+            write_metres(ws, fc, ele)?;
+        }
+        None => {
+            write_blank(ws, fc)?;
+        }
+    }
+
+    write_kilometres_running_with_map_hyperlink(ws, &fc.col_offset(1), point)?;
+    write_trackpoint_number(ws, &fc.col_offset(2), point.index)?;
+    Ok(())
+}
+
+/// Writes a row label on the Summary tab, left aligned and bold.
+fn write_summary_label(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    value: &str,
+) -> Result<(), GapixError> {
+    let format = fc.location_format().set_bold();
+    ws.write_string_with_format(fc.row, fc.col, value, &format)?;
+    Ok(())
+}
+
+/// Writes a string left aligned. Used for free text such as names.
+fn write_text(ws: &mut Worksheet, fc: &FormatControl, value: &str) -> Result<(), GapixError> {
+    ws.write_string_with_format(fc.row, fc.col, value, &fc.location_format())?;
+    Ok(())
+}
+
+/// Writes a string right aligned.
+fn write_string(ws: &mut Worksheet, fc: &FormatControl, value: &str) -> Result<(), GapixError> {
+    ws.write_string_with_format(fc.row, fc.col, value, &fc.string_format())?;
+    Ok(())
+}
+
+/// Writes a string right aligned and bold.
+fn write_string_bold(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    value: &str,
+) -> Result<(), GapixError> {
+    let format = fc.string_format().set_bold();
     ws.write_string_with_format(fc.row, fc.col, value, &format)?;
     Ok(())
 }
@@ -1144,6 +2407,20 @@ fn write_utc_date_as_local(
     Ok(())
 }
 
+/// Converts 'utc_date' to a local date and then writes just the day part of it,
+/// like "Sunday 1 September 2024".
+fn write_utc_date_as_local_day(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    utc_date: DateTime<Utc>,
+    point: RTreePoint,
+) -> Result<(), GapixError> {
+    let local_date = utc_to_appropriate_timezone(utc_date, point)?;
+    let excel_date = date_to_excel_date(local_date)?;
+    ws.write_with_format(fc.row, fc.col, &excel_date, &fc.local_day_format())?;
+    Ok(())
+}
+
 fn write_utc_date_as_local_option(
     ws: &mut Worksheet,
     fc: &FormatControl,
@@ -1212,6 +2489,8 @@ fn write_duration_option(
     Ok(())
 }
 
+/// Converts a duration into an Excel time value, which is measured in days.
+/// Durations of more than 24 hours are fine, they become values greater than 1.
 fn duration_to_excel_date(duration: TimeDelta) -> Result<ExcelDateTime, GapixError> {
     const SECONDS_PER_MINUTE: i64 = 60;
     const SECONDS_PER_HOUR: i64 = SECONDS_PER_MINUTE * 60;
@@ -1279,7 +2558,7 @@ fn write_temperature_data(
     max: Option<&EnrichedTrackPoint>,
     avg: Option<f64>,
 ) -> Result<(), GapixError> {
-    write_f64_option(ws, fc, avg)?;
+    write_f64_option(ws, fc, avg.map(|t| fc.units.temperature(t)))?;
 
     if let Some(min) = min {
         write_temperature_option(ws, &fc.col_offset(1), min.air_temp())?;
@@ -1304,12 +2583,32 @@ fn write_temperature_data(
     Ok(())
 }
 
+/// Writes the temperature of a point, followed by the distance and a link to
+/// the point.
+fn write_temperature_point(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    point: Option<&EnrichedTrackPoint>,
+) -> Result<(), GapixError> {
+    match point {
+        Some(point) => {
+            write_temperature_option(ws, fc, point.air_temp())?;
+            write_kilometres_running_with_map_hyperlink(ws, &fc.col_offset(1), point)?;
+            write_trackpoint_number(ws, &fc.col_offset(2), point.index)?;
+        }
+        None => write_blank(ws, fc)?,
+    }
+    Ok(())
+}
+
+/// Writes a temperature, which is specified in °C.
 fn write_temperature(
     ws: &mut Worksheet,
     fc: &FormatControl,
     temperature: f64,
 ) -> Result<(), GapixError> {
     let format = fc.temperature_format();
+    let temperature = fc.units.temperature(temperature);
     ws.write_number_with_format(fc.row, fc.col, temperature, &format)?;
     Ok(())
 }
@@ -1362,8 +2661,10 @@ fn write_trackpoint_number(
     Ok(())
 }
 
+/// Writes a height, which is specified in metres.
 fn write_metres(ws: &mut Worksheet, fc: &FormatControl, metres: f64) -> Result<(), GapixError> {
-    ws.write_number_with_format(fc.row, fc.col, metres, &fc.metres_format())?;
+    let height = fc.units.height(metres);
+    ws.write_number_with_format(fc.row, fc.col, height, &fc.metres_format())?;
     // TODO: Use conditional formatting to indicate negatives?
     Ok(())
 }
@@ -1381,12 +2682,41 @@ fn write_metres_option(
     Ok(())
 }
 
+/// Writes a distance that is specified in metres, such as the distance between
+/// two points, in the short-distance unit.
+fn write_short_distance(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    metres: f64,
+) -> Result<(), GapixError> {
+    let distance = fc.units.short_distance(metres);
+    ws.write_number_with_format(fc.row, fc.col, distance, &fc.metres_format())?;
+    Ok(())
+}
+
+/// Writes a rate of climb, which is specified in metres per km.
+fn write_climb_rate_option(
+    ws: &mut Worksheet,
+    fc: &FormatControl,
+    metres_per_km: Option<f64>,
+) -> Result<(), GapixError> {
+    if let Some(r) = metres_per_km {
+        let rate = fc.units.climb_rate(r);
+        ws.write_number_with_format(fc.row, fc.col, rate, &fc.metres_format())?;
+    } else {
+        write_blank(ws, fc)?;
+    }
+    Ok(())
+}
+
+/// Writes a distance, which is specified in km.
 fn write_kilometres(
     ws: &mut Worksheet,
     fc: &FormatControl,
     kilometres: f64,
 ) -> Result<(), GapixError> {
-    ws.write_number_with_format(fc.row, fc.col, kilometres, &fc.kilometres_format())?;
+    let distance = fc.units.distance(kilometres);
+    ws.write_number_with_format(fc.row, fc.col, distance, &fc.kilometres_format())?;
     Ok(())
 }
 
@@ -1395,15 +2725,17 @@ fn write_kilometres_running_with_map_hyperlink(
     fc: &FormatControl,
     point: &EnrichedTrackPoint,
 ) -> Result<(), GapixError> {
-    let km = point.running_metres / 1000.0;
-    let url = make_hyperlink_with_text((point.lat, point.lon), &format!("{:.3}", km));
+    let distance = fc.units.distance(point.running_metres / 1000.0);
+    let url = make_hyperlink_with_text((point.lat, point.lon), &format!("{:.3}", distance));
     let format = fc.kilometres_format();
     let format = format.set_align(FormatAlign::Right);
     ws.write_url_with_format(fc.row, fc.col, url, &format)?;
     Ok(())
 }
 
+/// Writes a speed, which is specified in km/h.
 fn write_speed(ws: &mut Worksheet, fc: &FormatControl, speed: f64) -> Result<(), GapixError> {
+    let speed = fc.units.speed(speed);
     ws.write_number_with_format(fc.row, fc.col, speed, &fc.speed_format())?;
     Ok(())
 }
@@ -1422,16 +2754,16 @@ fn write_speed_option(
     Ok(())
 }
 
-/// Little struct to control the colours and banding of the Excel output. 16
-/// bytes in size = 128 bits. These will fit into 2 registers, but if you change
-/// the write* methods to do pass-by-value you have to de-reference in a million
-/// places in the output* methods. So it's best to leave it all as pass by
-/// reference.
+/// Little struct to control the colours, banding and units of the Excel output.
+/// It is small, but if you change the write* methods to do pass-by-value you
+/// have to de-reference in a million places in the output* methods. So it's
+/// best to leave it all as pass by reference.
 struct FormatControl {
     row: u32,
     col: u16,
     current_background_color: Color,
     always_set_background_color: bool,
+    units: UnitSystem,
 }
 
 impl FormatControl {
@@ -1439,12 +2771,13 @@ impl FormatControl {
     const COLOR2: Color = Color::Theme(2, 1);
     const STARTING_ROW: u32 = 2;
 
-    fn new() -> Self {
+    fn new(units: UnitSystem) -> Self {
         Self {
             current_background_color: Self::COLOR1,
             col: 0,
             row: Self::STARTING_ROW,
             always_set_background_color: false,
+            units,
         }
     }
 
@@ -1455,6 +2788,7 @@ impl FormatControl {
             current_background_color: self.current_background_color,
             row: self.row,
             col: self.col + col_offset,
+            units: self.units,
         }
     }
 
@@ -1465,6 +2799,7 @@ impl FormatControl {
             current_background_color: self.current_background_color,
             row: self.row + row_offset,
             col: self.col,
+            units: self.units,
         }
     }
 
@@ -1475,6 +2810,7 @@ impl FormatControl {
             current_background_color: self.current_background_color,
             row: self.row + row_offset,
             col: self.col + col_offset,
+            units: self.units,
         }
     }
 
@@ -1557,8 +2893,17 @@ impl FormatControl {
         self.apply_background_color_if_needed(format)
     }
 
+    fn local_day_format(&self) -> Format {
+        let format = Format::new()
+            .set_num_format("dddd d mmmm yyyy")
+            .set_align(FormatAlign::Right);
+        self.apply_background_color_if_needed(format)
+    }
+
+    /// Durations are elapsed times rather than times of day, so the hours
+    /// must be in square brackets, otherwise Excel shows them modulo 24.
     fn duration_format(&self) -> Format {
-        let format = Format::new().set_num_format("hh:mm:ss");
+        let format = Format::new().set_num_format("[h]:mm:ss");
         self.apply_background_color_if_needed(format)
     }
 
@@ -1580,9 +2925,791 @@ impl FormatControl {
     /// Helper method.
     fn apply_background_color_if_needed(&self, format: Format) -> Format {
         let mut format = format;
-        if self.row % 2 == 0 || self.always_set_background_color {
+        if self.row.is_multiple_of(2) || self.always_set_background_color {
             format = format.set_background_color(self.current_background_color);
         }
         format
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{Cursor, Read},
+    };
+
+    use gapix_model::model::{GarminTrackpointExtensions, Gpx, Track};
+    use geo::Point;
+    use quick_xml::{events::Event, Reader};
+
+    use super::*;
+    use crate::stage::{detect_stages, distance_between_points_metres, StageDetectionParameters};
+
+    fn excel_days(duration: TimeDelta) -> f64 {
+        duration_to_excel_date(duration).unwrap().to_excel()
+    }
+
+    /// A cell value that was read back from a saved workbook. Dates and
+    /// durations are numbers, as they are in Excel.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Cell {
+        Number(f64),
+        Text(String),
+    }
+
+    /// The cells of one worksheet, keyed by (row, col), both 0-based.
+    struct Sheet {
+        cells: HashMap<(u32, u16), Cell>,
+    }
+
+    impl Sheet {
+        fn get(&self, row: u32, col: u16) -> Option<&Cell> {
+            self.cells.get(&(row, col))
+        }
+
+        fn number(&self, row: u32, col: u16) -> f64 {
+            match self.get(row, col) {
+                Some(Cell::Number(n)) => *n,
+                other => panic!("expected a number at ({row}, {col}), got {other:?}"),
+            }
+        }
+
+        fn text(&self, row: u32, col: u16) -> &str {
+            match self.get(row, col) {
+                Some(Cell::Text(t)) => t,
+                other => panic!("expected text at ({row}, {col}), got {other:?}"),
+            }
+        }
+
+        /// The number of rows, including any leading empty ones.
+        fn num_rows(&self) -> u32 {
+            self.cells.keys().map(|(row, _)| row + 1).max().unwrap_or(0)
+        }
+
+        /// The number of columns, including any leading empty ones.
+        fn num_cols(&self) -> u16 {
+            self.cells.keys().map(|(_, col)| col + 1).max().unwrap_or(0)
+        }
+
+        /// Returns the column that has 'heading' in 'row'.
+        fn column(&self, row: u32, heading: &str) -> u16 {
+            (0..self.num_cols())
+                .find(|&col| self.get(row, col) == Some(&Cell::Text(heading.to_string())))
+                .unwrap_or_else(|| panic!("no heading {heading:?} in row {row}"))
+        }
+
+        /// Returns the row that has 'label' in 'col'.
+        fn row(&self, col: u16, label: &str) -> u32 {
+            (0..self.num_rows())
+                .find(|&row| self.get(row, col) == Some(&Cell::Text(label.to_string())))
+                .unwrap_or_else(|| panic!("no label {label:?} in column {col}"))
+        }
+
+        /// Returns the number in the second column of the row labelled 'label'.
+        fn value(&self, label: &str) -> f64 {
+            self.number(self.row(0, label), 1)
+        }
+    }
+
+    /// Saves 'workbook' and returns the contents of the files in it, keyed by
+    /// their path, e.g. "xl/workbook.xml".
+    fn workbook_files(workbook: &mut Workbook) -> HashMap<String, String> {
+        let buffer = workbook.save_to_buffer().unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(buffer)).unwrap();
+        (0..archive.len())
+            .map(|idx| {
+                let mut file = archive.by_index(idx).unwrap();
+                let mut contents = String::new();
+                file.read_to_string(&mut contents).unwrap();
+                (file.name().to_string(), contents)
+            })
+            .collect()
+    }
+
+    /// Saves 'workbook' and reads back the cells of the sheet called 'name'.
+    fn read_sheet(workbook: &mut Workbook, name: &str) -> Sheet {
+        let files = workbook_files(workbook);
+        let read_file = |path: &str| files.get(path).cloned().unwrap_or_default();
+
+        // The worksheets are numbered in the order they appear in the workbook.
+        let sheet_names = element_texts_or_attributes(&read_file("xl/workbook.xml"), "sheet");
+        let sheet_num = sheet_names
+            .iter()
+            .position(|n| n == name)
+            .unwrap_or_else(|| panic!("no sheet called {name:?} in {sheet_names:?}"))
+            + 1;
+        let shared_strings = element_texts_or_attributes(&read_file("xl/sharedStrings.xml"), "si");
+        let xml = read_file(&format!("xl/worksheets/sheet{sheet_num}.xml"));
+
+        let mut cells = HashMap::new();
+        let mut reader = Reader::from_str(&xml);
+        let mut cell: Option<((u32, u16), bool)> = None;
+        let mut in_value = false;
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) if e.name().as_ref() == b"c" => {
+                    let reference = e.try_get_attribute("r").unwrap().unwrap();
+                    let is_shared_string = e
+                        .try_get_attribute("t")
+                        .unwrap()
+                        .is_some_and(|t| t.value.as_ref() == b"s");
+                    cell = Some((
+                        parse_cell_reference(&reference.unescape_value().unwrap()),
+                        is_shared_string,
+                    ));
+                }
+                Event::Start(e) if e.name().as_ref() == b"v" => in_value = true,
+                Event::End(e) if e.name().as_ref() == b"v" => in_value = false,
+                Event::Text(t) if in_value => {
+                    let (key, is_shared_string) = cell.expect("a value is inside a cell");
+                    let text = t.unescape().unwrap();
+                    let value = if is_shared_string {
+                        Cell::Text(shared_strings[text.parse::<usize>().unwrap()].clone())
+                    } else {
+                        Cell::Number(text.parse().unwrap())
+                    };
+                    cells.insert(key, value);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        Sheet { cells }
+    }
+
+    /// Returns the 'name' attribute of each 'element', or its text if it does
+    /// not have one.
+    fn element_texts_or_attributes(xml: &str, element: &str) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut reader = Reader::from_str(xml);
+        let mut text: Option<String> = None;
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(e) | Event::Empty(e) if e.name().as_ref() == element.as_bytes() => {
+                    match e.try_get_attribute("name").unwrap() {
+                        Some(name) => result.push(name.unescape_value().unwrap().into_owned()),
+                        None => text = Some(String::new()),
+                    }
+                }
+                Event::Text(t) => {
+                    if let Some(text) = text.as_mut() {
+                        text.push_str(&t.unescape().unwrap());
+                    }
+                }
+                Event::End(e) if e.name().as_ref() == element.as_bytes() => {
+                    if let Some(text) = text.take() {
+                        result.push(text);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Converts a reference such as "B3" to a 0-based (row, col).
+    fn parse_cell_reference(reference: &str) -> (u32, u16) {
+        let digits = reference.find(|c: char| c.is_ascii_digit()).unwrap();
+        let col = reference[..digits]
+            .bytes()
+            .fold(0, |col, letter| col * 26 + u16::from(letter - b'A') + 1);
+        (reference[digits..].parse::<u32>().unwrap() - 1, col - 1)
+    }
+
+    /// Part of a test ride, made of 'seconds' points one second apart.
+    struct Leg {
+        seconds: u32,
+        /// How far north the rider goes each second, in degrees of latitude.
+        /// 0.0001 is about 11.1m, or 40km/h.
+        lat_per_second: f64,
+        /// How far the rider climbs each second, in metres.
+        ele_per_second: f64,
+        heart_rate: Option<u8>,
+    }
+
+    /// Builds an EnrichedGpx from 'legs', starting at (53.0, -2.5) and 100m.
+    fn ride(legs: &[Leg]) -> EnrichedGpx {
+        let start = DateTime::parse_from_rfc3339("2024-09-01T05:00:00Z")
+            .unwrap()
+            .to_utc();
+        let (mut lat, mut ele, mut time) = (53.0, 100.0, start);
+        let mut points = Vec::new();
+        for leg in legs {
+            for _ in 0..leg.seconds {
+                let mut builder = Waypoint::builder(lat, -2.5).ele(ele).time(time);
+                if let Some(hr) = leg.heart_rate {
+                    builder = builder.garmin_extensions(GarminTrackpointExtensions {
+                        heart_rate: Some(hr),
+                        ..Default::default()
+                    });
+                }
+                points.push(builder.build().unwrap());
+                lat += leg.lat_per_second;
+                ele += leg.ele_per_second;
+                time += TimeDelta::seconds(1);
+            }
+        }
+
+        let gpx = Gpx::builder()
+            .track(Track::builder().points(points).build())
+            .build();
+        EnrichedGpx::try_from(&gpx).unwrap()
+    }
+
+    /// Detects the stages of 'gpx' with the default CLI parameters.
+    fn detect(gpx: &mut EnrichedGpx) -> StageList {
+        // Stage locations need the geocoding options to have been set. Without
+        // a download folder geocoding is disabled, so the locations are empty.
+        #[cfg(feature = "geocoding")]
+        crate::geocoding::initialise_geocoding_for_tests();
+
+        let params = StageDetectionParameters {
+            stopped_speed_kmh: 0.15,
+            min_metres_to_resume: 100.0,
+            min_duration_seconds: 300.0,
+        };
+        detect_stages(gpx, params)
+    }
+
+    /// A ride of 30 minutes: 10 minutes riding north at 40km/h while climbing
+    /// 60m, a 10 minute stop, and 10 minutes of flat riding. The heart rate is
+    /// 120bpm when moving and 90bpm when stopped.
+    fn ride_with_control() -> EnrichedGpx {
+        ride(&[
+            Leg {
+                seconds: 600,
+                lat_per_second: 0.0001,
+                ele_per_second: 0.1,
+                heart_rate: Some(120),
+            },
+            Leg {
+                seconds: 600,
+                lat_per_second: 0.0,
+                ele_per_second: 0.0,
+                heart_rate: Some(90),
+            },
+            Leg {
+                seconds: 600,
+                lat_per_second: 0.0001,
+                ele_per_second: 0.0,
+                heart_rate: Some(120),
+            },
+        ])
+    }
+
+    /// Asserts that 'actual' is within 'tolerance' of 'expected'.
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected} +/- {tolerance}, got {actual}"
+        );
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn duration_to_excel_date_just_under_one_day() {
+        let d = TimeDelta::hours(23) + TimeDelta::minutes(59);
+        let expected = (23.0 * 60.0 + 59.0) / (24.0 * 60.0);
+        assert!((excel_days(d) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn duration_to_excel_date_one_day() {
+        assert!((excel_days(TimeDelta::hours(24)) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn duration_to_excel_date_multi_day() {
+        // The time limit for a 1200km brevet.
+        let d = TimeDelta::hours(90) + TimeDelta::seconds(30);
+        let expected = 3.75 + 30.0 / 86400.0;
+        assert!((excel_days(d) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn column_names_round_trip() {
+        for (column, name) in StageColumn::NAMES {
+            assert_eq!(column.to_string(), name);
+            assert_eq!(name.parse::<StageColumn>().unwrap(), column);
+        }
+
+        for (column, name) in TrackPointColumn::NAMES {
+            assert_eq!(column.to_string(), name);
+            assert_eq!(name.parse::<TrackPointColumn>().unwrap(), column);
+        }
+
+        assert_eq!(
+            " Heart-Rate ".parse::<TrackPointColumn>().unwrap(),
+            TrackPointColumn::HeartRate
+        );
+        assert!("wattage".parse::<StageColumn>().is_err());
+    }
+
+    #[test]
+    fn standard_preset_is_the_default_and_has_all_stage_columns() {
+        let spec = ColumnSpec::default();
+        assert_eq!(spec, ColumnSpec::preset(ColumnPreset::Standard));
+        assert_eq!(spec.stages.len(), StageColumn::NAMES.len());
+        assert!(!spec.track_points.contains(&TrackPointColumn::Stage));
+    }
+
+    #[test]
+    fn debug_preset_adds_debugging_columns() {
+        let spec = ColumnSpec::preset(ColumnPreset::Debug);
+        assert_eq!(spec.track_points.len(), TrackPointColumn::NAMES.len());
+        assert_eq!(spec.track_points[0], TrackPointColumn::Index);
+        assert_eq!(spec.track_points[1], TrackPointColumn::Stage);
+    }
+
+    #[test]
+    fn duration_format_shows_elapsed_hours() {
+        let fc = FormatControl::new(UnitSystem::Metric);
+        assert!(format!("{:?}", fc.duration_format()).contains("[h]:mm:ss"));
+    }
+
+    #[test]
+    fn track_point_distances_are_in_the_short_distance_unit() {
+        let gpx = ride(&[Leg {
+            seconds: 3,
+            lat_per_second: 0.001,
+            ele_per_second: 1.0,
+            heart_rate: None,
+        }]);
+        let columns = [TrackPointColumn::Elevation, TrackPointColumn::Distance];
+
+        for (units, short, height) in [
+            (UnitSystem::Metric, "m", "m"),
+            (UnitSystem::Imperial, "ft", "ft"),
+            (UnitSystem::UK, "yd", "m"),
+        ] {
+            let mut workbook = Workbook::new();
+            let ws = workbook.add_worksheet();
+            ws.set_name("Track Points").unwrap();
+            write_trackpoints(
+                ws,
+                units,
+                &columns,
+                &gpx.points,
+                &StageList::default(),
+                Hyperlink::No,
+            )
+            .unwrap();
+
+            let sheet = read_sheet(&mut workbook, "Track Points");
+            let height_col = sheet.column(1, "Height");
+            let delta_col = sheet.column(1, &format!("Delta ({short})"));
+            let running_col = sheet.column(1, &format!("Running ({})", units.distance_unit()));
+            assert_eq!(sheet.text(0, height_col), format!("Elevation ({height})"));
+            assert_eq!(sheet.num_rows(), 5);
+
+            for (idx, p) in gpx.points.iter().enumerate() {
+                let row = idx as u32 + 2;
+                assert_close(sheet.number(row, height_col), units.height(p.ele.unwrap()));
+                assert_close(
+                    sheet.number(row, delta_col),
+                    units.short_distance(p.delta_metres),
+                );
+                assert_close(
+                    sheet.number(row, running_col),
+                    units.distance(p.running_metres / 1000.0),
+                );
+            }
+        }
+
+        // 0.001 degrees of latitude is about 111m.
+        assert!((gpx.points[1].delta_metres - 111.2).abs() < 0.5);
+    }
+
+    #[test]
+    fn summary_totals_are_in_the_chosen_units() {
+        let mut gpx = ride_with_control();
+        let stages = detect(&mut gpx);
+        const SECONDS_PER_DAY: f64 = 86400.0;
+        // 1199 of the 1799 gaps between points are 0.0001 degrees of latitude.
+        // The distance is the total of the stages, which do not include the
+        // gaps between one stage and the next.
+        let gap = distance_between_points_metres(
+            gpx.points[0].as_geo_point(),
+            gpx.points[1].as_geo_point(),
+        );
+        let km = stages.distance_km();
+        assert_near(km, 1199.0 * gap / 1000.0, 2.0 * gap / 1000.0);
+
+        for units in [UnitSystem::Metric, UnitSystem::Imperial, UnitSystem::UK] {
+            let mut workbook = Workbook::new();
+            let ws = workbook.add_worksheet();
+            ws.set_name("Summary").unwrap();
+            write_summary(ws, units, &gpx, &stages).unwrap();
+            let sheet = read_sheet(&mut workbook, "Summary");
+
+            let distance = sheet.value(&format!("Distance ({})", units.distance_unit()));
+            assert_near(distance, units.distance(km), 1e-9);
+
+            let elapsed = sheet.value("Elapsed Time") * SECONDS_PER_DAY;
+            let moving = sheet.value("Moving Time") * SECONDS_PER_DAY;
+            let control = sheet.value("Control Time") * SECONDS_PER_DAY;
+            assert_near(elapsed, 1799.0, 1e-3);
+            assert_near(moving + control, elapsed, 1e-3);
+            // Stage detection decides exactly when the stop starts and ends.
+            assert_near(control, 600.0, 30.0);
+            let control_row = sheet.row(0, "Control Time");
+            assert_near(sheet.number(control_row, 2), control / elapsed, 1e-9);
+
+            let ascent = sheet.value(&format!("Ascent ({})", units.height_unit()));
+            assert_near(ascent, units.height(60.0), units.height(0.2));
+            let descent = sheet.value(&format!("Descent ({})", units.height_unit()));
+            assert_near(descent, 0.0, 1e-9);
+            let max_ele = sheet.value(&format!("Max Elevation ({})", units.height_unit()));
+            assert_near(max_ele, units.height(160.0), units.height(0.2));
+
+            let speed_unit = units.speed_unit();
+            let avg_moving = sheet.value(&format!("Avg Moving Speed ({speed_unit})"));
+            assert_near(avg_moving, distance / (moving / 3600.0), 1e-6);
+            assert_near(avg_moving, units.speed(40.0), units.speed(2.0));
+            let avg_overall = sheet.value(&format!("Avg Overall Speed ({speed_unit})"));
+            assert_near(avg_overall, distance / (elapsed / 3600.0), 1e-6);
+
+            assert_near(sheet.value("Avg Heart Rate (bpm)"), 110.0, 1e-9);
+            assert_near(sheet.value("Max Heart Rate (bpm)"), 120.0, 1e-9);
+            assert_eq!(sheet.value("Stages"), 3.0);
+            assert_eq!(sheet.value("Moving Stages"), 2.0);
+            assert_eq!(sheet.value("Controls"), 1.0);
+            assert_eq!(sheet.value("Track Points"), 1800.0);
+        }
+    }
+
+    /// Writes the 'Waypoints' sheet for a track that goes south across the
+    /// equator at lon 0, with points at 'lats'.
+    fn waypoints_sheet(units: UnitSystem, lats: &[f64], waypoints: Vec<Waypoint>) -> Sheet {
+        let start = DateTime::parse_from_rfc3339("2024-09-01T05:00:00Z")
+            .unwrap()
+            .to_utc();
+        let points: Vec<_> = lats
+            .iter()
+            .enumerate()
+            .map(|(idx, &lat)| {
+                Waypoint::builder(lat, 0.0)
+                    .time(start + TimeDelta::minutes(idx as i64))
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let mut builder = Gpx::builder().track(Track::builder().points(points).build());
+        for w in waypoints {
+            builder = builder.waypoint(w);
+        }
+        let gpx = EnrichedGpx::try_from(&builder.build()).unwrap();
+
+        let mut workbook = Workbook::new();
+        let ws = workbook.add_worksheet();
+        ws.set_name("Waypoints").unwrap();
+        write_waypoints(ws, units, &gpx).unwrap();
+        read_sheet(&mut workbook, "Waypoints")
+    }
+
+    #[test]
+    fn waypoints_are_matched_to_the_nearest_track_point() {
+        let lats = [0.0002, 0.0001, -0.0001, -0.0002];
+        let midway = Waypoint::builder(0.0, 0.0).name("Midway").build().unwrap();
+        let far = Waypoint::builder(1.0, 0.0).name("Far").build().unwrap();
+        let track_point = |idx: usize| Point::new(0.0, lats[idx]);
+        let to_midway =
+            |idx| distance_between_points_metres(track_point(idx), midway.as_geo_point());
+        // The track passes 'midway' equally closely at points 1 and 2.
+        assert_eq!(to_midway(1), to_midway(2));
+        let first_gap = distance_between_points_metres(track_point(0), track_point(1));
+
+        for units in [UnitSystem::Metric, UnitSystem::UK] {
+            let sheet = waypoints_sheet(units, &lats, vec![midway.clone(), far.clone()]);
+            let point_col = sheet.column(1, "Point");
+            let distance_col = sheet.column(1, &format!("Distance ({})", units.distance_unit()));
+            let off_track_col =
+                sheet.column(1, &format!("Off Track ({})", units.short_distance_unit()));
+            let elapsed_col = sheet.column(1, "Elapsed");
+            assert_eq!(sheet.num_rows(), 4);
+
+            // Ties go to the earlier point, i.e. the first time it is passed.
+            assert_eq!(sheet.text(2, 0), "Midway");
+            assert_eq!(sheet.text(2, point_col), "1");
+            assert_eq!(
+                sheet.text(2, distance_col),
+                format!("{:.3}", units.distance(first_gap / 1000.0))
+            );
+            assert_near(
+                sheet.number(2, off_track_col),
+                units.short_distance(to_midway(1)),
+                1e-6,
+            );
+            assert_near(sheet.number(2, elapsed_col), 60.0 / 86400.0, 1e-9);
+
+            // There is no limit on how far from the track a waypoint can be.
+            assert_eq!(sheet.text(3, 0), "Far");
+            assert_eq!(sheet.text(3, point_col), "0");
+            assert_near(
+                sheet.number(3, off_track_col),
+                units.short_distance(110_552.0),
+                units.short_distance(10.0),
+            );
+        }
+    }
+
+    #[test]
+    fn track_without_waypoints_says_so() {
+        let sheet = waypoints_sheet(UnitSystem::Metric, &[0.0, 0.0001], Vec::new());
+        assert_eq!(sheet.text(FormatControl::STARTING_ROW, 0), "No waypoints");
+        assert_eq!(sheet.cells.len(), 1);
+    }
+
+    /// Returns the XML of all the charts in 'workbook', joined together.
+    fn chart_xml(workbook: &mut Workbook) -> String {
+        workbook_files(workbook)
+            .into_iter()
+            .filter(|(path, _)| path.starts_with("xl/charts/chart"))
+            .map(|(_, xml)| xml)
+            .collect()
+    }
+
+    #[test]
+    fn chart_data_has_a_row_per_point_and_per_stage() {
+        let mut gpx = ride_with_control();
+        let stages = detect(&mut gpx);
+        let units = UnitSystem::Imperial;
+        let options = SummaryOptions {
+            units,
+            ..Default::default()
+        };
+        let mut workbook = create_summary_xlsx(&options, &gpx, &stages).unwrap();
+        let sheet = read_sheet(&mut workbook, CHART_DATA_SHEET);
+
+        let headings = [
+            (CD_DISTANCE_COL, "Distance (mi)"),
+            (CD_ELEVATION_COL, "Elevation (ft)"),
+            (CD_SPEED_COL, "Speed (mph)"),
+            (CD_HEART_RATE_COL, "Heart Rate (bpm)"),
+            (CD_STAGE_DISTANCE_COL, "Stage Start (mi)"),
+            (CD_STAGE_ELEVATION_COL, "Stage Start (ft)"),
+            (CD_STAGE_LABEL_COL, "Stage"),
+            (CD_STAGE_MOVING_COL, "Moving (mins)"),
+            (CD_STAGE_CONTROL_COL, "Control (mins)"),
+        ];
+        for (col, heading) in headings {
+            assert_eq!(sheet.text(0, col), heading);
+        }
+        assert_eq!(sheet.num_cols(), CD_STAGE_CONTROL_COL + 1);
+
+        // The ride is shorter than MAX_CHART_POINTS, so every point is written.
+        assert_eq!(sheet.num_rows(), gpx.points.len() as u32 + 1);
+        for (row, p) in (1..).zip(&gpx.points) {
+            assert_close(
+                sheet.number(row, CD_DISTANCE_COL),
+                units.distance(p.running_metres / 1000.0),
+            );
+            assert_close(
+                sheet.number(row, CD_ELEVATION_COL),
+                units.height(p.ele.unwrap()),
+            );
+            assert_close(
+                sheet.number(row, CD_SPEED_COL),
+                units.speed(p.speed_kmh.unwrap()),
+            );
+            let hr = p.heart_rate().unwrap();
+            assert_close(sheet.number(row, CD_HEART_RATE_COL), hr.into());
+        }
+
+        assert_eq!(stages.len(), 3);
+        for (row, stage) in (1..).zip(stages.iter()) {
+            assert_close(
+                sheet.number(row, CD_STAGE_DISTANCE_COL),
+                units.distance(stage.start.running_metres / 1000.0),
+            );
+            let minutes = stage.duration().unwrap().num_milliseconds() as f64 / 60_000.0;
+            let (time_col, empty_col) = match stage.stage_type {
+                StageType::Moving => (CD_STAGE_MOVING_COL, CD_STAGE_CONTROL_COL),
+                StageType::Control => (CD_STAGE_CONTROL_COL, CD_STAGE_MOVING_COL),
+            };
+            assert_close(sheet.number(row, time_col), minutes);
+            assert_eq!(sheet.get(row, empty_col), None);
+        }
+        assert_eq!(sheet.text(1, CD_STAGE_LABEL_COL), "1 Moving");
+        assert_eq!(sheet.text(2, CD_STAGE_LABEL_COL), "2 Control");
+        assert_eq!(sheet.text(3, CD_STAGE_LABEL_COL), "3 Moving");
+        assert_eq!(sheet.get(4, CD_STAGE_LABEL_COL), None);
+
+        let charts = chart_xml(&mut workbook);
+        assert!(charts.contains("Speed (mph)"));
+        assert!(charts.contains("Heart Rate (bpm)"));
+    }
+
+    #[test]
+    fn heart_rate_is_not_charted_when_there_is_none() {
+        let mut gpx = ride(&[Leg {
+            seconds: 300,
+            lat_per_second: 0.0001,
+            ele_per_second: 0.1,
+            heart_rate: None,
+        }]);
+        let stages = detect(&mut gpx);
+        let mut workbook = create_summary_xlsx(&SummaryOptions::default(), &gpx, &stages).unwrap();
+
+        let sheet = read_sheet(&mut workbook, CHART_DATA_SHEET);
+        assert_eq!(sheet.num_rows(), 301);
+        assert_eq!(sheet.text(0, CD_HEART_RATE_COL), "Heart Rate (bpm)");
+        for row in 1..301 {
+            assert!(sheet.get(row, CD_DISTANCE_COL).is_some());
+            assert_eq!(sheet.get(row, CD_HEART_RATE_COL), None);
+        }
+
+        // The speed chart is still drawn, but without a heart rate series.
+        let charts = chart_xml(&mut workbook);
+        assert!(charts.contains("Speed (km/h)"));
+        assert!(!charts.contains("Heart Rate (bpm)"));
+    }
+
+    /// Gives 'gpx' the file name 'path' and the track name of an event, which
+    /// all the riders share.
+    fn event_ride(mut gpx: EnrichedGpx, path: &str) -> EnrichedGpx {
+        gpx.filename = Some(path.into());
+        gpx.track_name = Some("Event".to_string());
+        gpx
+    }
+
+    /// A ride that is shorter than ride_with_control(): 800 seconds riding
+    /// north at 40km/h without stopping, which is about 8.9km.
+    fn short_ride() -> EnrichedGpx {
+        ride(&[Leg {
+            seconds: 800,
+            lat_per_second: 0.0001,
+            ele_per_second: 0.0,
+            heart_rate: None,
+        }])
+    }
+
+    #[test]
+    fn comparison_rides_are_named_after_their_files() {
+        let mut long = event_ride(ride_with_control(), "riders/alice.gpx");
+        let mut short = event_ride(short_ride(), "riders/bob.fit");
+        let mut again = event_ride(short_ride(), "other/bob.fit");
+        let mut unnamed = short_ride();
+        unnamed.track_name = Some("Morning Ride".to_string());
+        let rides = vec![
+            (detect(&mut long), long),
+            (detect(&mut short), short),
+            (detect(&mut again), again),
+            (detect(&mut unnamed), unnamed),
+        ]
+        .into_iter()
+        .map(|(stages, gpx)| (gpx, stages))
+        .collect::<Vec<_>>();
+
+        let mut workbook = create_comparison_xlsx(UnitSystem::Metric, &rides).unwrap();
+        let sheet = read_sheet(&mut workbook, "Rides");
+        let names: Vec<_> = (2..6).map(|row| sheet.text(row, 0)).collect();
+        assert_eq!(names, ["alice", "bob (1)", "bob (2)", "Morning Ride"]);
+
+        let control_col = sheet.column(1, "Control");
+        assert_near(
+            sheet.number(2, control_col),
+            excel_days(rides[0].1.total_control_time().unwrap()),
+            1e-9,
+        );
+        assert_near(sheet.number(3, control_col), 0.0, 1e-9);
+        assert_eq!(sheet.number(2, sheet.column(1, "Stops")), 1.0);
+        assert_eq!(sheet.number(3, sheet.column(1, "Stops")), 0.0);
+
+        let sheet = read_sheet(&mut workbook, "Timing");
+        let headings: Vec<_> = [1, 3, 5, 7].map(|col| sheet.text(0, col)).into();
+        assert_eq!(headings, ["alice", "bob (1)", "bob (2)", "Morning Ride"]);
+    }
+
+    #[test]
+    fn comparison_timing_is_aligned_by_distance() {
+        let mut long = event_ride(ride_with_control(), "alice.gpx");
+        let mut short = event_ride(short_ride(), "bob.gpx");
+        let long_stages = detect(&mut long);
+        let short_stages = detect(&mut short);
+        // About 13.3km and 8.9km, so only the long ride reaches 10km.
+        assert!(long_stages.distance_km() > 10.0);
+        assert!(short_stages.distance_km() < 10.0);
+        let rides = vec![(long, long_stages), (short, short_stages)];
+
+        for (units, checkpoints) in [(UnitSystem::Metric, 1), (UnitSystem::Imperial, 0)] {
+            let mut workbook = create_comparison_xlsx(units, &rides).unwrap();
+            let sheet = read_sheet(&mut workbook, "Timing");
+            let finish_row = 2 + checkpoints;
+            assert_eq!(sheet.text(finish_row, 0), "Finish");
+            assert_eq!(sheet.num_rows(), finish_row + 1);
+            assert_eq!(sheet.text(0, 1), "alice");
+            assert_eq!(sheet.text(0, 3), "bob");
+
+            for (idx, (_, stages)) in rides.iter().enumerate() {
+                let col = 1 + 2 * idx as u16;
+                assert_near(
+                    sheet.number(finish_row, col),
+                    excel_days(stages.duration().unwrap()),
+                    1e-9,
+                );
+                let control = stages.total_control_time().unwrap_or_default();
+                assert_near(sheet.number(finish_row, col + 1), excel_days(control), 1e-9);
+            }
+
+            if checkpoints == 1 {
+                assert_close(sheet.number(2, 0), 10.0);
+                // The long ride reaches 10km about 300 seconds after the end of
+                // its 10 minute stop, so the whole stop is included.
+                assert_near(
+                    sheet.number(2, 1),
+                    excel_days(TimeDelta::seconds(1500)),
+                    1.5 / 86400.0,
+                );
+                assert_near(
+                    sheet.number(2, 2),
+                    excel_days(rides[0].1.total_control_time().unwrap()),
+                    1e-9,
+                );
+                // The short ride never gets there.
+                assert_eq!(sheet.get(2, 3), None);
+                assert_eq!(sheet.get(2, 4), None);
+            }
+        }
+    }
+
+    #[test]
+    fn control_time_until_counts_the_part_of_a_control_before_the_point() {
+        let mut gpx = ride_with_control();
+        let stages = detect(&mut gpx);
+        let control = stages
+            .iter()
+            .find(|s| s.stage_type == StageType::Control)
+            .unwrap();
+        let control_start = control.start.start_time().unwrap();
+
+        let before = &gpx.points[control.start.index - 10];
+        assert_eq!(control_time_until(&stages, before), Some(TimeDelta::zero()));
+
+        let during = &gpx.points[control.start.index + 100];
+        assert_eq!(
+            control_time_until(&stages, during),
+            Some(during.time.unwrap() - control_start)
+        );
+        assert_eq!(
+            control_time_until(&stages, during),
+            Some(TimeDelta::seconds(101))
+        );
+
+        let after = gpx.points.last().unwrap();
+        assert_eq!(
+            control_time_until(&stages, after),
+            stages.total_control_time()
+        );
+        assert_eq!(control_time_until(&stages, after), control.duration());
+    }
+}
@@ -1,18 +1,20 @@
 use std::{
     collections::HashMap,
     fs::{self, File},
-    io::{BufRead, BufReader, Write},
+    io::{BufRead, BufReader},
     path::{Path, PathBuf},
     sync::{LazyLock, OnceLock},
 };
+#[cfg(feature = "download")]
+use std::io::Write;
 
-use chrono_tz::Tz;
 use geo::{point, Distance, Geodesic};
 use log::{debug, error, info, warn};
 use logging_timer::{stime, time};
 use rstar::{PointDistance, RTree, RTreeObject, AABB};
-use tzf_rs::DefaultFinder;
 
+use crate::model_impls::RTreePoint;
+#[cfg(feature = "download")]
 use crate::byte_counter::ByteCounter;
 
 static OPTIONS: OnceLock<GeocodingOptions> = OnceLock::new();
@@ -35,10 +37,6 @@ static ADMIN_2_CODES: LazyLock<HashMap<String, String>> = LazyLock::new(load_adm
 /// This is where reverse-geocoding begins.
 static PLACES: LazyLock<RTree<Place>> = LazyLock::new(load_places);
 
-/// This is used to lookup a timezone (in string form such as "Europe/London")
-/// from a (lat,lon) pair.
-static TIMEZONES: LazyLock<tzf_rs::DefaultFinder> = LazyLock::new(DefaultFinder::new);
-
 /// Initialises the geocoding system. This involves downloading, filtering and
 /// loading various files from geonames.org. This is done on background threads
 /// so that hopefully the structures will be available as soon as they are
@@ -83,15 +81,19 @@ pub fn initialise_geocoding(mut options: GeocodingOptions) {
         LazyLock::force(&PLACES);
     });
 
-    std::thread::spawn(|| {
-        LazyLock::force(&TIMEZONES);
-    });
-
     std::thread::spawn(|| {
         LazyLock::force(&ADMIN_2_CODES);
     });
 }
 
+/// Initialises geocoding without a download folder, which disables it, so that
+/// tests that detect stages can run. Any number of tests can call this.
+#[cfg(test)]
+pub(crate) fn initialise_geocoding_for_tests() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| initialise_geocoding(GeocodingOptions::new(None, Vec::new(), false)));
+}
+
 /// Given a (lat, lon) finds the nearest place and returns a description of it.
 pub fn reverse_geocode_latlon(point: RTreePoint) -> Option<String> {
     let place = PLACES.nearest_neighbor(&point)?;
@@ -128,17 +130,16 @@ pub fn get_nearest_place(point: RTreePoint) -> Option<&'static Place> {
     PLACES.nearest_neighbor(&point)
 }
 
-/// Returns the name of the timezone at point, such as "Europe/London".
-pub fn get_timezone_name(point: RTreePoint) -> &'static str {
-    TIMEZONES.get_tz_name(point[1], point[0])
-}
+/// Returns all the places inside the rectangle with corners 'min' and 'max'.
+/// If geocoding has not been initialised, returns an empty list.
+pub fn get_places_in_area(min: RTreePoint, max: RTreePoint) -> Vec<&'static Place> {
+    if OPTIONS.get().is_none() {
+        return Vec::new();
+    }
 
-/// Given a point, finds the timezone.
-pub fn get_timezone(point: RTreePoint) -> Option<Tz> {
-    // I suppose parse() might fail, we are passing timezone names from tzf-rs
-    // into chrono-tz. They SHOULD be the same though.
-    let tz: Tz = get_timezone_name(point).parse().ok()?;
-    Some(tz)
+    PLACES
+        .locate_in_envelope(&AABB::from_corners(min, max))
+        .collect()
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -223,8 +224,6 @@ pub struct Place {
     pub admin2: String,
 }
 
-pub(crate) type RTreePoint = [f64; 2];
-
 impl Place {
     fn as_rtree_point(&self) -> RTreePoint {
         [self.lat, self.lon]
@@ -549,6 +548,7 @@ fn download_file_and_open(options: &GeocodingOptions, filename: &str) -> Option<
 
 /// Downloads the specified file from geonames.org. If an error occurs during
 /// any part of the process it is logged and None is returned.
+#[cfg(feature = "download")]
 fn download_file(options: &GeocodingOptions, filename: &str) -> Option<PathBuf> {
     assert!(!options.disable_geocoding());
     
@@ -615,6 +615,25 @@ fn download_file(options: &GeocodingOptions, filename: &str) -> Option<PathBuf>
     Some(out_filename)
 }
 
+/// Without the 'download' feature the files must already have been placed in
+/// the download folder. If the file is not there, it is logged and None is
+/// returned.
+#[cfg(not(feature = "download"))]
+fn download_file(options: &GeocodingOptions, filename: &str) -> Option<PathBuf> {
+    assert!(!options.disable_geocoding());
+
+    let out_filename = options.get_output_path(filename);
+    if Path::exists(&out_filename) {
+        Some(out_filename)
+    } else {
+        error!(
+            "File {:?} does not exist and downloading is not enabled in this build",
+            &out_filename
+        );
+        None
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct GeocodingOptions {
     /// Folder in which to place the downloaded files.
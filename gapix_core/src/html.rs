@@ -0,0 +1,470 @@
+//! Generates a single-file HTML report of a ride. Everything (styles, map and
+//! charts) is inline so that the file can be opened offline and shared easily,
+//! for example on a phone where spreadsheets are awkward to view. The report is
+//! built from the same data as the analysis workbook.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{info, warn};
+use logging_timer::time;
+
+use crate::{
+    byte_counter::ByteCounter,
+    dates::utc_to_appropriate_timezone,
+    error::GapixError,
+    map::{create_map_svg, MapOptions},
+    model::{EnrichedGpx, EnrichedTrackPoint},
+    profile::{create_elevation_profile_svg, draw_control_markers, ProfileOptions},
+    stage::{StageList, StageType},
+    svg::{draw_axes, escape, PlotArea, SvgBuilder, TRACK_COLOUR},
+    units::UnitSystem,
+};
+
+/// As with the charts in the workbook, there is no point in drawing tens of
+/// thousands of points, it just makes the file bigger.
+const MAX_REPORT_POINTS: usize = 2000;
+const MAP_WIDTH: f64 = 900.0;
+const MAP_MAX_HEIGHT: f64 = 700.0;
+const CHART_WIDTH: f64 = 900.0;
+const CHART_HEIGHT: f64 = 300.0;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 0 auto; max-width: 960px; padding: 8px; color: #222; }
+h1 { font-size: 1.5em; margin-bottom: 0.2em; }
+h2 { font-size: 1.2em; margin-top: 1.5em; border-bottom: 1px solid #ccc; }
+.subtitle { color: #666; margin-top: 0; }
+svg { width: 100%; height: auto; }
+.table-wrapper { overflow-x: auto; }
+table { border-collapse: collapse; font-size: 0.9em; }
+th, td { padding: 3px 8px; border-bottom: 1px solid #eee; white-space: nowrap; }
+th { background: #f0f0f0; text-align: left; }
+td.num { text-align: right; }
+tr.control td { background: #fde0e0; }
+"#;
+
+/// Creates the HTML report for a ride and returns it as a string. If there is
+/// no data for the map or the elevation profile that section is left out.
+#[time]
+pub fn create_html_report(
+    units: UnitSystem,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+) -> Result<String, GapixError> {
+    let name = gpx.ride_name();
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(&name)));
+    html.push_str(&format!("<style>{STYLE}</style>\n</head>\n<body>\n"));
+    html.push_str(&format!("<h1>{}</h1>\n", escape(&name)));
+
+    if let Some(start_time) = (!stages.is_empty()).then(|| stages.start_time()).flatten() {
+        let day = format_local_time(start_time, stages.first_point(), "%A %-d %B %Y");
+        html.push_str(&format!("<p class=\"subtitle\">{}</p>\n", escape(&day)));
+    }
+
+    write_summary(&mut html, units, gpx, stages);
+
+    if !gpx.points.is_empty() {
+        let points = gpx.sample_points(MAX_REPORT_POINTS);
+
+        let map_options = MapOptions {
+            units,
+            show_places: true,
+            width: MAP_WIDTH,
+            max_height: MAP_MAX_HEIGHT,
+            ..Default::default()
+        };
+        match create_map_svg(&map_options, gpx, stages) {
+            Ok(map) => {
+                html.push_str("<h2>Map</h2>\n");
+                html.push_str(&map);
+                html.push('\n');
+            }
+            Err(GapixError::NoTrackPoints) => {
+                warn!("Not drawing the map: {}", GapixError::NoTrackPoints)
+            }
+            Err(err) => return Err(err),
+        }
+
+        let profile_options = ProfileOptions {
+            units,
+            width: CHART_WIDTH,
+            height: CHART_HEIGHT,
+        };
+        match create_elevation_profile_svg(&profile_options, gpx, stages) {
+            Ok(profile) => {
+                html.push_str("<h2>Elevation</h2>\n");
+                html.push_str(&profile);
+                html.push('\n');
+            }
+            Err(GapixError::NoElevationData) => {
+                warn!(
+                    "Not drawing the elevation profile: {}",
+                    GapixError::NoElevationData
+                )
+            }
+            Err(err) => return Err(err),
+        }
+
+        if points.iter().any(|p| p.speed_kmh.is_some()) {
+            html.push_str("<h2>Speed</h2>\n");
+            html.push_str(&speed_chart_svg(units, &points, stages));
+            html.push('\n');
+        }
+    }
+
+    write_stages(&mut html, units, stages);
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Writes the HTML report to a file. The file will be overwritten if it
+/// already exists.
+pub fn write_html_report_to_file<P: AsRef<Path>>(
+    filename: P,
+    html: &str,
+) -> Result<(), GapixError> {
+    let filename = filename.as_ref();
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(err) => {
+            return Err(GapixError::CreateFile {
+                path: filename.to_owned(),
+                source: err,
+            })
+        }
+    };
+
+    let mut writer = ByteCounter::new(BufWriter::new(file));
+    writer.write_all(html.as_bytes())?;
+    writer.flush()?;
+
+    info!(
+        "HTML report {:?}, {} Kb",
+        &filename,
+        writer.bytes_written() / 1024
+    );
+    Ok(())
+}
+
+/// Writes the table of ride totals.
+fn write_summary(html: &mut String, units: UnitSystem, gpx: &EnrichedGpx, stages: &StageList) {
+    html.push_str("<h2>Summary</h2>\n<div class=\"table-wrapper\"><table>\n");
+
+    let mut row = |label: &str, value: Option<String>| {
+        let value = value.unwrap_or_default();
+        html.push_str(&format!(
+            "<tr><th>{}</th><td class=\"num\">{}</td></tr>\n",
+            escape(label),
+            escape(&value)
+        ));
+    };
+
+    if stages.is_empty() {
+        row("Stages", Some("No stages detected".to_string()));
+    } else {
+        row(
+            "Start",
+            stages
+                .start_time()
+                .map(|t| format_local_time(t, stages.first_point(), "%a %H:%M")),
+        );
+        row(
+            "Finish",
+            stages
+                .end_time()
+                .map(|t| format_local_time(t, stages.last_point(), "%a %H:%M")),
+        );
+        row(
+            "Distance",
+            Some(format_distance(units, stages.distance_km())),
+        );
+        row("Elapsed Time", stages.duration().map(format_duration));
+        row(
+            "Moving Time",
+            stages.total_moving_time().map(format_duration),
+        );
+        row(
+            "Control Time",
+            stages.total_control_time().map(format_duration),
+        );
+        row(
+            "Avg Overall Speed",
+            stages
+                .average_overall_speed()
+                .map(|s| format_speed(units, s)),
+        );
+        row(
+            "Avg Moving Speed",
+            stages
+                .average_moving_speed()
+                .map(|s| format_speed(units, s)),
+        );
+        row(
+            "Max Speed",
+            stages
+                .max_speed()
+                .and_then(|p| p.speed_kmh)
+                .map(|s| format_speed(units, s)),
+        );
+        row(
+            "Ascent",
+            stages
+                .total_ascent_metres()
+                .map(|m| format_height(units, m)),
+        );
+        row(
+            "Descent",
+            stages
+                .total_descent_metres()
+                .map(|m| format_height(units, m)),
+        );
+        row(
+            "Max Elevation",
+            stages
+                .max_elevation()
+                .and_then(|p| p.ele)
+                .map(|m| format_height(units, m)),
+        );
+        row(
+            "Avg Heart Rate",
+            gpx.avg_heart_rate().map(|hr| format!("{hr:.0} bpm")),
+        );
+        row(
+            "Avg Temp",
+            gpx.avg_temperature()
+                .map(|t| format!("{:.1}{}", units.temperature(t), units.temperature_unit())),
+        );
+        let num_controls = stages
+            .iter()
+            .filter(|s| s.stage_type == StageType::Control)
+            .count();
+        row("Controls", Some(num_controls.to_string()));
+    }
+
+    html.push_str("</table></div>\n");
+}
+
+/// Writes the table of stages, with the control stops highlighted.
+fn write_stages(html: &mut String, units: UnitSystem, stages: &StageList) {
+    if stages.is_empty() {
+        return;
+    }
+
+    html.push_str("<h2>Stages</h2>\n<div class=\"table-wrapper\"><table>\n<tr>");
+    for heading in [
+        "Stage",
+        "Type",
+        "Location",
+        "Start",
+        "Duration",
+        &format!("Distance ({})", units.distance_unit()),
+        &format!("Running ({})", units.distance_unit()),
+        &format!("Avg Speed ({})", units.speed_unit()),
+        &format!("Ascent ({})", units.height_unit()),
+        &format!("Descent ({})", units.height_unit()),
+    ] {
+        html.push_str(&format!("<th>{}</th>", escape(heading)));
+    }
+    html.push_str("</tr>\n");
+
+    for (idx, stage) in stages.iter().enumerate() {
+        let class = match stage.stage_type {
+            StageType::Moving => "moving",
+            StageType::Control => "control",
+        };
+
+        let cells = [
+            (false, (idx + 1).to_string()),
+            (false, stage.stage_type.to_string()),
+            (false, stage.reverse_geocode().unwrap_or_default()),
+            (
+                false,
+                stage
+                    .start
+                    .start_time()
+                    .map(|t| format_local_time(t, &stage.start, "%a %H:%M"))
+                    .unwrap_or_default(),
+            ),
+            (
+                true,
+                stage.duration().map(format_duration).unwrap_or_default(),
+            ),
+            (true, format!("{:.2}", units.distance(stage.distance_km()))),
+            (
+                true,
+                format!("{:.2}", units.distance(stage.running_distance_km())),
+            ),
+            (
+                true,
+                stage
+                    .average_speed_kmh()
+                    .map(|s| format!("{:.1}", units.speed(s)))
+                    .unwrap_or_default(),
+            ),
+            (
+                true,
+                stage
+                    .ascent_metres()
+                    .map(|m| format!("{:.0}", units.height(m)))
+                    .unwrap_or_default(),
+            ),
+            (
+                true,
+                stage
+                    .descent_metres()
+                    .map(|m| format!("{:.0}", units.height(m)))
+                    .unwrap_or_default(),
+            ),
+        ];
+
+        html.push_str(&format!("<tr class=\"{class}\">"));
+        for (numeric, value) in cells {
+            let class = if numeric { " class=\"num\"" } else { "" };
+            html.push_str(&format!("<td{class}>{}</td>", escape(&value)));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</table></div>\n");
+}
+
+/// Draws speed against distance. Each control is marked with a numbered
+/// vertical line, as on the elevation profile.
+fn speed_chart_svg(
+    units: UnitSystem,
+    points: &[&EnrichedTrackPoint],
+    stages: &StageList,
+) -> String {
+    let area = PlotArea::for_chart(CHART_WIDTH, CHART_HEIGHT);
+    let series: Vec<_> = points
+        .iter()
+        .filter_map(|p| {
+            p.speed_kmh
+                .map(|s| (units.distance(p.running_metres / 1000.0), units.speed(s)))
+        })
+        .collect();
+
+    let max_distance = series.last().map(|(d, _)| *d).unwrap_or_default();
+    let max_speed = series.iter().map(|(_, s)| *s).fold(0.0, f64::max);
+    let x_scale = area.x_scale((0.0, max_distance));
+    let y_scale = area.y_scale((0.0, max_speed * 1.05));
+
+    let mut svg = SvgBuilder::new(CHART_WIDTH, CHART_HEIGHT);
+    draw_axes(
+        &mut svg,
+        &area,
+        &x_scale,
+        &y_scale,
+        &format!("Distance ({})", units.distance_unit()),
+        &format!("Speed ({})", units.speed_unit()),
+    );
+
+    let line: Vec<_> = series
+        .iter()
+        .map(|(x, y)| (x_scale.map(*x), y_scale.map(*y)))
+        .collect();
+    svg.polyline(&line, TRACK_COLOUR, 1.5);
+
+    draw_control_markers(&mut svg, &area, &x_scale, units, stages);
+
+    svg.finish()
+}
+
+/// Formats a time in the timezone of 'point'. If the timezone cannot be
+/// determined the time is shown in UTC.
+fn format_local_time(utc_date: DateTime<Utc>, point: &EnrichedTrackPoint, fmt: &str) -> String {
+    match utc_to_appropriate_timezone(utc_date, point.as_rtree_point()) {
+        Ok(local_date) => local_date.format(fmt).to_string(),
+        Err(_) => format!("{} UTC", utc_date.format(fmt)),
+    }
+}
+
+/// Formats a duration as "h:mm:ss". Rides can last for days, so the hours are
+/// not wrapped at 24.
+fn format_duration(duration: TimeDelta) -> String {
+    let secs = duration.num_seconds().max(0);
+    format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+fn format_distance(units: UnitSystem, km: f64) -> String {
+    format!("{:.1} {}", units.distance(km), units.distance_unit())
+}
+
+fn format_speed(units: UnitSystem, kmh: f64) -> String {
+    format!("{:.1} {}", units.speed(kmh), units.speed_unit())
+}
+
+fn format_height(units: UnitSystem, metres: f64) -> String {
+    format!("{:.0} {}", units.height(metres), units.height_unit())
+}
+
+#[cfg(test)]
+mod tests {
+    use gapix_model::model::{Gpx, Track, Waypoint};
+
+    use super::*;
+
+    #[test]
+    fn format_duration_does_not_wrap_at_24_hours() {
+        assert_eq!(format_duration(TimeDelta::seconds(59)), "0:00:59");
+        assert_eq!(
+            format_duration(TimeDelta::seconds(3 * 3600 + 5 * 60 + 7)),
+            "3:05:07"
+        );
+        assert_eq!(format_duration(TimeDelta::seconds(27 * 3600)), "27:00:00");
+    }
+
+    #[test]
+    fn report_for_empty_ride_is_still_valid_html() {
+        let gpx = EnrichedGpx {
+            declaration: Default::default(),
+            filename: None,
+            version: "1.1".to_string(),
+            creator: "test".to_string(),
+            attributes: Default::default(),
+            metadata: Default::default(),
+            track_name: Some("Audax <200>".to_string()),
+            track_type: None,
+            waypoints: Vec::new(),
+            points: Vec::new(),
+        };
+
+        let html = create_html_report(UnitSystem::Metric, &gpx, &StageList::default()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Audax &lt;200&gt;</title>"));
+        assert!(html.contains("No stages detected"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn report_without_elevation_leaves_out_the_profile() {
+        // The map labels places, which needs the geocoding options to be set.
+        #[cfg(feature = "geocoding")]
+        crate::geocoding::initialise_geocoding_for_tests();
+
+        let points: Vec<_> = (0..100)
+            .map(|i| {
+                Waypoint::builder(53.0 + i as f64 * 0.0001, -2.5)
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let gpx = Gpx::builder()
+            .track(Track::builder().points(points).build())
+            .build();
+        let gpx = EnrichedGpx::try_from(&gpx).unwrap();
+
+        let html = create_html_report(UnitSystem::Metric, &gpx, &StageList::default()).unwrap();
+        assert!(html.contains("<h2>Map</h2>"));
+        assert!(!html.contains("<h2>Elevation</h2>"));
+    }
+}
@@ -0,0 +1,494 @@
+//! Serialises the analysis of a ride to JSON so that other tools (for example
+//! a web dashboard) can consume it without having to parse the xlsx.
+//!
+//! The structs in this module *are* the schema: they are deliberately separate
+//! from the model so that the model can change without breaking consumers.
+//! Any incompatible change to them must increment [JSON_SCHEMA_VERSION].
+//!
+//! Conventions:
+//! - All values are metric, regardless of the units chosen for the other
+//!   outputs: distances and elevations in metres, speeds in km/h, temperatures
+//!   in °C and durations in (fractional) seconds.
+//! - Times are RFC 3339 strings in UTC, such as "2024-09-01T05:10:44Z".
+//! - Values that are not available (e.g. heart rate when there was no heart
+//!   rate monitor) are written as `null`; fields are never omitted, except
+//!   `points` which is only present if asked for.
+//! - Trackpoint indexes are 0-based, matching the "Track Points" sheet.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use chrono::TimeDelta;
+use log::info;
+use serde::Serialize;
+
+use crate::{
+    byte_counter::ByteCounter,
+    dates::format_utc_date,
+    error::GapixError,
+    model::{EnrichedGpx, EnrichedTrackPoint},
+    stage::{Stage, StageList, StageType},
+};
+
+/// The version of the JSON schema. This is incremented whenever a field is
+/// removed, renamed or changes meaning. Adding a field is not considered an
+/// incompatible change.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Options that control the contents of the JSON summary.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonOptions {
+    /// Whether to include every trackpoint, with its derived data. This makes
+    /// the output much larger.
+    pub include_points: bool,
+}
+
+/// The top-level JSON document.
+#[derive(Debug, Serialize)]
+pub struct RideJson {
+    pub schema_version: u32,
+    /// The program that wrote the file, e.g. "gapix 3.0.0".
+    pub generator: String,
+    pub ride: RideInfoJson,
+    pub summary: SummaryJson,
+    pub stages: Vec<StageJson>,
+    pub waypoints: Vec<WaypointJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub points: Option<Vec<PointJson>>,
+}
+
+/// Identifies the ride.
+#[derive(Debug, Serialize)]
+pub struct RideInfoJson {
+    /// The track name, else the metadata name, else the file name.
+    pub name: String,
+    pub filename: Option<String>,
+    /// The device or program that recorded the ride.
+    pub creator: String,
+    pub track_type: Option<String>,
+}
+
+/// The ride totals, as on the "Summary" sheet.
+#[derive(Debug, Default, Serialize)]
+pub struct SummaryJson {
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub distance_metres: f64,
+    pub elapsed_seconds: Option<f64>,
+    pub moving_seconds: Option<f64>,
+    pub control_seconds: Option<f64>,
+    /// The fraction (0..1) of the elapsed time spent moving.
+    pub moving_fraction: Option<f64>,
+    pub average_overall_speed_kmh: Option<f64>,
+    pub average_moving_speed_kmh: Option<f64>,
+    pub max_speed_kmh: Option<f64>,
+    pub ascent_metres: Option<f64>,
+    pub descent_metres: Option<f64>,
+    pub min_elevation_metres: Option<f64>,
+    pub max_elevation_metres: Option<f64>,
+    pub average_heart_rate_bpm: Option<f64>,
+    pub max_heart_rate_bpm: Option<u8>,
+    pub average_temperature_celsius: Option<f64>,
+    pub min_temperature_celsius: Option<f64>,
+    pub max_temperature_celsius: Option<f64>,
+    pub stage_count: usize,
+    pub control_count: usize,
+    pub point_count: usize,
+}
+
+/// The type of a stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageTypeJson {
+    Moving,
+    Control,
+}
+
+impl From<StageType> for StageTypeJson {
+    fn from(value: StageType) -> Self {
+        match value {
+            StageType::Moving => StageTypeJson::Moving,
+            StageType::Control => StageTypeJson::Control,
+        }
+    }
+}
+
+/// A single stage, as on the "Stages" sheet.
+#[derive(Debug, Serialize)]
+pub struct StageJson {
+    /// The 1-based stage number.
+    pub number: usize,
+    pub stage_type: StageTypeJson,
+    /// The reverse-geocoded location: "Place" for a control, "Place1 to
+    /// Place2" for a moving stage. Null if geocoding is disabled.
+    pub location: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub start_point_index: usize,
+    pub end_point_index: usize,
+    pub start_lat: f64,
+    pub start_lon: f64,
+    /// The distance from the start of the ride to the start of the stage.
+    pub start_metres: f64,
+    pub distance_metres: f64,
+    pub average_speed_kmh: Option<f64>,
+    pub max_speed_kmh: Option<f64>,
+    pub ascent_metres: Option<f64>,
+    pub descent_metres: Option<f64>,
+    pub min_elevation_metres: Option<f64>,
+    pub max_elevation_metres: Option<f64>,
+    pub average_heart_rate_bpm: Option<f64>,
+    pub max_heart_rate_bpm: Option<u8>,
+    pub average_temperature_celsius: Option<f64>,
+}
+
+/// A file-level waypoint, matched to the nearest point on the track.
+#[derive(Debug, Serialize)]
+pub struct WaypointJson {
+    pub name: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub nearest_point_index: Option<usize>,
+    /// The distance from the start of the ride to the nearest point.
+    pub nearest_point_metres: Option<f64>,
+    /// How far the waypoint is from the track.
+    pub distance_from_track_metres: Option<f64>,
+}
+
+/// A single trackpoint with its derived data.
+#[derive(Debug, Serialize)]
+pub struct PointJson {
+    pub index: usize,
+    pub time: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub elevation_metres: Option<f64>,
+    /// The distance from the start of the ride.
+    pub running_metres: f64,
+    pub speed_kmh: Option<f64>,
+    pub running_ascent_metres: Option<f64>,
+    pub running_descent_metres: Option<f64>,
+    pub heart_rate_bpm: Option<u8>,
+    pub temperature_celsius: Option<f64>,
+    pub cadence_rpm: Option<u8>,
+    pub power_watts: Option<u16>,
+    /// The 1-based number of the stage the point is in.
+    pub stage: Option<usize>,
+}
+
+/// Builds the JSON document for a ride.
+pub fn create_json_summary(
+    options: &JsonOptions,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+) -> RideJson {
+    let ride = RideInfoJson {
+        name: gpx.ride_name(),
+        filename: gpx
+            .filename
+            .as_ref()
+            .and_then(|f| f.file_name())
+            .map(|f| f.to_string_lossy().into_owned()),
+        creator: gpx.creator.clone(),
+        track_type: gpx.track_type.clone(),
+    };
+
+    let stage_json = stages
+        .iter()
+        .enumerate()
+        .map(|(idx, stage)| stage_to_json(idx + 1, stage))
+        .collect();
+
+    let waypoints = gpx
+        .waypoints
+        .iter()
+        .map(|wp| {
+            let nearest = gpx.nearest_point(wp);
+            WaypointJson {
+                name: wp.name.clone(),
+                lat: wp.lat.value(),
+                lon: wp.lon.value(),
+                nearest_point_index: nearest.map(|(p, _)| p.index),
+                nearest_point_metres: nearest.map(|(p, _)| p.running_metres),
+                distance_from_track_metres: nearest.map(|(_, d)| d),
+            }
+        })
+        .collect();
+
+    let points = options.include_points.then(|| {
+        gpx.points
+            .iter()
+            .map(|p| point_to_json(p, stages))
+            .collect()
+    });
+
+    RideJson {
+        schema_version: JSON_SCHEMA_VERSION,
+        generator: format!("gapix {}", env!("CARGO_PKG_VERSION")),
+        ride,
+        summary: summary_to_json(gpx, stages),
+        stages: stage_json,
+        waypoints,
+        points,
+    }
+}
+
+/// Writes the JSON document to a file. The file will be overwritten if it
+/// already exists.
+pub fn write_json_summary_to_file<P: AsRef<Path>>(
+    filename: P,
+    ride: &RideJson,
+) -> Result<(), GapixError> {
+    let filename = filename.as_ref();
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(err) => {
+            return Err(GapixError::CreateFile {
+                path: filename.to_owned(),
+                source: err,
+            })
+        }
+    };
+
+    let mut writer = ByteCounter::new(BufWriter::new(file));
+    serde_json::to_writer_pretty(&mut writer, ride)?;
+    writer.flush()?;
+
+    info!(
+        "JSON summary {:?}, {} Kb",
+        &filename,
+        writer.bytes_written() / 1024
+    );
+    Ok(())
+}
+
+fn summary_to_json(gpx: &EnrichedGpx, stages: &StageList) -> SummaryJson {
+    let mut summary = SummaryJson {
+        average_heart_rate_bpm: gpx.avg_heart_rate(),
+        average_temperature_celsius: gpx.avg_temperature(),
+        point_count: gpx.points.len(),
+        ..Default::default()
+    };
+
+    if stages.is_empty() {
+        return summary;
+    }
+
+    summary.start_time = stages.start_time().as_ref().map(format_utc_date);
+    summary.end_time = stages.end_time().as_ref().map(format_utc_date);
+    summary.distance_metres = stages.distance_metres();
+    summary.elapsed_seconds = stages.duration().map(seconds);
+    summary.moving_seconds = stages.total_moving_time().map(seconds);
+    summary.control_seconds = stages.total_control_time().map(seconds);
+    summary.moving_fraction = stages.moving_percent();
+    summary.average_overall_speed_kmh = stages.average_overall_speed();
+    summary.average_moving_speed_kmh = stages.average_moving_speed();
+    summary.max_speed_kmh = stages.max_speed().and_then(|p| p.speed_kmh);
+    summary.ascent_metres = stages.total_ascent_metres();
+    summary.descent_metres = stages.total_descent_metres();
+    summary.min_elevation_metres = stages.min_elevation().and_then(|p| p.ele);
+    summary.max_elevation_metres = stages.max_elevation().and_then(|p| p.ele);
+    summary.max_heart_rate_bpm = stages.max_heart_rate().and_then(|p| p.heart_rate());
+    summary.min_temperature_celsius = stages.min_temperature().and_then(|p| p.air_temp());
+    summary.max_temperature_celsius = stages.max_temperature().and_then(|p| p.air_temp());
+    summary.stage_count = stages.len();
+    summary.control_count = stages
+        .iter()
+        .filter(|s| s.stage_type == StageType::Control)
+        .count();
+
+    summary
+}
+
+fn stage_to_json(number: usize, stage: &Stage) -> StageJson {
+    StageJson {
+        number,
+        stage_type: stage.stage_type.into(),
+        location: stage.reverse_geocode(),
+        start_time: stage.start.start_time().as_ref().map(format_utc_date),
+        end_time: stage.end.time.as_ref().map(format_utc_date),
+        duration_seconds: stage.duration().map(seconds),
+        start_point_index: stage.start.index,
+        end_point_index: stage.end.index,
+        start_lat: stage.start.lat,
+        start_lon: stage.start.lon,
+        start_metres: stage.start.running_metres,
+        distance_metres: stage.distance_metres(),
+        average_speed_kmh: stage.average_speed_kmh(),
+        max_speed_kmh: stage.max_speed.as_ref().and_then(|p| p.speed_kmh),
+        ascent_metres: stage.ascent_metres(),
+        descent_metres: stage.descent_metres(),
+        min_elevation_metres: stage.min_elevation.as_ref().and_then(|p| p.ele),
+        max_elevation_metres: stage.max_elevation.as_ref().and_then(|p| p.ele),
+        average_heart_rate_bpm: stage.avg_heart_rate,
+        max_heart_rate_bpm: stage.max_heart_rate.as_ref().and_then(|p| p.heart_rate()),
+        average_temperature_celsius: stage.avg_air_temp,
+    }
+}
+
+fn point_to_json(point: &EnrichedTrackPoint, stages: &StageList) -> PointJson {
+    let stage = stages
+        .iter()
+        .position(|s| s.start.index <= point.index && point.index <= s.end.index)
+        .map(|idx| idx + 1);
+
+    PointJson {
+        index: point.index,
+        time: point.time.as_ref().map(format_utc_date),
+        lat: point.lat,
+        lon: point.lon,
+        elevation_metres: point.ele,
+        running_metres: point.running_metres,
+        speed_kmh: point.speed_kmh,
+        running_ascent_metres: point.running_ascent_metres,
+        running_descent_metres: point.running_descent_metres,
+        heart_rate_bpm: point.heart_rate(),
+        temperature_celsius: point.air_temp(),
+        cadence_rpm: point.cadence(),
+        power_watts: point.power(),
+        stage,
+    }
+}
+
+fn seconds(duration: TimeDelta) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use gapix_model::model::{Gpx, Track, Waypoint};
+
+    use super::*;
+    use crate::stage::{detect_stages, StageDetectionParameters};
+
+    fn empty_gpx() -> EnrichedGpx {
+        EnrichedGpx {
+            declaration: Default::default(),
+            filename: Some("rides/audax.gpx".into()),
+            version: "1.1".to_string(),
+            creator: "Garmin".to_string(),
+            attributes: Default::default(),
+            metadata: Default::default(),
+            track_name: None,
+            track_type: Some("cycling".to_string()),
+            waypoints: Vec::new(),
+            points: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn empty_ride_has_schema_version_and_null_values() {
+        let ride =
+            create_json_summary(&JsonOptions::default(), &empty_gpx(), &StageList::default());
+        let value = serde_json::to_value(&ride).unwrap();
+
+        assert_eq!(value["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(value["ride"]["name"], "audax");
+        assert_eq!(value["ride"]["filename"], "audax.gpx");
+        assert_eq!(value["ride"]["track_type"], "cycling");
+        assert!(value["summary"]["start_time"].is_null());
+        assert_eq!(value["summary"]["stage_count"], 0);
+        assert_eq!(value["stages"], serde_json::json!([]));
+        // Points are only written when asked for.
+        assert!(value.get("points").is_none());
+    }
+
+    #[test]
+    fn points_are_included_when_requested() {
+        let options = JsonOptions {
+            include_points: true,
+        };
+        let ride = create_json_summary(&options, &empty_gpx(), &StageList::default());
+        let value = serde_json::to_value(&ride).unwrap();
+        assert_eq!(value["points"], serde_json::json!([]));
+    }
+
+    /// Makes a ride of 20 minutes, with a 10 minute stop in the middle, and
+    /// detects its stages: moving, control, moving.
+    fn ride_with_stages() -> (EnrichedGpx, StageList) {
+        // Stage locations need the geocoding options to have been set. Without
+        // a download folder geocoding is disabled, so the locations are null.
+        #[cfg(feature = "geocoding")]
+        crate::geocoding::initialise_geocoding_for_tests();
+
+        let start = DateTime::parse_from_rfc3339("2024-09-01T05:00:00Z")
+            .unwrap()
+            .to_utc();
+        let mut lat = 53.0;
+        let points: Vec<_> = (0..1200)
+            .map(|secs| {
+                if !(300..900).contains(&secs) {
+                    lat += 0.00005;
+                }
+                Waypoint::builder(lat, -2.5)
+                    .ele(100.0 + (secs % 60) as f64 / 10.0)
+                    .time(start + TimeDelta::seconds(secs))
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        let gpx = Gpx::builder()
+            .track(Track::builder().points(points).build())
+            .build();
+
+        let mut enriched = EnrichedGpx::try_from(&gpx).unwrap();
+        let params = StageDetectionParameters {
+            stopped_speed_kmh: 0.15,
+            min_metres_to_resume: 100.0,
+            min_duration_seconds: 300.0,
+        };
+        let stages = detect_stages(&mut enriched, params);
+        (enriched, stages)
+    }
+
+    #[test]
+    fn stages_and_summary_are_written() {
+        let (gpx, stages) = ride_with_stages();
+        let ride = create_json_summary(&JsonOptions::default(), &gpx, &stages);
+        let value = serde_json::to_value(&ride).unwrap();
+
+        let summary = &value["summary"];
+        assert_eq!(summary["start_time"], "2024-09-01T05:00:00Z");
+        assert_eq!(summary["end_time"], "2024-09-01T05:19:59Z");
+        assert_eq!(summary["elapsed_seconds"], 1199.0);
+        assert_eq!(summary["stage_count"], 3);
+        assert_eq!(summary["control_count"], 1);
+        assert_eq!(summary["point_count"], 1200);
+        assert_eq!(summary["distance_metres"], stages.distance_metres());
+        assert_eq!(summary["min_elevation_metres"], 100.0);
+        assert_eq!(summary["max_elevation_metres"], 105.9);
+        let moving = summary["moving_seconds"].as_f64().unwrap();
+        let control = summary["control_seconds"].as_f64().unwrap();
+        assert_eq!(moving + control, 1199.0);
+        assert!(summary["average_heart_rate_bpm"].is_null());
+
+        let json_stages = value["stages"].as_array().unwrap();
+        let types: Vec<_> = json_stages.iter().map(|s| &s["stage_type"]).collect();
+        assert_eq!(types, ["moving", "control", "moving"]);
+        for (idx, (json, stage)) in json_stages.iter().zip(stages.iter()).enumerate() {
+            assert_eq!(json["number"], idx + 1);
+            assert_eq!(json["start_point_index"], stage.start.index);
+            assert_eq!(json["end_point_index"], stage.end.index);
+            assert_eq!(json["start_metres"], stage.start.running_metres);
+            assert_eq!(json["distance_metres"], stage.distance_metres());
+            assert!(json["location"].is_null());
+        }
+        assert_eq!(json_stages[0]["start_time"], "2024-09-01T05:00:00Z");
+        assert_eq!(json_stages[0]["start_point_index"], 0);
+        assert_eq!(json_stages[2]["end_point_index"], 1199);
+        assert_eq!(json_stages[1]["duration_seconds"], control);
+    }
+
+    #[test]
+    fn stage_type_is_snake_case() {
+        assert_eq!(
+            serde_json::to_value(StageTypeJson::Control).unwrap(),
+            "control"
+        );
+    }
+}
@@ -1,11 +1,26 @@
-mod byte_counter;
 mod error;
+#[cfg(feature = "excel")]
 pub mod excel;
 pub mod dates;
+#[cfg(feature = "geocoding")]
 pub mod geocoding;
-pub mod gpx_writer;
+pub mod html;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod map;
 pub mod model;
 pub mod model_impls;
+pub mod profile;
 pub mod read;
+pub mod render;
 pub mod simplification;
 pub mod stage;
+mod svg;
+#[cfg(feature = "timezones")]
+pub mod timezones;
+pub mod units;
+
+// The GPX writer and validation live in the gapix_model crate along with the
+// model.
+pub use gapix_model::{gpx_writer, validation};
+use gapix_model::byte_counter;
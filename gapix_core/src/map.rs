@@ -0,0 +1,669 @@
+//! Draws a map of a ride as an image. No map tiles are used, so this works
+//! offline: the map is just the shape of the track, optionally coloured by
+//! speed, heart rate or stage type, with the start, finish, controls and
+//! waypoints marked, a scale bar, and optionally the names of nearby places
+//! from the geonames data used for reverse geocoding.
+
+use std::{f64::consts::PI, fmt, str::FromStr};
+
+#[cfg(feature = "geocoding")]
+use crate::geocoding::get_places_in_area;
+use crate::{
+    error::GapixError,
+    model::{EnrichedGpx, EnrichedTrackPoint},
+    model_impls::RTreePoint,
+    stage::{StageList, StageType},
+    svg::{
+        Anchor, SvgBuilder, CONTROL_COLOUR, FINISH_COLOUR, START_COLOUR, TRACK_COLOUR,
+        WAYPOINT_COLOUR,
+    },
+    units::UnitSystem,
+};
+
+/// Mean radius of the Earth, used to work out the scale of the map.
+const EARTH_RADIUS_METRES: f64 = 6_371_008.8;
+const MAX_MAP_POINTS: usize = 3000;
+const MARGIN: f64 = 25.0;
+const MIN_MAP_HEIGHT: f64 = 200.0;
+
+/// Places further than this from the track (in pixels) are not labelled.
+const MAX_PLACE_DISTANCE_PIXELS: f64 = 40.0;
+const MAX_PLACE_LABELS: usize = 40;
+const PLACE_FONT_SIZE: f64 = 10.0;
+const PLACE_COLOUR: &str = "#666666";
+
+const BACKGROUND_COLOUR: &str = "#f8f8f4";
+const TEXT_COLOUR: &str = "#222222";
+
+/// The colours used for speed and heart rate, from low to high.
+const SCALE_COLOURS: [&str; 8] = [
+    "#313695", "#4575b4", "#74add1", "#abd9e9", "#fee090", "#fdae61", "#f46d43", "#d73027",
+];
+
+/// How latitudes and longitudes are turned into x and y coordinates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapProjection {
+    /// A local equirectangular projection, scaled for the latitude of the
+    /// middle of the track. Accurate enough over the size of a ride.
+    #[default]
+    Equirectangular,
+    /// The projection used by most web maps, so the shape of the track matches
+    /// what you see in Google Maps, OpenStreetMap etc.
+    WebMercator,
+}
+
+impl fmt::Display for MapProjection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapProjection::Equirectangular => write!(f, "equirectangular"),
+            MapProjection::WebMercator => write!(f, "web-mercator"),
+        }
+    }
+}
+
+impl FromStr for MapProjection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "equirectangular" => Ok(MapProjection::Equirectangular),
+            "web-mercator" => Ok(MapProjection::WebMercator),
+            _ => Err(format!(
+                "unknown map projection '{s}', expected one of 'equirectangular' or 'web-mercator'"
+            )),
+        }
+    }
+}
+
+/// What the colour of the track represents.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapColouring {
+    /// The whole track is drawn in one colour.
+    Plain,
+    /// The track is coloured from blue (slow) to red (fast).
+    Speed,
+    /// The track is coloured from blue (low) to red (high) heart rate.
+    HeartRate,
+    /// Moving stages and controls are drawn in different colours.
+    #[default]
+    Stage,
+}
+
+impl fmt::Display for MapColouring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapColouring::Plain => write!(f, "plain"),
+            MapColouring::Speed => write!(f, "speed"),
+            MapColouring::HeartRate => write!(f, "heart-rate"),
+            MapColouring::Stage => write!(f, "stage"),
+        }
+    }
+}
+
+impl FromStr for MapColouring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "plain" => Ok(MapColouring::Plain),
+            "speed" => Ok(MapColouring::Speed),
+            "heart-rate" => Ok(MapColouring::HeartRate),
+            "stage" => Ok(MapColouring::Stage),
+            _ => Err(format!(
+                "unknown map colouring '{s}', expected one of 'plain', 'speed', 'heart-rate' or 'stage'"
+            )),
+        }
+    }
+}
+
+/// Options that control how the map is drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct MapOptions {
+    /// Used for the scale bar and the speed legend.
+    pub units: UnitSystem,
+    pub projection: MapProjection,
+    pub colouring: MapColouring,
+    /// Whether to label the places near the track. Requires the 'geocoding'
+    /// feature, and geocoding to have been initialised.
+    pub show_places: bool,
+    /// The width of the image, in pixels.
+    pub width: f64,
+    /// The maximum height of the image, in pixels. The actual height depends
+    /// on the shape of the track.
+    pub max_height: f64,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        Self {
+            units: Default::default(),
+            projection: Default::default(),
+            colouring: Default::default(),
+            show_places: false,
+            width: 1000.0,
+            max_height: 800.0,
+        }
+    }
+}
+
+/// Turns (lat, lon) into pixel coordinates, fitting the track into the image.
+struct Projector {
+    projection: MapProjection,
+    /// The cosine of the latitude of the middle of the track.
+    cos_mid_lat: f64,
+    /// The projected coordinates of the top-left corner of the track.
+    min_x: f64,
+    max_y: f64,
+    pixels_per_unit: f64,
+    x_offset: f64,
+    y_offset: f64,
+}
+
+impl Projector {
+    fn new(
+        projection: MapProjection,
+        points: &[&EnrichedTrackPoint],
+        width: f64,
+        max_height: f64,
+    ) -> (Self, f64) {
+        let min_lat = points.iter().map(|p| p.lat).fold(f64::MAX, f64::min);
+        let max_lat = points.iter().map(|p| p.lat).fold(f64::MIN, f64::max);
+        let mut projector = Self {
+            projection,
+            cos_mid_lat: ((min_lat + max_lat) / 2.0).to_radians().cos(),
+            min_x: 0.0,
+            max_y: 0.0,
+            pixels_per_unit: 1.0,
+            x_offset: 0.0,
+            y_offset: 0.0,
+        };
+
+        let projected: Vec<_> = points
+            .iter()
+            .map(|p| projector.project_unscaled(p.lat, p.lon))
+            .collect();
+        let min_x = projected.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+        let max_x = projected.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+        let min_y = projected.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+        let max_y = projected.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+        let x_extent = (max_x - min_x).max(1e-9);
+        let y_extent = (max_y - min_y).max(1e-9);
+
+        let drawable_width = width - 2.0 * MARGIN;
+        let pixels_per_unit =
+            (drawable_width / x_extent).min((max_height - 2.0 * MARGIN) / y_extent);
+        // Don't let an east-west track produce a sliver of an image, the
+        // legend needs some room.
+        let height = (y_extent * pixels_per_unit + 2.0 * MARGIN).clamp(MIN_MAP_HEIGHT, max_height);
+
+        projector.min_x = min_x;
+        projector.max_y = max_y;
+        projector.pixels_per_unit = pixels_per_unit;
+        projector.x_offset = (drawable_width - x_extent * pixels_per_unit) / 2.0 + MARGIN;
+        projector.y_offset = (height - y_extent * pixels_per_unit) / 2.0;
+
+        (projector, height)
+    }
+
+    /// Projects to coordinates in radians, with y increasing northwards.
+    fn project_unscaled(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let lat = lat.to_radians();
+        let lon = lon.to_radians();
+        match self.projection {
+            MapProjection::Equirectangular => (lon * self.cos_mid_lat, lat),
+            MapProjection::WebMercator => (lon, (PI / 4.0 + lat / 2.0).tan().ln()),
+        }
+    }
+
+    /// Projects to pixel coordinates.
+    fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let (x, y) = self.project_unscaled(lat, lon);
+        (
+            self.x_offset + (x - self.min_x) * self.pixels_per_unit,
+            self.y_offset + (self.max_y - y) * self.pixels_per_unit,
+        )
+    }
+
+    /// Returns the number of metres on the ground represented by one pixel,
+    /// in the middle of the track.
+    fn metres_per_pixel(&self) -> f64 {
+        let metres_per_unit = match self.projection {
+            MapProjection::Equirectangular => EARTH_RADIUS_METRES,
+            MapProjection::WebMercator => EARTH_RADIUS_METRES * self.cos_mid_lat,
+        };
+        metres_per_unit / self.pixels_per_unit
+    }
+}
+
+/// Creates a map of the ride as an SVG document.
+pub fn create_map_svg(
+    options: &MapOptions,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+) -> Result<String, GapixError> {
+    let points = gpx.sample_points(MAX_MAP_POINTS);
+    if points.is_empty() {
+        return Err(GapixError::NoTrackPoints);
+    }
+
+    let (projector, height) = Projector::new(
+        options.projection,
+        &points,
+        options.width,
+        options.max_height,
+    );
+    let mut svg = SvgBuilder::new(options.width, height);
+    svg.rect(0.0, 0.0, options.width, height, BACKGROUND_COLOUR, 1.0);
+
+    let track: Vec<_> = points
+        .iter()
+        .map(|p| projector.project(p.lat, p.lon))
+        .collect();
+
+    if options.show_places {
+        draw_places(&mut svg, &projector, &points, &track, options.width, height);
+    }
+
+    let legend = draw_track(&mut svg, options, &points, &track, stages);
+    draw_markers(&mut svg, &projector, gpx, stages, &points);
+    draw_scale_bar(&mut svg, &projector, options.units, height);
+    draw_legend(&mut svg, legend);
+
+    Ok(svg.finish())
+}
+
+/// What the track colours mean, for the legend.
+enum TrackLegend {
+    None,
+    Scale { label: String, min: f64, max: f64 },
+    Stage,
+}
+
+/// Draws the track. Consecutive segments of the same colour are drawn as one
+/// polyline, which keeps the file small.
+fn draw_track(
+    svg: &mut SvgBuilder,
+    options: &MapOptions,
+    points: &[&EnrichedTrackPoint],
+    track: &[(f64, f64)],
+    stages: &StageList,
+) -> TrackLegend {
+    let units = options.units;
+    let (colours, legend) = match options.colouring {
+        MapColouring::Plain => (vec![TRACK_COLOUR; points.len()], TrackLegend::None),
+        MapColouring::Speed => {
+            let speeds: Vec<_> = points
+                .iter()
+                .map(|p| p.speed_kmh.map(|s| units.speed(s)))
+                .collect();
+            scale_colours(&speeds, format!("Speed ({})", units.speed_unit()))
+        }
+        MapColouring::HeartRate => {
+            let rates: Vec<_> = points
+                .iter()
+                .map(|p| p.heart_rate().map(f64::from))
+                .collect();
+            scale_colours(&rates, "Heart Rate (bpm)".to_string())
+        }
+        MapColouring::Stage => {
+            let colours = points
+                .iter()
+                .map(|p| match stage_type_of(stages, p.index) {
+                    Some(StageType::Control) => CONTROL_COLOUR,
+                    _ => TRACK_COLOUR,
+                })
+                .collect();
+            (colours, TrackLegend::Stage)
+        }
+    };
+
+    // The segment from point i-1 to point i takes the colour of point i.
+    let mut run = vec![track[0]];
+    let mut run_colour = colours.get(1).copied().unwrap_or(TRACK_COLOUR);
+    for idx in 1..track.len() {
+        if colours[idx] != run_colour {
+            svg.polyline(&run, run_colour, 3.0);
+            run = vec![track[idx - 1]];
+            run_colour = colours[idx];
+        }
+        run.push(track[idx]);
+    }
+    svg.polyline(&run, run_colour, 3.0);
+
+    legend
+}
+
+/// Maps each value onto SCALE_COLOURS. The top of the scale is the 95th
+/// percentile rather than the maximum so that one GPS glitch doesn't turn the
+/// whole track blue. Points with no value are drawn in the plain track colour.
+fn scale_colours(values: &[Option<f64>], label: String) -> (Vec<&'static str>, TrackLegend) {
+    let mut sorted: Vec<_> = values.iter().flatten().copied().collect();
+    if sorted.is_empty() {
+        return (vec![TRACK_COLOUR; values.len()], TrackLegend::None);
+    }
+
+    sorted.sort_by(f64::total_cmp);
+    let min = sorted[sorted.len() * 5 / 100];
+    let max = sorted[sorted.len() * 95 / 100];
+
+    let colours = values
+        .iter()
+        .map(|v| match v {
+            Some(v) => SCALE_COLOURS[scale_bucket(*v, min, max)],
+            None => TRACK_COLOUR,
+        })
+        .collect();
+
+    (colours, TrackLegend::Scale { label, min, max })
+}
+
+fn scale_bucket(value: f64, min: f64, max: f64) -> usize {
+    if max <= min {
+        return SCALE_COLOURS.len() / 2;
+    }
+
+    let fraction = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    ((fraction * SCALE_COLOURS.len() as f64) as usize).min(SCALE_COLOURS.len() - 1)
+}
+
+/// Returns the type of the stage that the trackpoint with 'index' is in.
+fn stage_type_of(stages: &StageList, index: usize) -> Option<StageType> {
+    stages
+        .iter()
+        .find(|s| s.start.index <= index && index <= s.end.index)
+        .map(|s| s.stage_type)
+}
+
+fn draw_markers(
+    svg: &mut SvgBuilder,
+    projector: &Projector,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+    points: &[&EnrichedTrackPoint],
+) {
+    for wp in &gpx.waypoints {
+        let title = wp.name.clone().unwrap_or_else(|| "Waypoint".to_string());
+        svg.circle(
+            projector.project(wp.lat.value(), wp.lon.value()),
+            4.0,
+            WAYPOINT_COLOUR,
+            Some(&title),
+        );
+    }
+
+    for (idx, stage) in stages.iter().enumerate() {
+        if stage.stage_type == StageType::Control {
+            let mut title = format!("Stage {}: Control", idx + 1);
+            if let Some(location) = stage.reverse_geocode() {
+                title.push_str(&format!(" at {location}"));
+            }
+            let position = projector.project(stage.start.lat, stage.start.lon);
+            svg.circle(position, 6.0, CONTROL_COLOUR, Some(&title));
+            svg.text(
+                (position.0 + 8.0, position.1 - 6.0),
+                11.0,
+                Anchor::Start,
+                CONTROL_COLOUR,
+                &(idx + 1).to_string(),
+            );
+        }
+    }
+
+    // Draw the start last so that it is on top on circular routes.
+    let first = points[0];
+    let last = points[points.len() - 1];
+    svg.circle(
+        projector.project(last.lat, last.lon),
+        6.0,
+        FINISH_COLOUR,
+        Some("Finish"),
+    );
+    svg.circle(
+        projector.project(first.lat, first.lon),
+        6.0,
+        START_COLOUR,
+        Some("Start"),
+    );
+}
+
+/// Labels the places closest to the track, skipping any whose label would
+/// overlap one already drawn.
+fn draw_places(
+    svg: &mut SvgBuilder,
+    projector: &Projector,
+    points: &[&EnrichedTrackPoint],
+    track: &[(f64, f64)],
+    width: f64,
+    height: f64,
+) {
+    let min_lat = points.iter().map(|p| p.lat).fold(f64::MAX, f64::min);
+    let max_lat = points.iter().map(|p| p.lat).fold(f64::MIN, f64::max);
+    let min_lon = points.iter().map(|p| p.lon).fold(f64::MAX, f64::min);
+    let max_lon = points.iter().map(|p| p.lon).fold(f64::MIN, f64::max);
+
+    let mut candidates: Vec<_> = places_in_area([min_lat, min_lon], [max_lat, max_lon])
+        .into_iter()
+        .filter_map(|(lat, lon, name)| {
+            let position = projector.project(lat, lon);
+            let distance = track
+                .iter()
+                .map(|t| (t.0 - position.0).hypot(t.1 - position.1))
+                .fold(f64::MAX, f64::min);
+            (distance <= MAX_PLACE_DISTANCE_PIXELS).then_some((distance, position, name))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut labels: Vec<LabelBox> = Vec::new();
+    for (_, position, name) in candidates {
+        if labels.len() >= MAX_PLACE_LABELS {
+            break;
+        }
+
+        let label = LabelBox::new(position, name);
+        if label.right > width || label.top < 0.0 || label.bottom > height {
+            continue;
+        }
+        if labels.iter().any(|l| l.overlaps(&label)) {
+            continue;
+        }
+
+        svg.circle(position, 2.0, PLACE_COLOUR, None);
+        svg.text(
+            (label.left, label.bottom - 2.0),
+            PLACE_FONT_SIZE,
+            Anchor::Start,
+            PLACE_COLOUR,
+            name,
+        );
+        labels.push(label);
+    }
+}
+
+/// Returns the lat, lon and name of the places inside the rectangle with
+/// corners 'min' and 'max'.
+#[cfg(feature = "geocoding")]
+fn places_in_area(min: RTreePoint, max: RTreePoint) -> Vec<(f64, f64, &'static str)> {
+    get_places_in_area(min, max)
+        .into_iter()
+        .map(|place| (place.lat, place.lon, place.name.as_str()))
+        .collect()
+}
+
+/// Without the 'geocoding' feature there is no place data, so there is
+/// nothing to label.
+#[cfg(not(feature = "geocoding"))]
+fn places_in_area(_min: RTreePoint, _max: RTreePoint) -> Vec<(f64, f64, &'static str)> {
+    Vec::new()
+}
+
+/// The approximate area taken up by a place label, used to stop labels
+/// overlapping.
+struct LabelBox {
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
+
+impl LabelBox {
+    fn new(position: (f64, f64), text: &str) -> Self {
+        // There is no way of measuring text without the font, so assume an
+        // average character is a bit over half as wide as it is high.
+        let width = text.chars().count() as f64 * PLACE_FONT_SIZE * 0.6;
+        Self {
+            left: position.0 + 4.0,
+            top: position.1 - PLACE_FONT_SIZE,
+            right: position.0 + 4.0 + width,
+            bottom: position.1 + 2.0,
+        }
+    }
+
+    fn overlaps(&self, other: &LabelBox) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+}
+
+/// Draws a scale bar in the bottom-left corner, about a fifth of the width of
+/// the map long.
+fn draw_scale_bar(svg: &mut SvgBuilder, projector: &Projector, units: UnitSystem, height: f64) {
+    let metres_per_pixel = projector.metres_per_pixel();
+    let target_km = metres_per_pixel * 200.0 / 1000.0;
+    let target = units.distance(target_km);
+
+    let length = round_down(target);
+    let length_km = target_km * length / target;
+    let length_pixels = length_km * 1000.0 / metres_per_pixel;
+
+    let left = MARGIN;
+    let y = height - 10.0;
+    svg.line((left, y), (left + length_pixels, y), TEXT_COLOUR, 2.0);
+    svg.line((left, y - 5.0), (left, y), TEXT_COLOUR, 2.0);
+    svg.line(
+        (left + length_pixels, y - 5.0),
+        (left + length_pixels, y),
+        TEXT_COLOUR,
+        2.0,
+    );
+    svg.text(
+        (left + length_pixels + 6.0, y),
+        11.0,
+        Anchor::Start,
+        TEXT_COLOUR,
+        &format!("{length} {}", units.distance_unit()),
+    );
+}
+
+/// Rounds 'value' down to 1, 2 or 5 times a power of 10.
+fn round_down(value: f64) -> f64 {
+    let magnitude = 10f64.powf(value.log10().floor());
+    [5.0, 2.0, 1.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|v| *v <= value)
+        .unwrap_or(magnitude)
+}
+
+/// Draws the legend in the top-left corner: the markers, then what the track
+/// colours mean.
+fn draw_legend(svg: &mut SvgBuilder, legend: TrackLegend) {
+    let mut y = 14.0;
+    let x = 8.0;
+
+    for (colour, label) in [
+        (START_COLOUR, "Start"),
+        (FINISH_COLOUR, "Finish"),
+        (CONTROL_COLOUR, "Control"),
+        (WAYPOINT_COLOUR, "Waypoint"),
+    ] {
+        svg.circle((x + 5.0, y - 4.0), 4.0, colour, None);
+        svg.text((x + 14.0, y), 10.0, Anchor::Start, TEXT_COLOUR, label);
+        y += 14.0;
+    }
+
+    match legend {
+        TrackLegend::None => {}
+        TrackLegend::Stage => {
+            for (colour, label) in [(TRACK_COLOUR, "Moving"), (CONTROL_COLOUR, "Controlling")] {
+                svg.line((x, y - 4.0), (x + 10.0, y - 4.0), colour, 3.0);
+                svg.text((x + 14.0, y), 10.0, Anchor::Start, TEXT_COLOUR, label);
+                y += 14.0;
+            }
+        }
+        TrackLegend::Scale { label, min, max } => {
+            svg.text((x, y), 10.0, Anchor::Start, TEXT_COLOUR, &label);
+            y += 4.0;
+            for (idx, colour) in SCALE_COLOURS.iter().enumerate() {
+                svg.rect(x + idx as f64 * 12.0, y, 12.0, 8.0, colour, 1.0);
+            }
+            y += 19.0;
+            svg.text(
+                (x, y),
+                10.0,
+                Anchor::Start,
+                TEXT_COLOUR,
+                &format!("{min:.0}"),
+            );
+            let right = x + SCALE_COLOURS.len() as f64 * 12.0;
+            svg.text(
+                (right, y),
+                10.0,
+                Anchor::End,
+                TEXT_COLOUR,
+                &format!("{max:.0}"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_and_colouring_parse_and_display_round_trip() {
+        for p in [MapProjection::Equirectangular, MapProjection::WebMercator] {
+            assert_eq!(p.to_string().parse::<MapProjection>().unwrap(), p);
+        }
+        for c in [
+            MapColouring::Plain,
+            MapColouring::Speed,
+            MapColouring::HeartRate,
+            MapColouring::Stage,
+        ] {
+            assert_eq!(c.to_string().parse::<MapColouring>().unwrap(), c);
+        }
+        assert!("mollweide".parse::<MapProjection>().is_err());
+        assert!("cadence".parse::<MapColouring>().is_err());
+    }
+
+    #[test]
+    fn scale_bucket_clamps_to_range() {
+        assert_eq!(scale_bucket(-10.0, 0.0, 40.0), 0);
+        assert_eq!(scale_bucket(0.0, 0.0, 40.0), 0);
+        assert_eq!(scale_bucket(20.0, 0.0, 40.0), 4);
+        assert_eq!(scale_bucket(40.0, 0.0, 40.0), SCALE_COLOURS.len() - 1);
+        assert_eq!(scale_bucket(100.0, 0.0, 40.0), SCALE_COLOURS.len() - 1);
+        assert_eq!(scale_bucket(5.0, 5.0, 5.0), SCALE_COLOURS.len() / 2);
+    }
+
+    #[test]
+    fn round_down_gives_round_numbers() {
+        assert_eq!(round_down(7.3), 5.0);
+        assert_eq!(round_down(19.0), 10.0);
+        assert_eq!(round_down(2.0), 2.0);
+        assert_eq!(round_down(0.31), 0.2);
+    }
+
+    #[test]
+    fn label_boxes_overlap() {
+        let a = LabelBox::new((0.0, 20.0), "Chester");
+        let b = LabelBox::new((10.0, 22.0), "Wrexham");
+        let c = LabelBox::new((10.0, 60.0), "Wrexham");
+        assert!(a.overlaps(&b));
+        assert!(!a.overlaps(&c));
+    }
+}
@@ -1,199 +1,47 @@
 use chrono::{DateTime, TimeDelta, Utc};
 use geo::{point, Point};
-use log::debug;
 use logging_timer::time;
 
 use crate::{
     error::GapixError,
-    geocoding::RTreePoint,
-    model::{
-        Bounds, Email, EnrichedGpx, EnrichedTrackPoint, Extensions, FixType, Gpx, Link, Metadata,
-        Waypoint, XmlDeclaration,
-    },
+    model::{EnrichedGpx, EnrichedTrackPoint, Gpx, Waypoint},
     stage::{distance_between_points_metres, speed_kmh_from_duration},
 };
 
-/// Checks to see whether a valid is in the valid range for a degrees value
-/// 0.0..=360.0, and if so returns the value else returns an error.
-pub fn validate_degrees(value: f64) -> Result<f64, GapixError> {
-    if !(0.0_f64..=360.0).contains(&value) {
-        Err(GapixError::InvalidDegrees(value))
-    } else {
-        Ok(value)
-    }
-}
-
-/// Checks to see whether a valid is in the valid range for a 'dgpsStationType'
-/// field of 0..=1023, and if so returns the value else returns an error.
-pub fn validate_dgps_station_id(value: i64) -> Result<u16, GapixError> {
-    if !(0_i64..=1023).contains(&value) {
-        Err(GapixError::InvalidDGPSStationId(value))
-    } else {
-        // SAFETY: 0..=1023 will fit into a u16.
-        Ok(value as u16)
-    }
-}
-
-/// Checks to see whether a valid is in the valid range for a latitude value of
-/// -90.0..90.0, and if so returns the value else returns an error.
-pub fn validate_latitude(value: f64) -> Result<f64, GapixError> {
-    if !(-90.0_f64..=90.0).contains(&value) {
-        Err(GapixError::InvalidLatitude(value))
-    } else {
-        Ok(value)
-    }
-}
-
-/// Checks to see whether a valid is in the valid range for a longitude value of
-/// -180.0..180.0, and if so returns the value else returns an error.
-pub fn validate_longitude(value: f64) -> Result<f64, GapixError> {
-    if !(-180.0_f64..=180.0).contains(&value) {
-        Err(GapixError::InvalidLongitude(value))
-    } else {
-        Ok(value)
-    }
-}
-
-impl Default for Gpx {
-    /// Creates a new Gpx with 'gapix' as the creator.
-    fn default() -> Self {
-        let mut gpx = Self::new(XmlDeclaration::default(), Metadata::default());
-        gpx.creator = "gapix".into();
-        gpx
-    }
-}
-
-impl Gpx {
-    /// Creates a new Gpx from the mandatory fields.
-    pub fn new(declaration: XmlDeclaration, metadata: Metadata) -> Self {
-        Self {
-            declaration,
-            filename: Default::default(),
-            version: "1.1".to_string(),
-            creator: Default::default(),
-            attributes: Default::default(),
-            metadata,
-            waypoints: Default::default(),
-            routes: Default::default(),
-            tracks: Default::default(),
-            extensions: Default::default(),
-        }
-    }
-
-    pub fn set_default_garmin_attributes(&mut self) {
-        self.attributes.insert(
-            "xsi:schemaLocation".to_string(),
-            "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/11.xsd".to_string(),
-        );
-
-        self.attributes.insert(
-            "xmlns:ns3".to_string(),
-            "http://www.garmin.com/xmlschemas/TrackPointExtension/v1".to_string(),
-        );
-
-        self.attributes.insert(
-            "xmlns".to_string(),
-            "http://www.topografix.com/GPX/1/1".to_string(),
-        );
-
-        self.attributes.insert(
-            "xmlns:xsi".to_string(),
-            "http://www.w3.org/2001/XMLSchema-instance".to_string(),
-        );
-
-        self.attributes.insert(
-            "xmlns:ns2".to_string(),
-            "http://www.garmin.com/xmlschemas/GpxExtensions/v3".to_string(),
-        );
-    }
-
-    /// Returns the total number of points across all tracks and segments.
-    pub fn num_points(&self) -> usize {
-        self.tracks
-            .iter()
-            .map(|track| {
-                track
-                    .segments
-                    .iter()
-                    .map(|segment| segment.points.len())
-                    .sum::<usize>()
-            })
-            .sum()
-    }
-
-    /// Returns true if the GPX consists of a single track with one segment.
-    pub fn is_single_track(&self) -> bool {
-        self.tracks.len() == 1 && self.tracks[0].segments.len() == 1
-    }
-
-    /// Merges all the tracks and segments within the GPX into a single track
-    /// with one segment containing all the points. The name and type of the
-    /// first track in `self` is used to name the new track. If the GPX is
-    /// already in single track form then self is simply returned as-is (this is
-    /// a cheap operation in that case).
-    pub fn into_single_track(mut self) -> Self {
-        if self.is_single_track() {
-            return self;
-        }
-
-        let mut points = Vec::with_capacity(self.num_points());
-
-        // This copies the first track as well, which may seem a bit inefficient,
-        // but the obvious optimisation of moving all but the first track doesn't
-        // work because that track may have multiple segments. This function is
-        // only called once and the simpler code wins out over the fix for that
-        // problem.
-        let mut track_count = 0;
-        let mut segment_count = 0;
-        let mut point_count = 0;
-
-        for src_track in self.tracks.iter_mut() {
-            track_count += 1;
-
-            for src_segment in src_track.segments.iter_mut() {
-                segment_count += 1;
-                point_count += src_segment.points.len();
-                points.append(&mut src_segment.points);
-            }
-        }
-
-        self.tracks.truncate(1);
-        self.tracks.shrink_to_fit();
-        self.tracks[0].segments.truncate(1);
-        self.tracks[0].segments.shrink_to_fit();
-        self.tracks[0].segments[0].points = points;
+/// A (lat, lon) pair. This is the point type used in the RTree of places for
+/// reverse-geocoding, and for looking up timezones.
+pub(crate) type RTreePoint = [f64; 2];
 
-        debug!(
-            "Merged {} tracks with {} segments and {} points into a single track",
-            track_count, segment_count, point_count,
-        );
+// The validation functions live in the gapix_model crate along with the model.
+pub use gapix_model::model_impls::*;
 
-        self
-    }
+impl TryFrom<&Gpx> for EnrichedGpx {
+    type Error = GapixError;
 
     /// Makes an EnrichedGpx from the Gpx. Each of the new trackpoints will have
     /// derived data calculated where possible. An error is returned if the Gpx
     /// is not in single-track form.
-    pub fn to_enriched_gpx(&self) -> Result<EnrichedGpx, GapixError> {
-        if !self.is_single_track() {
+    fn try_from(gpx: &Gpx) -> Result<Self, Self::Error> {
+        if !gpx.is_single_track() {
             return Err(GapixError::MultipleTracksFound);
         }
 
         let mut egpx = EnrichedGpx {
-            filename: self.filename.clone(),
-            declaration: self.declaration.clone(),
-            metadata: self.metadata.clone(),
-            track_name: self.tracks[0].name.clone(),
-            track_type: self.tracks[0].r#type.clone(),
-            points: self.tracks[0].segments[0]
+            filename: gpx.filename.clone(),
+            declaration: gpx.declaration.clone(),
+            metadata: gpx.metadata.clone(),
+            track_name: gpx.tracks[0].name.clone(),
+            track_type: gpx.tracks[0].r#type.clone(),
+            waypoints: gpx.waypoints.clone(),
+            points: gpx.tracks[0].segments[0]
                 .points
                 .iter()
                 .enumerate()
                 .map(|(idx, tp)| EnrichedTrackPoint::new(idx, tp))
                 .collect(),
-            version: self.version.clone(),
-            creator: self.creator.clone(),
-            attributes: self.attributes.clone(),
+            version: gpx.version.clone(),
+            creator: gpx.creator.clone(),
+            attributes: gpx.attributes.clone(),
         };
 
         egpx.enrich_trackpoints();
@@ -202,110 +50,6 @@ impl Gpx {
     }
 }
 
-impl Extensions {
-    /// Constructs a new Extensions object from the raw value.
-    pub fn new<S: Into<String>>(value: S) -> Self {
-        Self {
-            raw_xml: value.into(),
-        }
-    }
-
-    /// Returns true if there are no extensions.
-    pub fn is_empty(&self) -> bool {
-        self.raw_xml.is_empty()
-    }
-}
-
-impl Default for XmlDeclaration {
-    fn default() -> Self {
-        Self {
-            version: "1.0".to_owned(),
-            encoding: Some("UTF-8".to_owned()),
-            standalone: Default::default(),
-        }
-    }
-}
-
-impl Waypoint {
-    /// Creates a new waypoint, validating that the lat and lon lie within the
-    /// acceptable ranges.
-    pub fn with_lat_lon(lat: f64, lon: f64) -> Result<Self, GapixError> {
-        Ok(Self {
-            lat: validate_latitude(lat)?,
-            lon: validate_longitude(lon)?,
-            ..Default::default()
-        })
-    }
-}
-
-impl Bounds {
-    /// Creates a new Bounds, validating that the lat and lon lie within the
-    /// acceptable ranges.
-    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Result<Self, GapixError> {
-        Ok(Self {
-            min_lat: validate_latitude(min_lat)?,
-            min_lon: validate_longitude(min_lon)?,
-            max_lat: validate_latitude(max_lat)?,
-            max_lon: validate_longitude(max_lon)?,
-        })
-    }
-}
-
-impl Email {
-    /// Constructs a new email element from the two mandatory fields, id and
-    /// domain.
-    pub fn new<S1, S2>(id: S1, domain: S2) -> Self
-    where
-        S1: Into<String>,
-        S2: Into<String>,
-    {
-        Self {
-            id: id.into(),
-            domain: domain.into(),
-        }
-    }
-}
-
-impl Link {
-    pub fn new<S>(href: S) -> Self
-    where
-        S: Into<String>,
-    {
-        Self {
-            text: None,
-            r#type: None,
-            href: href.into(),
-        }
-    }
-}
-
-impl TryFrom<String> for FixType {
-    type Error = GapixError;
-
-    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
-        match value.as_ref() {
-            "none" => Ok(FixType::None),
-            "2d" => Ok(FixType::TwoDimensional),
-            "3d" => Ok(FixType::ThreeDimensional),
-            "dgps" => Ok(FixType::DGPS),
-            "pps" => Ok(FixType::PPS),
-            _ => Err(GapixError::InvalidFixType(value)),
-        }
-    }
-}
-
-impl std::fmt::Display for FixType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            FixType::None => write!(f, "none"),
-            FixType::TwoDimensional => write!(f, "2d"),
-            FixType::ThreeDimensional => write!(f, "3d"),
-            FixType::DGPS => write!(f, "dgps"),
-            FixType::PPS => write!(f, "pps"),
-        }
-    }
-}
-
 impl EnrichedGpx {
     /// Returns the last valid index in the points array.
     /// Just a convenience fn to avoid off-by-one errors (hopefully).
@@ -345,6 +89,48 @@ impl EnrichedGpx {
         }
     }
 
+    /// Returns a name for the ride, for use in headings. This is the track name
+    /// if there is one, else the name from the metadata, else the filename.
+    pub fn ride_name(&self) -> String {
+        self.track_name
+            .clone()
+            .or_else(|| self.metadata.name.clone())
+            .or_else(|| {
+                self.filename
+                    .as_ref()
+                    .and_then(|f| f.file_stem())
+                    .map(|f| f.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns (at most) 'max_points' evenly spaced points from the track,
+    /// always including the last point. Used when drawing charts and maps,
+    /// where tens of thousands of points make no visible difference.
+    pub fn sample_points(&self, max_points: usize) -> Vec<&EnrichedTrackPoint> {
+        let step = self.points.len().div_ceil(max_points).max(1);
+        let mut points: Vec<_> = self.points.iter().step_by(step).collect();
+        if let Some(last) = self.points.last() {
+            if points.last().is_some_and(|p| p.index != last.index) {
+                points.push(last);
+            }
+        }
+        points
+    }
+
+    /// Finds the trackpoint nearest to 'waypoint' and returns it along with its
+    /// distance from the waypoint in metres. If the track passes the waypoint
+    /// more than once, the closest pass is returned. Returns None if there are
+    /// no trackpoints.
+    pub fn nearest_point(&self, waypoint: &Waypoint) -> Option<(&EnrichedTrackPoint, f64)> {
+        let target = waypoint.as_geo_point();
+
+        self.points
+            .iter()
+            .map(|p| (p, distance_between_points_metres(p.as_geo_point(), target)))
+            .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+    }
+
     /// Calculate a set of enriched TrackPoint information (distances, speed, climb).
     #[time]
     fn enrich_trackpoints(&mut self) {
@@ -444,8 +230,8 @@ impl EnrichedTrackPoint {
     fn new(index: usize, value: &Waypoint) -> Self {
         Self {
             index,
-            lat: value.lat,
-            lon: value.lon,
+            lat: value.lat.value(),
+            lon: value.lon.value(),
             ele: value.ele,
             time: value.time,
             garmin_extensions: value.garmin_extensions.clone(),
@@ -516,4 +302,8 @@ impl EnrichedTrackPoint {
     pub fn cadence(&self) -> Option<u8> {
         self.garmin_extensions.as_ref().and_then(|ext| ext.cadence)
     }
+
+    pub fn power(&self) -> Option<u16> {
+        self.garmin_extensions.as_ref().and_then(|ext| ext.power)
+    }
 }
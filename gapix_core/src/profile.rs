@@ -0,0 +1,420 @@
+//! Draws the elevation profile of a ride as an image: distance along the
+//! x-axis, elevation up the y-axis, shaded by gradient, with the controls
+//! marked and the significant climbs labelled.
+
+use crate::{
+    error::GapixError,
+    model::EnrichedGpx,
+    stage::{StageList, StageType},
+    svg::{draw_axes, Anchor, LinearScale, PlotArea, SvgBuilder, CONTROL_COLOUR},
+    units::UnitSystem,
+};
+
+/// We resample the track to at most this many points. This smooths out some of
+/// the noise in GPS elevations, which otherwise makes the gradient shading
+/// flicker between colours.
+const MAX_PROFILE_POINTS: usize = 600;
+/// ...but never to less than this many metres between points.
+const MIN_PROFILE_INTERVAL_METRES: f64 = 50.0;
+
+/// A climb must gain at least this much height to be labelled.
+const MIN_CLIMB_GAIN_METRES: f64 = 50.0;
+/// A climb must have at least this average gradient, in percent, to be
+/// labelled. This stops long drags up river valleys being counted.
+const MIN_CLIMB_GRADIENT: f64 = 3.0;
+/// A climb is considered to have finished once we have descended this much
+/// from its highest point. Smaller dips are treated as part of the climb.
+const MAX_CLIMB_DIP_METRES: f64 = 20.0;
+/// Climb labels closer together than this (in pixels) are skipped so that they
+/// don't overlap.
+const MIN_CLIMB_LABEL_SPACING: f64 = 90.0;
+
+const OUTLINE_COLOUR: &str = "#555555";
+const LABEL_COLOUR: &str = "#222222";
+
+/// The colours used to shade the profile, as (minimum gradient in percent,
+/// colour, legend label). Descents are all the same colour.
+const GRADIENT_COLOURS: [(f64, &str, &str); 6] = [
+    (f64::NEG_INFINITY, "#9ecae1", "Descent"),
+    (0.0, "#74c476", "0-3%"),
+    (3.0, "#fdd835", "3-6%"),
+    (6.0, "#fb8c00", "6-9%"),
+    (9.0, "#e53935", "9-12%"),
+    (12.0, "#8e0000", "12%+"),
+];
+
+/// Options that control how the elevation profile is drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileOptions {
+    pub units: UnitSystem,
+    /// The width of the image, in pixels.
+    pub width: f64,
+    /// The height of the image, in pixels.
+    pub height: f64,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self {
+            units: Default::default(),
+            width: 1200.0,
+            height: 400.0,
+        }
+    }
+}
+
+/// A significant climb on the profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Climb {
+    /// The distance from the start of the track to the bottom of the climb.
+    pub start_metres: f64,
+    /// The distance from the start of the track to the top of the climb.
+    pub end_metres: f64,
+    pub start_elevation: f64,
+    pub end_elevation: f64,
+}
+
+impl Climb {
+    /// Returns the height gained from the bottom to the top of the climb.
+    pub fn gain_metres(&self) -> f64 {
+        self.end_elevation - self.start_elevation
+    }
+
+    /// Returns the length of the climb.
+    pub fn length_metres(&self) -> f64 {
+        self.end_metres - self.start_metres
+    }
+
+    /// Returns the average gradient of the climb, in percent.
+    pub fn average_gradient(&self) -> f64 {
+        gradient_percent(self.length_metres(), self.gain_metres())
+    }
+}
+
+/// Finds the significant climbs in an elevation profile, which is a list of
+/// (distance, elevation) pairs in metres, in order of distance.
+pub fn find_climbs(profile: &[(f64, f64)]) -> Vec<Climb> {
+    let mut climbs = Vec::new();
+    if profile.is_empty() {
+        return climbs;
+    }
+
+    let mut bottom = 0;
+    let mut top = 0;
+
+    for idx in 1..profile.len() {
+        let elevation = profile[idx].1;
+
+        if elevation > profile[top].1 {
+            top = idx;
+        } else if profile[top].1 - elevation > MAX_CLIMB_DIP_METRES {
+            push_climb(&mut climbs, profile, bottom, top);
+            bottom = idx;
+            top = idx;
+        } else if top == bottom || elevation < profile[bottom].1 {
+            // Not climbing, so the bottom of the next climb can't be any
+            // earlier than here.
+            bottom = idx;
+            top = idx;
+        }
+    }
+
+    push_climb(&mut climbs, profile, bottom, top);
+    climbs
+}
+
+fn push_climb(climbs: &mut Vec<Climb>, profile: &[(f64, f64)], bottom: usize, top: usize) {
+    if top <= bottom {
+        return;
+    }
+
+    let climb = Climb {
+        start_metres: profile[bottom].0,
+        end_metres: profile[top].0,
+        start_elevation: profile[bottom].1,
+        end_elevation: profile[top].1,
+    };
+
+    if climb.gain_metres() >= MIN_CLIMB_GAIN_METRES
+        && climb.average_gradient() >= MIN_CLIMB_GRADIENT
+    {
+        climbs.push(climb);
+    }
+}
+
+/// Creates the elevation profile of the ride as an SVG document.
+pub fn create_elevation_profile_svg(
+    options: &ProfileOptions,
+    gpx: &EnrichedGpx,
+    stages: &StageList,
+) -> Result<String, GapixError> {
+    let profile = resample_profile(gpx);
+    if profile.len() < 2 {
+        return Err(GapixError::NoElevationData);
+    }
+
+    let units = options.units;
+    let area = PlotArea::for_chart(options.width, options.height);
+
+    let min_ele = profile.iter().map(|(_, e)| *e).fold(f64::MAX, f64::min);
+    let max_ele = profile.iter().map(|(_, e)| *e).fold(f64::MIN, f64::max);
+    // Don't start the y-axis at zero, it flattens out the hills. Leave some
+    // room at the top for the climb labels and the legend.
+    let range = (max_ele - min_ele).max(10.0);
+    let y_domain = (
+        units.height(min_ele - range * 0.05),
+        units.height(max_ele + range * 0.25),
+    );
+    let max_distance = profile[profile.len() - 1].0;
+    let x_scale = area.x_scale((0.0, units.distance(max_distance / 1000.0)));
+    let y_scale = area.y_scale(y_domain);
+    let to_xy = |(metres, elevation): (f64, f64)| {
+        (
+            x_scale.map(units.distance(metres / 1000.0)),
+            y_scale.map(units.height(elevation)),
+        )
+    };
+
+    let mut svg = SvgBuilder::new(options.width, options.height);
+    svg.rect(0.0, 0.0, options.width, options.height, "white", 1.0);
+    draw_axes(
+        &mut svg,
+        &area,
+        &x_scale,
+        &y_scale,
+        &format!("Distance ({})", units.distance_unit()),
+        &format!("Elevation ({})", units.height_unit()),
+    );
+
+    // Consecutive segments of the same colour are drawn as a single polygon,
+    // which keeps the file small and avoids hairline gaps between them.
+    let mut run: Vec<(f64, f64)> = Vec::new();
+    let mut run_colour = "";
+    for pair in profile.windows(2) {
+        let gradient = gradient_percent(pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+        let colour = gradient_colour(gradient);
+        if colour != run_colour && !run.is_empty() {
+            fill_run(&mut svg, &area, &run, run_colour);
+            run.clear();
+        }
+        if run.is_empty() {
+            run.push(to_xy(pair[0]));
+        }
+        run.push(to_xy(pair[1]));
+        run_colour = colour;
+    }
+    fill_run(&mut svg, &area, &run, run_colour);
+
+    let outline: Vec<_> = profile.iter().map(|p| to_xy(*p)).collect();
+    svg.polyline(&outline, OUTLINE_COLOUR, 1.0);
+
+    draw_control_markers(&mut svg, &area, &x_scale, units, stages);
+    draw_climb_labels(&mut svg, &area, &profile, units, to_xy);
+    draw_legend(&mut svg, &area);
+
+    Ok(svg.finish())
+}
+
+/// Fills the area between a run of the profile and the x-axis.
+fn fill_run(svg: &mut SvgBuilder, area: &PlotArea, run: &[(f64, f64)], colour: &str) {
+    let (Some(first), Some(last)) = (run.first(), run.last()) else {
+        return;
+    };
+
+    let mut polygon = run.to_vec();
+    polygon.push((last.0, area.bottom));
+    polygon.push((first.0, area.bottom));
+    svg.polygon(&polygon, colour, 1.0);
+}
+
+/// Marks each control with a numbered vertical line. The number is the stage
+/// number, so it matches the Stages table.
+pub(crate) fn draw_control_markers(
+    svg: &mut SvgBuilder,
+    area: &PlotArea,
+    x_scale: &LinearScale,
+    units: UnitSystem,
+    stages: &StageList,
+) {
+    for (idx, stage) in stages.iter().enumerate() {
+        if stage.stage_type == StageType::Control {
+            let x = x_scale.map(units.distance(stage.start.running_metres / 1000.0));
+            svg.dashed_line((x, area.top), (x, area.bottom), CONTROL_COLOUR, 1.0);
+            svg.text(
+                (x + 3.0, area.top + 10.0),
+                10.0,
+                Anchor::Start,
+                CONTROL_COLOUR,
+                &(idx + 1).to_string(),
+            );
+        }
+    }
+}
+
+fn draw_climb_labels<F>(
+    svg: &mut SvgBuilder,
+    area: &PlotArea,
+    profile: &[(f64, f64)],
+    units: UnitSystem,
+    to_xy: F,
+) where
+    F: Fn((f64, f64)) -> (f64, f64),
+{
+    let mut last_label_x = f64::MIN;
+
+    for climb in find_climbs(profile) {
+        let (x, y) = to_xy((climb.end_metres, climb.end_elevation));
+        if x - last_label_x < MIN_CLIMB_LABEL_SPACING {
+            continue;
+        }
+        last_label_x = x;
+
+        let label = format!(
+            "{:.0}{} @ {:.1}%",
+            units.height(climb.gain_metres()),
+            units.height_unit(),
+            climb.average_gradient()
+        );
+        svg.line((x, y - 2.0), (x, y - 10.0), LABEL_COLOUR, 1.0);
+        // Keep labels near the ends of the chart inside it.
+        let anchor = if x + MIN_CLIMB_LABEL_SPACING / 2.0 > area.right {
+            Anchor::End
+        } else if x - MIN_CLIMB_LABEL_SPACING / 2.0 < area.left {
+            Anchor::Start
+        } else {
+            Anchor::Middle
+        };
+        svg.text((x, y - 13.0), 10.0, anchor, LABEL_COLOUR, &label);
+    }
+}
+
+fn draw_legend(svg: &mut SvgBuilder, area: &PlotArea) {
+    const ENTRY_WIDTH: f64 = 62.0;
+
+    let mut x = area.right - ENTRY_WIDTH * GRADIENT_COLOURS.len() as f64;
+    let y = area.top + 4.0;
+    for (_, colour, label) in GRADIENT_COLOURS {
+        svg.rect(x, y, 10.0, 10.0, colour, 1.0);
+        svg.text(
+            (x + 14.0, y + 9.0),
+            10.0,
+            Anchor::Start,
+            LABEL_COLOUR,
+            label,
+        );
+        x += ENTRY_WIDTH;
+    }
+}
+
+/// Returns the (distance, elevation) of the points with an elevation, thinned
+/// out to at most MAX_PROFILE_POINTS. The last point is always included so the
+/// profile reaches the end of the ride.
+fn resample_profile(gpx: &EnrichedGpx) -> Vec<(f64, f64)> {
+    let points: Vec<_> = gpx
+        .points
+        .iter()
+        .filter_map(|p| p.ele.map(|e| (p.running_metres, e)))
+        .collect();
+
+    let Some(last) = points.last().copied() else {
+        return points;
+    };
+
+    let interval = (last.0 / MAX_PROFILE_POINTS as f64).max(MIN_PROFILE_INTERVAL_METRES);
+    let mut profile = vec![points[0]];
+    for point in &points[1..] {
+        if point.0 - profile[profile.len() - 1].0 >= interval {
+            profile.push(*point);
+        }
+    }
+
+    if profile[profile.len() - 1] != last {
+        profile.push(last);
+    }
+
+    profile
+}
+
+/// Returns the gradient in percent. A zero-length run is treated as flat.
+fn gradient_percent(run_metres: f64, rise_metres: f64) -> f64 {
+    if run_metres <= 0.0 {
+        0.0
+    } else {
+        rise_metres / run_metres * 100.0
+    }
+}
+
+fn gradient_colour(gradient: f64) -> &'static str {
+    GRADIENT_COLOURS
+        .iter()
+        .rev()
+        .find(|(min, _, _)| gradient >= *min)
+        .map(|(_, colour, _)| *colour)
+        .unwrap_or(GRADIENT_COLOURS[0].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_colour_buckets() {
+        assert_eq!(gradient_colour(-5.0), "#9ecae1");
+        assert_eq!(gradient_colour(0.0), "#74c476");
+        assert_eq!(gradient_colour(2.9), "#74c476");
+        assert_eq!(gradient_colour(3.0), "#fdd835");
+        assert_eq!(gradient_colour(25.0), "#8e0000");
+    }
+
+    #[test]
+    fn find_climbs_ignores_flat_profile() {
+        let profile: Vec<_> = (0..100).map(|i| (i as f64 * 100.0, 50.0)).collect();
+        assert!(find_climbs(&profile).is_empty());
+    }
+
+    #[test]
+    fn find_climbs_finds_single_climb() {
+        // 2km flat, then 2km at 5%, then 2km descending.
+        let mut profile = Vec::new();
+        for i in 0..=20 {
+            profile.push((i as f64 * 100.0, 100.0));
+        }
+        for i in 1..=20 {
+            profile.push((2000.0 + i as f64 * 100.0, 100.0 + i as f64 * 5.0));
+        }
+        for i in 1..=20 {
+            profile.push((4000.0 + i as f64 * 100.0, 200.0 - i as f64 * 5.0));
+        }
+
+        let climbs = find_climbs(&profile);
+        assert_eq!(climbs.len(), 1);
+        let climb = climbs[0];
+        assert_eq!(climb.start_metres, 2000.0);
+        assert_eq!(climb.end_metres, 4000.0);
+        assert_eq!(climb.gain_metres(), 100.0);
+        assert_eq!(climb.average_gradient(), 5.0);
+    }
+
+    #[test]
+    fn find_climbs_treats_small_dips_as_part_of_the_climb() {
+        let profile = vec![
+            (0.0, 0.0),
+            (1000.0, 50.0),
+            (1200.0, 40.0),
+            (2000.0, 100.0),
+            (3000.0, 0.0),
+        ];
+
+        let climbs = find_climbs(&profile);
+        assert_eq!(climbs.len(), 1);
+        assert_eq!(climbs[0].start_metres, 0.0);
+        assert_eq!(climbs[0].end_metres, 2000.0);
+    }
+
+    #[test]
+    fn find_climbs_ignores_shallow_climbs() {
+        // 100m gain over 10km is only 1%.
+        let profile = vec![(0.0, 0.0), (10_000.0, 100.0)];
+        assert!(find_climbs(&profile).is_empty());
+    }
+}
@@ -107,7 +107,14 @@ fn parse_record_message(data: &FitDataRecord, gpx: &mut Gpx) -> Result<(), Gapix
         Ok(hr) => extensions.heart_rate = Some(hr as u8),
         Err(_) => { /* ignore */},
     };
-    if extensions.air_temp.is_some() || extensions.heart_rate.is_some() {
+    if let Ok(power) = get_field_f64(data.fields(), "power") {
+        // Like the GPX reader, skip values that do not fit in a u16.
+        let power = power.round();
+        if (0.0..=u16::MAX as f64).contains(&power) {
+            extensions.power = Some(power as u16);
+        }
+    }
+    if extensions.air_temp.is_some() || extensions.heart_rate.is_some() || extensions.power.is_some() {
         tp.garmin_extensions = Some(extensions);
     }
 
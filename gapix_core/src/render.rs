@@ -0,0 +1,210 @@
+//! Writes the images we generate (elevation profiles and maps) to file. The
+//! images are always built as SVG; if a PNG is wanted the SVG is rasterized
+//! with resvg, which is pure Rust and needs no GPU. PNG output needs the 'png'
+//! feature.
+
+#[cfg(feature = "png")]
+use std::sync::{Arc, LazyLock};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use log::info;
+#[cfg(feature = "png")]
+use log::warn;
+#[cfg(feature = "png")]
+use logging_timer::time;
+#[cfg(feature = "png")]
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{fontdb, Options, Tree},
+};
+
+use crate::{byte_counter::ByteCounter, error::GapixError};
+
+/// Fonts commonly installed on Linux, Mac and Windows, in order of preference.
+/// Used if the system does not have a font for the generic 'sans-serif' family
+/// (which resvg assumes is Arial).
+#[cfg(feature = "png")]
+const FALLBACK_FONT_FAMILIES: [&str; 4] =
+    ["DejaVu Sans", "Liberation Sans", "Helvetica", "Noto Sans"];
+
+/// Loading the system fonts takes a noticeable amount of time, so we only do
+/// it once, and only if we actually need to render a PNG.
+#[cfg(feature = "png")]
+static FONTS: LazyLock<Arc<fontdb::Database>> = LazyLock::new(load_fonts);
+
+/// The format of an image file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// A vector image, which can be scaled without loss and viewed in any
+    /// browser.
+    #[default]
+    Svg,
+    /// A bitmap image, for places that do not accept SVG.
+    Png,
+}
+
+impl ImageFormat {
+    /// Returns the file extension for the format, without the leading '.'.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Svg => "svg",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "svg" => Ok(ImageFormat::Svg),
+            "png" => Ok(ImageFormat::Png),
+            _ => Err(format!(
+                "unknown image format '{s}', expected one of 'svg' or 'png'"
+            )),
+        }
+    }
+}
+
+/// Rasterizes an SVG document to a PNG of the same size as the SVG.
+#[cfg(feature = "png")]
+#[time]
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>, GapixError> {
+    let options = Options {
+        fontdb: FONTS.clone(),
+        ..Default::default()
+    };
+
+    let tree = Tree::from_str(svg, &options).map_err(|e| GapixError::ImageRender(e.to_string()))?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        GapixError::ImageRender(format!(
+            "cannot create a {}x{} image",
+            size.width(),
+            size.height()
+        ))
+    })?;
+
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| GapixError::ImageRender(e.to_string()))
+}
+
+/// Writes 'svg' to a file in the specified format. The file will be
+/// overwritten if it already exists.
+pub fn write_image_to_file<P: AsRef<Path>>(
+    filename: P,
+    svg: &str,
+    format: ImageFormat,
+) -> Result<(), GapixError> {
+    let filename = filename.as_ref();
+
+    #[cfg(feature = "png")]
+    let png;
+    let bytes = match format {
+        ImageFormat::Svg => svg.as_bytes(),
+        #[cfg(feature = "png")]
+        ImageFormat::Png => {
+            png = svg_to_png(svg)?;
+            &png
+        }
+        #[cfg(not(feature = "png"))]
+        ImageFormat::Png => {
+            return Err(GapixError::FeatureNotEnabled {
+                what: "Writing PNG images",
+                feature: "png",
+            })
+        }
+    };
+
+    let file = match File::create(filename) {
+        Ok(f) => f,
+        Err(err) => {
+            return Err(GapixError::CreateFile {
+                path: filename.to_owned(),
+                source: err,
+            })
+        }
+    };
+
+    let mut writer = ByteCounter::new(BufWriter::new(file));
+    writer.write_all(bytes)?;
+    writer.flush()?;
+
+    info!(
+        "Image file {:?}, {} Kb",
+        &filename,
+        writer.bytes_written() / 1024
+    );
+    Ok(())
+}
+
+#[cfg(feature = "png")]
+fn load_fonts() -> Arc<fontdb::Database> {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+
+    let has_family = |db: &fontdb::Database, family: &str| {
+        db.faces()
+            .any(|face| face.families.iter().any(|(name, _)| name == family))
+    };
+
+    if !has_family(&db, "Arial") {
+        let fallback = FALLBACK_FONT_FAMILIES
+            .iter()
+            .find(|family| has_family(&db, family))
+            .map(|family| family.to_string())
+            .or_else(|| {
+                db.faces()
+                    .next()
+                    .and_then(|face| face.families.first())
+                    .map(|(family, _)| family.clone())
+            });
+
+        match fallback {
+            Some(family) => db.set_sans_serif_family(family),
+            None => warn!("No system fonts found, text will be missing from PNG images"),
+        }
+    }
+
+    Arc::new(db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_format_parse_and_display_round_trip() {
+        for format in [ImageFormat::Svg, ImageFormat::Png] {
+            assert_eq!(format.to_string().parse::<ImageFormat>().unwrap(), format);
+        }
+        assert_eq!("PNG".parse::<ImageFormat>().unwrap(), ImageFormat::Png);
+        assert!("gif".parse::<ImageFormat>().is_err());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn svg_to_png_produces_png_of_same_size() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20"><rect width="40" height="20" fill="red"/></svg>"#;
+        let png = svg_to_png(svg).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        // The IHDR chunk has the width and height as big-endian u32s.
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 40);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 20);
+    }
+}
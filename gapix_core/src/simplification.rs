@@ -35,7 +35,7 @@ pub fn metres_to_epsilon(metres: u16) -> f64 {
 pub fn reduce_trackpoints_by_rdp(points: &mut Vec<Waypoint>, epsilon: f64) {
     let line_string: LineString<_> = points
         .iter()
-        .map(|p| coord! { x: p.lon, y: p.lat })
+        .map(|p| coord! { x: p.lon.value(), y: p.lat.value() })
         .collect();
     let indices_to_keep: HashSet<usize> = HashSet::from_iter(line_string.simplify_idx(&epsilon));
 
@@ -11,11 +11,12 @@ use geo::{Distance, Geodesic, Point};
 use log::{debug, info, warn};
 use logging_timer::time;
 use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use crate::{
-    geocoding::reverse_geocode_latlon,
-    model::{EnrichedGpx, EnrichedTrackPoint},
-};
+#[cfg(feature = "geocoding")]
+use crate::geocoding::reverse_geocode_latlon;
+use crate::model::{EnrichedGpx, EnrichedTrackPoint};
 
 /// Calculates speed in km/h from metres and seconds.
 pub fn speed_kmh(metres: f64, seconds: f64) -> f64 {
@@ -48,6 +49,7 @@ pub struct StageDetectionParameters {
 /// Represents a stage from a GPX track. The stage can represent
 /// you moving, or controlling.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stage {
     pub stage_type: StageType,
     // The first point in the entire track. We need this to calculate various
@@ -68,6 +70,7 @@ pub struct Stage {
 
 /// The type of a Stage.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum StageType {
     Moving,
     Control,
@@ -131,6 +134,7 @@ impl Stage {
     /// Reverse geocodes the stage, i.e. looks up the place name from
     /// the (lat,lon) coordinates and returns it. For a Control stage, this
     /// is just "Name", for a Moving stage, returns "Name1 to Name2".
+    #[cfg(feature = "geocoding")]
     pub fn reverse_geocode(&self) -> Option<String> {
         let start_desc = reverse_geocode_latlon(self.start.as_rtree_point());
 
@@ -150,6 +154,13 @@ impl Stage {
         }
     }
 
+    /// Without the 'geocoding' feature there is no place data, so this always
+    /// returns None.
+    #[cfg(not(feature = "geocoding"))]
+    pub fn reverse_geocode(&self) -> Option<String> {
+        None
+    }
+
     /// Returns the duration of the stage.
     pub fn duration(&self) -> Option<TimeDelta> {
         // Be careful to use the time that the 'start' TrackPoint
@@ -246,6 +257,7 @@ impl Stage {
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct StageList(Vec<Stage>);
 
 impl Index<usize> for StageList {
@@ -292,7 +304,7 @@ impl StageList {
         idxs
     }
 
-    pub fn iter(&self) -> slice::Iter<Stage> {
+    pub fn iter(&self) -> slice::Iter<'_, Stage> {
         self.0.iter()
     }
 
@@ -392,10 +404,8 @@ impl StageList {
                 current_min.min_elevation.as_ref(),
                 stage.min_elevation.as_ref(),
             ) {
-                (Some(min_ele), Some(stg)) => {
-                    if stg.ele < min_ele.ele {
-                        current_min = stage;
-                    }
+                (Some(min_ele), Some(stg)) if stg.ele < min_ele.ele => {
+                    current_min = stage;
                 }
                 (None, Some(_)) => {
                     current_min = stage;
@@ -416,10 +426,8 @@ impl StageList {
                 current_max.max_elevation.as_ref(),
                 stage.max_elevation.as_ref(),
             ) {
-                (Some(max_ele), Some(stg)) => {
-                    if stg.ele > max_ele.ele {
-                        current_max = stage;
-                    }
+                (Some(max_ele), Some(stg)) if stg.ele > max_ele.ele => {
+                    current_max = stage;
                 }
                 (None, Some(_)) => {
                     current_max = stage;
@@ -447,10 +455,8 @@ impl StageList {
 
         for stage in self.iter() {
             match (current_max.max_speed.as_ref(), stage.max_speed.as_ref()) {
-                (Some(max_sp), Some(stg)) => {
-                    if stg.speed_kmh > max_sp.speed_kmh {
-                        current_max = stage;
-                    }
+                (Some(max_sp), Some(stg)) if stg.speed_kmh > max_sp.speed_kmh => {
+                    current_max = stage;
                 }
                 (None, Some(_)) => {
                     current_max = stage;
@@ -0,0 +1,405 @@
+//! A minimal SVG builder. We only need a handful of elements to draw charts
+//! and track maps, so rather than take a dependency we build the markup as a
+//! string.
+
+// Colours that are used consistently across all our images.
+pub(crate) const TRACK_COLOUR: &str = "#1f6fd1";
+pub(crate) const CONTROL_COLOUR: &str = "#d62728";
+pub(crate) const START_COLOUR: &str = "#2ca02c";
+pub(crate) const FINISH_COLOUR: &str = "#222222";
+pub(crate) const WAYPOINT_COLOUR: &str = "#9467bd";
+
+/// How a piece of text is anchored relative to its x coordinate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    Start,
+    Middle,
+    End,
+}
+
+impl Anchor {
+    fn as_str(self) -> &'static str {
+        match self {
+            Anchor::Start => "start",
+            Anchor::Middle => "middle",
+            Anchor::End => "end",
+        }
+    }
+}
+
+/// Accumulates the elements of an SVG document.
+pub(crate) struct SvgBuilder {
+    buf: String,
+}
+
+impl SvgBuilder {
+    /// Starts a new document of the specified size in pixels. The viewBox is
+    /// the same size, so all coordinates are in pixels.
+    pub(crate) fn new(width: f64, height: f64) -> Self {
+        let buf = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif">"#
+        );
+        Self { buf }
+    }
+
+    pub(crate) fn rect(
+        &mut self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: &str,
+        opacity: f64,
+    ) {
+        self.buf.push_str(&format!(
+            r#"<rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{fill}" fill-opacity="{opacity}"/>"#
+        ));
+    }
+
+    pub(crate) fn line(&mut self, from: (f64, f64), to: (f64, f64), stroke: &str, width: f64) {
+        self.buf.push_str(&format!(
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{stroke}" stroke-width="{width}"/>"#,
+            from.0, from.1, to.0, to.1
+        ));
+    }
+
+    /// Draws a dashed line, used for grid lines and markers.
+    pub(crate) fn dashed_line(
+        &mut self,
+        from: (f64, f64),
+        to: (f64, f64),
+        stroke: &str,
+        width: f64,
+    ) {
+        self.buf.push_str(&format!(
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{stroke}" stroke-width="{width}" stroke-dasharray="4 3"/>"#,
+            from.0, from.1, to.0, to.1
+        ));
+    }
+
+    pub(crate) fn polyline(&mut self, points: &[(f64, f64)], stroke: &str, width: f64) {
+        if points.is_empty() {
+            return;
+        }
+
+        self.buf.push_str(&format!(
+            r#"<polyline points="{}" fill="none" stroke="{stroke}" stroke-width="{width}" stroke-linejoin="round" stroke-linecap="round"/>"#,
+            format_points(points)
+        ));
+    }
+
+    pub(crate) fn polygon(&mut self, points: &[(f64, f64)], fill: &str, opacity: f64) {
+        if points.is_empty() {
+            return;
+        }
+
+        self.buf.push_str(&format!(
+            r#"<polygon points="{}" fill="{fill}" fill-opacity="{opacity}" stroke="none"/>"#,
+            format_points(points)
+        ));
+    }
+
+    /// Draws a circle. If 'title' is specified it is shown as a tooltip by
+    /// browsers when the mouse hovers over the circle.
+    pub(crate) fn circle(
+        &mut self,
+        centre: (f64, f64),
+        radius: f64,
+        fill: &str,
+        title: Option<&str>,
+    ) {
+        let (cx, cy) = centre;
+        match title {
+            Some(title) => self.buf.push_str(&format!(
+                r#"<circle cx="{cx:.1}" cy="{cy:.1}" r="{radius}" fill="{fill}" stroke="white" stroke-width="1.5"><title>{}</title></circle>"#,
+                escape(title)
+            )),
+            None => self.buf.push_str(&format!(
+                r#"<circle cx="{cx:.1}" cy="{cy:.1}" r="{radius}" fill="{fill}" stroke="white" stroke-width="1.5"/>"#
+            )),
+        }
+    }
+
+    pub(crate) fn text(
+        &mut self,
+        position: (f64, f64),
+        size: f64,
+        anchor: Anchor,
+        fill: &str,
+        text: &str,
+    ) {
+        let (x, y) = position;
+        self.buf.push_str(&format!(
+            r#"<text x="{x:.1}" y="{y:.1}" font-size="{size}" text-anchor="{}" fill="{fill}">{}</text>"#,
+            anchor.as_str(),
+            escape(text)
+        ));
+    }
+
+    /// Draws text rotated 90° anti-clockwise about its position, used for the
+    /// y-axis labels of charts.
+    pub(crate) fn vertical_text(
+        &mut self,
+        position: (f64, f64),
+        size: f64,
+        fill: &str,
+        text: &str,
+    ) {
+        let (x, y) = position;
+        self.buf.push_str(&format!(
+            r#"<text x="{x:.1}" y="{y:.1}" font-size="{size}" text-anchor="middle" fill="{fill}" transform="rotate(-90 {x:.1} {y:.1})">{}</text>"#,
+            escape(text)
+        ));
+    }
+
+    /// Closes the document and returns the markup.
+    pub(crate) fn finish(mut self) -> String {
+        self.buf.push_str("</svg>");
+        self.buf
+    }
+}
+
+fn format_points(points: &[(f64, f64)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escapes the characters that are special in XML (and hence in SVG and HTML)
+/// text and attribute values.
+pub(crate) fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Maps values from a domain (e.g. 0..200 km) onto a range of pixels. The
+/// range can be 'backwards', as it is for y-axes where larger values are
+/// further up the page.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LinearScale {
+    domain: (f64, f64),
+    range: (f64, f64),
+}
+
+impl LinearScale {
+    /// Creates a new scale. If the domain is empty (min == max) it is widened
+    /// slightly so that we don't divide by zero.
+    pub(crate) fn new(domain: (f64, f64), range: (f64, f64)) -> Self {
+        let domain = if domain.1 - domain.0 == 0.0 {
+            (domain.0 - 1.0, domain.1 + 1.0)
+        } else {
+            domain
+        };
+
+        Self { domain, range }
+    }
+
+    pub(crate) fn map(&self, value: f64) -> f64 {
+        let fraction = (value - self.domain.0) / (self.domain.1 - self.domain.0);
+        self.range.0 + fraction * (self.range.1 - self.range.0)
+    }
+
+    pub(crate) fn domain(&self) -> (f64, f64) {
+        self.domain
+    }
+}
+
+/// Returns a set of 'nice' tick values (multiples of 1, 2 or 5 times a power
+/// of 10) covering 'min' to 'max', with at most about 'max_ticks' of them.
+pub(crate) fn nice_ticks(min: f64, max: f64, max_ticks: usize) -> Vec<f64> {
+    if !min.is_finite() || !max.is_finite() || max <= min || max_ticks == 0 {
+        return Vec::new();
+    }
+
+    let rough_step = (max - min) / max_ticks as f64;
+    let magnitude = 10f64.powf(rough_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= rough_step)
+        .unwrap_or(10.0 * magnitude);
+
+    let mut ticks = Vec::new();
+    let mut tick = (min / step).ceil() * step;
+    while tick <= max + step * 1e-9 {
+        // Avoid printing "-0".
+        ticks.push(if tick.abs() < step * 1e-9 { 0.0 } else { tick });
+        tick += step;
+    }
+    ticks
+}
+
+/// Formats a tick value, using as many decimal places as the tick spacing
+/// requires.
+pub(crate) fn format_tick(value: f64, ticks: &[f64]) -> String {
+    let step = match ticks {
+        [a, b, ..] => b - a,
+        _ => 1.0,
+    };
+
+    if step >= 1.0 {
+        format!("{value:.0}")
+    } else {
+        let decimals = (-step.log10().floor()) as usize;
+        format!("{value:.decimals$}")
+    }
+}
+
+/// The area of a chart that the data is drawn in, i.e. the whole chart less
+/// the margins used for the axes and their labels.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlotArea {
+    pub(crate) left: f64,
+    pub(crate) top: f64,
+    pub(crate) right: f64,
+    pub(crate) bottom: f64,
+}
+
+impl PlotArea {
+    /// Returns the plot area for a chart of the specified size, leaving room
+    /// for the axes on the left and bottom.
+    pub(crate) fn for_chart(width: f64, height: f64) -> Self {
+        Self {
+            left: 60.0,
+            top: 15.0,
+            right: width - 20.0,
+            bottom: height - 45.0,
+        }
+    }
+
+    pub(crate) fn x_scale(&self, domain: (f64, f64)) -> LinearScale {
+        LinearScale::new(domain, (self.left, self.right))
+    }
+
+    pub(crate) fn y_scale(&self, domain: (f64, f64)) -> LinearScale {
+        LinearScale::new(domain, (self.bottom, self.top))
+    }
+}
+
+/// Draws the x and y axes of a chart, with tick marks, light grid lines and
+/// axis titles.
+pub(crate) fn draw_axes(
+    svg: &mut SvgBuilder,
+    area: &PlotArea,
+    x_scale: &LinearScale,
+    y_scale: &LinearScale,
+    x_label: &str,
+    y_label: &str,
+) {
+    const AXIS_COLOUR: &str = "#444444";
+    const GRID_COLOUR: &str = "#dddddd";
+
+    let (x_min, x_max) = x_scale.domain();
+    let x_ticks = nice_ticks(x_min, x_max, 10);
+    for tick in &x_ticks {
+        let x = x_scale.map(*tick);
+        svg.line((x, area.top), (x, area.bottom), GRID_COLOUR, 1.0);
+        svg.line((x, area.bottom), (x, area.bottom + 5.0), AXIS_COLOUR, 1.0);
+        let label = format_tick(*tick, &x_ticks);
+        svg.text(
+            (x, area.bottom + 18.0),
+            11.0,
+            Anchor::Middle,
+            AXIS_COLOUR,
+            &label,
+        );
+    }
+
+    let (y_min, y_max) = y_scale.domain();
+    let y_ticks = nice_ticks(y_min, y_max, 6);
+    for tick in &y_ticks {
+        let y = y_scale.map(*tick);
+        svg.line((area.left, y), (area.right, y), GRID_COLOUR, 1.0);
+        svg.line((area.left - 5.0, y), (area.left, y), AXIS_COLOUR, 1.0);
+        let label = format_tick(*tick, &y_ticks);
+        svg.text(
+            (area.left - 8.0, y + 4.0),
+            11.0,
+            Anchor::End,
+            AXIS_COLOUR,
+            &label,
+        );
+    }
+
+    svg.line(
+        (area.left, area.bottom),
+        (area.right, area.bottom),
+        AXIS_COLOUR,
+        1.0,
+    );
+    svg.line(
+        (area.left, area.top),
+        (area.left, area.bottom),
+        AXIS_COLOUR,
+        1.0,
+    );
+
+    let centre_x = (area.left + area.right) / 2.0;
+    svg.text(
+        (centre_x, area.bottom + 38.0),
+        12.0,
+        Anchor::Middle,
+        AXIS_COLOUR,
+        x_label,
+    );
+    let centre_y = (area.top + area.bottom) / 2.0;
+    svg.vertical_text((16.0, centre_y), 12.0, AXIS_COLOUR, y_label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_replaces_special_characters() {
+        assert_eq!(escape("Fish & <Chips>"), "Fish &amp; &lt;Chips&gt;");
+        assert_eq!(escape(r#"say "hi" it's"#), "say &quot;hi&quot; it&#39;s");
+    }
+
+    #[test]
+    fn linear_scale_maps_backwards_ranges() {
+        let scale = LinearScale::new((0.0, 100.0), (200.0, 0.0));
+        assert_eq!(scale.map(0.0), 200.0);
+        assert_eq!(scale.map(25.0), 150.0);
+        assert_eq!(scale.map(100.0), 0.0);
+    }
+
+    #[test]
+    fn linear_scale_with_empty_domain_does_not_divide_by_zero() {
+        let scale = LinearScale::new((5.0, 5.0), (0.0, 100.0));
+        assert_eq!(scale.map(5.0), 50.0);
+    }
+
+    #[test]
+    fn nice_ticks_uses_round_steps() {
+        assert_eq!(
+            nice_ticks(0.0, 100.0, 5),
+            vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(
+            nice_ticks(13.0, 87.0, 10),
+            vec![20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0]
+        );
+        assert_eq!(nice_ticks(0.0, 1.0, 4), vec![0.0, 0.5, 1.0]);
+        assert!(nice_ticks(1.0, 1.0, 4).is_empty());
+    }
+
+    #[test]
+    fn format_tick_uses_decimals_for_small_steps() {
+        assert_eq!(format_tick(20.0, &[0.0, 20.0]), "20");
+        assert_eq!(format_tick(0.5, &[0.0, 0.5]), "0.5");
+        assert_eq!(format_tick(0.25, &[0.0, 0.05]), "0.25");
+    }
+}
@@ -0,0 +1,31 @@
+use std::sync::LazyLock;
+
+use chrono_tz::Tz;
+use tzf_rs::DefaultFinder;
+
+use crate::model_impls::RTreePoint;
+
+/// This is used to lookup a timezone (in string form such as "Europe/London")
+/// from a (lat,lon) pair.
+static TIMEZONES: LazyLock<DefaultFinder> = LazyLock::new(DefaultFinder::new);
+
+/// Initialises the timezone finder on a background thread. It takes a while to
+/// load, so this means it will hopefully be available as soon as it is needed.
+pub fn initialise_timezones() {
+    std::thread::spawn(|| {
+        LazyLock::force(&TIMEZONES);
+    });
+}
+
+/// Returns the name of the timezone at point, such as "Europe/London".
+pub fn get_timezone_name(point: RTreePoint) -> &'static str {
+    TIMEZONES.get_tz_name(point[1], point[0])
+}
+
+/// Given a point, finds the timezone.
+pub fn get_timezone(point: RTreePoint) -> Option<Tz> {
+    // I suppose parse() might fail, we are passing timezone names from tzf-rs
+    // into chrono-tz. They SHOULD be the same though.
+    let tz: Tz = get_timezone_name(point).parse().ok()?;
+    Some(tz)
+}
@@ -0,0 +1,205 @@
+//! Contains the unit systems that analysis output can be displayed in.
+//! Internally everything is always stored in metric (metres, km, km/h and °C),
+//! conversion only happens at the point of output.
+
+use std::{fmt, str::FromStr};
+
+const MILES_PER_KM: f64 = 0.621_371_192;
+const FEET_PER_METRE: f64 = 3.280_839_895;
+const YARDS_PER_METRE: f64 = 1.093_613_298;
+
+/// The system of units used when displaying distances, speeds, heights and
+/// temperatures. Distances have two units: a long one for the length of a ride
+/// and a short one for the gaps between points.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    /// km, km/h, metres and °C.
+    #[default]
+    Metric,
+    /// Miles, mph, feet and °F.
+    Imperial,
+    /// The mixture used in the UK: miles, yards and mph for distances and
+    /// speeds (as on road signs), but metres for heights (as on Ordnance
+    /// Survey maps) and °C.
+    UK,
+}
+
+impl UnitSystem {
+    /// Converts a distance in km to the long-distance unit.
+    pub fn distance(self, km: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => km,
+            UnitSystem::Imperial | UnitSystem::UK => km * MILES_PER_KM,
+        }
+    }
+
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "km",
+            UnitSystem::Imperial | UnitSystem::UK => "mi",
+        }
+    }
+
+    /// Converts a speed in km/h to the speed unit.
+    pub fn speed(self, kmh: f64) -> f64 {
+        // Same ratio as for distances.
+        self.distance(kmh)
+    }
+
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "km/h",
+            UnitSystem::Imperial | UnitSystem::UK => "mph",
+        }
+    }
+
+    /// Converts a short distance in metres, such as the distance between two
+    /// points, to the short-distance unit.
+    pub fn short_distance(self, metres: f64) -> f64 {
+        match self {
+            UnitSystem::Metric => metres,
+            UnitSystem::Imperial => metres * FEET_PER_METRE,
+            UnitSystem::UK => metres * YARDS_PER_METRE,
+        }
+    }
+
+    pub fn short_distance_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m",
+            UnitSystem::Imperial => "ft",
+            UnitSystem::UK => "yd",
+        }
+    }
+
+    /// Converts a height in metres to the height unit.
+    pub fn height(self, metres: f64) -> f64 {
+        match self {
+            UnitSystem::Metric | UnitSystem::UK => metres,
+            UnitSystem::Imperial => metres * FEET_PER_METRE,
+        }
+    }
+
+    pub fn height_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric | UnitSystem::UK => "m",
+            UnitSystem::Imperial => "ft",
+        }
+    }
+
+    /// Converts a rate of climb in metres per km into height units per
+    /// distance unit, e.g. ft/mi.
+    pub fn climb_rate(self, metres_per_km: f64) -> f64 {
+        self.height(metres_per_km) / self.distance(1.0)
+    }
+
+    pub fn climb_rate_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "m/km",
+            UnitSystem::Imperial => "ft/mi",
+            UnitSystem::UK => "m/mi",
+        }
+    }
+
+    /// Converts a temperature in °C to the temperature unit.
+    pub fn temperature(self, celsius: f64) -> f64 {
+        match self {
+            UnitSystem::Metric | UnitSystem::UK => celsius,
+            UnitSystem::Imperial => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn temperature_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric | UnitSystem::UK => "°C",
+            UnitSystem::Imperial => "°F",
+        }
+    }
+
+    /// Formats a distance in km for display in messages, e.g. "12.345km" or
+    /// "7.671mi".
+    pub fn format_distance(self, km: f64) -> String {
+        format!("{:.3}{}", self.distance(km), self.distance_unit())
+    }
+}
+
+impl fmt::Display for UnitSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitSystem::Metric => write!(f, "metric"),
+            UnitSystem::Imperial => write!(f, "imperial"),
+            UnitSystem::UK => write!(f, "uk"),
+        }
+    }
+}
+
+impl FromStr for UnitSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "metric" => Ok(UnitSystem::Metric),
+            "imperial" => Ok(UnitSystem::Imperial),
+            "uk" => Ok(UnitSystem::UK),
+            _ => Err(format!(
+                "unknown unit system '{s}', expected one of 'metric', 'imperial' or 'uk'"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn metric_is_unchanged() {
+        let u = UnitSystem::Metric;
+        assert_close(u.distance(200.0), 200.0);
+        assert_close(u.short_distance(200.0), 200.0);
+        assert_close(u.speed(25.0), 25.0);
+        assert_close(u.height(1000.0), 1000.0);
+        assert_close(u.climb_rate(10.0), 10.0);
+        assert_close(u.temperature(20.0), 20.0);
+    }
+
+    #[test]
+    fn imperial_conversions() {
+        let u = UnitSystem::Imperial;
+        assert_close(u.distance(1.609344), 1.0);
+        assert_close(u.short_distance(0.3048), 1.0);
+        assert_close(u.speed(16.09344), 10.0);
+        assert_close(u.height(0.3048), 1.0);
+        assert_close(u.temperature(100.0), 212.0);
+        assert_close(u.temperature(-40.0), -40.0);
+        // 10 m/km is 52.8 ft/mi.
+        assert_close(u.climb_rate(10.0), 52.8);
+    }
+
+    #[test]
+    fn uk_is_miles_and_yards_with_metres_and_celsius() {
+        let u = UnitSystem::UK;
+        assert_close(u.distance(1.609344), 1.0);
+        assert_close(u.short_distance(0.9144), 1.0);
+        assert_eq!(u.short_distance_unit(), "yd");
+        assert_close(u.height(100.0), 100.0);
+        assert_close(u.temperature(20.0), 20.0);
+        assert_close(u.climb_rate(10.0), 16.09344);
+        assert_eq!(u.climb_rate_unit(), "m/mi");
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for u in [UnitSystem::Metric, UnitSystem::Imperial, UnitSystem::UK] {
+            assert_eq!(u.to_string().parse::<UnitSystem>().unwrap(), u);
+        }
+        assert_eq!("UK".parse::<UnitSystem>().unwrap(), UnitSystem::UK);
+        assert!("furlongs".parse::<UnitSystem>().is_err());
+    }
+}
@@ -0,0 +1,462 @@
+//! Builders for the main parts of the model. The model types all have public
+//! fields, so they can be constructed directly, but the builders are more
+//! convenient when writing a GPX from code, and they validate the coordinates.
+//!
+//! The model holds text the way it appears in the XML, i.e. still escaped, so
+//! that a file that is read and then written is unchanged. The builders take
+//! plain text and escape it, so "Fish & Chips" is stored as "Fish &amp; Chips".
+//!
+//! ```
+//! use gapix_model::model::{Gpx, Track, Waypoint};
+//!
+//! let track = Track::builder()
+//!     .name("Morning Ride")
+//!     .r#type("cycling")
+//!     .point(Waypoint::builder(53.0, -2.5).ele(100.0).build()?)
+//!     .point(Waypoint::builder(53.001, -2.5).ele(101.0).build()?)
+//!     .build();
+//!
+//! let gpx = Gpx::builder().creator("my program").track(track).build();
+//! assert_eq!(gpx.num_points(), 2);
+//! # Ok::<(), gapix_model::GpxError>(())
+//! ```
+
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+
+use crate::{
+    error::GpxError,
+    model::{
+        Degrees, Extensions, FixType, GarminRoutePointExtensions, GarminTrackpointExtensions,
+        GarminWaypointExtensions, Gpx, Latitude, Link, Longitude, Metadata, Route, Track,
+        TrackSegment, Waypoint,
+    },
+};
+
+/// A builder for [Gpx]. Create one using [Gpx::builder].
+#[derive(Debug, Clone, Default)]
+pub struct GpxBuilder {
+    gpx: Gpx,
+}
+
+impl Gpx {
+    /// Returns a builder for a Gpx. The Gpx has 'gapix' as the creator unless
+    /// [GpxBuilder::creator] is called.
+    pub fn builder() -> GpxBuilder {
+        GpxBuilder::default()
+    }
+}
+
+impl GpxBuilder {
+    /// Sets the 'creator' attribute, which is the program or device that
+    /// created the file.
+    pub fn creator<S: Into<String>>(mut self, creator: S) -> Self {
+        self.gpx.creator = escaped(creator);
+        self
+    }
+
+    /// Adds the namespace attributes that Garmin devices write, which are
+    /// needed if any of the points have Garmin extensions.
+    pub fn garmin_attributes(mut self) -> Self {
+        self.gpx.set_default_garmin_attributes();
+        self
+    }
+
+    /// Adds an attribute to the 'gpx' element, e.g. a namespace declaration.
+    pub fn attribute<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.gpx.attributes.insert(key.into(), escaped(value));
+        self
+    }
+
+    /// Replaces the metadata.
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.gpx.metadata = metadata;
+        self
+    }
+
+    /// Sets the name in the metadata.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.gpx.metadata.name = Some(escaped(name));
+        self
+    }
+
+    /// Sets the description in the metadata.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.gpx.metadata.description = Some(escaped(description));
+        self
+    }
+
+    /// Sets the creation time in the metadata.
+    pub fn time(mut self, time: DateTime<Utc>) -> Self {
+        self.gpx.metadata.time = Some(time);
+        self
+    }
+
+    /// Adds a file-level waypoint.
+    pub fn waypoint(mut self, waypoint: Waypoint) -> Self {
+        self.gpx.waypoints.push(waypoint);
+        self
+    }
+
+    /// Adds a route.
+    pub fn route(mut self, route: Route) -> Self {
+        self.gpx.routes.push(route);
+        self
+    }
+
+    /// Adds a track.
+    pub fn track(mut self, track: Track) -> Self {
+        self.gpx.tracks.push(track);
+        self
+    }
+
+    /// Sets the file-level extensions.
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.gpx.extensions = Some(extensions);
+        self
+    }
+
+    pub fn build(self) -> Gpx {
+        self.gpx
+    }
+}
+
+/// A builder for [Track]. Create one using [Track::builder].
+#[derive(Debug, Clone, Default)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl Track {
+    /// Returns a builder for a Track.
+    pub fn builder() -> TrackBuilder {
+        TrackBuilder::default()
+    }
+}
+
+impl TrackBuilder {
+    /// Sets the GPS name of the track.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.track.name = Some(escaped(name));
+        self
+    }
+
+    /// Sets the GPS comment for the track.
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.track.comment = Some(escaped(comment));
+        self
+    }
+
+    /// Sets the user description of the track.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.track.description = Some(escaped(description));
+        self
+    }
+
+    /// Sets the source of the data.
+    pub fn source<S: Into<String>>(mut self, source: S) -> Self {
+        self.track.source = Some(escaped(source));
+        self
+    }
+
+    /// Adds a link to an external resource.
+    pub fn link(mut self, link: Link) -> Self {
+        self.track.links.push(link);
+        self
+    }
+
+    /// Sets the GPS track number.
+    pub fn number(mut self, number: u32) -> Self {
+        self.track.number = Some(number);
+        self
+    }
+
+    /// Sets the type (classification) of the track, e.g. "cycling".
+    pub fn r#type<S: Into<String>>(mut self, r#type: S) -> Self {
+        self.track.r#type = Some(escaped(r#type));
+        self
+    }
+
+    /// Sets the track-level extensions.
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.track.extensions = Some(extensions);
+        self
+    }
+
+    /// Adds a point to the last segment of the track, creating the segment
+    /// if there isn't one yet.
+    pub fn point(mut self, point: Waypoint) -> Self {
+        if self.track.segments.is_empty() {
+            self.track.segments.push(TrackSegment::default());
+        }
+        self.track.segments.last_mut().unwrap().points.push(point);
+        self
+    }
+
+    /// Adds several points to the last segment of the track, creating the
+    /// segment if there isn't one yet.
+    pub fn points<I: IntoIterator<Item = Waypoint>>(mut self, points: I) -> Self {
+        if self.track.segments.is_empty() {
+            self.track.segments.push(TrackSegment::default());
+        }
+        self.track
+            .segments
+            .last_mut()
+            .unwrap()
+            .points
+            .extend(points);
+        self
+    }
+
+    /// Starts a new segment. Subsequent points are added to it. Use this
+    /// where GPS reception was lost.
+    pub fn new_segment(mut self) -> Self {
+        self.track.segments.push(TrackSegment::default());
+        self
+    }
+
+    pub fn build(self) -> Track {
+        self.track
+    }
+}
+
+/// A builder for [Waypoint]. Create one using [Waypoint::builder]. The lat and
+/// lon (and magvar and course, if set) are validated when
+/// [WaypointBuilder::build] is called.
+#[derive(Debug, Clone)]
+pub struct WaypointBuilder {
+    lat: f64,
+    lon: f64,
+    magvar: Option<f64>,
+    course: Option<f64>,
+    waypoint: Waypoint,
+}
+
+impl Waypoint {
+    /// Returns a builder for a Waypoint at the specified position, which is
+    /// in decimal degrees.
+    pub fn builder(lat: f64, lon: f64) -> WaypointBuilder {
+        WaypointBuilder {
+            lat,
+            lon,
+            magvar: None,
+            course: None,
+            waypoint: Waypoint::default(),
+        }
+    }
+}
+
+impl WaypointBuilder {
+    /// Sets the elevation, in metres.
+    pub fn ele(mut self, ele: f64) -> Self {
+        self.waypoint.ele = Some(ele);
+        self
+    }
+
+    /// Sets the time, which is always UTC.
+    pub fn time(mut self, time: DateTime<Utc>) -> Self {
+        self.waypoint.time = Some(time);
+        self
+    }
+
+    /// Sets the course, in degrees. This is only written to GPX 1.0.
+    pub fn course(mut self, course: f64) -> Self {
+        self.course = Some(course);
+        self
+    }
+
+    /// Sets the speed, in metres per second. This is only written to GPX 1.0.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.waypoint.speed = Some(speed);
+        self
+    }
+
+    /// Sets the magnetic variation, in degrees.
+    pub fn magvar(mut self, magvar: f64) -> Self {
+        self.magvar = Some(magvar);
+        self
+    }
+
+    /// Sets the GPS name of the waypoint.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.waypoint.name = Some(escaped(name));
+        self
+    }
+
+    /// Sets the GPS comment.
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.waypoint.comment = Some(escaped(comment));
+        self
+    }
+
+    /// Sets the user description.
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.waypoint.description = Some(escaped(description));
+        self
+    }
+
+    /// Sets the source of the data.
+    pub fn source<S: Into<String>>(mut self, source: S) -> Self {
+        self.waypoint.source = Some(escaped(source));
+        self
+    }
+
+    /// Adds a link to an external resource.
+    pub fn link(mut self, link: Link) -> Self {
+        self.waypoint.links.push(link);
+        self
+    }
+
+    /// Sets the GPS symbol name.
+    pub fn symbol<S: Into<String>>(mut self, symbol: S) -> Self {
+        self.waypoint.symbol = Some(escaped(symbol));
+        self
+    }
+
+    /// Sets the type (classification) of the waypoint.
+    pub fn r#type<S: Into<String>>(mut self, r#type: S) -> Self {
+        self.waypoint.r#type = Some(escaped(r#type));
+        self
+    }
+
+    /// Sets the type of GPS fix.
+    pub fn fix(mut self, fix: FixType) -> Self {
+        self.waypoint.fix = Some(fix);
+        self
+    }
+
+    /// Sets the extensions.
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.waypoint.extensions = Some(extensions);
+        self
+    }
+
+    /// Sets the Garmin trackpoint extensions (heart rate, cadence etc.).
+    pub fn garmin_extensions(mut self, garmin_extensions: GarminTrackpointExtensions) -> Self {
+        self.waypoint.garmin_extensions = Some(garmin_extensions);
+        self
+    }
+
+    /// Sets the Garmin waypoint extensions (categories, address etc.).
+    pub fn garmin_waypoint_extensions(
+        mut self,
+        garmin_waypoint_extensions: GarminWaypointExtensions,
+    ) -> Self {
+        self.waypoint.garmin_waypoint_extensions = Some(garmin_waypoint_extensions);
+        self
+    }
+
+    /// Sets the Garmin route point extensions (the shaping points of a
+    /// calculated route).
+    pub fn garmin_route_point_extensions(
+        mut self,
+        garmin_route_point_extensions: GarminRoutePointExtensions,
+    ) -> Self {
+        self.waypoint.garmin_route_point_extensions = Some(garmin_route_point_extensions);
+        self
+    }
+
+    /// Validates the waypoint and returns it.
+    pub fn build(mut self) -> Result<Waypoint, GpxError> {
+        self.waypoint.lat = Latitude::new(self.lat)?;
+        self.waypoint.lon = Longitude::new(self.lon)?;
+        self.waypoint.magvar = self.magvar.map(Degrees::new).transpose()?;
+        self.waypoint.course = self.course.map(Degrees::new).transpose()?;
+        Ok(self.waypoint)
+    }
+}
+
+/// Escapes the characters that are special in XML text and attribute values.
+fn escaped<S: Into<String>>(s: S) -> String {
+    escape(s.into().as_str()).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gpx_writer::{write_gpx_to_writer, OutputOptions},
+        read::read_gpx_from_slice,
+    };
+
+    #[test]
+    fn waypoint_builder_validates_lat_lon() {
+        assert!(Waypoint::builder(53.0, -2.5).build().is_ok());
+        assert!(matches!(
+            Waypoint::builder(90.5, -2.5).build(),
+            Err(GpxError::InvalidLatitude(_))
+        ));
+        assert!(matches!(
+            Waypoint::builder(53.0, -180.5).build(),
+            Err(GpxError::InvalidLongitude(_))
+        ));
+        assert!(matches!(
+            Waypoint::builder(53.0, -2.5).magvar(361.0).build(),
+            Err(GpxError::InvalidDegrees(_))
+        ));
+    }
+
+    #[test]
+    fn track_builder_adds_points_to_last_segment() {
+        let p = || Waypoint::builder(53.0, -2.5).build().unwrap();
+        let track = Track::builder()
+            .name("Ride")
+            .point(p())
+            .points([p(), p()])
+            .new_segment()
+            .point(p())
+            .build();
+
+        assert_eq!(track.name.as_deref(), Some("Ride"));
+        assert_eq!(track.segments.len(), 2);
+        assert_eq!(track.segments[0].points.len(), 3);
+        assert_eq!(track.segments[1].points.len(), 1);
+    }
+
+    #[test]
+    fn gpx_builder_defaults_to_gapix_creator() {
+        let gpx = Gpx::builder().name("Ride").build();
+        assert_eq!(gpx.creator, "gapix");
+        assert_eq!(gpx.version, "1.1");
+        assert_eq!(gpx.metadata.name.as_deref(), Some("Ride"));
+
+        let gpx = Gpx::builder().creator("Garmin").garmin_attributes().build();
+        assert_eq!(gpx.creator, "Garmin");
+        assert!(gpx.attributes.contains_key("xmlns:ns3"));
+    }
+
+    #[test]
+    fn text_is_escaped_so_it_round_trips() {
+        let gpx = Gpx::builder()
+            .creator("Fish & Chips \"Ltd\"")
+            .name("Fish & Chips <ride>")
+            .track(
+                Track::builder()
+                    .name("Out & back")
+                    .point(
+                        Waypoint::builder(53.0, -2.5)
+                            .name("<start>")
+                            .build()
+                            .unwrap(),
+                    )
+                    .build(),
+            )
+            .build();
+        assert_eq!(
+            gpx.metadata.name.as_deref(),
+            Some("Fish &amp; Chips &lt;ride&gt;")
+        );
+
+        let mut buffer: Vec<u8> = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx, OutputOptions::full()).unwrap();
+        let gpx2 = read_gpx_from_slice(&buffer).unwrap();
+
+        assert_eq!(gpx2.creator, gpx.creator);
+        assert_eq!(gpx2.metadata.name, gpx.metadata.name);
+        assert_eq!(gpx2.tracks[0].name, gpx.tracks[0].name);
+        assert_eq!(
+            gpx2.tracks[0].segments[0].points[0].name.as_deref(),
+            Some("&lt;start&gt;")
+        );
+    }
+}
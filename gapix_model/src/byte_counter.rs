@@ -1,6 +1,8 @@
 use std::io::{self, Seek, Write};
 
-pub(crate) struct ByteCounter<W> {
+/// A Write adapter that counts the bytes written through it, so that the size
+/// of output files can be logged.
+pub struct ByteCounter<W> {
     inner: W,
     count: usize,
 }
@@ -9,7 +11,7 @@ impl<W> ByteCounter<W>
 where
     W: Write,
 {
-    pub(crate) fn new(inner: W) -> Self {
+    pub fn new(inner: W) -> Self {
         ByteCounter { inner, count: 0 }
     }
 
@@ -17,7 +19,7 @@ where
     //     self.inner
     // }
 
-    pub(crate) fn bytes_written(&self) -> usize {
+    pub fn bytes_written(&self) -> usize {
         self.count
     }
 }
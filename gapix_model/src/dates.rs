@@ -0,0 +1,7 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Formats 'utc_date' into a string like "2024-09-01T05:10:44Z".
+/// This is the format that GPX files contain.
+pub fn format_utc_date(utc_date: &DateTime<Utc>) -> String {
+    utc_date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
@@ -0,0 +1,273 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    num::TryFromIntError,
+    path::{Path, PathBuf},
+};
+
+use crate::read::xml_reader_extensions::XmlReaderConversions;
+use quick_xml::events::attributes::AttrError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GpxError {
+    #[error(transparent)]
+    XmlError(#[from] quick_xml::Error),
+    #[error(transparent)]
+    XmlEncodingError(#[from] quick_xml::encoding::EncodingError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("Mandatory attribute {0} was not found on the element")]
+    MandatoryAttributeNotFound(String),
+    #[error("Mandatory element {0} was not found")]
+    MandatoryElementNotFound(String),
+    #[error("Could not parse {from} into type {dest_type}")]
+    ParseFailure { from: String, dest_type: String },
+    #[error("Unexpected Start element {0}")]
+    UnexpectedStartElement(String),
+    #[error("Unexpected End element {0}")]
+    UnexpectedEndElement(String),
+    #[error("Did not find the {0} element")]
+    ElementNotFound(String),
+    #[error("Element {element} must come before {after}")]
+    ElementOutOfOrder { element: String, after: String },
+    #[error("Element {element} has unexpected extra attributes {attributes}")]
+    UnexpectedAttributes { element: String, attributes: String },
+    #[error("Did not find an Event::Text element, buffer position = {0}, event={1}")]
+    MissingText(u64, String),
+    #[error("Date could not be parsed: {0}")]
+    DateParseFailure(String),
+    #[error("{0} is not a valid fix type. Valid values are 'none', '2d', '3d', 'dgps', 'pps'")]
+    InvalidFixType(String),
+    #[error("Could not create file {path:?}")]
+    CreateFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not perform a numeric conversion: {0}")]
+    NumericConversionError(String),
+    #[error("Unexpected event received from Xml parser: {0}")]
+    UnexpectedEvent(String),
+    #[error("Unexpected EOF. Check file for corruption")]
+    UnexpectedEof,
+    #[error("Invalid DGPS station Id of {0}. Valid range is 0..=1023")]
+    InvalidDGPSStationId(i64),
+    #[error("Invalid latitude of {0}. Valid range is -90.0..=90.0")]
+    InvalidLatitude(f64),
+    #[error("Invalid longitude of {0}. Valid range is -180.0..=180.0")]
+    InvalidLongitude(f64),
+    #[error("Invalid degrees of {0}. Valid range is 0.0..=360")]
+    InvalidDegrees(f64),
+    #[error("Cannot {0} at this point in the document")]
+    InvalidWriteOrder(&'static str),
+    #[error("{source} at {location}")]
+    Located {
+        location: Box<ErrorLocation>,
+        source: Box<GpxError>,
+    },
+}
+
+/// Where in a document a read error happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLocation {
+    /// The byte offset from the start of the document.
+    pub position: u64,
+    /// The 1-based line and column (counted in bytes). These are only known
+    /// when the document can be read again to find them, i.e. when reading
+    /// from a file or a slice.
+    pub line_column: Option<(usize, usize)>,
+    /// The path to the element being read, such as
+    /// `gpx/trk[0]/trkseg[2]/trkpt[10453]/time`. Elements that can repeat
+    /// have their (0-based) index. Empty if the error happened before the
+    /// 'gpx' element.
+    pub path: String,
+}
+
+impl fmt::Display for ErrorLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((line, column)) = self.line_column {
+            write!(f, "line {line}, column {column} (byte {})", self.position)?;
+        } else {
+            write!(f, "byte {}", self.position)?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<AttrError> for GpxError {
+    fn from(value: AttrError) -> Self {
+        Self::XmlError(value.into())
+    }
+}
+
+impl From<TryFromIntError> for GpxError {
+    fn from(value: TryFromIntError) -> Self {
+        Self::NumericConversionError(value.to_string())
+    }
+}
+
+impl GpxError {
+    pub(crate) fn bad_start<C: XmlReaderConversions>(bytes: &[u8], converter: &C) -> Self {
+        match converter.bytes_to_string(bytes) {
+            Ok(s) => Self::UnexpectedStartElement(s),
+            Err(err) => err,
+        }
+    }
+
+    pub(crate) fn bad_end<C: XmlReaderConversions>(bytes: &[u8], converter: &C) -> Self {
+        match converter.bytes_to_string(bytes) {
+            Ok(s) => Self::UnexpectedEndElement(s),
+            Err(err) => err,
+        }
+    }
+
+    pub(crate) fn bad_event(event: Result<quick_xml::events::Event<'_>, quick_xml::Error>) -> Self {
+        match event {
+            Ok(quick_xml::events::Event::Eof) => Self::UnexpectedEof,
+            Ok(event) => Self::UnexpectedEvent(format!("{:?}", event)),
+            Err(err) => err.into(),
+        }
+    }
+
+    /// Returns where in the document the error happened, if known. Errors from
+    /// the GPX readers have a location, other errors do not.
+    pub fn location(&self) -> Option<&ErrorLocation> {
+        match self {
+            Self::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without its location.
+    pub fn inner(&self) -> &GpxError {
+        match self {
+            Self::Located { source, .. } => source,
+            _ => self,
+        }
+    }
+
+    /// Fills in the line and column of a located error by reading the
+    /// document again up to the error position.
+    pub(crate) fn with_line_column<R: BufRead>(mut self, document: R) -> Self {
+        if let Self::Located { location, .. } = &mut self {
+            if let Ok(line_column) = find_line_column(document, location.position) {
+                location.line_column = Some(line_column);
+            }
+        }
+        self
+    }
+
+    /// Like [GpxError::with_line_column], but re-opens a file.
+    pub(crate) fn with_line_column_from_file(self, path: &Path) -> Self {
+        if self.location().is_none() {
+            return self;
+        }
+
+        match File::open(path) {
+            Ok(file) => self.with_line_column(BufReader::new(file)),
+            Err(_) => self,
+        }
+    }
+
+    /// Returns true if the error means that the rest of the document cannot
+    /// be read, because it ends early or is not well-formed XML.
+    pub(crate) fn is_truncation(&self) -> bool {
+        matches!(
+            self,
+            Self::UnexpectedEof
+                | Self::XmlError(quick_xml::Error::Syntax(_) | quick_xml::Error::IllFormed(_))
+        )
+    }
+}
+
+/// Returns the 1-based line and column of the byte at 'position'.
+fn find_line_column<R: BufRead>(document: R, position: u64) -> std::io::Result<(usize, usize)> {
+    let mut line = 1;
+    let mut column = 1;
+    for byte in document.bytes().take(position as usize) {
+        if byte? == b'\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    Ok((line, column))
+}
+
+/// A problem that was skipped over when reading a GPX in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadWarning {
+    /// An element that is not allowed within 'parent' was skipped, along with
+    /// everything inside it.
+    SkippedElement { parent: String, element: String },
+    /// Attributes that are not allowed on an element were ignored.
+    /// 'attributes' is a comma-separated list of their names.
+    SkippedAttributes { element: String, attributes: String },
+    /// The document ends early or stops being well-formed XML at byte
+    /// 'position', so everything after the last complete element was
+    /// discarded.
+    Truncated { position: u64, error: String },
+    /// There was no XML declaration, a default one was used.
+    MissingDeclaration,
+    /// An element within 'parent' came after 'after', which the XSD requires
+    /// it to come before. It was read anyway.
+    ElementOutOfOrder {
+        parent: String,
+        element: String,
+        after: String,
+    },
+    /// The value of an extension element that is read into a typed field,
+    /// such as the heart rate, could not be parsed or was out of range, so
+    /// it was ignored.
+    SkippedExtensionValue {
+        element: String,
+        value: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for ReadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SkippedElement { parent, element } => {
+                write!(f, "Skipped unexpected element {element} within {parent}")
+            }
+            Self::SkippedAttributes {
+                element,
+                attributes,
+            } => write!(
+                f,
+                "Ignored unexpected attributes {attributes} on element {element}"
+            ),
+            Self::Truncated { position, error } => write!(
+                f,
+                "Stopped reading at byte {position}, the document is truncated or malformed: {error}"
+            ),
+            Self::MissingDeclaration => write!(f, "The XML declaration is missing"),
+            Self::ElementOutOfOrder {
+                parent,
+                element,
+                after,
+            } => write!(
+                f,
+                "Element {element} within {parent} came after {after}, it should come before it"
+            ),
+            Self::SkippedExtensionValue {
+                element,
+                value,
+                error,
+            } => write!(
+                f,
+                "Ignored the value {value} of extension element {element}: {error}"
+            ),
+        }
+    }
+}
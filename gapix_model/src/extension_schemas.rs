@@ -0,0 +1,162 @@
+//! The registry of known trackpoint extension schemas. Devices and programs
+//! record sensor data such as heart rate and power in the extensions of
+//! trackpoints, but they do not agree on how. Each schema here lists the
+//! elements it uses and the typed field of [GarminTrackpointExtensions] that
+//! each one is read into, so analysis works the same whichever device recorded
+//! the file.
+//!
+//! Elements are matched on their namespace and local name. The prefixes vary
+//! from file to file, so they are resolved using the 'xmlns' attributes of the
+//! 'gpx' element and of the extensions themselves. Elements in other
+//! namespaces are ignored, however generic their names, because they may mean
+//! something else entirely.
+//!
+//! [GarminTrackpointExtensions]: crate::model::GarminTrackpointExtensions
+
+/// The typed fields that extension elements can be read into. See the fields
+/// of [GarminTrackpointExtensions](crate::model::GarminTrackpointExtensions)
+/// for the units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackpointField {
+    AirTemp,
+    WaterTemp,
+    Depth,
+    HeartRate,
+    Cadence,
+    Speed,
+    Course,
+    Bearing,
+    Power,
+}
+
+/// An element of an extension schema.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtensionElement {
+    /// The name of the element, without any namespace prefix.
+    pub local_name: &'static str,
+    /// The field the value of the element is read into.
+    pub field: TrackpointField,
+    /// The number the value is multiplied by to convert it to the units of
+    /// the field.
+    pub scale: f64,
+}
+
+/// A known schema of trackpoint extensions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExtensionSchema {
+    /// A name for people to read.
+    pub name: &'static str,
+    /// The namespace of the schema. None for elements that are written
+    /// without a prefix or a default namespace.
+    pub namespace: Option<&'static str>,
+    pub elements: &'static [ExtensionElement],
+}
+
+const fn element(local_name: &'static str, field: TrackpointField) -> ExtensionElement {
+    ExtensionElement {
+        local_name,
+        field,
+        scale: 1.0,
+    }
+}
+
+/// Version 2 of the Garmin TrackPointExtension is version 1 plus the speed,
+/// course and bearing, so they share the elements.
+const GARMIN_TRACKPOINT_ELEMENTS: &[ExtensionElement] = &[
+    element("atemp", TrackpointField::AirTemp),
+    element("wtemp", TrackpointField::WaterTemp),
+    element("depth", TrackpointField::Depth),
+    element("hr", TrackpointField::HeartRate),
+    element("cad", TrackpointField::Cadence),
+    element("speed", TrackpointField::Speed),
+    element("course", TrackpointField::Course),
+    element("bearing", TrackpointField::Bearing),
+];
+
+/// The known schemas.
+pub static KNOWN_EXTENSION_SCHEMAS: &[ExtensionSchema] = &[
+    ExtensionSchema {
+        name: "Garmin TrackPointExtension v1",
+        namespace: Some("http://www.garmin.com/xmlschemas/TrackPointExtension/v1"),
+        elements: GARMIN_TRACKPOINT_ELEMENTS,
+    },
+    ExtensionSchema {
+        name: "Garmin TrackPointExtension v2",
+        namespace: Some("http://www.garmin.com/xmlschemas/TrackPointExtension/v2"),
+        elements: GARMIN_TRACKPOINT_ELEMENTS,
+    },
+    ExtensionSchema {
+        name: "Garmin PowerExtension",
+        namespace: Some("http://www.garmin.com/xmlschemas/PowerExtension/v1"),
+        elements: &[
+            element("PowerInWatts", TrackpointField::Power),
+            ExtensionElement {
+                local_name: "PowerInMilliwatts",
+                field: TrackpointField::Power,
+                scale: 0.001,
+            },
+        ],
+    },
+    ExtensionSchema {
+        name: "ClueTrust GPXData",
+        namespace: Some("http://www.cluetrust.com/XML/GPXDATA/1/0"),
+        elements: &[
+            element("hr", TrackpointField::HeartRate),
+            element("cadence", TrackpointField::Cadence),
+            element("temp", TrackpointField::AirTemp),
+            element("speed", TrackpointField::Speed),
+        ],
+    },
+    ExtensionSchema {
+        name: "Plain elements, as written by Wahoo, Hammerhead, Strava and others",
+        namespace: None,
+        elements: &[
+            element("power", TrackpointField::Power),
+            element("heartrate", TrackpointField::HeartRate),
+            element("cadence", TrackpointField::Cadence),
+            element("temperature", TrackpointField::AirTemp),
+        ],
+    },
+];
+
+/// Returns the element of the known schemas with the namespace 'namespace'
+/// and the local name 'name'.
+pub fn find_extension_element(
+    namespace: Option<&str>,
+    name: &[u8],
+) -> Option<&'static ExtensionElement> {
+    KNOWN_EXTENSION_SCHEMAS
+        .iter()
+        .filter(|schema| schema.namespace == namespace)
+        .flat_map(|schema| schema.elements)
+        .find(|element| element.local_name.as_bytes() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GARMIN_V1: &str = "http://www.garmin.com/xmlschemas/TrackPointExtension/v1";
+    const CLUETRUST: &str = "http://www.cluetrust.com/XML/GPXDATA/1/0";
+
+    #[test]
+    fn elements_are_found_by_namespace_and_name() {
+        let element = find_extension_element(Some(GARMIN_V1), b"hr").unwrap();
+        assert_eq!(element.field, TrackpointField::HeartRate);
+
+        let element = find_extension_element(Some(CLUETRUST), b"temp").unwrap();
+        assert_eq!(element.field, TrackpointField::AirTemp);
+
+        let element = find_extension_element(None, b"power").unwrap();
+        assert_eq!(element.field, TrackpointField::Power);
+    }
+
+    #[test]
+    fn unknown_elements_are_not_found() {
+        assert!(find_extension_element(Some(GARMIN_V1), b"TrackPointExtension").is_none());
+        assert!(find_extension_element(Some(GARMIN_V1), b"Hr").is_none());
+        assert!(find_extension_element(Some(GARMIN_V1), b"power").is_none());
+        assert!(find_extension_element(Some("http://example.com/foo"), b"hr").is_none());
+        assert!(find_extension_element(None, b"hr").is_none());
+    }
+}
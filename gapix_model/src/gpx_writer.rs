@@ -0,0 +1,1889 @@
+use std::{
+    borrow::Cow,
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use indent_write::io::IndentWriter;
+use log::info;
+use logging_timer::time;
+
+use crate::{
+    byte_counter::ByteCounter,
+    dates::format_utc_date,
+    error::GpxError,
+    model::{
+        Bounds, Copyright, Email, Extensions, GarminRoutePointExtensions,
+        GarminTrackpointExtensions, GarminWaypointExtensions, Gpx, Link, Metadata, Person, Route,
+        Track, Waypoint, XmlDeclaration,
+    },
+};
+
+/// Controls what values get written when writing a GPX, and how. Start from
+/// [OutputOptions::full] or [OutputOptions::audax_uk_diy] and change the
+/// fields as needed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// Whether to write the GPX-level waypoints and routes.
+    pub waypoints_and_routes: bool,
+    /// Whether to write all the details of trackpoints. If false, only the
+    /// lat, lon, elevation and time are written (plus the extensions, subject
+    /// to [OutputOptions::extensions]).
+    pub full_trackpoints: bool,
+    /// Number of decimal places for latitudes and longitudes, including the
+    /// bounds. None writes the shortest representation that reads back to the
+    /// same value.
+    pub lat_lon_decimals: Option<usize>,
+    /// Number of decimal places for elevations.
+    pub elevation_decimals: Option<usize>,
+    /// Number of decimal places for the other numbers on a waypoint, i.e. the
+    /// magvar, geoid height, dilutions of precision and age of DGPS data.
+    pub other_decimals: Option<usize>,
+    /// Whether to write the elevation of points.
+    pub elevation: bool,
+    /// Whether to write the time of points.
+    pub time: bool,
+    /// Which extensions to write.
+    pub extensions: ExtensionsOutput,
+    /// If true, elements are not indented. Indentation is a large part of the
+    /// size of a GPX, because there are several elements per trackpoint.
+    pub compact: bool,
+    /// The version of GPX to write. This is used instead of [Gpx::version].
+    pub gpx_version: GpxVersion,
+    /// Whether to write the metadata bounds computed from the points by
+    /// [Gpx::compute_bounds], rather than the bounds in the metadata, which
+    /// are stale or missing after joining, trimming or simplifying.
+    pub recompute_bounds: bool,
+    /// Whether to write the time of the first trackpoint as the metadata time
+    /// and the name of the first track as the metadata name. If there is no
+    /// such time or name the metadata value is written as it is.
+    pub recompute_time_and_name: bool,
+}
+
+/// The version of GPX to write.
+///
+/// GPX 1.0 has no equivalent of some parts of the model, which are not written:
+/// the copyright, author link and metadata extensions, the 'type' of routes and
+/// tracks, the extensions of segments and all links except the first of each
+/// element. Other extensions are written as they are, directly within their
+/// element, which is how GPX 1.0 allows elements from other namespaces. The
+/// 'course' and 'speed' of trackpoints are only written in GPX 1.0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GpxVersion {
+    V1_0,
+    V1_1,
+}
+
+impl GpxVersion {
+    fn as_str(self) -> &'static str {
+        match self {
+            GpxVersion::V1_0 => "1.0",
+            GpxVersion::V1_1 => "1.1",
+        }
+    }
+
+    fn namespace(self) -> &'static str {
+        match self {
+            GpxVersion::V1_0 => "http://www.topografix.com/GPX/1/0",
+            GpxVersion::V1_1 => "http://www.topografix.com/GPX/1/1",
+        }
+    }
+}
+
+/// Controls which extensions are written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExtensionsOutput {
+    /// All extensions are written as they were read.
+    All,
+    /// Only the Garmin trackpoint extensions (heart rate, cadence and
+    /// temperatures) are written, from the values in
+    /// [Waypoint::garmin_extensions]. They use the 'ns3' namespace prefix, so
+    /// the Gpx should have the attributes from
+    /// [Gpx::set_default_garmin_attributes].
+    GarminTrackpoint,
+    /// Only the Garmin extensions of points are written, from the values in
+    /// [Waypoint::garmin_extensions], [Waypoint::garmin_waypoint_extensions]
+    /// and [Waypoint::garmin_route_point_extensions]. The GpxExtensions v3
+    /// elements use the 'ns2' namespace prefix, see
+    /// [ExtensionsOutput::GarminTrackpoint].
+    Garmin,
+    /// No extensions are written.
+    None,
+}
+
+impl OutputOptions {
+    /// Writes all the detail that exists in the model.
+    pub fn full() -> Self {
+        Self {
+            waypoints_and_routes: true,
+            full_trackpoints: true,
+            lat_lon_decimals: None,
+            elevation_decimals: None,
+            other_decimals: None,
+            elevation: true,
+            time: true,
+            extensions: ExtensionsOutput::All,
+            compact: false,
+            gpx_version: GpxVersion::V1_1,
+            recompute_bounds: false,
+            recompute_time_and_name: false,
+        }
+    }
+
+    /// Writes just enough data to be a valid Audax UK DIY submission. This will
+    /// exclude GPX-level waypoints and routes and all extensions, including
+    /// those of the metadata, tracks and segments, and waypoints within tracks
+    /// (aka trackpoints) will have the minimal required information instead of
+    /// full detail, with lat and lon to 6dp and elevation to 1dp. The rest of
+    /// the 'metadata' and the details of tracks are fully written because they
+    /// are a small part of the overall file size (which is dominated by
+    /// trackpoints).
+    pub fn audax_uk_diy() -> Self {
+        Self {
+            waypoints_and_routes: false,
+            full_trackpoints: false,
+            lat_lon_decimals: Some(6),
+            elevation_decimals: Some(1),
+            extensions: ExtensionsOutput::None,
+            ..Self::full()
+        }
+    }
+
+    fn indent(&self) -> &'static str {
+        if self.compact {
+            ""
+        } else {
+            "  "
+        }
+    }
+
+    /// Returns the metadata to write for `gpx`, with the values recomputed
+    /// that the options ask for.
+    fn metadata<'a>(&self, gpx: &'a Gpx) -> Cow<'a, Metadata> {
+        if !self.recompute_bounds && !self.recompute_time_and_name {
+            return Cow::Borrowed(&gpx.metadata);
+        }
+
+        let mut metadata = gpx.metadata.clone();
+        if self.recompute_bounds {
+            metadata.bounds = gpx.compute_bounds();
+        }
+        if self.recompute_time_and_name {
+            let time = gpx
+                .tracks
+                .iter()
+                .flat_map(|track| &track.segments)
+                .flat_map(|segment| &segment.points)
+                .find_map(|p| p.time);
+            if time.is_some() {
+                metadata.time = time;
+            }
+            if let Some(name) = gpx.tracks.iter().find_map(|track| track.name.as_ref()) {
+                metadata.name = Some(name.clone());
+            }
+        }
+
+        Cow::Owned(metadata)
+    }
+
+    /// Returns the extensions to write, given the extensions of an element
+    /// other than a trackpoint.
+    fn extensions<'a>(&self, extensions: Option<&'a Extensions>) -> Option<&'a Extensions> {
+        match self.extensions {
+            ExtensionsOutput::All => extensions,
+            ExtensionsOutput::GarminTrackpoint
+            | ExtensionsOutput::Garmin
+            | ExtensionsOutput::None => None,
+        }
+    }
+}
+
+impl Default for OutputOptions {
+    /// Returns OutputOptions::full().
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+/// Formats a number to a number of decimal places, or the shortest
+/// representation if that is None.
+struct Decimals(f64, Option<usize>);
+
+impl fmt::Display for Decimals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(decimals) => write!(f, "{:.*}", decimals, self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+/// Writes a GPX to file with full-fidelity, i.e. everything we can write is
+/// written.
+pub fn write_gpx_to_file<P: AsRef<Path>>(
+    output_file: P,
+    gpx: &Gpx,
+    options: OutputOptions,
+) -> Result<(), GpxError> {
+    let output_file = output_file.as_ref();
+
+    let file = match File::create(output_file) {
+        Ok(f) => f,
+        Err(err) => {
+            return Err(GpxError::CreateFile {
+                path: output_file.to_owned(),
+                source: err,
+            })
+        }
+    };
+
+    let w = BufWriter::new(file);
+    let mut w = ByteCounter::new(w);
+    write_gpx_to_writer(&mut w, gpx, options)?;
+    info!(
+        "Wrote GPX file {:?}, {} Kb",
+        output_file,
+        w.bytes_written() / 1024
+    );
+    Ok(())
+}
+
+/// Writes a GPX to the specified writer. What gets written is controlled by
+/// `output_options`.
+#[time]
+pub fn write_gpx_to_writer<W: Write>(
+    w: &mut W,
+    gpx: &Gpx,
+    output_options: OutputOptions,
+) -> Result<(), GpxError> {
+    let mut writer = GpxStreamWriter::new(w, output_options);
+    writer.open_document(gpx)?;
+    writer.write_metadata(&output_options.metadata(gpx))?;
+
+    for wp in &gpx.waypoints {
+        writer.write_waypoint(wp)?;
+    }
+    for route in &gpx.routes {
+        writer.write_route(route)?;
+    }
+
+    for track in &gpx.tracks {
+        writer.begin_track(track)?;
+        for segment in &track.segments {
+            writer.begin_segment()?;
+            for p in &segment.points {
+                writer.write_trackpoint(p)?;
+            }
+            writer.end_segment(segment.extensions.as_ref())?;
+        }
+        writer.end_track()?;
+    }
+
+    writer.finish(gpx.extensions.as_ref())?;
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum WriterState {
+    NotStarted,
+    InGpx,
+    InTrack,
+    InSegment,
+    Finished,
+}
+
+/// The parts of the 'gpx' element, in the order that the XSD requires them.
+/// A part can be skipped, but the writer cannot go back to an earlier one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    BeforeMetadata,
+    Metadata,
+    Waypoints,
+    Routes,
+    Tracks,
+}
+
+impl WriterState {
+    /// The number of indents for elements written in this state.
+    fn depth(self) -> usize {
+        match self {
+            WriterState::NotStarted | WriterState::Finished => 0,
+            WriterState::InGpx => 1,
+            WriterState::InTrack => 2,
+            WriterState::InSegment => 3,
+        }
+    }
+}
+
+/// Writes a GPX a piece at a time, so that a huge file can be written (for
+/// example while converting or filtering one read with
+/// [GpxStreamReader](crate::read::GpxStreamReader)) without holding the whole
+/// model in memory. The calls must be made in document order:
+///
+/// ```text
+/// open_document
+/// write_metadata
+/// write_waypoint (any number)
+/// write_route (any number)
+/// begin_track
+///     begin_segment
+///         write_trackpoint (any number)
+///     end_segment
+/// end_track
+/// finish
+/// ```
+///
+/// Anything else, such as writing a waypoint after a track, returns
+/// [GpxError::InvalidWriteOrder]. The [OutputOptions]
+/// are applied in the same way as [write_gpx_to_writer], so for example with
+/// [OutputOptions::audax_uk_diy] the file-level waypoints, routes and
+/// extensions are silently skipped. If [GpxStreamWriter::finish] is not called the
+/// document will not be complete.
+pub struct GpxStreamWriter<W> {
+    writer: W,
+    output_options: OutputOptions,
+    state: WriterState,
+    section: Section,
+}
+
+impl GpxStreamWriter<BufWriter<File>> {
+    /// Creates the output file and returns a writer for it.
+    pub fn create_file<P: AsRef<Path>>(
+        output_file: P,
+        output_options: OutputOptions,
+    ) -> Result<Self, GpxError> {
+        let output_file = output_file.as_ref();
+        match File::create(output_file) {
+            Ok(f) => Ok(Self::new(BufWriter::new(f), output_options)),
+            Err(err) => Err(GpxError::CreateFile {
+                path: output_file.to_owned(),
+                source: err,
+            }),
+        }
+    }
+}
+
+impl<W: Write> GpxStreamWriter<W> {
+    pub fn new(writer: W, output_options: OutputOptions) -> Self {
+        Self {
+            writer,
+            output_options,
+            state: WriterState::NotStarted,
+            section: Section::BeforeMetadata,
+        }
+    }
+
+    /// Writes the XML declaration and the opening 'gpx' element, using the
+    /// declaration, creator, version and attributes of `gpx`. Nothing else in
+    /// `gpx` is written.
+    pub fn open_document(&mut self, gpx: &Gpx) -> Result<(), GpxError> {
+        self.check_state(WriterState::NotStarted, "open the document")?;
+        write_declaration(&mut self.writer, &gpx.declaration)?;
+        write_gpx_open(&mut self.writer, gpx, self.output_options.gpx_version)?;
+        self.state = WriterState::InGpx;
+        Ok(())
+    }
+
+    /// Writes the metadata as it is. [OutputOptions::recompute_bounds] and
+    /// [OutputOptions::recompute_time_and_name] do not apply, because the
+    /// points have not been seen yet; use [Gpx::compute_bounds] beforehand if
+    /// the whole Gpx is available.
+    pub fn write_metadata(&mut self, metadata: &Metadata) -> Result<(), GpxError> {
+        self.check_state(WriterState::InGpx, "write the metadata")?;
+        self.advance(Section::Metadata, "write the metadata")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        match self.output_options.gpx_version {
+            GpxVersion::V1_0 => write_metadata_1_0(&mut w, metadata, &self.output_options),
+            GpxVersion::V1_1 => write_metadata(&mut w, metadata, &self.output_options),
+        }
+    }
+
+    /// Writes a file-level waypoint.
+    pub fn write_waypoint(&mut self, waypoint: &Waypoint) -> Result<(), GpxError> {
+        self.check_state(WriterState::InGpx, "write a waypoint")?;
+        self.advance(Section::Waypoints, "write a waypoint")?;
+        if !self.output_options.waypoints_and_routes {
+            return Ok(());
+        }
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        write_waypoint(&mut w, waypoint, "wpt", &self.output_options, true)
+    }
+
+    pub fn write_route(&mut self, route: &Route) -> Result<(), GpxError> {
+        self.check_state(WriterState::InGpx, "write a route")?;
+        self.advance(Section::Routes, "write a route")?;
+        if !self.output_options.waypoints_and_routes {
+            return Ok(());
+        }
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        write_route(&mut w, route, &self.output_options)
+    }
+
+    /// Opens a 'trk' element and writes the details of `track`. The segments
+    /// of `track` are not written, use [GpxStreamWriter::begin_segment] for
+    /// that.
+    pub fn begin_track(&mut self, track: &Track) -> Result<(), GpxError> {
+        self.check_state(WriterState::InGpx, "begin a track")?;
+        self.advance(Section::Tracks, "begin a track")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        writeln!(w, "<trk>")?;
+        w.indent();
+        write_track_details(&mut w, track, &self.output_options)?;
+        self.state = WriterState::InTrack;
+        Ok(())
+    }
+
+    pub fn begin_segment(&mut self) -> Result<(), GpxError> {
+        self.check_state(WriterState::InTrack, "begin a segment")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        writeln!(w, "<trkseg>")?;
+        self.state = WriterState::InSegment;
+        Ok(())
+    }
+
+    pub fn write_trackpoint(&mut self, point: &Waypoint) -> Result<(), GpxError> {
+        self.check_state(WriterState::InSegment, "write a trackpoint")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        let full = self.output_options.full_trackpoints;
+        write_waypoint(&mut w, point, "trkpt", &self.output_options, full)
+    }
+
+    /// Closes the current segment. In the XSD the extensions of a segment come
+    /// after its points, which is why they are passed here.
+    pub fn end_segment(&mut self, extensions: Option<&Extensions>) -> Result<(), GpxError> {
+        self.check_state(WriterState::InSegment, "end a segment")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        if self.output_options.gpx_version == GpxVersion::V1_1 {
+            write_extensions(
+                &mut w,
+                self.output_options.extensions(extensions),
+                GpxVersion::V1_1,
+            )?;
+        }
+        w.outdent();
+        writeln!(w, "</trkseg>")?;
+        self.state = WriterState::InTrack;
+        Ok(())
+    }
+
+    pub fn end_track(&mut self) -> Result<(), GpxError> {
+        self.check_state(WriterState::InTrack, "end a track")?;
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth() - 1);
+        writeln!(w, "</trk>")?;
+        self.state = WriterState::InGpx;
+        Ok(())
+    }
+
+    /// Closes any open segment and track, writes the file-level extensions
+    /// and closes the document. Returns the underlying writer, which has been
+    /// flushed.
+    pub fn finish(mut self, extensions: Option<&Extensions>) -> Result<W, GpxError> {
+        if self.state == WriterState::InSegment {
+            self.end_segment(None)?;
+        }
+        if self.state == WriterState::InTrack {
+            self.end_track()?;
+        }
+        self.check_state(WriterState::InGpx, "finish the document")?;
+
+        let mut w = IndentWriter::new(self.output_options.indent(), &mut self.writer);
+        indent_to(&mut w, self.state.depth());
+        write_extensions(
+            &mut w,
+            self.output_options.extensions(extensions),
+            self.output_options.gpx_version,
+        )?;
+        w.outdent();
+        writeln!(w, "</gpx>")?;
+
+        self.state = WriterState::Finished;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn check_state(&self, expected: WriterState, operation: &'static str) -> Result<(), GpxError> {
+        if self.state == expected {
+            Ok(())
+        } else {
+            Err(GpxError::InvalidWriteOrder(operation))
+        }
+    }
+
+    /// Moves on to `section`. There is only one metadata element, the other
+    /// sections can be written to several times.
+    fn advance(&mut self, section: Section, operation: &'static str) -> Result<(), GpxError> {
+        if section < self.section || (section == Section::Metadata && self.section == section) {
+            return Err(GpxError::InvalidWriteOrder(operation));
+        }
+        self.section = section;
+        Ok(())
+    }
+}
+
+fn indent_to<W: Write>(w: &mut IndentWriter<W>, depth: usize) {
+    for _ in 0..depth {
+        w.indent();
+    }
+}
+
+fn write_declaration<W: Write>(w: &mut W, declaration: &XmlDeclaration) -> Result<(), GpxError> {
+    write!(w, "<?xml version=\"{}\"", declaration.version)?;
+    if let Some(encoding) = &declaration.encoding {
+        write!(w, " encoding=\"{}\"", encoding)?;
+    }
+    if let Some(standalone) = &declaration.standalone {
+        write!(w, " standalone=\"{}\"", standalone)?;
+    }
+    writeln!(w, "?>")?;
+    Ok(())
+}
+
+/// Writes the opening 'gpx' element. The GPX namespace in the 'xmlns' and
+/// 'xsi:schemaLocation' attributes is changed to the one for `version`, so a
+/// Gpx read from one version can be written as the other.
+fn write_gpx_open<W: Write>(w: &mut W, info: &Gpx, version: GpxVersion) -> Result<(), GpxError> {
+    writeln!(
+        w,
+        "<gpx creator=\"{}\" version=\"{}\"",
+        info.creator,
+        version.as_str()
+    )?;
+    if version == GpxVersion::V1_0 && !info.attributes.contains_key("xmlns") {
+        writeln!(w, "  xmlns=\"{}\"", version.namespace())?;
+    }
+    for (key, value) in &info.attributes {
+        if key == "xmlns" || key == "xsi:schemaLocation" {
+            let value = value
+                .replace(GpxVersion::V1_0.namespace(), version.namespace())
+                .replace(GpxVersion::V1_1.namespace(), version.namespace());
+            writeln!(w, "  {}=\"{}\"", key, value)?;
+        } else {
+            writeln!(w, "  {}=\"{}\"", key, value)?;
+        }
+    }
+    writeln!(w, ">")?;
+    Ok(())
+}
+
+fn write_metadata<W: Write>(
+    w: &mut IndentWriter<W>,
+    metadata: &Metadata,
+    output_options: &OutputOptions,
+) -> Result<(), GpxError> {
+    writeln!(w, "<metadata>")?;
+    w.indent();
+    if let Some(name) = &metadata.name {
+        writeln!(w, "<name>{}</name>", name)?;
+    }
+    if let Some(desc) = &metadata.description {
+        writeln!(w, "<desc>{}</desc>", desc)?;
+    }
+    if let Some(author) = &metadata.author {
+        write_person(w, author, "author")?;
+    }
+    if let Some(copyright) = &metadata.copyright {
+        write_copyright(w, copyright)?;
+    }
+    for link in &metadata.links {
+        write_link(w, link)?;
+    }
+    if let Some(time) = &metadata.time {
+        writeln!(w, "<time>{}</time>", format_utc_date(time))?;
+    }
+    if let Some(keywords) = &metadata.keywords {
+        writeln!(w, "<keywords>{}</keywords>", keywords)?;
+    }
+    if let Some(bounds) = &metadata.bounds {
+        write_bounds(w, bounds, output_options.lat_lon_decimals)?;
+    }
+    write_extensions(
+        w,
+        output_options.extensions(metadata.extensions.as_ref()),
+        GpxVersion::V1_1,
+    )?;
+
+    w.outdent();
+    writeln!(w, "</metadata>")?;
+    Ok(())
+}
+
+/// GPX 1.0 has no 'metadata' element, the parts of it that exist in GPX 1.0
+/// are written directly within the 'gpx' element.
+fn write_metadata_1_0<W: Write>(
+    w: &mut W,
+    metadata: &Metadata,
+    output_options: &OutputOptions,
+) -> Result<(), GpxError> {
+    if let Some(name) = &metadata.name {
+        writeln!(w, "<name>{}</name>", name)?;
+    }
+    if let Some(desc) = &metadata.description {
+        writeln!(w, "<desc>{}</desc>", desc)?;
+    }
+    if let Some(author) = &metadata.author {
+        if let Some(name) = &author.name {
+            writeln!(w, "<author>{}</author>", name)?;
+        }
+        if let Some(email) = &author.email {
+            writeln!(w, "<email>{}@{}</email>", email.id, email.domain)?;
+        }
+    }
+    write_url_1_0(w, &metadata.links)?;
+    if let Some(time) = &metadata.time {
+        writeln!(w, "<time>{}</time>", format_utc_date(time))?;
+    }
+    if let Some(keywords) = &metadata.keywords {
+        writeln!(w, "<keywords>{}</keywords>", keywords)?;
+    }
+    if let Some(bounds) = &metadata.bounds {
+        write_bounds(w, bounds, output_options.lat_lon_decimals)?;
+    }
+    Ok(())
+}
+
+fn write_bounds<W: Write>(
+    w: &mut W,
+    bounds: &Bounds,
+    decimals: Option<usize>,
+) -> Result<(), GpxError> {
+    writeln!(
+        w,
+        "<bounds minlat=\"{}\" maxlat=\"{}\" minlon=\"{}\" maxlon=\"{}\"/>",
+        Decimals(bounds.min_lat.value(), decimals),
+        Decimals(bounds.max_lat.value(), decimals),
+        Decimals(bounds.min_lon.value(), decimals),
+        Decimals(bounds.max_lon.value(), decimals)
+    )?;
+    Ok(())
+}
+
+fn write_person<W: Write>(
+    w: &mut IndentWriter<W>,
+    person: &Person,
+    element_name: &str,
+) -> Result<(), GpxError> {
+    writeln!(w, "<{}>", element_name)?;
+    w.indent();
+    if let Some(name) = &person.name {
+        writeln!(w, "<name>{}</name>", name)?;
+    }
+    if let Some(email) = &person.email {
+        write_email(w, email)?;
+    }
+    if let Some(link) = &person.link {
+        write_link(w, link)?;
+    }
+    w.outdent();
+    writeln!(w, "</{}>", element_name)?;
+    Ok(())
+}
+
+fn write_copyright<W: Write>(
+    w: &mut IndentWriter<W>,
+    copyright: &Copyright,
+) -> Result<(), GpxError> {
+    writeln!(w, "<copyright>")?;
+    w.indent();
+    if let Some(year) = &copyright.year {
+        writeln!(w, "<year>{}</year>", year)?;
+    }
+    if let Some(license) = &copyright.license {
+        writeln!(w, "<license>{}</license>", license)?;
+    }
+    writeln!(w, "<author>{}</author>", copyright.author)?;
+    w.outdent();
+    writeln!(w, "</copyright>")?;
+    Ok(())
+}
+
+fn write_email<W: Write>(w: &mut W, email: &Email) -> Result<(), GpxError> {
+    writeln!(
+        w,
+        "<email id=\"{}\" domain=\"{}\" />",
+        email.id, email.domain
+    )?;
+    Ok(())
+}
+
+/// Writes the links of an element. GPX 1.0 only allows one link, as a 'url'
+/// and 'urlname' pair, so the first is used.
+fn write_links<W: Write>(
+    w: &mut IndentWriter<W>,
+    links: &[Link],
+    version: GpxVersion,
+) -> Result<(), GpxError> {
+    match version {
+        GpxVersion::V1_0 => write_url_1_0(w, links),
+        GpxVersion::V1_1 => {
+            for link in links {
+                write_link(w, link)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_url_1_0<W: Write>(w: &mut W, links: &[Link]) -> Result<(), GpxError> {
+    if let Some(link) = links.first() {
+        writeln!(w, "<url>{}</url>", link.href)?;
+        if let Some(text) = &link.text {
+            writeln!(w, "<urlname>{}</urlname>", text)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_link<W: Write>(w: &mut IndentWriter<W>, link: &Link) -> Result<(), GpxError> {
+    writeln!(w, "<link href=\"{}\">", link.href)?;
+    w.indent();
+    if let Some(text) = &link.text {
+        writeln!(w, "<text>{}</text>", text)?;
+    }
+    if let Some(r#type) = &link.r#type {
+        writeln!(w, "<type>{}</type>", r#type)?;
+    }
+    w.outdent();
+    writeln!(w, "</link>")?;
+    Ok(())
+}
+
+fn write_route<W: Write>(
+    w: &mut IndentWriter<W>,
+    route: &Route,
+    output_options: &OutputOptions,
+) -> Result<(), GpxError> {
+    writeln!(w, "<rte>")?;
+    w.indent();
+    if let Some(name) = &route.name {
+        writeln!(w, "<name>{}</name>", name)?;
+    }
+    if let Some(comment) = &route.comment {
+        writeln!(w, "<cmt>{}</cmt>", comment)?;
+    }
+    if let Some(desc) = &route.description {
+        writeln!(w, "<desc>{}</desc>", desc)?;
+    }
+    if let Some(source) = &route.source {
+        writeln!(w, "<src>{}</src>", source)?;
+    }
+    write_links(w, &route.links, output_options.gpx_version)?;
+    if let Some(number) = &route.number {
+        writeln!(w, "<number>{}</number>", number)?;
+    }
+    if let Some(route_type) = &route.r#type {
+        if output_options.gpx_version == GpxVersion::V1_1 {
+            writeln!(w, "<type>{}</type>", route_type)?;
+        }
+    }
+    write_extensions(
+        w,
+        output_options.extensions(route.extensions.as_ref()),
+        output_options.gpx_version,
+    )?;
+    for pt in &route.points {
+        write_waypoint(w, pt, "rtept", output_options, true)?;
+    }
+    w.outdent();
+    writeln!(w, "</rte>")?;
+    Ok(())
+}
+
+fn write_track_details<W: Write>(
+    w: &mut IndentWriter<W>,
+    track: &Track,
+    output_options: &OutputOptions,
+) -> Result<(), GpxError> {
+    if let Some(name) = &track.name {
+        writeln!(w, "<name>{}</name>", name)?;
+    }
+    if let Some(comment) = &track.comment {
+        writeln!(w, "<cmt>{}</cmt>", comment)?;
+    }
+    if let Some(desc) = &track.description {
+        writeln!(w, "<desc>{}</desc>", desc)?;
+    }
+    if let Some(source) = &track.source {
+        writeln!(w, "<src>{}</src>", source)?;
+    }
+    write_links(w, &track.links, output_options.gpx_version)?;
+    if let Some(number) = &track.number {
+        writeln!(w, "<number>{}</number>", number)?;
+    }
+    if let Some(track_type) = &track.r#type {
+        if output_options.gpx_version == GpxVersion::V1_1 {
+            writeln!(w, "<type>{}</type>", track_type)?;
+        }
+    }
+    write_extensions(
+        w,
+        output_options.extensions(track.extensions.as_ref()),
+        output_options.gpx_version,
+    )?;
+    Ok(())
+}
+
+/// Writes a waypoint, route point or trackpoint. If 'full' is false only the
+/// lat, lon, elevation and time are written, plus the extensions.
+fn write_waypoint<W: Write>(
+    w: &mut IndentWriter<W>,
+    point: &Waypoint,
+    element_name: &str,
+    output_options: &OutputOptions,
+    full: bool,
+) -> Result<(), GpxError> {
+    let lat_lon_decimals = output_options.lat_lon_decimals;
+    let other_decimals = output_options.other_decimals;
+
+    writeln!(
+        w,
+        "<{element_name} lat=\"{}\" lon=\"{}\">",
+        Decimals(point.lat.value(), lat_lon_decimals),
+        Decimals(point.lon.value(), lat_lon_decimals)
+    )?;
+    w.indent();
+    if let Some(ele) = point.ele {
+        if output_options.elevation {
+            writeln!(
+                w,
+                "<ele>{}</ele>",
+                Decimals(ele, output_options.elevation_decimals)
+            )?;
+        }
+    }
+    if let Some(t) = point.time {
+        if output_options.time {
+            writeln!(w, "<time>{}</time>", format_utc_date(&t))?;
+        }
+    }
+
+    let version = output_options.gpx_version;
+    if full && version == GpxVersion::V1_0 && element_name == "trkpt" {
+        if let Some(course) = point.course {
+            writeln!(
+                w,
+                "<course>{}</course>",
+                Decimals(course.value(), other_decimals)
+            )?;
+        }
+        if let Some(speed) = point.speed {
+            writeln!(w, "<speed>{}</speed>", Decimals(speed, other_decimals))?;
+        }
+    }
+
+    if full {
+        if let Some(magvar) = point.magvar {
+            writeln!(
+                w,
+                "<magvar>{}</magvar>",
+                Decimals(magvar.value(), other_decimals)
+            )?;
+        }
+        if let Some(geoid_height) = point.geoid_height {
+            writeln!(
+                w,
+                "<geoidheight>{}</geoidheight>",
+                Decimals(geoid_height, other_decimals)
+            )?;
+        }
+        if let Some(name) = &point.name {
+            writeln!(w, "<name>{name}</name>")?;
+        }
+        if let Some(comment) = &point.comment {
+            writeln!(w, "<cmt>{comment}</cmt>")?;
+        }
+        if let Some(desc) = &point.description {
+            writeln!(w, "<desc>{desc}</desc>")?;
+        }
+        if let Some(src) = &point.source {
+            writeln!(w, "<src>{src}</src>")?;
+        }
+        write_links(w, &point.links, version)?;
+        if let Some(sym) = &point.symbol {
+            writeln!(w, "<sym>{sym}</sym>")?;
+        }
+        if let Some(point_type) = &point.r#type {
+            writeln!(w, "<type>{point_type}</type>")?;
+        }
+        if let Some(fix) = &point.fix {
+            writeln!(w, "<fix>{fix}</fix>")?;
+        }
+        if let Some(sat) = &point.num_satellites {
+            writeln!(w, "<sat>{sat}</sat>")?;
+        }
+        if let Some(hdop) = point.hdop {
+            writeln!(w, "<hdop>{}</hdop>", Decimals(hdop, other_decimals))?;
+        }
+        if let Some(vdop) = point.vdop {
+            writeln!(w, "<vdop>{}</vdop>", Decimals(vdop, other_decimals))?;
+        }
+        if let Some(pdop) = point.pdop {
+            writeln!(w, "<pdop>{}</pdop>", Decimals(pdop, other_decimals))?;
+        }
+        if let Some(age) = point.age_of_dgps_data {
+            writeln!(
+                w,
+                "<ageofdgpsdata>{}</ageofdgpsdata>",
+                Decimals(age, other_decimals)
+            )?;
+        }
+        if let Some(id) = point.dgps_id {
+            writeln!(w, "<dgpsid>{id}</dgpsid>")?;
+        }
+    }
+
+    let mut garmin = String::new();
+    match output_options.extensions {
+        ExtensionsOutput::All => write_extensions(w, point.extensions.as_ref(), version)?,
+        ExtensionsOutput::GarminTrackpoint => {
+            if let Some(ext) = &point.garmin_extensions {
+                garmin += &garmin_trackpoint_extensions_xml(ext, other_decimals);
+            }
+        }
+        ExtensionsOutput::Garmin => {
+            if let Some(ext) = &point.garmin_extensions {
+                garmin += &garmin_trackpoint_extensions_xml(ext, other_decimals);
+            }
+            if let Some(ext) = &point.garmin_waypoint_extensions {
+                garmin += &garmin_waypoint_extensions_xml(ext, other_decimals);
+            }
+            if let Some(ext) = &point.garmin_route_point_extensions {
+                garmin += &garmin_route_point_extensions_xml(ext, lat_lon_decimals);
+            }
+        }
+        ExtensionsOutput::None => {}
+    }
+    if !garmin.is_empty() {
+        write_extensions(w, Some(&Extensions::new(garmin)), version)?;
+    }
+    w.outdent();
+    writeln!(w, "</{element_name}>")?;
+    Ok(())
+}
+
+/// Returns the Garmin trackpoint extensions, from the parsed values rather than
+/// the raw XML. Returns an empty string if none of the values are set. Power
+/// is not part of the TrackPointExtension, so it is not written.
+fn garmin_trackpoint_extensions_xml(
+    garmin: &GarminTrackpointExtensions,
+    decimals: Option<usize>,
+) -> String {
+    let mut xml = String::new();
+    if let Some(air_temp) = garmin.air_temp {
+        xml += &format!("<ns3:atemp>{}</ns3:atemp>", Decimals(air_temp, decimals));
+    }
+    if let Some(water_temp) = garmin.water_temp {
+        xml += &format!("<ns3:wtemp>{}</ns3:wtemp>", Decimals(water_temp, decimals));
+    }
+    if let Some(depth) = garmin.depth {
+        xml += &format!("<ns3:depth>{}</ns3:depth>", Decimals(depth, decimals));
+    }
+    if let Some(heart_rate) = garmin.heart_rate {
+        xml += &format!("<ns3:hr>{heart_rate}</ns3:hr>");
+    }
+    if let Some(cadence) = garmin.cadence {
+        xml += &format!("<ns3:cad>{cadence}</ns3:cad>");
+    }
+    if let Some(speed) = garmin.speed {
+        xml += &format!("<ns3:speed>{}</ns3:speed>", Decimals(speed, decimals));
+    }
+    if let Some(course) = garmin.course {
+        xml += &format!(
+            "<ns3:course>{}</ns3:course>",
+            Decimals(course.value(), decimals)
+        );
+    }
+    if let Some(bearing) = garmin.bearing {
+        xml += &format!(
+            "<ns3:bearing>{}</ns3:bearing>",
+            Decimals(bearing.value(), decimals)
+        );
+    }
+
+    if xml.is_empty() {
+        xml
+    } else {
+        format!("<ns3:TrackPointExtension>{xml}</ns3:TrackPointExtension>")
+    }
+}
+
+/// Returns the Garmin GpxExtensions v3 waypoint extension, from the parsed
+/// values rather than the raw XML.
+fn garmin_waypoint_extensions_xml(
+    garmin: &GarminWaypointExtensions,
+    decimals: Option<usize>,
+) -> String {
+    let mut xml = String::new();
+    if let Some(proximity) = garmin.proximity {
+        xml += &format!(
+            "<ns2:Proximity>{}</ns2:Proximity>",
+            Decimals(proximity, decimals)
+        );
+    }
+    if let Some(temperature) = garmin.temperature {
+        xml += &format!(
+            "<ns2:Temperature>{}</ns2:Temperature>",
+            Decimals(temperature, decimals)
+        );
+    }
+    if let Some(depth) = garmin.depth {
+        xml += &format!("<ns2:Depth>{}</ns2:Depth>", Decimals(depth, decimals));
+    }
+    if let Some(display_mode) = &garmin.display_mode {
+        xml += &format!("<ns2:DisplayMode>{display_mode}</ns2:DisplayMode>");
+    }
+    if !garmin.categories.is_empty() {
+        xml += "<ns2:Categories>";
+        for category in &garmin.categories {
+            xml += &format!("<ns2:Category>{category}</ns2:Category>");
+        }
+        xml += "</ns2:Categories>";
+    }
+    if let Some(address) = &garmin.address {
+        xml += "<ns2:Address>";
+        for street_address in &address.street_address {
+            xml += &format!("<ns2:StreetAddress>{street_address}</ns2:StreetAddress>");
+        }
+        if let Some(city) = &address.city {
+            xml += &format!("<ns2:City>{city}</ns2:City>");
+        }
+        if let Some(state) = &address.state {
+            xml += &format!("<ns2:State>{state}</ns2:State>");
+        }
+        if let Some(country) = &address.country {
+            xml += &format!("<ns2:Country>{country}</ns2:Country>");
+        }
+        if let Some(postal_code) = &address.postal_code {
+            xml += &format!("<ns2:PostalCode>{postal_code}</ns2:PostalCode>");
+        }
+        xml += "</ns2:Address>";
+    }
+    for phone_number in &garmin.phone_numbers {
+        match &phone_number.category {
+            Some(category) => xml += &format!("<ns2:PhoneNumber Category=\"{category}\">"),
+            None => xml += "<ns2:PhoneNumber>",
+        }
+        xml += &format!("{}</ns2:PhoneNumber>", phone_number.number);
+    }
+
+    format!("<ns2:WaypointExtension>{xml}</ns2:WaypointExtension>")
+}
+
+/// Returns the Garmin GpxExtensions v3 route point extension, from the parsed
+/// values rather than the raw XML.
+fn garmin_route_point_extensions_xml(
+    garmin: &GarminRoutePointExtensions,
+    decimals: Option<usize>,
+) -> String {
+    let mut xml = String::new();
+    if let Some(subclass) = &garmin.subclass {
+        xml += &format!("<ns2:Subclass>{subclass}</ns2:Subclass>");
+    }
+    for point in &garmin.points {
+        let lat = Decimals(point.lat.value(), decimals);
+        let lon = Decimals(point.lon.value(), decimals);
+        match &point.subclass {
+            Some(subclass) => {
+                xml += &format!(
+                    "<ns2:rpt lat=\"{lat}\" lon=\"{lon}\"><ns2:Subclass>{subclass}</ns2:Subclass></ns2:rpt>"
+                )
+            }
+            None => xml += &format!("<ns2:rpt lat=\"{lat}\" lon=\"{lon}\"/>"),
+        }
+    }
+
+    format!("<ns2:RoutePointExtension>{xml}</ns2:RoutePointExtension>")
+}
+
+/// Writes extensions. In GPX 1.0 there is no 'extensions' element, the raw XML
+/// is written directly within the parent element.
+fn write_extensions<W: Write>(
+    w: &mut W,
+    extensions: Option<&Extensions>,
+    version: GpxVersion,
+) -> Result<(), GpxError> {
+    if let Some(ext) = extensions {
+        match version {
+            GpxVersion::V1_0 => writeln!(w, "{}", ext.raw_xml)?,
+            GpxVersion::V1_1 => writeln!(w, "<extensions>{}</extensions>", ext.raw_xml)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::{
+        model::*,
+        read::{read_gpx_from_slice, GpxItem, GpxStreamReader},
+    };
+    use std::{collections::HashMap, iter::zip};
+
+    /// We construct 'gpx1', then write it to a buffer. We then
+    /// deserialize the buffer into 'gpx2'. The two models should be identical
+    /// if we did everything correctly.
+    #[test]
+    fn round_trip_entire_model() {
+        let gpx1 = make_fully_populated_gpx();
+        let mut buffer = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx1, OutputOptions::full()).unwrap();
+
+        // write_gpx_to_file(
+        //     "/home/phil/repos/mine/gapix/target/release/round_trip.gpx",
+        //     &gpx1,
+        //     OutputOptions::full(),
+        // )
+        // .unwrap();
+
+        let gpx2 = read_gpx_from_slice(&buffer).unwrap();
+
+        // Filename is not written to or parsed from the buffer.
+
+        assert_eq!(gpx1.declaration, gpx2.declaration);
+        assert_eq!(gpx1.version, gpx2.version);
+        assert_eq!(gpx1.creator, gpx2.creator);
+        assert_eq!(gpx1.attributes, gpx2.attributes);
+        compare_metadata(&gpx1.metadata, &gpx2.metadata);
+        for (wp1, wp2) in zip(&gpx1.waypoints, &gpx2.waypoints) {
+            compare_waypoint(wp1, wp2);
+        }
+        for (route1, route2) in zip(&gpx1.routes, &gpx2.routes) {
+            compare_route(route1, route2);
+        }
+        for (track1, track2) in zip(&gpx1.tracks, &gpx2.tracks) {
+            compare_track(track1, track2);
+        }
+        assert_eq!(gpx1.extensions, gpx2.extensions);
+    }
+
+    /// Copying a document a piece at a time with the stream reader and writer
+    /// gives exactly the same output as writing the whole model.
+    #[test]
+    fn stream_copy_matches_full_write() {
+        let mut gpx = make_fully_populated_gpx();
+        // The attributes are in a HashMap, keep just one so the order is fixed.
+        gpx.attributes.remove("key2");
+        let mut expected = Vec::new();
+        write_gpx_to_writer(&mut expected, &gpx, OutputOptions::full()).unwrap();
+
+        let mut writer = GpxStreamWriter::new(Vec::new(), OutputOptions::full());
+        let mut gpx_extensions = None;
+        for item in GpxStreamReader::new(expected.as_slice()) {
+            match item.unwrap() {
+                GpxItem::Start(start) => writer.open_document(&start).unwrap(),
+                GpxItem::Metadata(metadata) => writer.write_metadata(&metadata).unwrap(),
+                GpxItem::Waypoint(waypoint) => writer.write_waypoint(&waypoint).unwrap(),
+                GpxItem::Route(route) => writer.write_route(&route).unwrap(),
+                GpxItem::TrackStart(track) => writer.begin_track(&track).unwrap(),
+                GpxItem::SegmentStart => writer.begin_segment().unwrap(),
+                GpxItem::TrackPoint(point) => writer.write_trackpoint(&point).unwrap(),
+                GpxItem::SegmentEnd { extensions } => {
+                    writer.end_segment(extensions.as_ref()).unwrap()
+                }
+                GpxItem::TrackEnd => writer.end_track().unwrap(),
+                GpxItem::Extensions(extensions) => gpx_extensions = Some(extensions),
+            }
+        }
+        let actual = writer.finish(gpx_extensions.as_ref()).unwrap();
+
+        assert_eq!(
+            String::from_utf8(actual).unwrap(),
+            String::from_utf8(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn stream_writer_rejects_calls_out_of_order() {
+        let point = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        let mut writer = GpxStreamWriter::new(Vec::new(), OutputOptions::full());
+        assert!(matches!(
+            writer.write_trackpoint(&point),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+
+        writer.open_document(&Gpx::default()).unwrap();
+        assert!(matches!(
+            writer.begin_segment(),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+
+        writer.begin_track(&Track::default()).unwrap();
+        assert!(matches!(
+            writer.write_waypoint(&point),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+        writer.begin_segment().unwrap();
+        writer.write_trackpoint(&point).unwrap();
+        writer.end_segment(None).unwrap();
+        writer.end_track().unwrap();
+        assert!(matches!(
+            writer.write_waypoint(&point),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+        assert!(matches!(
+            writer.write_route(&Route::default()),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+        writer.begin_track(&Track::default()).unwrap();
+        writer.begin_segment().unwrap();
+
+        // finish() closes the open segment and track.
+        let output = writer.finish(None).unwrap();
+        let gpx = read_gpx_from_slice(&output).unwrap();
+        assert_eq!(gpx.tracks[0].segments[0].points.len(), 1);
+        assert_eq!(gpx.tracks.len(), 2);
+    }
+
+    #[test]
+    fn stream_writer_rejects_going_back_to_an_earlier_section() {
+        let point = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        let mut writer = GpxStreamWriter::new(Vec::new(), OutputOptions::full());
+        writer.open_document(&Gpx::default()).unwrap();
+        writer.write_metadata(&Metadata::default()).unwrap();
+        assert!(matches!(
+            writer.write_metadata(&Metadata::default()),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+
+        writer.write_waypoint(&point).unwrap();
+        writer.write_waypoint(&point).unwrap();
+        assert!(matches!(
+            writer.write_metadata(&Metadata::default()),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+
+        writer.write_route(&Route::default()).unwrap();
+        assert!(matches!(
+            writer.write_waypoint(&point),
+            Err(GpxError::InvalidWriteOrder(_))
+        ));
+
+        let output = writer.finish(None).unwrap();
+        let gpx = read_gpx_from_slice(&output).unwrap();
+        assert_eq!(gpx.waypoints.len(), 2);
+        assert_eq!(gpx.routes.len(), 1);
+    }
+
+    #[test]
+    fn stream_writer_applies_output_options() {
+        let point = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        let mut writer = GpxStreamWriter::new(Vec::new(), OutputOptions::audax_uk_diy());
+        writer.open_document(&Gpx::default()).unwrap();
+        writer.write_waypoint(&point).unwrap();
+        writer.begin_track(&Track::default()).unwrap();
+        writer.begin_segment().unwrap();
+        writer.write_trackpoint(&point).unwrap();
+        let output = writer
+            .finish(Some(&Extensions::new("<foo>bar</foo>")))
+            .unwrap();
+
+        let gpx = read_gpx_from_slice(&output).unwrap();
+        assert!(gpx.waypoints.is_empty());
+        assert!(gpx.extensions.is_none());
+        assert_eq!(gpx.num_points(), 1);
+    }
+
+    /// Writes a Gpx containing one trackpoint and returns the trackpoint
+    /// element as a string.
+    fn write_trackpoint_with(point: Waypoint, output_options: OutputOptions) -> String {
+        let mut gpx = Gpx::default();
+        let mut track = Track::default();
+        let mut segment = TrackSegment::default();
+        segment.points.push(point);
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+        let mut buffer = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx, output_options).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let start = output.find("<trkpt").unwrap();
+        let end = output.find("</trkpt>").unwrap() + "</trkpt>".len();
+        output[start..end].to_string()
+    }
+
+    fn make_detailed_trackpoint() -> Waypoint {
+        let mut point = Waypoint::with_lat_lon(53.123456789, -2.987654321).unwrap();
+        point.ele = Some(101.2345);
+        point.time = Some(DateTime::from_timestamp(1725166800, 0).unwrap());
+        point.hdop = Some(1.23456);
+        point.name = Some("Point".to_string());
+        point.extensions = Some(Extensions::new("<foo>bar</foo>"));
+        point.garmin_extensions = Some(GarminTrackpointExtensions {
+            air_temp: Some(15.25),
+            heart_rate: Some(120),
+            cadence: Some(85),
+            ..Default::default()
+        });
+        point
+    }
+
+    #[test]
+    fn output_options_full_writes_everything() {
+        let output = write_trackpoint_with(make_detailed_trackpoint(), OutputOptions::full());
+        assert!(output.contains(r#"lat="53.123456789" lon="-2.987654321""#));
+        assert!(output.contains("<ele>101.2345</ele>"));
+        assert!(output.contains("<time>"));
+        assert!(output.contains("<hdop>1.23456</hdop>"));
+        assert!(output.contains("<name>Point</name>"));
+        assert!(output.contains("<extensions><foo>bar</foo></extensions>"));
+    }
+
+    #[test]
+    fn output_options_audax_uk_diy_writes_minimal_trackpoints() {
+        let output =
+            write_trackpoint_with(make_detailed_trackpoint(), OutputOptions::audax_uk_diy());
+        assert!(output.contains(r#"lat="53.123457" lon="-2.987654""#));
+        assert!(output.contains("<ele>101.2</ele>"));
+        assert!(output.contains("<time>"));
+        assert!(!output.contains("<hdop>"));
+        assert!(!output.contains("<name>"));
+        assert!(!output.contains("<extensions>"));
+    }
+
+    #[test]
+    fn output_options_precision() {
+        let options = OutputOptions {
+            lat_lon_decimals: Some(3),
+            elevation_decimals: Some(0),
+            other_decimals: Some(1),
+            ..OutputOptions::full()
+        };
+        let output = write_trackpoint_with(make_detailed_trackpoint(), options);
+        assert!(output.contains(r#"lat="53.123" lon="-2.988""#));
+        assert!(output.contains("<ele>101</ele>"));
+        assert!(output.contains("<hdop>1.2</hdop>"));
+    }
+
+    #[test]
+    fn output_options_recompute_metadata() {
+        let mut gpx = Gpx::default();
+        gpx.metadata.name = Some("Old name".to_string());
+        gpx.metadata.bounds = Some(Bounds::new(1.0, 2.0, 3.0, 4.0).unwrap());
+        let mut wp = Waypoint::with_lat_lon(53.5, -2.5).unwrap();
+        gpx.waypoints.push(wp.clone());
+        wp = Waypoint::with_lat_lon(53.0, -2.0).unwrap();
+        wp.time = Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        gpx.tracks.push(Track {
+            name: Some("Ride".to_string()),
+            segments: vec![TrackSegment {
+                points: vec![wp],
+                extensions: None,
+            }],
+            ..Default::default()
+        });
+
+        let write = |options| {
+            let mut buffer = Vec::new();
+            write_gpx_to_writer(&mut buffer, &gpx, options).unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let output = write(OutputOptions::full());
+        assert!(output.contains(r#"<bounds minlat="1" maxlat="3" minlon="2" maxlon="4"/>"#));
+        assert!(output.contains("<name>Old name</name>"));
+
+        let output = write(OutputOptions {
+            recompute_bounds: true,
+            ..OutputOptions::full()
+        });
+        assert!(output.contains(r#"<bounds minlat="53" maxlat="53.5" minlon="-2.5" maxlon="-2"/>"#));
+        assert!(output.contains("<name>Old name</name>"));
+
+        let output = write(OutputOptions {
+            recompute_time_and_name: true,
+            ..OutputOptions::full()
+        });
+        assert!(output.contains(r#"<bounds minlat="1" "#));
+        assert!(output.contains("<name>Ride</name>\n    <time>2023-11-14T22:13:20Z</time>"));
+    }
+
+    #[test]
+    fn output_options_can_drop_time_and_elevation() {
+        let options = OutputOptions {
+            time: false,
+            elevation: false,
+            ..OutputOptions::full()
+        };
+        let output = write_trackpoint_with(make_detailed_trackpoint(), options);
+        assert!(!output.contains("<ele>"));
+        assert!(!output.contains("<time>"));
+        assert!(output.contains("<name>Point</name>"));
+    }
+
+    #[test]
+    fn output_options_garmin_trackpoint_extensions() {
+        let options = OutputOptions {
+            extensions: ExtensionsOutput::GarminTrackpoint,
+            ..OutputOptions::full()
+        };
+        let output = write_trackpoint_with(make_detailed_trackpoint(), options);
+        assert!(!output.contains("<foo>"));
+        assert!(output.contains(
+            "<extensions><ns3:TrackPointExtension><ns3:atemp>15.25</ns3:atemp>\
+             <ns3:hr>120</ns3:hr><ns3:cad>85</ns3:cad></ns3:TrackPointExtension></extensions>"
+        ));
+    }
+
+    #[test]
+    fn output_options_garmin_round_trips_typed_extensions() {
+        let mut waypoint = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        waypoint.extensions = Some(Extensions::new("<foo>bar</foo>"));
+        waypoint.garmin_waypoint_extensions = Some(GarminWaypointExtensions {
+            proximity: Some(25.0),
+            categories: vec!["Cafes".to_string()],
+            address: Some(GarminAddress {
+                street_address: vec!["1 High Street".to_string()],
+                city: Some("Chester".to_string()),
+                ..Default::default()
+            }),
+            phone_numbers: vec![GarminPhoneNumber {
+                number: "01234 567890".to_string(),
+                category: Some("Phone".to_string()),
+            }],
+            ..Default::default()
+        });
+
+        let mut route_point = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        route_point.garmin_route_point_extensions = Some(GarminRoutePointExtensions {
+            subclass: Some("000000000000FFFFFFFFFFFFFFFFFFFFFFFF".to_string()),
+            points: vec![
+                GarminAutoRoutePoint {
+                    lat: Latitude::new(53.1).unwrap(),
+                    lon: Longitude::new(-2.6).unwrap(),
+                    subclass: Some("1100000000000000".to_string()),
+                },
+                GarminAutoRoutePoint {
+                    lat: Latitude::new(53.2).unwrap(),
+                    lon: Longitude::new(-2.7).unwrap(),
+                    subclass: None,
+                },
+            ],
+        });
+
+        let mut trackpoint = make_detailed_trackpoint();
+        let garmin = trackpoint.garmin_extensions.as_mut().unwrap();
+        garmin.speed = Some(8.5);
+        garmin.course = Some(Degrees::new(270.0).unwrap());
+        garmin.bearing = Some(Degrees::new(265.5).unwrap());
+
+        let mut gpx1 = Gpx::default();
+        gpx1.set_default_garmin_attributes();
+        gpx1.waypoints.push(waypoint);
+        gpx1.routes.push(Route {
+            points: vec![route_point],
+            ..Default::default()
+        });
+        gpx1.tracks.push(Track {
+            segments: vec![TrackSegment {
+                points: vec![trackpoint],
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        let options = OutputOptions {
+            extensions: ExtensionsOutput::Garmin,
+            ..OutputOptions::full()
+        };
+        let mut buffer = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx1, options).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(!output.contains("<foo>"));
+
+        let gpx2 = read_gpx_from_slice(output.as_bytes()).unwrap();
+        assert_eq!(
+            gpx1.waypoints[0].garmin_waypoint_extensions,
+            gpx2.waypoints[0].garmin_waypoint_extensions
+        );
+        assert_eq!(
+            gpx1.routes[0].points[0].garmin_route_point_extensions,
+            gpx2.routes[0].points[0].garmin_route_point_extensions
+        );
+        let garmin2 = gpx2.tracks[0].segments[0].points[0]
+            .garmin_extensions
+            .as_ref()
+            .unwrap();
+        assert_eq!(garmin2.heart_rate, Some(120));
+        assert_eq!(garmin2.speed, Some(8.5));
+        assert_eq!(garmin2.course.unwrap().value(), 270.0);
+        assert_eq!(garmin2.bearing.unwrap().value(), 265.5);
+    }
+
+    #[test]
+    fn output_options_compact() {
+        let gpx = make_fully_populated_gpx();
+        let mut buffer = Vec::new();
+        let options = OutputOptions {
+            compact: true,
+            ..OutputOptions::full()
+        };
+        write_gpx_to_writer(&mut buffer, &gpx, options).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        // Only the attributes of the gpx element, which are on lines of their
+        // own, are indented.
+        assert!(output
+            .lines()
+            .filter(|line| line.starts_with(' '))
+            .all(|line| !line.contains('<')));
+
+        // Compact output reads back to the same model.
+        let gpx2 = read_gpx_from_slice(output.as_bytes()).unwrap();
+        for (track1, track2) in zip(&gpx.tracks, &gpx2.tracks) {
+            compare_track(track1, track2);
+        }
+    }
+
+    #[test]
+    fn gpx_1_0_round_trip() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.0" creator="Logger" xmlns:logger="http://example.com/logger">
+  <name>Old ride</name>
+  <author>Phil</author>
+  <email>phil@example.com</email>
+  <url>http://example.com/ride</url>
+  <urlname>The ride</urlname>
+  <bounds minlat="53.0" minlon="-2.6" maxlat="53.1" maxlon="-2.5"/>
+  <rte>
+    <name>Route</name>
+    <url>http://example.com/route</url>
+    <rtept lat="53.0" lon="-2.5"></rtept>
+  </rte>
+  <trk>
+    <name>Track</name>
+    <logger:device>XYZ</logger:device>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5">
+        <time>2024-03-01T09:00:00Z</time>
+        <course>123.5</course>
+        <speed>5.25</speed>
+        <logger:hr>120</logger:hr>
+      </trkpt>
+    </trkseg>
+  </trk>
+  <logger:summary distance="1000"/>
+</gpx>"#;
+        let gpx1 = read_gpx_from_slice(data.as_bytes()).unwrap();
+        let options = OutputOptions {
+            gpx_version: GpxVersion::V1_0,
+            ..OutputOptions::full()
+        };
+        let mut buffer = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx1, options).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.contains(r#"version="1.0""#));
+        assert!(output.contains(r#"xmlns="http://www.topografix.com/GPX/1/0""#));
+        assert!(!output.contains("<metadata>"));
+        assert!(!output.contains("<extensions>"));
+
+        let gpx2 = read_gpx_from_slice(output.as_bytes()).unwrap();
+        assert_eq!(gpx2.version, "1.0");
+        compare_metadata(&gpx1.metadata, &gpx2.metadata);
+        compare_route(&gpx1.routes[0], &gpx2.routes[0]);
+        compare_track(&gpx1.tracks[0], &gpx2.tracks[0]);
+        assert_eq!(gpx1.extensions, gpx2.extensions);
+    }
+
+    #[test]
+    fn gpx_1_0_flattens_metadata_and_drops_what_it_cannot_represent() {
+        let gpx = make_fully_populated_gpx();
+        let options = OutputOptions {
+            gpx_version: GpxVersion::V1_0,
+            extensions: ExtensionsOutput::None,
+            ..OutputOptions::full()
+        };
+        let mut buffer = Vec::new();
+        write_gpx_to_writer(&mut buffer, &gpx, options).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.contains("<author>First Person</author>"));
+        assert!(output.contains("<email>first_person@gmail.com</email>"));
+        assert!(output.contains("<url>http://jpeg1.com</url>"));
+        assert!(output.contains("<urlname>Metadata Link 1</urlname>"));
+        assert!(!output.contains("http://mp3.com"));
+        assert!(!output.contains("<copyright>"));
+        assert!(!output.contains("<link"));
+    }
+
+    #[test]
+    fn course_and_speed_are_only_written_to_gpx_1_0() {
+        let mut point = make_detailed_trackpoint();
+        point.course = Some(Degrees::new(90.0).unwrap());
+        point.speed = Some(4.5);
+
+        let output = write_trackpoint_with(point.clone(), OutputOptions::full());
+        assert!(!output.contains("<course>"));
+        assert!(!output.contains("<speed>"));
+
+        let options = OutputOptions {
+            gpx_version: GpxVersion::V1_0,
+            ..OutputOptions::full()
+        };
+        let output = write_trackpoint_with(point, options);
+        let time = output.find("<time>").unwrap();
+        let course = output.find("<course>90</course>").unwrap();
+        let speed = output.find("<speed>4.5</speed>").unwrap();
+        let hdop = output.find("<hdop>").unwrap();
+        assert!(time < course && course < speed && speed < hdop);
+        assert!(output.contains("<foo>bar</foo>"));
+    }
+
+    fn compare_metadata(md1: &Metadata, md2: &Metadata) {
+        assert_eq!(md1.name, md2.name);
+        assert_eq!(md1.description, md2.description);
+        assert_eq!(md1.author, md2.author);
+        assert_eq!(md1.copyright, md2.copyright);
+        assert_eq!(md1.links, md2.links);
+        assert_eq!(md1.time, md2.time);
+        assert_eq!(md1.keywords, md2.keywords);
+        let b1 = md1.bounds.as_ref().unwrap();
+        let b2 = md2.bounds.as_ref().unwrap();
+        assert_eq!(b1.min_lat, b2.min_lat);
+        assert_eq!(b1.min_lon, b2.min_lon);
+        assert_eq!(b1.max_lat, b2.max_lat);
+        assert_eq!(b1.max_lon, b2.max_lon);
+        assert_eq!(md1.extensions, md2.extensions);
+    }
+
+    fn compare_route(route1: &Route, route2: &Route) {
+        assert_eq!(route1.name, route2.name);
+        assert_eq!(route1.comment, route2.comment);
+        assert_eq!(route1.description, route2.description);
+        assert_eq!(route1.source, route2.source);
+        assert_eq!(route1.links, route2.links);
+        assert_eq!(route1.number, route2.number);
+        assert_eq!(route1.r#type, route2.r#type);
+        assert_eq!(route1.extensions, route2.extensions);
+        for (wp1, wp2) in zip(&route1.points, &route2.points) {
+            compare_waypoint(wp1, wp2);
+        }
+    }
+    fn compare_track(track1: &Track, track2: &Track) {
+        assert_eq!(track1.name, track2.name);
+        assert_eq!(track1.comment, track2.comment);
+        assert_eq!(track1.description, track2.description);
+        assert_eq!(track1.source, track2.source);
+        assert_eq!(track1.links, track2.links);
+        assert_eq!(track1.number, track2.number);
+        assert_eq!(track1.r#type, track2.r#type);
+        assert_eq!(track1.extensions, track2.extensions);
+        for (segment1, segment2) in zip(&track1.segments, &track2.segments) {
+            assert_eq!(segment1.extensions, segment2.extensions);
+            for (wp1, wp2) in zip(&segment1.points, &segment2.points) {
+                compare_waypoint(wp1, wp2);
+            }
+        }
+    }
+
+    fn compare_waypoint(wp1: &Waypoint, wp2: &Waypoint) {
+        assert_eq!(wp1.lat, wp2.lat);
+        assert_eq!(wp1.lon, wp2.lon);
+        assert_eq!(wp1.ele, wp2.ele);
+        assert_eq!(wp1.time, wp2.time);
+        assert_eq!(wp1.course, wp2.course);
+        assert_eq!(wp1.speed, wp2.speed);
+        assert_eq!(wp1.magvar, wp2.magvar);
+        assert_eq!(wp1.geoid_height, wp2.geoid_height);
+        assert_eq!(wp1.name, wp2.name);
+        assert_eq!(wp1.comment, wp2.comment);
+        assert_eq!(wp1.description, wp2.description);
+        assert_eq!(wp1.source, wp2.source);
+        assert_eq!(wp1.links, wp2.links);
+        assert_eq!(wp1.symbol, wp2.symbol);
+        assert_eq!(wp1.r#type, wp2.r#type);
+        assert_eq!(wp1.fix, wp2.fix);
+        assert_eq!(wp1.num_satellites, wp2.num_satellites);
+        assert_eq!(wp1.hdop, wp2.hdop);
+        assert_eq!(wp1.vdop, wp2.vdop);
+        assert_eq!(wp1.pdop, wp2.pdop);
+        assert_eq!(wp1.age_of_dgps_data, wp2.age_of_dgps_data);
+        assert_eq!(wp1.dgps_id, wp2.dgps_id);
+        assert_eq!(wp1.extensions, wp2.extensions);
+    }
+
+    fn make_fully_populated_gpx() -> Gpx {
+        let declaration = XmlDeclaration {
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: Some("false".to_string()),
+        };
+
+        let copyright = Copyright {
+            year: Some(2024),
+            license: Some("MIT".to_string()),
+            author: "Homer Simpson".to_string(),
+        };
+
+        let author = Person {
+            name: Some("First Person".to_string()),
+            email: Some(Email {
+                id: "first_person".to_string(),
+                domain: "gmail.com".to_string(),
+            }),
+            link: Some(make_link("Author Link", "txt", "http://example.com")),
+        };
+
+        let bounds = Bounds::new(10.0, 20.0, 30.0, 40.0).unwrap();
+
+        let metadata = Metadata {
+            name: Some("My GPX".to_string()),
+            description: Some("My GPX Description".to_string()),
+            author: Some(author),
+            copyright: Some(copyright),
+            links: vec![
+                make_link("Metadata Link 1", "jpeg", "http://jpeg1.com"),
+                make_link("Metadata Link 2", "mp3", "http://mp3.com"),
+            ],
+            time: Some(
+                DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z")
+                    .unwrap()
+                    .to_utc(),
+            ),
+            keywords: Some("keyword1, keyword2".to_string()),
+            bounds: Some(bounds),
+            extensions: Some(Extensions::new(
+                "The raw inner text of some metadata extensions",
+            )),
+        };
+
+        let mut gpx = Gpx::new(declaration, metadata);
+        gpx.version = "1.1".to_string();
+        gpx.creator = "GAPIX".to_string();
+        gpx.attributes = HashMap::new();
+        gpx.attributes
+            .insert("key1".to_string(), "value1".to_string());
+        gpx.attributes
+            .insert("key2".to_string(), "value2".to_string());
+        gpx.waypoints = make_waypoints(5, "Main", -20.0);
+        gpx.routes = make_routes(7, 20.0);
+        gpx.tracks = make_tracks(2, 40.0);
+        gpx.extensions = Some(Extensions::new("The raw inner text of some GPX extensions"));
+
+        gpx
+    }
+
+    fn make_link(text: &str, r#type: &str, href: &str) -> Link {
+        Link {
+            text: Some(text.to_string()),
+            r#type: Some(r#type.to_string()),
+            href: href.to_string(),
+        }
+    }
+
+    fn make_routes(count: usize, latlon_base: f64) -> Vec<Route> {
+        (0..count)
+            .map(|i| Route {
+                name: Some(format!("Route {i}")),
+                comment: Some(format!("Route {i} Comment")),
+                description: Some(format!("Route {i} Description")),
+                source: Some(format!("Route {i} Source")),
+                links: vec![
+                    make_link(&format!("Route {i} Link 1"), "jpeg", "http://jpeg1.com"),
+                    make_link(&format!("Route {i} Link 2"), "mp3", "http://mp3.com"),
+                    make_link(&format!("Route {i} Link 3"), "txt", "http://txt.com"),
+                ],
+                number: Some(i as u32),
+                r#type: Some(format!("Route {i} Type")),
+                extensions: Some(Extensions::new(format!("Route {i} Extensions"))),
+                points: make_waypoints(4, "Route", latlon_base),
+            })
+            .collect()
+    }
+
+    fn make_tracks(count: usize, latlon_base: f64) -> Vec<Track> {
+        (0..count)
+            .map(|i| Track {
+                name: Some(format!("Route {i}")),
+                comment: Some(format!("Route {i} Comment")),
+                description: Some(format!("Route {i} Description")),
+                source: Some(format!("Route {i} Source")),
+                links: vec![
+                    make_link(&format!("Route {i} Link 1"), "jpeg", "http://jpeg1.com"),
+                    make_link(&format!("Route {i} Link 2"), "mp3", "http://mp3.com"),
+                    make_link(&format!("Route {i} Link 3"), "txt", "http://txt.com"),
+                ],
+                number: Some(i as u32),
+                r#type: Some(format!("Route {i} Type")),
+                extensions: Some(Extensions::new(format!("Route {i} Extensions"))),
+                segments: vec![
+                    TrackSegment {
+                        points: make_waypoints(20, "Track", latlon_base),
+                        extensions: Some(Extensions::new(format!(
+                            "Track Segment {i}.1 Extensions"
+                        ))),
+                    },
+                    TrackSegment {
+                        points: make_waypoints(30, "Track", latlon_base),
+                        extensions: Some(Extensions::new(format!(
+                            "Track Segment {i}.2 Extensions"
+                        ))),
+                    },
+                    TrackSegment {
+                        points: make_waypoints(50, "Track", latlon_base),
+                        extensions: Some(Extensions::new(format!(
+                            "Track Segment {i}.2 Extensions"
+                        ))),
+                    },
+                ],
+            })
+            .collect()
+    }
+
+    /// Generate 'count' waypoints, arranging for them to have unique names
+    /// (sub-elements) and lat-lon (attributes) values.
+    fn make_waypoints(count: usize, name: &str, latlon_base: f64) -> Vec<Waypoint> {
+        (0..count)
+            .map(|i| {
+                // Generate some waypo
+                let fullname = format!("{name} Waypoint {i}");
+                make_waypoint(i, fullname, latlon_base)
+            })
+            .collect()
+    }
+
+    fn make_waypoint<S: Into<String>>(i: usize, name: S, latlon_base: f64) -> Waypoint {
+        let lat = latlon_base + i as f64;
+        let lon = latlon_base - i as f64;
+        let mut wp = Waypoint::with_lat_lon(lat, lon).unwrap();
+
+        wp.ele = Some(12.3);
+        wp.time = Some(
+            DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z")
+                .unwrap()
+                .to_utc(),
+        );
+        wp.magvar = Some(Degrees::new(98.121242354365).unwrap());
+        wp.geoid_height = Some(123.8487);
+        wp.name = Some(name.into());
+        wp.comment = Some(format!("Waypoint {i} Comment"));
+        wp.description = Some(format!("Waypoint {i} Description"));
+        wp.source = Some(format!("Waypoint {i} Source"));
+        wp.links = vec![
+            make_link(&format!("Waypoint {i} Link 1"), "jpeg", "http://jpeg1.com"),
+            make_link(&format!("Waypoint {i} Link 2"), "mp3", "http://mp3.com"),
+            make_link(&format!("Waypoint {i} Link 3"), "txt", "http://txt.com"),
+        ];
+        wp.symbol = Some(format!("Waypoint {i} Symbol"));
+        wp.r#type = Some(format!("Waypoint {i} Type"));
+        wp.fix = Some(FixType::PPS);
+        wp.num_satellites = Some(12 + i as u16);
+        wp.hdop = Some(1.1 + i as f64);
+        wp.vdop = Some(1.1 - i as f64);
+        wp.pdop = Some(100.1 + i as f64);
+        wp.age_of_dgps_data = Some(20.0 + i as f64);
+        wp.dgps_id = Some(DgpsStationId::new(200 + i as u16).unwrap());
+        wp.extensions = Some(Extensions::new(format!("Waypoint {i}Extensions")));
+
+        wp
+    }
+}
@@ -0,0 +1,18 @@
+//! The GPX data model, and functions to read it from and write it to GPX files.
+//!
+//! This crate has no dependencies on the analysis parts of gapix (spreadsheets,
+//! reverse geocoding etc.) so it can be used on its own. The `serde` feature
+//! derives Serialize and Deserialize for the model.
+
+pub mod builders;
+pub mod byte_counter;
+pub mod dates;
+mod error;
+pub mod extension_schemas;
+pub mod gpx_writer;
+pub mod model;
+pub mod model_impls;
+pub mod read;
+pub mod validation;
+
+pub use error::{ErrorLocation, GpxError, ReadWarning};
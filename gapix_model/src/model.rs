@@ -0,0 +1,554 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Comparison of the GPX crate. In addition to this, it is 8 times
+// slower when parsing a GPX.
+//
+// Gpx: lacks attributes, the XML declaration
+// Metadata: full
+// Waypoint: lacks magvar, extensions, lat/lon is available via the Point method
+// Route: full (apart from extensions)
+// Track: full (apart from extensions)
+// Person: full
+// Email: they represent as a single string
+// Copyright: full (their author is optional)
+// Link: full (they use type_)
+// Bounds: full
+// TrackSegment: full (apart from extensions)
+
+/// Data parsed from a GPX file, based on the XSD description at
+/// https://www.topografix.com/GPX/1/1/gpx.xsd. GPX 1.0 files are read into the
+/// same model, see [crate::read].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Gpx {
+    /// Represents the 'xml' declaration tag - the first line of an XML file.
+    pub declaration: XmlDeclaration,
+    /// The filename field is not part of the XSD, but it is convenient to have
+    /// it so it can be used as an identifier for the GPX data.
+    pub filename: Option<PathBuf>,
+
+    /// The 'version' attribute. This is "1.1", or "1.0" if the Gpx was read
+    /// from a GPX 1.0 file.
+    pub version: String,
+    /// The 'creator' attribute.
+    pub creator: String,
+    /// The other attributes (excluding creator and version, which
+    /// are mandatory.)
+    pub attributes: HashMap<String, String>,
+    /// Metadata about the file.
+    pub metadata: Metadata,
+    /// A list of waypoints.
+    pub waypoints: Vec<Waypoint>,
+    /// A list of routes.
+    pub routes: Vec<Route>,
+    /// A list of tracks.
+    pub tracks: Vec<Track>,
+    /// Arbitrary extended information. Represented as an unparsed string.
+    pub extensions: Option<Extensions>,
+}
+
+/// Represents the 'xml' declaration - the first line of an XML file (not just
+/// GPX files).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct XmlDeclaration {
+    pub version: String,
+    pub encoding: Option<String>,
+    pub standalone: Option<String>,
+}
+
+/// The metadata element contains information about the GPX file, such as
+/// author, and copyright restrictions.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metadata {
+    /// The name of the GPX file.
+    pub name: Option<String>,
+    /// A description of the GPX file.
+    pub description: Option<String>,
+    /// The person or organization who created the GPX file.
+    pub author: Option<Person>,
+    /// Copyright and license information governing use of the file.
+    pub copyright: Option<Copyright>,
+    /// Zero or more URLs associated with the file.
+    pub links: Vec<Link>,
+    /// The creation date of the file.
+    pub time: Option<DateTime<Utc>>,
+    /// Keywords associated with the file.
+    pub keywords: Option<String>,
+    /// Minimum and maximum coordinates which describe the extent of the
+    /// coordinates in the file.
+    pub bounds: Option<Bounds>,
+    /// Arbitrary extended information. Represented as an unparsed string.
+    pub extensions: Option<Extensions>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Extensions {
+    pub(crate) raw_xml: String,
+}
+
+/// A pair of (lat, lon) coordinates which constitute a bounding box.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Bounds {
+    /// The minimum latitude.
+    pub min_lat: Latitude,
+    /// The minimum longitude.
+    pub min_lon: Longitude,
+    /// The maximum latitude.
+    pub max_lat: Latitude,
+    /// The maximum longitude.
+    pub max_lon: Longitude,
+}
+
+/// Information about the copyright holder and any license governing use of this
+/// file. By linking to an appropriate license, you may place your data into the
+/// public domain or grant additional usage rights.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Copyright {
+    /// The year of copyright.
+    pub year: Option<i16>,
+    /// A link to an external resource containing the licence text.
+    pub license: Option<String>,
+    /// The author/holder of the copyright.
+    pub author: String,
+}
+
+/// Represents the 'personType' from the XSD. This can be a person or an
+/// organisation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Person {
+    /// Name of person or organization.
+    pub name: Option<String>,
+    /// Email address.
+    pub email: Option<Email>,
+    /// Link to Web site or other external information about person.
+    pub link: Option<Link>,
+}
+
+/// Represents the 'emailType' from the XSD.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Email {
+    /// The first part of the email address (before the '@').
+    pub id: String,
+    /// The domain half of the email address (e.g. gmail.com).
+    pub domain: String,
+}
+
+/// Represents the 'linkType' from the XSD. A link to an external resource (Web
+/// page, digital photo, video clip, etc.) with additional information.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Link {
+    /// Text of hyperlink
+    pub text: Option<String>,
+    /// Mime type of content (image/jpeg)
+    pub r#type: Option<String>,
+    /// URL of hyperlink
+    pub href: String,
+}
+
+/// A Route is an ordered list of waypoints representing a series of turn points
+/// leading to a destination.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Route {
+    /// GPS name of the route.
+    pub name: Option<String>,
+    /// GPS comment for the route.
+    pub comment: Option<String>,
+    /// User description of the route.
+    pub description: Option<String>,
+    /// Source of data. Included to give user some idea of reliability and accuracy of data.
+    pub source: Option<String>,
+    /// Zero or more URLs associated with the route.
+    pub links: Vec<Link>,
+    /// GPS route number.
+    pub number: Option<u32>,
+    /// Type (classification) of the track.
+    pub r#type: Option<String>,
+    /// Arbitrary extended information. Represented as an unparsed string.
+    pub extensions: Option<Extensions>,
+    /// The list of points in the route.
+    pub points: Vec<Waypoint>,
+}
+
+/// A Track is an ordered list of points describing a path.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Track {
+    /// GPS name of the track.
+    pub name: Option<String>,
+    /// GPS comment for the track.
+    pub comment: Option<String>,
+    /// User description of the track.
+    pub description: Option<String>,
+    /// Source of data. Included to give user some idea of reliability and accuracy of data.
+    pub source: Option<String>,
+    /// Zero or more URLs associated with the track.
+    pub links: Vec<Link>,
+    /// GPS track number.
+    pub number: Option<u32>,
+    /// Type (classification) of the track.
+    pub r#type: Option<String>,
+    /// Arbitrary extended information. Represented as an unparsed string.
+    pub extensions: Option<Extensions>,
+    /// List of segments in the track. A Track Segment holds a list of Track
+    /// Points which are logically connected in order. To represent a single GPS
+    /// track where GPS reception was lost, or the GPS receiver was turned off,
+    /// start a new Track Segment for each continuous span of track data.
+    pub segments: Vec<TrackSegment>,
+}
+
+/// A Track Segment holds a list of Track Points which are logically connected
+/// in order. To represent a single GPS track where GPS reception was lost, or
+/// the GPS receiver was turned off, start a new Track Segment for each
+/// continuous span of track data.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TrackSegment {
+    /// The set of points in the segment.
+    pub points: Vec<Waypoint>,
+    /// Arbitrary extended information.
+    pub extensions: Option<Extensions>,
+}
+
+/// Represents a waypoint, a point of interest, a named feature on a map or a
+/// point within a track. In the case of a trackpoint, very few fields are
+/// likely to be filled in by typical GPS units.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Waypoint {
+    /// The latitude of the point. This is always in decimal degrees, and always
+    /// in WGS84 datum.
+    pub lat: Latitude,
+    /// The longitude of the point. This is always in decimal degrees, and
+    /// always in WGS84 datum.
+    pub lon: Longitude,
+    /// Elevation (in meters) of the point.
+    pub ele: Option<f64>,
+    /// Creation/modification timestamp for the waypoint. Date and time in are in
+    /// Univeral Coordinated Time (UTC), not local time! Conforms to ISO 8601
+    /// specification for date/time representation. Fractional seconds are
+    /// allowed for millisecond timing in tracklogs.
+    pub time: Option<DateTime<Utc>>,
+    /// Course (direction of travel, in degrees) at the point. This only exists
+    /// in GPX 1.0, where it is only allowed on trackpoints. It is not written
+    /// to GPX 1.1.
+    pub course: Option<Degrees>,
+    /// Speed (in meters per second) at the point. Like 'course', this only
+    /// exists on GPX 1.0 trackpoints.
+    pub speed: Option<f64>,
+    /// Magnetic variation (in degrees) at the point
+    pub magvar: Option<Degrees>,
+    /// Height (in meters) of geoid (mean sea level) above WGS84 earth
+    /// ellipsoid. As defined in NMEA GGA message.
+    pub geoid_height: Option<f64>,
+    /// The GPS name of the waypoint. This field will be transferred to and from
+    /// the GPS. GPX does not place restrictions on the length of this field or
+    /// the characters contained in it. It is up to the receiving application to
+    /// validate the field before sending it to the GPS.
+    pub name: Option<String>,
+    /// GPS waypoint comment. Sent to GPS as comment.
+    pub comment: Option<String>,
+    /// A text description of the element. Holds additional information about
+    /// the element intended for the user, not the GPS.
+    pub description: Option<String>,
+    /// Source of data. Included to give user some idea of reliability and
+    /// accuracy of data.
+    pub source: Option<String>,
+    /// Links to additional information about the waypoint.
+    pub links: Vec<Link>,
+    /// Text of GPS symbol name.
+    pub symbol: Option<String>,
+    /// Type (classification) of the waypoint.
+    pub r#type: Option<String>,
+    /// Type of GPX fix.
+    pub fix: Option<FixType>,
+    /// Number of satellites used to calculate the GPX fix.
+    pub num_satellites: Option<u16>,
+    /// Horizontal dilution of precision.
+    pub hdop: Option<f64>,
+    /// Vertical dilution of precision.
+    pub vdop: Option<f64>,
+    /// Position dilution of precision.
+    pub pdop: Option<f64>,
+    /// Number of seconds since last DGPS update.
+    pub age_of_dgps_data: Option<f64>,
+    /// ID of DGPS station used in differential correction.
+    pub dgps_id: Option<DgpsStationId>,
+    /// Arbitrary extended information. Represented as an unparsed string,
+    /// and holds the entire content within the &lt;extensions&gt; element.
+    pub extensions: Option<Extensions>,
+    /// Returns the Garmin trackpoint extensions, if they exist. This
+    /// information is parsed out from the `extensions` field of trackpoints
+    /// based on the XSDs at
+    /// https://www8.garmin.com/xmlschemas/TrackPointExtensionv1.xsd and
+    /// https://www8.garmin.com/xmlschemas/TrackPointExtensionv2.xsd
+    pub garmin_extensions: Option<GarminTrackpointExtensions>,
+    /// Returns the Garmin waypoint extensions, if they exist. This
+    /// information is parsed out from the `extensions` field of file-level
+    /// waypoints based on the 'WaypointExtension' in the XSD at
+    /// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+    pub garmin_waypoint_extensions: Option<GarminWaypointExtensions>,
+    /// Returns the Garmin route point extensions, if they exist. This
+    /// information is parsed out from the `extensions` field of route points
+    /// based on the 'RoutePointExtension' in the XSD at
+    /// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+    pub garmin_route_point_extensions: Option<GarminRoutePointExtensions>,
+}
+
+/// A latitude in decimal degrees, in the WGS84 datum. Always in the range
+/// -90.0..=90.0; use [Latitude::new] to create one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct Latitude(pub(crate) f64);
+
+/// A longitude in decimal degrees, in the WGS84 datum. Always in the range
+/// -180.0..=180.0; use [Longitude::new] to create one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct Longitude(pub(crate) f64);
+
+/// An angle in degrees, such as a magnetic variation. Always in the range
+/// 0.0..=360.0; use [Degrees::new] to create one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
+pub struct Degrees(pub(crate) f64);
+
+/// The id of a DGPS station, from the 'dgpsStationType' in the XSD. Always in
+/// the range 0..=1023; use [DgpsStationId::new] to create one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u16", into = "u16"))]
+pub struct DgpsStationId(pub(crate) u16);
+
+/// Type of GPS fix. none means GPS had no fix. To signify "the fix info is
+/// unknown", leave out fixType entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FixType {
+    /// From the Xml value 'none'.
+    None,
+    /// From the Xml value '2d'.
+    TwoDimensional,
+    /// From the Xml value '3d'.
+    ThreeDimensional,
+    /// From the Xml value 'dgps'.
+    DGPS,
+    /// From the Xml value 'pps'.
+    /// Indicates a military signal was used
+    PPS,
+}
+
+/// All the Garmin TrackPoint extensions according to
+/// https://www8.garmin.com/xmlschemas/TrackPointExtensionv1.xsd and
+/// https://www8.garmin.com/xmlschemas/TrackPointExtensionv2.xsd. Version 2 is
+/// version 1 plus the speed, course and bearing.
+///
+/// Despite the name, these are the typed values from any of the
+/// [KNOWN_EXTENSION_SCHEMAS](crate::extension_schemas::KNOWN_EXTENSION_SCHEMAS),
+/// such as the power from other devices' extensions.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminTrackpointExtensions {
+    /// Air temperature.
+    pub air_temp: Option<f64>,
+    /// Water temperature.
+    pub water_temp: Option<f64>,
+    /// Water depth.
+    pub depth: Option<f64>,
+    /// Heart rate in beats per minute. 1..=255.
+    pub heart_rate: Option<u8>,
+    /// Cadence in rpm. 0..=254.
+    pub cadence: Option<u8>,
+    /// Speed in meters per second. Version 2 only.
+    pub speed: Option<f64>,
+    /// Course (direction of travel) in degrees. Version 2 only.
+    pub course: Option<Degrees>,
+    /// Bearing (direction to the next point) in degrees. Version 2 only.
+    pub bearing: Option<Degrees>,
+    /// Power in watts. This is not part of the Garmin TrackPointExtension.
+    pub power: Option<u16>,
+    /// Arbitrary extended information. Represented as an unparsed string.
+    pub extensions: Option<String>,
+}
+
+/// The Garmin extensions of a waypoint, from the 'WaypointExtension' in
+/// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminWaypointExtensions {
+    /// Proximity alarm distance in meters.
+    pub proximity: Option<f64>,
+    /// Temperature in degrees Celsius.
+    pub temperature: Option<f64>,
+    /// Depth in meters.
+    pub depth: Option<f64>,
+    /// How the waypoint is shown on the map: "SymbolOnly", "SymbolAndName" or
+    /// "SymbolAndDescription".
+    pub display_mode: Option<String>,
+    /// The categories the waypoint is in.
+    pub categories: Vec<String>,
+    pub address: Option<GarminAddress>,
+    pub phone_numbers: Vec<GarminPhoneNumber>,
+}
+
+/// A postal address, from the 'AddressType' in
+/// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminAddress {
+    /// The lines of the street address. The XSD allows at most 2.
+    pub street_address: Vec<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    pub postal_code: Option<String>,
+}
+
+/// A phone number, from the 'PhoneNumberType' in
+/// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminPhoneNumber {
+    pub number: String,
+    /// The kind of number, for example "Mobile" or "Fax".
+    pub category: Option<String>,
+}
+
+/// The Garmin extensions of a route point, from the 'RoutePointExtension' in
+/// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd. When a Garmin
+/// device or program calculates a route it stores the shape of the road
+/// between each route point and the next as a list of 'rpt' points, so the
+/// route can be drawn without routing it again.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminRoutePointExtensions {
+    /// The map data the route was calculated with, as a hex string.
+    pub subclass: Option<String>,
+    /// The shaping points from this route point to the next.
+    pub points: Vec<GarminAutoRoutePoint>,
+}
+
+/// A shaping point of a route, from the 'AutoRoutePointType' in
+/// https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GarminAutoRoutePoint {
+    pub lat: Latitude,
+    pub lon: Longitude,
+    pub subclass: Option<String>,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::read::read_gpx_from_slice;
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="Garmin Edge 1040" version="1.1" xmlns="http://www.topografix.com/GPX/1/1" xmlns:ns3="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+  <metadata>
+    <name>Morning Ride</name>
+    <link href="https://example.com"><text>Example</text></link>
+    <time>2024-09-01T05:00:00Z</time>
+    <bounds minlat="53.0" minlon="-2.5" maxlat="53.1" maxlon="-2.4"/>
+  </metadata>
+  <wpt lat="53.05" lon="-2.45">
+    <name>Control 1</name>
+    <sym>Flag</sym>
+  </wpt>
+  <trk>
+    <name>Morning Ride</name>
+    <type>cycling</type>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5">
+        <ele>100.0</ele>
+        <time>2024-09-01T05:00:00Z</time>
+        <extensions><ns3:TrackPointExtension><ns3:atemp>15.0</ns3:atemp><ns3:hr>120</ns3:hr><ns3:cad>85</ns3:cad></ns3:TrackPointExtension></extensions>
+      </trkpt>
+      <trkpt lat="53.001" lon="-2.5">
+        <ele>101.0</ele>
+        <time>2024-09-01T05:00:10.500Z</time>
+        <extensions><ns3:TrackPointExtension><ns3:atemp>15.0</ns3:atemp><ns3:hr>122</ns3:hr><ns3:cad>86</ns3:cad></ns3:TrackPointExtension></extensions>
+      </trkpt>
+      <trkpt lat="53.002" lon="-2.5">
+        <ele>99.5</ele>
+        <time>2024-09-01T05:00:21Z</time>
+      </trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+    /// Serializes 'value', deserializes it again, and checks that the result
+    /// serializes to the same JSON. Most of the model does not implement
+    /// PartialEq, so this is the simplest way of comparing.
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        // Compare as Values, because the order of the keys of the attributes
+        // HashMap is not stable.
+        let json = serde_json::to_string(value).unwrap();
+        let value2: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&value2).unwrap(),
+            serde_json::to_value(value).unwrap()
+        );
+        value2
+    }
+
+    #[test]
+    fn gpx_round_trip() {
+        let gpx = read_gpx_from_slice(GPX.as_bytes()).unwrap();
+        let gpx2 = round_trip(&gpx);
+
+        assert_eq!(gpx2.attributes, gpx.attributes);
+        assert_eq!(gpx2.metadata.bounds.unwrap().max_lon, -2.4);
+        assert_eq!(gpx2.waypoints[0].name.as_deref(), Some("Control 1"));
+        let point = &gpx2.tracks[0].segments[0].points[1];
+        assert_eq!(point.time, gpx.tracks[0].segments[0].points[1].time);
+        assert_eq!(
+            point.garmin_extensions.as_ref().unwrap().heart_rate,
+            Some(122)
+        );
+        assert_eq!(
+            point.extensions,
+            gpx.tracks[0].segments[0].points[1].extensions
+        );
+    }
+
+    #[test]
+    fn times_and_extensions_are_readable() {
+        let gpx = read_gpx_from_slice(GPX.as_bytes()).unwrap();
+        let value = serde_json::to_value(&gpx).unwrap();
+
+        assert_eq!(value["metadata"]["time"], "2024-09-01T05:00:00Z");
+        let point = &value["tracks"][0]["segments"][0]["points"][0];
+        assert!(point["extensions"]
+            .as_str()
+            .unwrap()
+            .contains("TrackPointExtension"));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut value = serde_json::to_value(Waypoint::with_lat_lon(53.0, -2.5).unwrap()).unwrap();
+        assert_eq!(value["lat"], 53.0);
+        value["lat"] = serde_json::json!(91.0);
+        assert!(serde_json::from_value::<Waypoint>(value).is_err());
+    }
+}
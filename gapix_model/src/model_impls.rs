@@ -0,0 +1,505 @@
+use std::fmt;
+
+use geo_types::{point, Point};
+use log::debug;
+
+use crate::{
+    error::GpxError,
+    model::{
+        Bounds, Degrees, DgpsStationId, Email, Extensions, FixType, Gpx, Latitude, Link, Longitude,
+        Metadata, Waypoint, XmlDeclaration,
+    },
+};
+
+/// Checks to see whether a valid is in the valid range for a degrees value
+/// 0.0..=360.0, and if so returns the value else returns an error.
+pub fn validate_degrees(value: f64) -> Result<f64, GpxError> {
+    if !(0.0_f64..=360.0).contains(&value) {
+        Err(GpxError::InvalidDegrees(value))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Checks to see whether a valid is in the valid range for a 'dgpsStationType'
+/// field of 0..=1023, and if so returns the value else returns an error.
+pub fn validate_dgps_station_id(value: i64) -> Result<u16, GpxError> {
+    if !(0_i64..=1023).contains(&value) {
+        Err(GpxError::InvalidDGPSStationId(value))
+    } else {
+        // SAFETY: 0..=1023 will fit into a u16.
+        Ok(value as u16)
+    }
+}
+
+/// Checks to see whether a valid is in the valid range for a latitude value of
+/// -90.0..90.0, and if so returns the value else returns an error.
+pub fn validate_latitude(value: f64) -> Result<f64, GpxError> {
+    if !(-90.0_f64..=90.0).contains(&value) {
+        Err(GpxError::InvalidLatitude(value))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Checks to see whether a valid is in the valid range for a longitude value of
+/// -180.0..180.0, and if so returns the value else returns an error.
+pub fn validate_longitude(value: f64) -> Result<f64, GpxError> {
+    if !(-180.0_f64..=180.0).contains(&value) {
+        Err(GpxError::InvalidLongitude(value))
+    } else {
+        Ok(value)
+    }
+}
+
+impl Default for Gpx {
+    /// Creates a new Gpx with 'gapix' as the creator.
+    fn default() -> Self {
+        let mut gpx = Self::new(XmlDeclaration::default(), Metadata::default());
+        gpx.creator = "gapix".into();
+        gpx
+    }
+}
+
+impl Gpx {
+    /// Creates a new Gpx from the mandatory fields.
+    pub fn new(declaration: XmlDeclaration, metadata: Metadata) -> Self {
+        Self {
+            declaration,
+            filename: Default::default(),
+            version: "1.1".to_string(),
+            creator: Default::default(),
+            attributes: Default::default(),
+            metadata,
+            waypoints: Default::default(),
+            routes: Default::default(),
+            tracks: Default::default(),
+            extensions: Default::default(),
+        }
+    }
+
+    /// Sets the attributes that Garmin devices write, which declare the
+    /// namespaces of the Garmin extensions. The 'ns3' prefix is for
+    /// TrackPointExtension v2, which is a superset of v1, and 'ns2' is for
+    /// GpxExtensions v3. These are the prefixes used when writing the typed
+    /// Garmin extensions.
+    pub fn set_default_garmin_attributes(&mut self) {
+        self.attributes.insert(
+            "xsi:schemaLocation".to_string(),
+            "http://www.topografix.com/GPX/1/1 http://www.topografix.com/GPX/11.xsd".to_string(),
+        );
+
+        self.attributes.insert(
+            "xmlns:ns3".to_string(),
+            "http://www.garmin.com/xmlschemas/TrackPointExtension/v2".to_string(),
+        );
+
+        self.attributes.insert(
+            "xmlns".to_string(),
+            "http://www.topografix.com/GPX/1/1".to_string(),
+        );
+
+        self.attributes.insert(
+            "xmlns:xsi".to_string(),
+            "http://www.w3.org/2001/XMLSchema-instance".to_string(),
+        );
+
+        self.attributes.insert(
+            "xmlns:ns2".to_string(),
+            "http://www.garmin.com/xmlschemas/GpxExtensions/v3".to_string(),
+        );
+    }
+
+    /// Returns the total number of points across all tracks and segments.
+    pub fn num_points(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|track| {
+                track
+                    .segments
+                    .iter()
+                    .map(|segment| segment.points.len())
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Returns all the points in the GPX: the waypoints, then the route points,
+    /// then the trackpoints.
+    pub fn all_points(&self) -> impl Iterator<Item = &Waypoint> {
+        self.waypoints
+            .iter()
+            .chain(self.routes.iter().flat_map(|route| &route.points))
+            .chain(
+                self.tracks
+                    .iter()
+                    .flat_map(|track| &track.segments)
+                    .flat_map(|segment| &segment.points),
+            )
+    }
+
+    /// Returns the smallest bounds that contain all the waypoints, route
+    /// points and trackpoints, or None if there are no points. The bounds in
+    /// the metadata are not used or changed; they are only as accurate as the
+    /// program that wrote the file, and are stale once points are added or
+    /// removed.
+    pub fn compute_bounds(&self) -> Option<Bounds> {
+        let mut points = self.all_points();
+        let first = points.next()?;
+        let mut bounds = Bounds {
+            min_lat: first.lat,
+            min_lon: first.lon,
+            max_lat: first.lat,
+            max_lon: first.lon,
+        };
+
+        for p in points {
+            if p.lat < bounds.min_lat {
+                bounds.min_lat = p.lat;
+            }
+            if p.lat > bounds.max_lat {
+                bounds.max_lat = p.lat;
+            }
+            if p.lon < bounds.min_lon {
+                bounds.min_lon = p.lon;
+            }
+            if p.lon > bounds.max_lon {
+                bounds.max_lon = p.lon;
+            }
+        }
+
+        Some(bounds)
+    }
+
+    /// Returns true if the GPX consists of a single track with one segment.
+    pub fn is_single_track(&self) -> bool {
+        self.tracks.len() == 1 && self.tracks[0].segments.len() == 1
+    }
+
+    /// Merges all the tracks and segments within the GPX into a single track
+    /// with one segment containing all the points. The name and type of the
+    /// first track in `self` is used to name the new track. If the GPX is
+    /// already in single track form then self is simply returned as-is (this is
+    /// a cheap operation in that case).
+    pub fn into_single_track(mut self) -> Self {
+        if self.is_single_track() {
+            return self;
+        }
+
+        let mut points = Vec::with_capacity(self.num_points());
+
+        // This copies the first track as well, which may seem a bit inefficient,
+        // but the obvious optimisation of moving all but the first track doesn't
+        // work because that track may have multiple segments. This function is
+        // only called once and the simpler code wins out over the fix for that
+        // problem.
+        let mut track_count = 0;
+        let mut segment_count = 0;
+        let mut point_count = 0;
+
+        for src_track in self.tracks.iter_mut() {
+            track_count += 1;
+
+            for src_segment in src_track.segments.iter_mut() {
+                segment_count += 1;
+                point_count += src_segment.points.len();
+                points.append(&mut src_segment.points);
+            }
+        }
+
+        self.tracks.truncate(1);
+        self.tracks.shrink_to_fit();
+        self.tracks[0].segments.truncate(1);
+        self.tracks[0].segments.shrink_to_fit();
+        self.tracks[0].segments[0].points = points;
+
+        debug!(
+            "Merged {} tracks with {} segments and {} points into a single track",
+            track_count, segment_count, point_count,
+        );
+
+        self
+    }
+}
+
+impl Extensions {
+    /// Constructs a new Extensions object from the raw value.
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Self {
+            raw_xml: value.into(),
+        }
+    }
+
+    /// Returns true if there are no extensions.
+    pub fn is_empty(&self) -> bool {
+        self.raw_xml.is_empty()
+    }
+
+    /// Returns the raw XML of the extensions.
+    pub fn as_str(&self) -> &str {
+        &self.raw_xml
+    }
+}
+
+impl Default for XmlDeclaration {
+    fn default() -> Self {
+        Self {
+            version: "1.0".to_owned(),
+            encoding: Some("UTF-8".to_owned()),
+            standalone: Default::default(),
+        }
+    }
+}
+
+impl Waypoint {
+    /// Creates a new waypoint at the specified position.
+    pub fn new(lat: Latitude, lon: Longitude) -> Self {
+        Self {
+            lat,
+            lon,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new waypoint, validating that the lat and lon lie within the
+    /// acceptable ranges.
+    pub fn with_lat_lon(lat: f64, lon: f64) -> Result<Self, GpxError> {
+        Ok(Self::new(Latitude::new(lat)?, Longitude::new(lon)?))
+    }
+
+    /// Makes a geo::Point from the waypoint.
+    pub fn as_geo_point(&self) -> Point {
+        point! { x: self.lon.value(), y: self.lat.value() }
+    }
+}
+
+impl Bounds {
+    /// Creates a new Bounds, validating that the lat and lon lie within the
+    /// acceptable ranges.
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Result<Self, GpxError> {
+        Ok(Self {
+            min_lat: Latitude::new(min_lat)?,
+            min_lon: Longitude::new(min_lon)?,
+            max_lat: Latitude::new(max_lat)?,
+            max_lon: Longitude::new(max_lon)?,
+        })
+    }
+}
+
+impl Email {
+    /// Constructs a new email element from the two mandatory fields, id and
+    /// domain.
+    pub fn new<S1, S2>(id: S1, domain: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            domain: domain.into(),
+        }
+    }
+}
+
+impl Link {
+    pub fn new<S>(href: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            text: None,
+            r#type: None,
+            href: href.into(),
+        }
+    }
+}
+
+impl TryFrom<String> for FixType {
+    type Error = GpxError;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.as_ref() {
+            "none" => Ok(FixType::None),
+            "2d" => Ok(FixType::TwoDimensional),
+            "3d" => Ok(FixType::ThreeDimensional),
+            "dgps" => Ok(FixType::DGPS),
+            "pps" => Ok(FixType::PPS),
+            _ => Err(GpxError::InvalidFixType(value)),
+        }
+    }
+}
+
+impl std::fmt::Display for FixType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FixType::None => write!(f, "none"),
+            FixType::TwoDimensional => write!(f, "2d"),
+            FixType::ThreeDimensional => write!(f, "3d"),
+            FixType::DGPS => write!(f, "dgps"),
+            FixType::PPS => write!(f, "pps"),
+        }
+    }
+}
+
+/// Implements the constructor, accessor and conversions for one of the
+/// validated f64 newtypes.
+macro_rules! impl_f64_newtype {
+    ($name:ident, $validate:ident) => {
+        impl $name {
+            /// Creates a new value, returning an error if it is out of range.
+            pub fn new(value: f64) -> Result<Self, GpxError> {
+                Ok(Self($validate(value)?))
+            }
+
+            /// Returns the underlying value.
+            pub fn value(self) -> f64 {
+                self.0
+            }
+        }
+
+        impl TryFrom<f64> for $name {
+            type Error = GpxError;
+
+            fn try_from(value: f64) -> Result<Self, Self::Error> {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for f64 {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl PartialEq<f64> for $name {
+            fn eq(&self, other: &f64) -> bool {
+                self.0 == *other
+            }
+        }
+
+        impl fmt::Display for $name {
+            /// Formats the underlying value, so format specifiers such as
+            /// precision are respected.
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+impl_f64_newtype!(Latitude, validate_latitude);
+impl_f64_newtype!(Longitude, validate_longitude);
+impl_f64_newtype!(Degrees, validate_degrees);
+
+impl DgpsStationId {
+    /// Creates a new id, returning an error if it is out of range.
+    pub fn new(value: u16) -> Result<Self, GpxError> {
+        Self::try_from(i64::from(value))
+    }
+
+    /// Returns the underlying value.
+    pub fn value(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<i64> for DgpsStationId {
+    type Error = GpxError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        Ok(Self(validate_dgps_station_id(value)?))
+    }
+}
+
+impl TryFrom<u16> for DgpsStationId {
+    type Error = GpxError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<DgpsStationId> for u16 {
+    fn from(value: DgpsStationId) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for DgpsStationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Route, Track, TrackSegment};
+
+    use super::*;
+
+    #[test]
+    fn newtypes_validate_their_range() {
+        assert_eq!(Latitude::new(-90.0).unwrap(), -90.0);
+        assert!(matches!(
+            Latitude::new(90.1),
+            Err(GpxError::InvalidLatitude(_))
+        ));
+        assert_eq!(Longitude::new(180.0).unwrap(), 180.0);
+        assert!(matches!(
+            Longitude::new(-180.1),
+            Err(GpxError::InvalidLongitude(_))
+        ));
+        assert_eq!(Degrees::new(360.0).unwrap(), 360.0);
+        assert!(matches!(
+            Degrees::new(-0.1),
+            Err(GpxError::InvalidDegrees(_))
+        ));
+        assert_eq!(DgpsStationId::new(1023).unwrap().value(), 1023);
+        assert!(matches!(
+            DgpsStationId::new(1024),
+            Err(GpxError::InvalidDGPSStationId(1024))
+        ));
+        assert!(DgpsStationId::try_from(-1_i64).is_err());
+    }
+
+    #[test]
+    fn compute_bounds_covers_all_points() {
+        let mut gpx = Gpx::default();
+        assert!(gpx.compute_bounds().is_none());
+
+        gpx.waypoints.push(Waypoint::with_lat_lon(10.0, 20.0).unwrap());
+        gpx.routes.push(Route {
+            points: vec![Waypoint::with_lat_lon(-5.0, 25.0).unwrap()],
+            ..Default::default()
+        });
+        gpx.tracks.push(Track {
+            segments: vec![TrackSegment {
+                points: vec![Waypoint::with_lat_lon(12.0, -30.0).unwrap()],
+                extensions: None,
+            }],
+            ..Default::default()
+        });
+
+        let bounds = gpx.compute_bounds().unwrap();
+        assert_eq!(bounds.min_lat, -5.0);
+        assert_eq!(bounds.max_lat, 12.0);
+        assert_eq!(bounds.min_lon, -30.0);
+        assert_eq!(bounds.max_lon, 25.0);
+    }
+
+    #[test]
+    fn newtypes_display_like_the_underlying_value() {
+        let lat = Latitude::new(53.123456789).unwrap();
+        assert_eq!(lat.to_string(), "53.123456789");
+        assert_eq!(format!("{lat:.3}"), "53.123");
+        assert_eq!(DgpsStationId::new(89).unwrap().to_string(), "89");
+    }
+
+    #[test]
+    fn waypoint_geo_point_has_lon_as_x() {
+        let wp = Waypoint::with_lat_lon(53.0, -2.5).unwrap();
+        let p = wp.as_geo_point();
+        assert_eq!(p.x(), -2.5);
+        assert_eq!(p.y(), 53.0);
+    }
+}
@@ -5,9 +5,9 @@ use std::{
 
 use quick_xml::events::BytesStart;
 
-use crate::error::GapixError;
+use crate::error::GpxError;
 
-use crate::read::xml_reader_extensions::XmlReaderConversions;
+use crate::read::{xml_reader::XmlReader, xml_reader_extensions::XmlReaderConversions};
 
 #[derive(Debug)]
 pub(crate) struct Attributes {
@@ -21,7 +21,7 @@ impl Attributes {
     pub(crate) fn new<C: XmlReaderConversions>(
         start_element: &BytesStart<'_>,
         converter: &C,
-    ) -> Result<Self, GapixError> {
+    ) -> Result<Self, GpxError> {
         let start_element_name = converter
             .bytes_to_string(start_element.name().into_inner())?
             .to_owned();
@@ -44,21 +44,24 @@ impl Attributes {
     }
 
     /// Helper method. Checks to see if an element has any attributes and bails with
-    /// an error if it does.
-    pub(crate) fn check_is_empty<C: XmlReaderConversions>(
+    /// an error if it does (or records a warning, in lenient mode).
+    pub(crate) fn check_is_empty<R>(
         start_element: &BytesStart<'_>,
-        converter: &C,
-    ) -> Result<(), GapixError> {
+        xml_reader: &mut XmlReader<R>,
+    ) -> Result<(), GpxError> {
         if start_element.attributes().count() == 0 {
             return Ok(());
         }
 
-        let attrs = Attributes::new(start_element, converter)?;
-        attrs.check_is_empty_now()
+        let attrs = Attributes::new(start_element, xml_reader)?;
+        attrs.check_is_empty_now(xml_reader)
     }
 
     /// Checks to see whether an attribute set is now empty.
-    pub(crate) fn check_is_empty_now(&self) -> Result<(), GapixError> {
+    pub(crate) fn check_is_empty_now<R>(
+        &self,
+        xml_reader: &mut XmlReader<R>,
+    ) -> Result<(), GpxError> {
         if self.is_empty() {
             return Ok(());
         }
@@ -75,10 +78,7 @@ impl Attributes {
             joined_attributes.push_str(item);
         }
 
-        Err(GapixError::UnexpectedAttributes {
-            element: self.start_element_name.clone(),
-            attributes: joined_attributes,
-        })
+        xml_reader.unexpected_attributes(self.start_element_name.clone(), joined_attributes)
     }
 
     /// Returns the number of attributes.
@@ -98,7 +98,7 @@ impl Attributes {
 
     /// Gets a mandatory attribute. The attribute is removed from the list
     /// of attributes and returned to the caller.
-    pub(crate) fn get<S, T>(&mut self, key: S) -> Result<T, GapixError>
+    pub(crate) fn get<S, T>(&mut self, key: S) -> Result<T, GpxError>
     where
         S: Into<String>,
         T: FromStr,
@@ -107,10 +107,10 @@ impl Attributes {
 
         let value = match self.data.entry(key.clone()) {
             Entry::Occupied(occupied_entry) => occupied_entry.remove(),
-            _ => return Err(GapixError::MandatoryAttributeNotFound(key)),
+            _ => return Err(GpxError::MandatoryAttributeNotFound(key)),
         };
 
-        value.parse::<T>().map_err(|_| GapixError::ParseFailure {
+        value.parse::<T>().map_err(|_| GpxError::ParseFailure {
             from: value,
             dest_type: std::any::type_name::<T>().to_string(),
         })
@@ -122,11 +122,10 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn get_works_for_extant_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<bounds minlat="-1.1" maxlat="1.1" minlon="-53.1111" maxlon="88.88">"#,
         );
         let start = start_parse(&mut xml_reader);
@@ -139,14 +138,14 @@ mod tests {
 
     #[test]
     fn get_returns_error_for_non_existing_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<bounds minlat="-1.1" maxlat="1.1" minlon="-53.1111" maxlon="88.88">"#,
         );
         let start = start_parse(&mut xml_reader);
         let mut attrs = Attributes::new(&start, &xml_reader).unwrap();
-        let result: Result<_, GapixError> = attrs.get::<&str, String>("blah");
+        let result: Result<_, GpxError> = attrs.get::<&str, String>("blah");
         match result {
-            Err(GapixError::MandatoryAttributeNotFound(a)) if a == "blah" => {}
+            Err(GpxError::MandatoryAttributeNotFound(a)) if a == "blah" => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -1,22 +1,25 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
-use crate::{error::GapixError, model::Copyright};
+use crate::{error::GpxError, model::Copyright};
 
-use super::{attributes::Attributes, xml_reader_extensions::XmlReaderExtensions};
+use super::{
+    attributes::Attributes, xml_reader::XmlReader, xml_reader_extensions::XmlReaderExtensions,
+};
 
-pub(crate) fn parse_copyright(
+pub(crate) fn parse_copyright<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Copyright, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Copyright, GpxError> {
     Attributes::check_is_empty(start_element, xml_reader)?;
 
     let mut copyright = Copyright::default();
 
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"year" => {
                     copyright.year = Some(xml_reader.read_inner_as()?);
@@ -27,7 +30,7 @@ pub(crate) fn parse_copyright(
                 b"author" => {
                     copyright.author = xml_reader.read_inner_as()?;
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::End(e)) => {
                 let n = e.name();
@@ -35,19 +38,20 @@ pub(crate) fn parse_copyright(
 
                 if n == start_element.name().as_ref() {
                     if copyright.author.is_empty() {
-                        return Err(GapixError::MandatoryElementNotFound("author".to_string()));
+                        return Err(GpxError::MandatoryElementNotFound("author".to_string()));
                     }
 
                     return Ok(copyright);
                 } else if n == b"year" || n == b"license" || n == b"author" {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
@@ -57,11 +61,10 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_copyright_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright>
                  <year>2024</year>
                  <license>MIT</license>
@@ -78,7 +81,7 @@ mod tests {
 
     #[test]
     fn valid_copyright_author_only() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright>
                  <author>Homer Simpson</author>
                </copyright>"#,
@@ -93,7 +96,7 @@ mod tests {
 
     #[test]
     fn valid_copyright_missing_license() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright>
                  <year>2024</year>
                  <author>Homer Simpson</author>
@@ -109,7 +112,7 @@ mod tests {
 
     #[test]
     fn missing_author() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright>
                  <year>2024</year>
                  <license>MIT</license>
@@ -123,7 +126,7 @@ mod tests {
 
     #[test]
     fn extra_elements() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright>
                  <author>Homer Simpson</author>
                  <foo>bar</foo>
@@ -132,14 +135,14 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_copyright(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<copyright foo="bar">
                  <author>Homer Simpson</author>
                </copyright>"#,
@@ -147,7 +150,7 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_copyright(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -1,6 +1,6 @@
 use quick_xml::events::BytesDecl;
 
-use crate::{error::GapixError, model::XmlDeclaration};
+use crate::{error::GpxError, model::XmlDeclaration};
 
 use super::xml_reader_extensions::XmlReaderConversions;
 
@@ -9,7 +9,7 @@ use super::xml_reader_extensions::XmlReaderConversions;
 pub(crate) fn parse_declaration<C: XmlReaderConversions>(
     declaration: &BytesDecl<'_>,
     converter: &C,
-) -> Result<XmlDeclaration, GapixError> {
+) -> Result<XmlDeclaration, GpxError> {
     let version = converter.cow_to_string(declaration.version()?)?;
 
     let encoding = if let Some(enc) = declaration.encoding() {
@@ -83,8 +83,8 @@ mod tests {
     fn missing_version() {
         let mut xml_reader = Reader::from_str(r#"<?xml encoding="UTF-8"?>"#);
         let start = start_parse_of_decl(&mut xml_reader);
-        match parse_declaration(&start, &mut xml_reader) {
-            Err(GapixError::XmlError { .. }) => {}
+        match parse_declaration(&start, &xml_reader) {
+            Err(GpxError::XmlError { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
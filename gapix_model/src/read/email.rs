@@ -1,18 +1,18 @@
 use quick_xml::events::BytesStart;
 
-use crate::{error::GapixError, model::Email};
+use crate::{error::GpxError, model::Email};
 
-use super::{attributes::Attributes, xml_reader_extensions::XmlReaderConversions};
+use super::{attributes::Attributes, xml_reader::XmlReader};
 
 /// Parses an element of the form: <email id="phil" domain="gmail.com">
-pub(crate) fn parse_email<C: XmlReaderConversions>(
+pub(crate) fn parse_email<R>(
     start_element: &BytesStart<'_>,
-    converter: &C,
-) -> Result<Email, GapixError> {
-    let mut attributes = Attributes::new(start_element, converter)?;
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Email, GpxError> {
+    let mut attributes = Attributes::new(start_element, xml_reader)?;
     let id: String = attributes.get("id")?;
     let domain: String = attributes.get("domain")?;
-    attributes.check_is_empty_now()?;
+    attributes.check_is_empty_now(xml_reader)?;
     Ok(Email::new(id, domain))
 }
 
@@ -21,38 +21,37 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_email() {
-        let mut xml_reader = Reader::from_str(r#"<email id="phil" domain="gmail.com">"#);
+        let mut xml_reader = XmlReader::from_str(r#"<email id="phil" domain="gmail.com">"#);
         let start = start_parse(&mut xml_reader);
-        let result = parse_email(&start, &xml_reader).unwrap();
+        let result = parse_email(&start, &mut xml_reader).unwrap();
         assert_eq!(result.id, "phil");
         assert_eq!(result.domain, "gmail.com");
     }
 
     #[test]
     fn missing_domain() {
-        let mut xml_reader = Reader::from_str(r#"<email id="phil">"#);
+        let mut xml_reader = XmlReader::from_str(r#"<email id="phil">"#);
         let start = start_parse(&mut xml_reader);
-        let result = parse_email(&start, &xml_reader);
+        let result = parse_email(&start, &mut xml_reader);
         assert!(result.is_err());
     }
 
     #[test]
     fn missing_id() {
-        let mut xml_reader = Reader::from_str(r#"<email domain="gmail.com">"#);
+        let mut xml_reader = XmlReader::from_str(r#"<email domain="gmail.com">"#);
         let start = start_parse(&mut xml_reader);
-        let result = parse_email(&start, &xml_reader);
+        let result = parse_email(&start, &mut xml_reader);
         assert!(result.is_err());
     }
 
     #[test]
     fn missing_both() {
-        let mut xml_reader = Reader::from_str(r#"<email>"#);
+        let mut xml_reader = XmlReader::from_str(r#"<email>"#);
         let start = start_parse(&mut xml_reader);
-        let result = parse_email(&start, &xml_reader);
+        let result = parse_email(&start, &mut xml_reader);
         assert!(result.is_err());
     }
 
@@ -61,11 +60,11 @@ mod tests {
     #[test]
     fn extra_elements() {
         let mut xml_reader =
-            Reader::from_str(r#"<email id="phil" domain="gmail.com"><foo>bar</foo></email>"#);
+            XmlReader::from_str(r#"<email id="phil" domain="gmail.com"><foo>bar</foo></email>"#);
 
         let start = start_parse(&mut xml_reader);
         match parse_email(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -73,10 +72,11 @@ mod tests {
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(r#"<email id="phil" domain="gmail.com" foo="bar">"#);
+        let mut xml_reader =
+            XmlReader::from_str(r#"<email id="phil" domain="gmail.com" foo="bar">"#);
         let start = start_parse(&mut xml_reader);
         match parse_email(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -0,0 +1,98 @@
+use std::io::BufRead;
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Writer,
+};
+
+use crate::{error::GpxError, model::Extensions};
+
+use super::{
+    attributes::Attributes, xml_reader::XmlReader, xml_reader_extensions::XmlReaderConversions,
+};
+
+/// Parses an 'extensions' element. The contents can be anything at all, so
+/// they are stored as the raw XML. Readers over a `BufRead` cannot hand back
+/// a slice of the input, so the inner events are written back out as they are
+/// read. Each event is the raw bytes from the file, so the result is the same
+/// as the original text.
+pub(crate) fn parse_extensions<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Extensions, GpxError> {
+    Attributes::check_is_empty(start_element, xml_reader)?;
+
+    let mut writer = Writer::new(Vec::new());
+    write_inner_events(xml_reader, &mut writer)?;
+
+    let text = xml_reader.bytes_to_string(&writer.into_inner())?;
+    Ok(Extensions::new(text.trim()))
+}
+
+/// Writes the events within the element that has just been started to
+/// 'writer', stopping after reading its End. The End itself is not written.
+pub(crate) fn write_inner_events<R: BufRead>(
+    xml_reader: &mut XmlReader<R>,
+    writer: &mut Writer<Vec<u8>>,
+) -> Result<(), GpxError> {
+    let mut depth = 0;
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf)? {
+            Event::Start(start) => {
+                depth += 1;
+                writer.write_event(Event::Start(start))?;
+            }
+            Event::End(end) => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+                writer.write_event(Event::End(end))?;
+            }
+            Event::Eof => return Err(GpxError::UnexpectedEof),
+            event => writer.write_event(event)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::read::xml_reader_extensions::start_parse;
+
+    use super::*;
+
+    #[test]
+    fn valid_empty() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<extensions>  
+               </extensions>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_extensions(&start, &mut xml_reader).unwrap();
+        assert!(result.raw_xml.is_empty());
+    }
+
+    #[test]
+    fn valid_extensions_and_preserves_newlines() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<extensions>  
+                  <foo bar="42">inner text</foo>
+<plod>12</plod>
+               </extensions>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_extensions(&start, &mut xml_reader).unwrap();
+        assert_eq!(
+            result.raw_xml,
+            r#"<foo bar="42">inner text</foo>
+<plod>12</plod>"#
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{error::GpxError, model::Gpx};
+
+use super::{
+    attributes::Attributes,
+    extensions::parse_extensions,
+    gpx_1_0::{
+        is_foreign_element, is_metadata_element, parse_foreign_element, parse_metadata_element,
+    },
+    metadata::parse_metadata,
+    route::parse_route,
+    track::parse_track,
+    waypoint::parse_waypoint,
+    xml_reader::XmlReader,
+};
+
+/// The elements within 'gpx', in the order that the XSD requires.
+const GPX_ELEMENT_ORDER: [&[u8]; 5] = [b"metadata", b"wpt", b"rte", b"trk", b"extensions"];
+
+/// Checks that the elements within 'gpx' come in the order that the XSD
+/// requires. Elements that can repeat, such as 'wpt', may follow each other.
+#[derive(Debug, Default)]
+pub(crate) struct GpxElementOrder {
+    /// The index in GPX_ELEMENT_ORDER of the latest element seen so far.
+    latest: usize,
+}
+
+impl GpxElementOrder {
+    /// Called at the start of each element within 'gpx'. An element that
+    /// should have come before one already seen is an error, unless the
+    /// reader is lenient.
+    pub(crate) fn check<R>(
+        &mut self,
+        element: &[u8],
+        xml_reader: &mut XmlReader<R>,
+    ) -> Result<(), GpxError> {
+        let Some(idx) = GPX_ELEMENT_ORDER.iter().position(|e| *e == element) else {
+            return Ok(());
+        };
+
+        if idx < self.latest {
+            xml_reader.out_of_order(b"gpx", element, GPX_ELEMENT_ORDER[self.latest])
+        } else {
+            self.latest = idx;
+            Ok(())
+        }
+    }
+}
+
+/// Parses the 'gpx' element itself.
+pub(crate) fn parse_gpx<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Gpx, GpxError> {
+    let mut attributes = Attributes::new(start_element, xml_reader)?;
+
+    let mut gpx = Gpx {
+        creator: attributes.get("creator")?,
+        version: attributes.get("version")?,
+        attributes: attributes.into_inner(),
+        ..Default::default()
+    };
+    xml_reader.set_gpx_1_0(gpx.version == "1.0");
+    xml_reader.set_namespaces(&gpx.attributes);
+
+    // In lenient mode a truncated document keeps everything read before the
+    // point of truncation.
+    let result = read_gpx(start_element, &mut gpx, xml_reader);
+    xml_reader.recover(result)?;
+    Ok(gpx)
+}
+
+fn read_gpx<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    gpx: &mut Gpx,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let mut buf = Vec::new();
+    let mut order = GpxElementOrder::default();
+    loop {
+        if xml_reader.is_truncated() {
+            return Ok(());
+        }
+
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
+            Ok(Event::Start(start)) => {
+                order.check(start.name().as_ref(), xml_reader)?;
+                match start.name().as_ref() {
+                    b"metadata" => {
+                        gpx.metadata = parse_metadata(&start, xml_reader)?;
+                    }
+                    b"wpt" => {
+                        let waypoint = parse_waypoint(&start, xml_reader)?;
+                        gpx.waypoints.push(waypoint);
+                    }
+                    b"rte" => {
+                        let route = parse_route(&start, xml_reader)?;
+                        gpx.routes.push(route);
+                    }
+                    b"trk" => {
+                        let track = parse_track(&start, xml_reader)?;
+                        gpx.tracks.push(track);
+                    }
+                    b"extensions" => {
+                        gpx.extensions = Some(parse_extensions(&start, xml_reader)?);
+                    }
+                    name if xml_reader.is_gpx_1_0() && is_metadata_element(name) => {
+                        parse_metadata_element(&start, &mut gpx.metadata, xml_reader)?;
+                    }
+                    name if xml_reader.is_gpx_1_0() && is_foreign_element(name) => {
+                        parse_foreign_element(&start, false, &mut gpx.extensions, xml_reader)?;
+                    }
+                    _ => xml_reader.unexpected_start(start_element, &start)?,
+                }
+            }
+            Ok(Event::Empty(empty)) if xml_reader.is_gpx_1_0() => match empty.name().as_ref() {
+                b"bounds" => {
+                    parse_metadata_element(&empty, &mut gpx.metadata, xml_reader)?;
+                }
+                name if is_foreign_element(name) => {
+                    parse_foreign_element(&empty, true, &mut gpx.extensions, xml_reader)?;
+                }
+                _ => (),
+            },
+            Ok(Event::End(e)) => {
+                let n = e.name();
+                let n = n.as_ref();
+
+                if n == start_element.name().as_ref() {
+                    return Ok(());
+                } else if n == b"metadata"
+                    || n == b"wpt"
+                    || n == b"rte"
+                    || n == b"trk"
+                    || n == b"extensions"
+                    || (xml_reader.is_gpx_1_0() && is_metadata_element(n))
+                {
+                    // These are expected endings, do nothing.
+                } else {
+                    return Err(GpxError::bad_end(n, xml_reader));
+                }
+            }
+            Ok(Event::Eof) => {
+                return Err(GpxError::UnexpectedEof);
+            }
+            Err(e) => return Err(e.into()),
+            //bail!("Error at position {}: {:?}", xml_reader.error_position(), e),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! GPX 1.0 documents are read into the same model as GPX 1.1. The differences
+//! handled here are:
+//!
+//! - there is no 'metadata' element, its contents are directly within 'gpx'
+//!   and the author is just a name plus an 'email' element of the form
+//!   "id@domain"
+//! - links are a 'url' element optionally followed by a 'urlname' element
+//! - there is no 'extensions' element, instead elements from other namespaces
+//!   can appear at the end of 'gpx', 'wpt', 'rte', 'trk' and the points. These
+//!   are kept as extensions.
+//!
+//! The 'course' and 'speed' elements of trackpoints are read by the waypoint
+//! parser.
+
+use std::io::BufRead;
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Writer,
+};
+
+use crate::{
+    error::GpxError,
+    model::{Email, Extensions, Link, Metadata},
+};
+
+use super::{
+    bounds::parse_bounds,
+    extensions::write_inner_events,
+    xml_reader::XmlReader,
+    xml_reader_extensions::{XmlReaderConversions, XmlReaderExtensions},
+};
+
+/// The elements within a GPX 1.0 'gpx' element that are read into the
+/// metadata.
+const METADATA_ELEMENTS: [&[u8]; 9] = [
+    b"name",
+    b"desc",
+    b"author",
+    b"email",
+    b"url",
+    b"urlname",
+    b"time",
+    b"keywords",
+    b"bounds",
+];
+
+/// Returns true if 'name' is one of the GPX 1.0 elements that are read into
+/// the metadata.
+pub(crate) fn is_metadata_element(name: &[u8]) -> bool {
+    METADATA_ELEMENTS.contains(&name)
+}
+
+/// Parses one of the GPX 1.0 elements that are read into the metadata.
+pub(crate) fn parse_metadata_element<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    metadata: &mut Metadata,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    match start_element.name().as_ref() {
+        b"name" => metadata.name = Some(xml_reader.read_inner_as()?),
+        b"desc" => metadata.description = Some(xml_reader.read_inner_as()?),
+        b"author" => {
+            let author = metadata.author.get_or_insert_with(Default::default);
+            author.name = Some(xml_reader.read_inner_as()?);
+        }
+        b"email" => {
+            let email: String = xml_reader.read_inner_as()?;
+            let Some((id, domain)) = email.rsplit_once('@') else {
+                return Err(GpxError::ParseFailure {
+                    from: email,
+                    dest_type: "Email".to_string(),
+                });
+            };
+            let author = metadata.author.get_or_insert_with(Default::default);
+            author.email = Some(Email::new(id, domain));
+        }
+        b"url" => parse_url(&mut metadata.links, xml_reader)?,
+        b"urlname" => parse_urlname(&mut metadata.links, xml_reader)?,
+        b"time" => metadata.time = Some(xml_reader.read_inner_as_time()?),
+        b"keywords" => metadata.keywords = Some(xml_reader.read_inner_as()?),
+        b"bounds" => metadata.bounds = Some(parse_bounds(start_element, xml_reader)?),
+        _ => {
+            return Err(GpxError::bad_start(
+                start_element.name().as_ref(),
+                xml_reader,
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a 'url' element, which becomes a new link.
+pub(crate) fn parse_url<R: BufRead>(
+    links: &mut Vec<Link>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let href: String = xml_reader.read_inner_as()?;
+    links.push(Link::new(href));
+    Ok(())
+}
+
+/// Parses a 'urlname' element, which is the text of the link from the
+/// preceding 'url' element. Without a 'url' there is nothing to name, so it is
+/// ignored.
+pub(crate) fn parse_urlname<R: BufRead>(
+    links: &mut [Link],
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let text: String = xml_reader.read_inner_as()?;
+    if let Some(link) = links.last_mut() {
+        link.text = Some(text);
+    }
+    Ok(())
+}
+
+/// Returns true if the element is from another namespace, i.e. has a prefix.
+pub(crate) fn is_foreign_element(name: &[u8]) -> bool {
+    name.contains(&b':')
+}
+
+/// Parses an element from another namespace, appending its raw XML to
+/// 'extensions'. 'is_empty' is true for an element of the form `<foo/>`,
+/// which has no contents or End to read.
+pub(crate) fn parse_foreign_element<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    is_empty: bool,
+    extensions: &mut Option<Extensions>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let mut writer = Writer::new(Vec::new());
+    if is_empty {
+        writer.write_event(Event::Empty(start_element.borrow()))?;
+    } else {
+        writer.write_event(Event::Start(start_element.borrow()))?;
+        write_inner_events(xml_reader, &mut writer)?;
+        writer.write_event(Event::End(start_element.to_end()))?;
+    }
+
+    let text = xml_reader.bytes_to_string(&writer.into_inner())?;
+    match extensions {
+        Some(extensions) => extensions.raw_xml.push_str(text.trim()),
+        None => *extensions = Some(Extensions::new(text.trim())),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::read::xml_reader_extensions::start_parse;
+
+    use super::*;
+
+    #[test]
+    fn email_is_split_into_id_and_domain() {
+        let mut xml_reader = XmlReader::from_str("<email>phil@example.com</email>");
+        let start = start_parse(&mut xml_reader);
+        let mut metadata = Metadata::default();
+        parse_metadata_element(&start, &mut metadata, &mut xml_reader).unwrap();
+        let email = metadata.author.unwrap().email.unwrap();
+        assert_eq!(email.id, "phil");
+        assert_eq!(email.domain, "example.com");
+    }
+
+    #[test]
+    fn email_without_at_sign_is_an_error() {
+        let mut xml_reader = XmlReader::from_str("<email>phil</email>");
+        let start = start_parse(&mut xml_reader);
+        let mut metadata = Metadata::default();
+        let result = parse_metadata_element(&start, &mut metadata, &mut xml_reader);
+        assert!(matches!(result, Err(GpxError::ParseFailure { .. })));
+    }
+
+    #[test]
+    fn urlname_sets_text_of_previous_url() {
+        let mut xml_reader =
+            XmlReader::from_str("<url>http://example.com</url><urlname>Example</urlname>");
+        let mut links = Vec::new();
+        start_parse(&mut xml_reader);
+        parse_url(&mut links, &mut xml_reader).unwrap();
+        // The End of 'url' is left for the caller.
+        xml_reader.read_event().unwrap();
+        start_parse(&mut xml_reader);
+        parse_urlname(&mut links, &mut xml_reader).unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].href, "http://example.com");
+        assert_eq!(links[0].text.as_deref(), Some("Example"));
+    }
+
+    #[test]
+    fn foreign_elements_are_appended_to_extensions() {
+        let mut xml_reader = XmlReader::from_str(r#"<ns:a x="1"><ns:b>2</ns:b></ns:a><ns:c/>"#);
+        let mut extensions = None;
+        let start = start_parse(&mut xml_reader);
+        parse_foreign_element(&start, false, &mut extensions, &mut xml_reader).unwrap();
+        let mut buf = Vec::new();
+        let Ok(Event::Empty(empty)) = xml_reader.read_event_into(&mut buf) else {
+            panic!("Expected an empty element");
+        };
+        let empty = empty.into_owned();
+        parse_foreign_element(&empty, true, &mut extensions, &mut xml_reader).unwrap();
+        assert_eq!(
+            extensions.unwrap().as_str(),
+            r#"<ns:a x="1"><ns:b>2</ns:b></ns:a><ns:c/>"#
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Parsing of the Garmin GpxExtensions v3 extensions of waypoints and route
+//! points. See https://www8.garmin.com/xmlschemas/GpxExtensionsv3.xsd
+//!
+//! Like [parse_garmin_trackpoint_extensions](super::trackpoint_extensions::parse_garmin_trackpoint_extensions),
+//! these functions are fed the inner Xml from an &lt;extensions&gt; tag. The
+//! elements are matched on their local names, so any namespace prefix works.
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::{
+    error::GpxError,
+    model::{
+        GarminAddress, GarminAutoRoutePoint, GarminPhoneNumber, GarminRoutePointExtensions,
+        GarminWaypointExtensions, Latitude, Longitude,
+    },
+};
+
+use super::{attributes::Attributes, xml_reader_extensions::XmlReaderExtensions};
+
+/// Parses the 'WaypointExtension' element, if there is one.
+pub(crate) fn parse_garmin_waypoint_extensions(
+    s: &str,
+) -> Result<Option<GarminWaypointExtensions>, GpxError> {
+    let mut xml_reader = Reader::from_str(s);
+    if !skip_to_start(&mut xml_reader, b"WaypointExtension")? {
+        return Ok(None);
+    }
+
+    let mut ext = GarminWaypointExtensions::default();
+    loop {
+        match xml_reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"Proximity" => {
+                    ext.proximity = Some(xml_reader.read_inner_as()?);
+                }
+                b"Temperature" => {
+                    ext.temperature = Some(xml_reader.read_inner_as()?);
+                }
+                b"Depth" => {
+                    ext.depth = Some(xml_reader.read_inner_as()?);
+                }
+                b"DisplayMode" => {
+                    ext.display_mode = Some(xml_reader.read_inner_as_string()?);
+                }
+                b"Categories" => { /* The Category elements within are read below */ }
+                b"Category" => {
+                    ext.categories.push(xml_reader.read_inner_as_string()?);
+                }
+                b"Address" => {
+                    ext.address = Some(parse_address(&mut xml_reader)?);
+                }
+                b"PhoneNumber" => {
+                    let category = Attributes::new(&e, &xml_reader)?
+                        .into_inner()
+                        .remove("Category");
+                    let number = xml_reader.read_inner_as_string()?;
+                    ext.phone_numbers
+                        .push(GarminPhoneNumber { number, category });
+                }
+                _ => {
+                    // Such as the 'Extensions' of the extension.
+                    xml_reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"WaypointExtension" => {
+                return Ok(Some(ext));
+            }
+            // Ignore spurious Event::Text, I think they are newlines.
+            Ok(Event::Text(_)) | Ok(Event::End(_)) | Ok(Event::Empty(_)) => {}
+            Ok(Event::Eof) => return Err(GpxError::UnexpectedEof),
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+/// Parses the contents of an 'Address' element.
+fn parse_address(xml_reader: &mut Reader<&[u8]>) -> Result<GarminAddress, GpxError> {
+    let mut address = GarminAddress::default();
+    loop {
+        match xml_reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"StreetAddress" => {
+                    address
+                        .street_address
+                        .push(xml_reader.read_inner_as_string()?);
+                }
+                b"City" => {
+                    address.city = Some(xml_reader.read_inner_as_string()?);
+                }
+                b"State" => {
+                    address.state = Some(xml_reader.read_inner_as_string()?);
+                }
+                b"Country" => {
+                    address.country = Some(xml_reader.read_inner_as_string()?);
+                }
+                b"PostalCode" => {
+                    address.postal_code = Some(xml_reader.read_inner_as_string()?);
+                }
+                _ => {
+                    xml_reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::End(e)) if e.local_name().as_ref() == b"Address" => return Ok(address),
+            Ok(Event::Text(_)) | Ok(Event::End(_)) | Ok(Event::Empty(_)) => {}
+            Ok(Event::Eof) => return Err(GpxError::UnexpectedEof),
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+/// Parses the 'RoutePointExtension' element, if there is one.
+pub(crate) fn parse_garmin_route_point_extensions(
+    s: &str,
+) -> Result<Option<GarminRoutePointExtensions>, GpxError> {
+    let mut xml_reader = Reader::from_str(s);
+    if !skip_to_start(&mut xml_reader, b"RoutePointExtension")? {
+        return Ok(None);
+    }
+
+    let mut ext = GarminRoutePointExtensions::default();
+    // The 'Subclass' element appears both directly within the extension and
+    // within each 'rpt'.
+    let mut in_rpt = false;
+    loop {
+        match xml_reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"Subclass" => {
+                    let subclass = Some(xml_reader.read_inner_as_string()?);
+                    match ext.points.last_mut() {
+                        Some(point) if in_rpt => point.subclass = subclass,
+                        _ => ext.subclass = subclass,
+                    }
+                }
+                b"rpt" => {
+                    ext.points.push(parse_rpt(&e, &xml_reader)?);
+                    in_rpt = true;
+                }
+                _ => {
+                    xml_reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::Empty(e)) if e.local_name().as_ref() == b"rpt" => {
+                ext.points.push(parse_rpt(&e, &xml_reader)?);
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"RoutePointExtension" => return Ok(Some(ext)),
+                b"rpt" => in_rpt = false,
+                _ => {}
+            },
+            Ok(Event::Text(_)) | Ok(Event::Empty(_)) => {}
+            Ok(Event::Eof) => return Err(GpxError::UnexpectedEof),
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+/// Parses the attributes of an 'rpt' element.
+fn parse_rpt(
+    start_element: &BytesStart<'_>,
+    xml_reader: &Reader<&[u8]>,
+) -> Result<GarminAutoRoutePoint, GpxError> {
+    let mut attributes = Attributes::new(start_element, xml_reader)?;
+    Ok(GarminAutoRoutePoint {
+        lat: Latitude::new(attributes.get("lat")?)?,
+        lon: Longitude::new(attributes.get("lon")?)?,
+        subclass: None,
+    })
+}
+
+/// Reads up to and including the Start of the element with the local name
+/// 'name'. Returns false if there is no such element.
+fn skip_to_start(xml_reader: &mut Reader<&[u8]>, name: &[u8]) -> Result<bool, GpxError> {
+    loop {
+        match xml_reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == name => return Ok(true),
+            Event::Eof => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waypoint_extensions() {
+        let xml = r#"<gpxx:WaypointExtension>
+            <gpxx:Proximity>25.5</gpxx:Proximity>
+            <gpxx:DisplayMode>SymbolAndName</gpxx:DisplayMode>
+            <gpxx:Categories>
+              <gpxx:Category>Cafes</gpxx:Category>
+              <gpxx:Category>Controls</gpxx:Category>
+            </gpxx:Categories>
+            <gpxx:Address>
+              <gpxx:StreetAddress>1 High Street</gpxx:StreetAddress>
+              <gpxx:StreetAddress>Upper Town</gpxx:StreetAddress>
+              <gpxx:City>Chester</gpxx:City>
+              <gpxx:Country>UK</gpxx:Country>
+              <gpxx:PostalCode>CH1 1AA</gpxx:PostalCode>
+            </gpxx:Address>
+            <gpxx:PhoneNumber Category="Mobile">07700 900000</gpxx:PhoneNumber>
+            <gpxx:PhoneNumber>01234 567890</gpxx:PhoneNumber>
+            <gpxx:Extensions><foo:bar>1</foo:bar></gpxx:Extensions>
+          </gpxx:WaypointExtension>"#;
+
+        let ext = parse_garmin_waypoint_extensions(xml).unwrap().unwrap();
+        assert_eq!(ext.proximity, Some(25.5));
+        assert_eq!(ext.display_mode.as_deref(), Some("SymbolAndName"));
+        assert_eq!(ext.categories, ["Cafes", "Controls"]);
+        let address = ext.address.unwrap();
+        assert_eq!(address.street_address, ["1 High Street", "Upper Town"]);
+        assert_eq!(address.city.as_deref(), Some("Chester"));
+        assert_eq!(address.state, None);
+        assert_eq!(address.postal_code.as_deref(), Some("CH1 1AA"));
+        assert_eq!(
+            ext.phone_numbers,
+            [
+                GarminPhoneNumber {
+                    number: "07700 900000".to_string(),
+                    category: Some("Mobile".to_string())
+                },
+                GarminPhoneNumber {
+                    number: "01234 567890".to_string(),
+                    category: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn route_point_extensions() {
+        let xml = r#"<gpxx:RoutePointExtension>
+            <gpxx:Subclass>000000000000FFFFFFFFFFFFFFFFFFFFFFFF</gpxx:Subclass>
+            <gpxx:rpt lat="53.1" lon="-2.5">
+              <gpxx:Subclass>1100000000000000</gpxx:Subclass>
+            </gpxx:rpt>
+            <gpxx:rpt lat="53.2" lon="-2.6"/>
+          </gpxx:RoutePointExtension>"#;
+
+        let ext = parse_garmin_route_point_extensions(xml).unwrap().unwrap();
+        assert_eq!(
+            ext.subclass.as_deref(),
+            Some("000000000000FFFFFFFFFFFFFFFFFFFFFFFF")
+        );
+        assert_eq!(ext.points.len(), 2);
+        assert_eq!(ext.points[0].lat.value(), 53.1);
+        assert_eq!(ext.points[0].subclass.as_deref(), Some("1100000000000000"));
+        assert_eq!(ext.points[1].lon.value(), -2.6);
+        assert_eq!(ext.points[1].subclass, None);
+    }
+
+    #[test]
+    fn other_extensions_are_not_garmin_extensions() {
+        let xml = "<foo:bar>1</foo:bar>";
+        assert!(parse_garmin_waypoint_extensions(xml).unwrap().is_none());
+        assert!(parse_garmin_route_point_extensions(xml).unwrap().is_none());
+    }
+}
@@ -1,25 +1,28 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
-use crate::{error::GapixError, model::Link};
+use crate::{error::GpxError, model::Link};
 
-use super::{attributes::Attributes, xml_reader_extensions::XmlReaderExtensions};
+use super::{
+    attributes::Attributes, xml_reader::XmlReader, xml_reader_extensions::XmlReaderExtensions,
+};
 
-pub(crate) fn parse_link(
+pub(crate) fn parse_link<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Link, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Link, GpxError> {
     let mut attributes = Attributes::new(start_element, xml_reader)?;
     let mut link = Link {
         href: attributes.get("href")?,
         ..Default::default()
     };
-    attributes.check_is_empty_now()?;
+    attributes.check_is_empty_now(xml_reader)?;
 
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"text" => {
                     link.text = Some(xml_reader.read_inner_as()?);
@@ -27,7 +30,7 @@ pub(crate) fn parse_link(
                 b"type" => {
                     link.r#type = Some(xml_reader.read_inner_as()?);
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::End(e)) => {
                 let n = e.name();
@@ -37,12 +40,13 @@ pub(crate) fn parse_link(
                 } else if n == b"text" || n == b"type" {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
@@ -52,11 +56,10 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_link_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<link href="http://example.com">
                  <text>Some text here</text>
                  <type>jpeg</type>
@@ -72,7 +75,7 @@ mod tests {
 
     #[test]
     fn valid_link_href_only() {
-        let mut xml_reader = Reader::from_str(r#"<link href="http://example.com"></link>"#);
+        let mut xml_reader = XmlReader::from_str(r#"<link href="http://example.com"></link>"#);
 
         let start = start_parse(&mut xml_reader);
         let result = parse_link(&start, &mut xml_reader).unwrap();
@@ -83,7 +86,7 @@ mod tests {
 
     #[test]
     fn missing_href() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<link>
                  <text>Some text here</text>
                  <type>jpeg</type>
@@ -97,7 +100,7 @@ mod tests {
 
     #[test]
     fn extra_elements() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<link href="http://example.com">
                  <text>Some text here</text>
                  <type>jpeg</type>
@@ -107,21 +110,21 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_link(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<link href="http://example.com" foo="bar">
                </link>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_link(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -1,25 +1,27 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
-use crate::{error::GapixError, model::Metadata};
+use crate::{error::GpxError, model::Metadata};
 
 use super::{
     attributes::Attributes, bounds::parse_bounds, copyright::parse_copyright,
-    extensions::parse_extensions, link::parse_link, person::parse_person, xml_reader_extensions::XmlReaderExtensions,
+    extensions::parse_extensions, link::parse_link, person::parse_person, xml_reader::XmlReader,
+    xml_reader_extensions::XmlReaderExtensions,
 };
 
-pub(crate) fn parse_metadata(
+pub(crate) fn parse_metadata<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Metadata, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Metadata, GpxError> {
     Attributes::check_is_empty(start_element, xml_reader)?;
 
     let mut metadata = Metadata::default();
 
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"name" => {
                     metadata.name = Some(xml_reader.read_inner_as()?);
@@ -50,14 +52,14 @@ pub(crate) fn parse_metadata(
                 b"extensions" => {
                     metadata.extensions = Some(parse_extensions(&start, xml_reader)?);
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::Empty(start)) => {
                 // Bounds can come as <bounds /> which will trigger this case.
                 if start.name().as_ref() == b"bounds" {
                     metadata.bounds = Some(parse_bounds(&start, xml_reader)?);
                 } else {
-                    return Err(GapixError::bad_event(Ok(Event::Empty(start))));
+                    xml_reader.unexpected_empty(start_element, &start)?;
                 }
             }
             Ok(Event::End(e)) => {
@@ -77,12 +79,12 @@ pub(crate) fn parse_metadata(
                 {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
@@ -93,11 +95,10 @@ mod tests {
 
     use super::*;
     use chrono::DateTime;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_metadata_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<metadata>
                  <name>Homer Simpson</name>
                  <desc>description</desc>
@@ -144,7 +145,11 @@ mod tests {
         assert_eq!(result.links[1].href, "http://example2.com");
         assert_eq!(
             result.time,
-            Some(DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z").unwrap().to_utc())
+            Some(
+                DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z")
+                    .unwrap()
+                    .to_utc()
+            )
         );
         assert_eq!(result.keywords, Some("keyword1, keyword2".to_string()));
         let bounds = result.bounds.unwrap();
@@ -158,7 +163,7 @@ mod tests {
 
     #[test]
     fn valid_metadata_self_closing_bounds() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<metadata>
                  <name>Homer Simpson</name>
                  <desc>description</desc>
@@ -205,7 +210,11 @@ mod tests {
         assert_eq!(result.links[1].href, "http://example2.com");
         assert_eq!(
             result.time,
-            Some(DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z").unwrap().to_utc())
+            Some(
+                DateTime::parse_from_rfc3339("2024-02-02T10:10:54.000Z")
+                    .unwrap()
+                    .to_utc()
+            )
         );
         assert_eq!(result.keywords, Some("keyword1, keyword2".to_string()));
         let bounds = result.bounds.unwrap();
@@ -219,7 +228,7 @@ mod tests {
 
     #[test]
     fn extra_elements() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<metadata>
                  <foo>bar</foo>
                </metadata>"#,
@@ -227,21 +236,21 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_metadata(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<metadata foo="bar">
                </metadata>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_metadata(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
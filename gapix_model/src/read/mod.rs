@@ -0,0 +1,492 @@
+#![allow(clippy::single_match)]
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use declaration::parse_declaration;
+use gpx::parse_gpx;
+use log::info;
+use logging_timer::time;
+use quick_xml::{events::Event, Reader};
+use xml_reader::XmlReader;
+use xml_reader_extensions::XmlReaderConversions;
+
+use crate::{
+    error::{GpxError, ReadWarning},
+    model::{Gpx, XmlDeclaration},
+};
+
+mod attributes;
+mod bounds;
+mod copyright;
+mod declaration;
+mod email;
+mod extensions;
+mod gpx;
+mod gpx_1_0;
+mod gpx_extensions;
+mod link;
+mod metadata;
+mod person;
+mod route;
+mod stream;
+mod track;
+mod track_segment;
+mod trackpoint_extensions;
+mod waypoint;
+pub(crate) mod xml_reader;
+pub(crate) mod xml_reader_extensions;
+
+pub use stream::{
+    read_gpx_trackpoints_from_file, read_gpx_trackpoints_from_reader, GpxItem, GpxStreamReader,
+};
+
+/// Reads a GPX from a file.
+///
+/// Note: The file is read through a buffer rather than being loaded into RAM
+/// first, but the whole model is still built. To process a large file a point
+/// at a time use [`GpxStreamReader`] or [`read_gpx_trackpoints_from_file`].
+///
+/// Errors in the document are returned as [GpxError::Located], which says
+/// where the problem is. The line and column are only known when reading from
+/// a file or a slice.
+#[time]
+pub fn read_gpx_from_file<P: AsRef<Path>>(input_file: P) -> Result<Gpx, GpxError> {
+    let input_file = input_file.as_ref();
+    info!("Reading GPX file {:?}", input_file);
+    let reader = BufReader::new(File::open(input_file)?);
+    let mut gpx =
+        read_gpx_from_reader(reader).map_err(|err| err.with_line_column_from_file(input_file))?;
+    gpx.filename = Some(input_file.to_owned());
+    Ok(gpx)
+}
+
+/// Reads a GPX from any buffered reader.
+pub fn read_gpx_from_reader<R: BufRead>(reader: R) -> Result<Gpx, GpxError> {
+    let xml_reader = Reader::from_reader(reader);
+    read_gpx_from_xml_reader(xml_reader)
+}
+
+/// Reads a GPX from a slice of bytes.
+pub fn read_gpx_from_slice(data: &[u8]) -> Result<Gpx, GpxError> {
+    let xml_reader = Reader::from_reader(data);
+    read_gpx_from_xml_reader(xml_reader).map_err(|err| err.with_line_column(data))
+}
+
+/// Reads a GPX from a Quick-Xml Reader. This works for readers over slices
+/// as well as over anything that implements `BufRead`.
+#[time]
+pub fn read_gpx_from_xml_reader<R: BufRead>(xml_reader: Reader<R>) -> Result<Gpx, GpxError> {
+    read_document(&mut XmlReader::new(xml_reader))
+}
+
+/// Reads a GPX from a file in lenient mode. Elements and attributes that are
+/// not allowed by the GPX schema are skipped, and if the file is truncated (as
+/// happens when a device dies mid-ride) everything up to the last complete
+/// element is kept, so all the complete trackpoints are recovered. Each of
+/// these problems is returned as a [ReadWarning].
+///
+/// Other problems, such as a value that cannot be parsed, are still errors.
+#[time]
+pub fn read_gpx_from_file_lenient<P: AsRef<Path>>(
+    input_file: P,
+) -> Result<(Gpx, Vec<ReadWarning>), GpxError> {
+    let input_file = input_file.as_ref();
+    info!("Reading GPX file {:?} in lenient mode", input_file);
+    let reader = BufReader::new(File::open(input_file)?);
+    let (mut gpx, warnings) = read_gpx_from_reader_lenient(reader)
+        .map_err(|err| err.with_line_column_from_file(input_file))?;
+    gpx.filename = Some(input_file.to_owned());
+    Ok((gpx, warnings))
+}
+
+/// Reads a GPX from any buffered reader in lenient mode. See
+/// [read_gpx_from_file_lenient].
+pub fn read_gpx_from_reader_lenient<R: BufRead>(
+    reader: R,
+) -> Result<(Gpx, Vec<ReadWarning>), GpxError> {
+    let mut xml_reader = XmlReader::lenient(Reader::from_reader(reader));
+    let gpx = read_document(&mut xml_reader)?;
+    Ok((gpx, xml_reader.into_warnings()))
+}
+
+/// Reads a GPX from a slice of bytes in lenient mode. See
+/// [read_gpx_from_file_lenient].
+pub fn read_gpx_from_slice_lenient(data: &[u8]) -> Result<(Gpx, Vec<ReadWarning>), GpxError> {
+    read_gpx_from_reader_lenient(data).map_err(|err| err.with_line_column(data))
+}
+
+/// Reads the whole document. Errors are returned with their location.
+fn read_document<R: BufRead>(xml_reader: &mut XmlReader<R>) -> Result<Gpx, GpxError> {
+    let result = parse_document(xml_reader);
+    result.map_err(|err| xml_reader.locate(err))
+}
+
+fn parse_document<R: BufRead>(xml_reader: &mut XmlReader<R>) -> Result<Gpx, GpxError> {
+    let mut xml_declaration: Option<XmlDeclaration> = None;
+    let mut gpx: Option<Gpx> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        if xml_reader.is_truncated() {
+            return finish_document(xml_reader, xml_declaration, gpx);
+        }
+
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
+            Ok(Event::Decl(decl)) => {
+                xml_declaration = Some(parse_declaration(&decl, xml_reader)?);
+            }
+            Ok(Event::Start(start)) => match start.name().as_ref() {
+                b"gpx" => {
+                    gpx = Some(parse_gpx(&start, xml_reader)?);
+                }
+                e => {
+                    let name = xml_reader.bytes_to_string(e)?;
+                    return Err(GpxError::UnexpectedStartElement(name));
+                }
+            },
+            Ok(Event::Eof) => {
+                // We should already have consumed the closing '<gpx>' tag in parse_gpx().
+                // So the next thing will be EOF.
+                return finish_document(xml_reader, xml_declaration, gpx);
+            }
+            Err(e) if gpx.is_some() => {
+                // Anything after the end of the 'gpx' element is garbage.
+                xml_reader.recover_err(e.into())?;
+            }
+            Err(e) => return Err(e.into()),
+            _ => (),
+        }
+    }
+}
+
+fn finish_document<R>(
+    xml_reader: &mut XmlReader<R>,
+    xml_declaration: Option<XmlDeclaration>,
+    gpx: Option<Gpx>,
+) -> Result<Gpx, GpxError> {
+    let Some(mut gpx) = gpx else {
+        return Err(GpxError::ElementNotFound("gpx".to_string()));
+    };
+
+    gpx.declaration = match xml_declaration {
+        Some(declaration) => declaration,
+        None if xml_reader.is_lenient() => {
+            xml_reader.warn(ReadWarning::MissingDeclaration);
+            XmlDeclaration::default()
+        }
+        None => return Err(GpxError::ElementNotFound("xml".to_string())),
+    };
+
+    Ok(gpx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRUNCATED_GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="Garmin Edge 1040" version="1.1">
+  <trk>
+    <name>Morning Ride</name>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5"><ele>100.0</ele></trkpt>
+      <trkpt lat="53.001" lon="-2.5"><ele>101.0</ele></trkpt>
+      <trkpt lat="53.002" lon="-2.5"><ele>10"#;
+
+    #[test]
+    fn strict_read_fails_on_truncated_file() {
+        let err = read_gpx_from_slice(TRUNCATED_GPX.as_bytes()).unwrap_err();
+        assert!(matches!(err.inner(), GpxError::UnexpectedEof));
+    }
+
+    #[test]
+    fn lenient_read_recovers_complete_trackpoints_of_truncated_file() {
+        let (gpx, warnings) = read_gpx_from_slice_lenient(TRUNCATED_GPX.as_bytes()).unwrap();
+        assert_eq!(gpx.creator, "Garmin Edge 1040");
+        assert_eq!(gpx.tracks[0].name.as_deref(), Some("Morning Ride"));
+        assert_eq!(gpx.num_points(), 2);
+        assert!(matches!(
+            warnings.as_slice(),
+            [ReadWarning::Truncated { .. }]
+        ));
+    }
+
+    #[test]
+    fn lenient_read_recovers_from_file_cut_inside_a_tag() {
+        let cut = TRUNCATED_GPX.rfind("<trkpt").unwrap();
+        let data = format!("{}<trkpt lat=\"53", &TRUNCATED_GPX[..cut]);
+        let (gpx, warnings) = read_gpx_from_slice_lenient(data.as_bytes()).unwrap();
+        assert_eq!(gpx.num_points(), 2);
+        assert!(matches!(
+            warnings.as_slice(),
+            [ReadWarning::Truncated { .. }]
+        ));
+    }
+
+    #[test]
+    fn lenient_read_skips_unknown_elements() {
+        let data = r#"<gpx creator="me" version="1.1">
+  <vendor:settings><vendor:mode>race</vendor:mode></vendor:settings>
+  <trk>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5" vendor:accuracy="3"><ele>100.0</ele><vendor:power>250</vendor:power></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+        assert!(read_gpx_from_slice(data.as_bytes()).is_err());
+
+        let (gpx, warnings) = read_gpx_from_slice_lenient(data.as_bytes()).unwrap();
+        assert_eq!(gpx.num_points(), 1);
+        assert_eq!(gpx.tracks[0].segments[0].points[0].ele, Some(100.0));
+        assert_eq!(
+            warnings,
+            [
+                ReadWarning::SkippedElement {
+                    parent: "gpx".to_string(),
+                    element: "vendor:settings".to_string()
+                },
+                ReadWarning::SkippedAttributes {
+                    element: "trkpt".to_string(),
+                    attributes: "vendor:accuracy".to_string()
+                },
+                ReadWarning::SkippedElement {
+                    parent: "trkpt".to_string(),
+                    element: "vendor:power".to_string()
+                },
+                ReadWarning::MissingDeclaration,
+            ]
+        );
+    }
+
+    #[test]
+    fn elements_out_of_order_are_errors_unless_lenient() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="me" version="1.1">
+  <trk>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5"><ele>100.0</ele></trkpt>
+    </trkseg>
+  </trk>
+  <wpt lat="53.0" lon="-2.5"><name>Control</name></wpt>
+  <trk><name>Second</name></trk>
+</gpx>"#;
+
+        let err = read_gpx_from_slice(data.as_bytes()).unwrap_err();
+        assert!(matches!(
+            err.inner(),
+            GpxError::ElementOutOfOrder { element, after } if element == "wpt" && after == "trk"
+        ));
+        assert_eq!(err.location().unwrap().path, "gpx/wpt[0]");
+
+        let (gpx, warnings) = read_gpx_from_slice_lenient(data.as_bytes()).unwrap();
+        assert_eq!(gpx.waypoints[0].name.as_deref(), Some("Control"));
+        assert_eq!(gpx.tracks.len(), 2);
+        assert_eq!(
+            warnings,
+            [ReadWarning::ElementOutOfOrder {
+                parent: "gpx".to_string(),
+                element: "wpt".to_string(),
+                after: "trk".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn errors_have_a_location() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="me" version="1.1">
+  <trk>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5"><ele>100.0</ele></trkpt>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5"><ele>100.0</ele></trkpt>
+      <trkpt lat="53.1" lon="-2.5">
+        <ele>100.0</ele>
+        <time>yesterday</time>
+      </trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+        let err = read_gpx_from_slice(data.as_bytes()).unwrap_err();
+        assert!(matches!(err.inner(), GpxError::DateParseFailure(_)));
+        let location = err.location().unwrap();
+        assert_eq!(location.path, "gpx/trk[0]/trkseg[1]/trkpt[1]/time");
+        assert_eq!(location.line_column, Some((11, 24)));
+        assert_eq!(location.position, 324);
+        let (offset, _) = data.match_indices("yesterday").next().unwrap();
+        assert!(location.position as usize > offset);
+        assert!(err
+            .to_string()
+            .ends_with(" at line 11, column 24 (byte 324) in gpx/trk[0]/trkseg[1]/trkpt[1]/time"));
+    }
+
+    #[test]
+    fn errors_in_point_extensions_include_the_point() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="me" version="1.1">
+  <rte>
+    <rtept lat="53.0" lon="-2.5"></rtept>
+    <rtept lat="53.1" lon="-2.5">
+      <extensions>
+        <gpxx:RoutePointExtension>
+          <gpxx:rpt lat="99.0" lon="-2.5"/>
+        </gpxx:RoutePointExtension>
+      </extensions>
+    </rtept>
+  </rte>
+</gpx>"#;
+
+        let err = read_gpx_from_slice(data.as_bytes()).unwrap_err();
+        assert!(matches!(err.inner(), GpxError::InvalidLatitude(_)));
+        assert_eq!(err.location().unwrap().path, "gpx/rte[0]/rtept[1]");
+    }
+
+    #[test]
+    fn bad_extension_values_are_errors_unless_lenient() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="me" version="1.1" xmlns:foo="http://example.com/foo"
+  xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v2">
+  <trk>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5">
+        <extensions>
+          <foo:course>-10</foo:course>
+          <foo:speed>fast</foo:speed>
+          <foo:power><foo:avg>3</foo:avg></foo:power>
+          <gpxtpx:TrackPointExtension>
+            <gpxtpx:hr>999</gpxtpx:hr>
+            <gpxtpx:cad>90</gpxtpx:cad>
+          </gpxtpx:TrackPointExtension>
+        </extensions>
+      </trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+        let err = read_gpx_from_slice(data.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("999"), "{err}");
+
+        let (gpx, warnings) = read_gpx_from_slice_lenient(data.as_bytes()).unwrap();
+        let ext = gpx.tracks[0].segments[0].points[0]
+            .garmin_extensions
+            .as_ref()
+            .unwrap();
+        assert_eq!(ext.cadence, Some(90));
+        assert_eq!(ext.heart_rate, None);
+        assert_eq!(ext.course, None);
+        assert_eq!(ext.speed, None);
+        assert_eq!(ext.power, None);
+        assert!(matches!(
+            warnings.as_slice(),
+            [ReadWarning::SkippedExtensionValue { element, .. }] if element == "gpxtpx:hr"
+        ));
+    }
+
+    #[test]
+    fn errors_from_readers_have_no_line() {
+        let data = r#"<?xml version="1.0"?><gpx creator="me" version="1.1"><wpt lat="99" lon="0"></wpt></gpx>"#;
+        let err = read_gpx_from_reader(data.as_bytes()).unwrap_err();
+        let location = err.location().unwrap();
+        assert_eq!(location.line_column, None);
+    }
+
+    const GPX_1_0: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.0" creator="Logger" xmlns="http://www.topografix.com/GPX/1/0" xmlns:logger="http://example.com/logger">
+  <name>Old ride</name>
+  <author>Phil</author>
+  <email>phil@example.com</email>
+  <url>http://example.com/ride</url>
+  <urlname>The ride</urlname>
+  <time>2024-03-01T09:00:00Z</time>
+  <bounds minlat="53.0" minlon="-2.6" maxlat="53.1" maxlon="-2.5"/>
+  <wpt lat="53.05" lon="-2.55">
+    <name>Cafe</name>
+    <url>http://example.com/cafe</url>
+  </wpt>
+  <trk>
+    <name>Track</name>
+    <logger:device>XYZ</logger:device>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5">
+        <ele>100.0</ele>
+        <time>2024-03-01T09:00:00Z</time>
+        <course>123.5</course>
+        <speed>5.25</speed>
+        <logger:hr>120</logger:hr>
+      </trkpt>
+    </trkseg>
+  </trk>
+  <logger:summary distance="1000"/>
+</gpx>"#;
+
+    #[test]
+    fn gpx_1_0_is_read_into_the_model() {
+        let gpx = read_gpx_from_slice(GPX_1_0.as_bytes()).unwrap();
+        assert_eq!(gpx.version, "1.0");
+
+        let metadata = &gpx.metadata;
+        assert_eq!(metadata.name.as_deref(), Some("Old ride"));
+        let author = metadata.author.as_ref().unwrap();
+        assert_eq!(author.name.as_deref(), Some("Phil"));
+        assert_eq!(author.email.as_ref().unwrap().domain, "example.com");
+        assert_eq!(metadata.links[0].href, "http://example.com/ride");
+        assert_eq!(metadata.links[0].text.as_deref(), Some("The ride"));
+        assert!(metadata.time.is_some());
+        assert!(metadata.bounds.is_some());
+
+        assert_eq!(gpx.waypoints[0].links[0].href, "http://example.com/cafe");
+
+        let track = &gpx.tracks[0];
+        assert_eq!(
+            track.extensions.as_ref().unwrap().as_str(),
+            "<logger:device>XYZ</logger:device>"
+        );
+        let point = &track.segments[0].points[0];
+        assert_eq!(point.course.unwrap().value(), 123.5);
+        assert_eq!(point.speed, Some(5.25));
+        assert_eq!(
+            point.extensions.as_ref().unwrap().as_str(),
+            "<logger:hr>120</logger:hr>"
+        );
+        assert_eq!(
+            gpx.extensions.as_ref().unwrap().as_str(),
+            r#"<logger:summary distance="1000"/>"#
+        );
+    }
+
+    #[test]
+    fn gpx_1_0_elements_are_not_allowed_in_gpx_1_1() {
+        let data = GPX_1_0.replace(r#"version="1.0""#, r#"version="1.1""#);
+        let err = read_gpx_from_slice(data.as_bytes()).unwrap_err();
+        assert!(matches!(err.inner(), GpxError::UnexpectedStartElement(_)));
+    }
+
+    #[test]
+    fn stream_reads_gpx_1_0_in_the_same_way() {
+        let expected = read_gpx_from_slice(GPX_1_0.as_bytes()).unwrap();
+        let mut points = Vec::new();
+        let gpx = GpxStreamReader::new(GPX_1_0.as_bytes())
+            .for_each_trackpoint(|point| points.push(point))
+            .unwrap();
+        assert_eq!(gpx.metadata.name, expected.metadata.name);
+        assert_eq!(gpx.metadata.author, expected.metadata.author);
+        assert_eq!(gpx.metadata.links, expected.metadata.links);
+        assert_eq!(gpx.waypoints[0].links, expected.waypoints[0].links);
+        assert_eq!(gpx.tracks[0].extensions, expected.tracks[0].extensions);
+        assert_eq!(gpx.extensions, expected.extensions);
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].speed, Some(5.25));
+        assert_eq!(
+            points[0].extensions,
+            expected.tracks[0].segments[0].points[0].extensions
+        );
+    }
+}
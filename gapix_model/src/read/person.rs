@@ -1,22 +1,26 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
-use crate::{error::GapixError, model::Person};
+use crate::{error::GpxError, model::Person};
 
-use super::{attributes::Attributes, email::parse_email, link::parse_link, xml_reader_extensions::XmlReaderExtensions};
+use super::{
+    attributes::Attributes, email::parse_email, link::parse_link, xml_reader::XmlReader,
+    xml_reader_extensions::XmlReaderExtensions,
+};
 
-pub(crate) fn parse_person(
+pub(crate) fn parse_person<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Person, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Person, GpxError> {
     Attributes::check_is_empty(start_element, xml_reader)?;
 
     let mut person = Person::default();
 
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"name" => {
                     person.name = Some(xml_reader.read_inner_as()?);
@@ -28,14 +32,14 @@ pub(crate) fn parse_person(
                 b"link" => {
                     person.link = Some(parse_link(&start, xml_reader)?);
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::Empty(start)) => {
                 // Email can come as <email /> which will trigger this case.
                 if start.name().as_ref() == b"email" {
                     person.email = Some(parse_email(&start, xml_reader)?);
                 } else {
-                    return Err(GapixError::bad_event(Ok(Event::Empty(start))));
+                    xml_reader.unexpected_empty(start_element, &start)?;
                 }
             }
             Ok(Event::End(e)) => {
@@ -46,12 +50,12 @@ pub(crate) fn parse_person(
                 } else if n == b"name" || n == b"email" || n == b"link" {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
@@ -61,11 +65,10 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_person_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<person>
                  <name>Homer Simpson</name>
                  <email id="phil" domain="gmail.com"></email>
@@ -88,7 +91,7 @@ mod tests {
 
     #[test]
     fn valid_person_self_closing_email() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<person>
                  <name>Homer Simpson</name>
                  <email id="phil" domain="gmail.com" />
@@ -105,7 +108,7 @@ mod tests {
 
     #[test]
     fn valid_person_no_fields() {
-        let mut xml_reader = Reader::from_str(r#"<person></person>"#);
+        let mut xml_reader = XmlReader::from_str(r#"<person></person>"#);
 
         let start = start_parse(&mut xml_reader);
         let result = parse_person(&start, &mut xml_reader).unwrap();
@@ -116,21 +119,21 @@ mod tests {
 
     #[test]
     fn extra_fields() {
-        let mut xml_reader = Reader::from_str(r#"<person><foo>bar</foo></person>"#);
+        let mut xml_reader = XmlReader::from_str(r#"<person><foo>bar</foo></person>"#);
 
         let start = start_parse(&mut xml_reader);
         match parse_person(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(r#"<person foo="bar"></person>"#);
+        let mut xml_reader = XmlReader::from_str(r#"<person foo="bar"></person>"#);
 
         let start = start_parse(&mut xml_reader);
         match parse_person(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -1,25 +1,46 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
-use crate::{error::GapixError, model::Route};
+use crate::{error::GpxError, model::Route};
 
 use super::{
-    attributes::Attributes, extensions::parse_extensions, link::parse_link,
-    waypoint::parse_waypoint, xml_reader_extensions::XmlReaderExtensions,
+    attributes::Attributes,
+    extensions::parse_extensions,
+    gpx_1_0::{is_foreign_element, parse_foreign_element, parse_url, parse_urlname},
+    link::parse_link,
+    waypoint::parse_waypoint,
+    xml_reader::XmlReader,
+    xml_reader_extensions::XmlReaderExtensions,
 };
 
-pub(crate) fn parse_route(
+pub(crate) fn parse_route<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Route, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Route, GpxError> {
     Attributes::check_is_empty(start_element, xml_reader)?;
 
+    // In lenient mode a truncated route keeps everything read before the
+    // point of truncation.
     let mut route = Route::default();
+    let result = read_route(start_element, &mut route, xml_reader);
+    xml_reader.recover(result)?;
+    Ok(route)
+}
 
+fn read_route<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    route: &mut Route,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        if xml_reader.is_truncated() {
+            return Ok(());
+        }
+
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"name" => {
                     route.name = Some(xml_reader.read_inner_as()?);
@@ -50,18 +71,27 @@ pub(crate) fn parse_route(
                     let point = parse_waypoint(&start, xml_reader)?;
                     route.points.push(point);
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                b"url" if xml_reader.is_gpx_1_0() => parse_url(&mut route.links, xml_reader)?,
+                b"urlname" if xml_reader.is_gpx_1_0() => {
+                    parse_urlname(&mut route.links, xml_reader)?;
+                }
+                name if xml_reader.is_gpx_1_0() && is_foreign_element(name) => {
+                    parse_foreign_element(&start, false, &mut route.extensions, xml_reader)?;
+                }
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::End(e)) => {
                 let n = e.name();
                 let n = n.as_ref();
                 if n == start_element.name().as_ref() {
-                    return Ok(route);
+                    return Ok(());
                 } else if n == b"name"
                     || n == b"cmt"
                     || n == b"desc"
                     || n == b"src"
                     || n == b"link"
+                    || n == b"url"
+                    || n == b"urlname"
                     || n == b"number"
                     || n == b"type"
                     || n == b"extensions"
@@ -69,12 +99,18 @@ pub(crate) fn parse_route(
                 {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
+            Ok(Event::Empty(empty))
+                if xml_reader.is_gpx_1_0() && is_foreign_element(empty.name().as_ref()) =>
+            {
+                parse_foreign_element(&empty, true, &mut route.extensions, xml_reader)?;
+            }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
@@ -84,11 +120,10 @@ mod tests {
     use crate::read::xml_reader_extensions::start_parse;
 
     use super::*;
-    use quick_xml::Reader;
 
     #[test]
     fn valid_route_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<rte>
                  <name>Route name</name>
                  <cmt>Route comment</cmt>
@@ -125,7 +160,7 @@ mod tests {
 
     #[test]
     fn extra_elements() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<rte>
                  <foo>bar</foo>
                </rte>"#,
@@ -133,21 +168,21 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_route(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<rte foo="bar">
                </rte>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_route(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -0,0 +1,699 @@
+//! A streaming GPX reader. Rather than building the whole model, it yields the
+//! parts of the document as they are read, so the trackpoints of a huge file
+//! can be processed one at a time in constant memory.
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::{
+    error::{GpxError, ReadWarning},
+    model::{Extensions, Gpx, Metadata, Route, Track, TrackSegment, Waypoint, XmlDeclaration},
+};
+
+use super::{
+    attributes::Attributes,
+    declaration::parse_declaration,
+    extensions::parse_extensions,
+    gpx::GpxElementOrder,
+    gpx_1_0::{
+        is_foreign_element, is_metadata_element, parse_foreign_element, parse_metadata_element,
+    },
+    metadata::parse_metadata,
+    route::parse_route,
+    track::{is_track_detail, parse_track_detail},
+    waypoint::parse_waypoint,
+    xml_reader::XmlReader,
+};
+
+/// An item produced by [GpxStreamReader]. The items are produced in the order
+/// they appear in the document.
+#[derive(Debug, Clone)]
+pub enum GpxItem {
+    /// The start of the document. The Gpx has the declaration, the filename
+    /// (if reading from a file) and the attributes of the 'gpx' element, but
+    /// nothing else.
+    Start(Box<Gpx>),
+    Metadata(Metadata),
+    /// A file-level waypoint.
+    Waypoint(Waypoint),
+    /// A route. Routes are usually small, so they are read whole.
+    Route(Route),
+    /// The start of a track. The Track has all of its details apart from the
+    /// segments, which follow as [GpxItem::SegmentStart],
+    /// [GpxItem::TrackPoint] and [GpxItem::SegmentEnd] items.
+    TrackStart(Track),
+    SegmentStart,
+    TrackPoint(Waypoint),
+    /// The end of a segment. In the XSD the extensions of a segment come after
+    /// its points, which is why they are here.
+    SegmentEnd {
+        extensions: Option<Extensions>,
+    },
+    TrackEnd,
+    /// The file-level extensions.
+    Extensions(Extensions),
+}
+
+#[derive(Debug)]
+enum State {
+    BeforeGpx,
+    InGpx,
+    /// The Track is None once it has been handed out in a TrackStart.
+    InTrack(Option<Track>),
+    InSegment(Option<Extensions>),
+    AfterGpx,
+    Finished,
+}
+
+/// Reads a GPX from any `BufRead` a piece at a time. Use it as an iterator of
+/// [GpxItem], or use [GpxStreamReader::trackpoints] if only the points are
+/// needed.
+///
+/// Once an error has been returned the iterator is finished.
+///
+/// By default the reader is strict. Call [GpxStreamReader::lenient] to skip
+/// unexpected elements and attributes and to end the stream quietly, after the
+/// last complete item, if the document is truncated. The problems are then
+/// available from [GpxStreamReader::warnings].
+pub struct GpxStreamReader<R> {
+    xml_reader: XmlReader<R>,
+    buf: Vec<u8>,
+    state: State,
+    declaration: Option<XmlDeclaration>,
+    filename: Option<PathBuf>,
+    pending: VecDeque<GpxItem>,
+    /// GPX 1.0 has no 'metadata' element, so the metadata is collected from
+    /// the elements directly within 'gpx' and handed out before the next item.
+    gpx_1_0_metadata: Option<Metadata>,
+    /// The elements from other namespaces at the end of a GPX 1.0 'gpx',
+    /// handed out as extensions at the end of the document.
+    gpx_1_0_extensions: Option<Extensions>,
+    order: GpxElementOrder,
+}
+
+impl GpxStreamReader<BufReader<File>> {
+    /// Opens a file for streaming. The filename is set on the Gpx in the
+    /// [GpxItem::Start] item.
+    pub fn from_file<P: AsRef<Path>>(input_file: P) -> Result<Self, GpxError> {
+        let input_file = input_file.as_ref();
+        info!("Streaming GPX file {:?}", input_file);
+        let mut stream = Self::new(BufReader::new(File::open(input_file)?));
+        stream.filename = Some(input_file.to_owned());
+        Ok(stream)
+    }
+}
+
+impl<R: BufRead> GpxStreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            xml_reader: XmlReader::new(Reader::from_reader(reader)),
+            buf: Vec::new(),
+            state: State::BeforeGpx,
+            declaration: None,
+            filename: None,
+            pending: VecDeque::new(),
+            gpx_1_0_metadata: None,
+            gpx_1_0_extensions: None,
+            order: GpxElementOrder::default(),
+        }
+    }
+
+    /// Makes the reader lenient. See [crate::read::read_gpx_from_file_lenient].
+    pub fn lenient(mut self) -> Self {
+        self.xml_reader.set_lenient(true);
+        self
+    }
+
+    /// Returns the problems that were skipped over so far by a lenient reader.
+    pub fn warnings(&self) -> &[ReadWarning] {
+        self.xml_reader.warnings()
+    }
+
+    /// Returns an iterator over just the trackpoints of all the tracks.
+    pub fn trackpoints(self) -> impl Iterator<Item = Result<Waypoint, GpxError>> {
+        self.filter_map(|item| match item {
+            Ok(GpxItem::TrackPoint(point)) => Some(Ok(point)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        })
+    }
+
+    /// Reads the whole document, calling `f` with each trackpoint instead of
+    /// storing it. Returns everything else: the metadata, waypoints, routes and
+    /// tracks, with the track segments present but empty.
+    pub fn for_each_trackpoint<F: FnMut(Waypoint)>(mut self, mut f: F) -> Result<Gpx, GpxError> {
+        let mut gpx = Gpx::default();
+
+        while let Some(item) = self.next_item()? {
+            match item {
+                GpxItem::Start(start) => gpx = *start,
+                GpxItem::Metadata(metadata) => gpx.metadata = metadata,
+                GpxItem::Waypoint(waypoint) => gpx.waypoints.push(waypoint),
+                GpxItem::Route(route) => gpx.routes.push(route),
+                GpxItem::TrackStart(track) => gpx.tracks.push(track),
+                GpxItem::SegmentStart => gpx
+                    .tracks
+                    .last_mut()
+                    .expect("SegmentStart is always preceded by TrackStart")
+                    .segments
+                    .push(TrackSegment::default()),
+                GpxItem::TrackPoint(point) => f(point),
+                GpxItem::SegmentEnd { extensions } => {
+                    gpx.tracks
+                        .last_mut()
+                        .and_then(|t| t.segments.last_mut())
+                        .expect("SegmentEnd is always preceded by SegmentStart")
+                        .extensions = extensions;
+                }
+                GpxItem::TrackEnd => {}
+                GpxItem::Extensions(extensions) => gpx.extensions = Some(extensions),
+            }
+        }
+
+        Ok(gpx)
+    }
+
+    /// Reads the next item from the document. Returns None at the end of the
+    /// document.
+    pub fn next_item(&mut self) -> Result<Option<GpxItem>, GpxError> {
+        match self.read_item() {
+            Err(err) => {
+                // A lenient reader treats a truncated document as ending early.
+                if let Err(err) = self.xml_reader.recover_err(err) {
+                    let err = self.xml_reader.locate(err);
+                    return Err(match &self.filename {
+                        Some(filename) => err.with_line_column_from_file(filename),
+                        None => err,
+                    });
+                }
+                self.state = State::Finished;
+                Ok(None)
+            }
+            item => item,
+        }
+    }
+
+    /// Returns the GPX 1.0 metadata collected so far, if any, queueing 'item'
+    /// to follow it. Otherwise returns 'item'.
+    fn after_gpx_1_0_metadata(&mut self, item: GpxItem) -> GpxItem {
+        match self.gpx_1_0_metadata.take() {
+            Some(metadata) => {
+                self.pending.push_back(item);
+                GpxItem::Metadata(metadata)
+            }
+            None => item,
+        }
+    }
+
+    fn read_item(&mut self) -> Result<Option<GpxItem>, GpxError> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Ok(Some(item));
+            }
+
+            self.buf.clear();
+            let event = match self.state {
+                State::Finished => return Ok(None),
+                _ => self.xml_reader.read_event_into(&mut self.buf)?,
+            };
+
+            match &mut self.state {
+                State::BeforeGpx => match event {
+                    Event::Decl(decl) => {
+                        self.declaration = Some(parse_declaration(&decl, &self.xml_reader)?);
+                    }
+                    Event::Start(start) => {
+                        if start.name().as_ref() != b"gpx" {
+                            return Err(GpxError::bad_start(
+                                start.name().as_ref(),
+                                &self.xml_reader,
+                            ));
+                        }
+
+                        let declaration = match self.declaration.take() {
+                            Some(declaration) => declaration,
+                            None if self.xml_reader.is_lenient() => {
+                                self.xml_reader.warn(ReadWarning::MissingDeclaration);
+                                XmlDeclaration::default()
+                            }
+                            None => return Err(GpxError::ElementNotFound("xml".to_string())),
+                        };
+                        let mut attributes = Attributes::new(&start, &self.xml_reader)?;
+                        let gpx = Gpx {
+                            declaration,
+                            filename: self.filename.clone(),
+                            creator: attributes.get("creator")?,
+                            version: attributes.get("version")?,
+                            attributes: attributes.into_inner(),
+                            ..Default::default()
+                        };
+                        self.xml_reader.set_gpx_1_0(gpx.version == "1.0");
+                        self.xml_reader.set_namespaces(&gpx.attributes);
+
+                        self.state = State::InGpx;
+                        return Ok(Some(GpxItem::Start(Box::new(gpx))));
+                    }
+                    Event::Eof => return Err(GpxError::ElementNotFound("gpx".to_string())),
+                    _ => {}
+                },
+                State::InGpx => match event {
+                    Event::Start(start) => {
+                        self.order
+                            .check(start.name().as_ref(), &mut self.xml_reader)?;
+                        match start.name().as_ref() {
+                            b"metadata" => {
+                                let metadata = parse_metadata(&start, &mut self.xml_reader)?;
+                                return Ok(Some(GpxItem::Metadata(metadata)));
+                            }
+                            b"wpt" => {
+                                let waypoint = parse_waypoint(&start, &mut self.xml_reader)?;
+                                return Ok(Some(
+                                    self.after_gpx_1_0_metadata(GpxItem::Waypoint(waypoint)),
+                                ));
+                            }
+                            b"rte" => {
+                                let route = parse_route(&start, &mut self.xml_reader)?;
+                                return Ok(Some(
+                                    self.after_gpx_1_0_metadata(GpxItem::Route(route)),
+                                ));
+                            }
+                            b"trk" => {
+                                Attributes::check_is_empty(&start, &mut self.xml_reader)?;
+                                self.state = State::InTrack(Some(Track::default()));
+                                if let Some(metadata) = self.gpx_1_0_metadata.take() {
+                                    return Ok(Some(GpxItem::Metadata(metadata)));
+                                }
+                            }
+                            b"extensions" => {
+                                let extensions = parse_extensions(&start, &mut self.xml_reader)?;
+                                return Ok(Some(GpxItem::Extensions(extensions)));
+                            }
+                            name if self.xml_reader.is_gpx_1_0() && is_metadata_element(name) => {
+                                let metadata =
+                                    self.gpx_1_0_metadata.get_or_insert_with(Default::default);
+                                parse_metadata_element(&start, metadata, &mut self.xml_reader)?;
+                            }
+                            name if self.xml_reader.is_gpx_1_0() && is_foreign_element(name) => {
+                                parse_foreign_element(
+                                    &start,
+                                    false,
+                                    &mut self.gpx_1_0_extensions,
+                                    &mut self.xml_reader,
+                                )?;
+                            }
+                            _ => self
+                                .xml_reader
+                                .unexpected_start(&BytesStart::new("gpx"), &start)?,
+                        }
+                    }
+                    Event::End(end) => {
+                        let n = end.name();
+                        let n = n.as_ref();
+                        if self.xml_reader.is_gpx_1_0() && is_metadata_element(n) {
+                            continue;
+                        }
+                        if n != b"gpx" {
+                            return Err(GpxError::bad_end(n, &self.xml_reader));
+                        }
+
+                        if let Some(metadata) = self.gpx_1_0_metadata.take() {
+                            self.pending.push_back(GpxItem::Metadata(metadata));
+                        }
+                        if let Some(extensions) = self.gpx_1_0_extensions.take() {
+                            self.pending.push_back(GpxItem::Extensions(extensions));
+                        }
+                        self.state = State::AfterGpx;
+                    }
+                    Event::Empty(empty) if self.xml_reader.is_gpx_1_0() => {
+                        match empty.name().as_ref() {
+                            b"bounds" => {
+                                let metadata =
+                                    self.gpx_1_0_metadata.get_or_insert_with(Default::default);
+                                parse_metadata_element(&empty, metadata, &mut self.xml_reader)?;
+                            }
+                            name if is_foreign_element(name) => {
+                                parse_foreign_element(
+                                    &empty,
+                                    true,
+                                    &mut self.gpx_1_0_extensions,
+                                    &mut self.xml_reader,
+                                )?;
+                            }
+                            _ => {}
+                        }
+                    }
+                    Event::Eof => return Err(GpxError::UnexpectedEof),
+                    _ => {}
+                },
+                State::InTrack(track) => match event {
+                    Event::Start(start) if start.name().as_ref() == b"trkseg" => {
+                        Attributes::check_is_empty(&start, &mut self.xml_reader)?;
+                        if let Some(track) = track.take() {
+                            self.pending.push_back(GpxItem::TrackStart(track));
+                        }
+                        self.pending.push_back(GpxItem::SegmentStart);
+                        self.state = State::InSegment(None);
+                    }
+                    Event::Start(start) => {
+                        // The XSD puts the segments last, so anything after the
+                        // first segment is read but has nowhere to go.
+                        let mut ignored = Track::default();
+                        let details = track.as_mut().unwrap_or(&mut ignored);
+                        if !parse_track_detail(&start, details, &mut self.xml_reader)? {
+                            self.xml_reader
+                                .unexpected_start(&BytesStart::new("trk"), &start)?;
+                        }
+                    }
+                    Event::End(end) if end.name().as_ref() == b"trk" => {
+                        if let Some(track) = track.take() {
+                            self.pending.push_back(GpxItem::TrackStart(track));
+                        }
+                        self.pending.push_back(GpxItem::TrackEnd);
+                        self.state = State::InGpx;
+                    }
+                    Event::End(end) => {
+                        if !is_track_detail(end.name().as_ref()) {
+                            return Err(GpxError::bad_end(end.name().as_ref(), &self.xml_reader));
+                        }
+                    }
+                    Event::Empty(empty)
+                        if self.xml_reader.is_gpx_1_0()
+                            && is_foreign_element(empty.name().as_ref()) =>
+                    {
+                        let mut ignored = Track::default();
+                        let details = track.as_mut().unwrap_or(&mut ignored);
+                        parse_foreign_element(
+                            &empty,
+                            true,
+                            &mut details.extensions,
+                            &mut self.xml_reader,
+                        )?;
+                    }
+                    Event::Empty(empty) => self
+                        .xml_reader
+                        .unexpected_empty(&BytesStart::new("trk"), &empty)?,
+                    // Ignore spurious Event::Text, I think they are newlines.
+                    Event::Text(_) => {}
+                    event => return Err(GpxError::bad_event(Ok(event))),
+                },
+                State::InSegment(extensions) => match event {
+                    Event::Start(start) => match start.name().as_ref() {
+                        b"trkpt" => {
+                            let point = parse_waypoint(&start, &mut self.xml_reader)?;
+                            return Ok(Some(GpxItem::TrackPoint(point)));
+                        }
+                        b"extensions" => {
+                            *extensions = Some(parse_extensions(&start, &mut self.xml_reader)?);
+                        }
+                        _ => self
+                            .xml_reader
+                            .unexpected_start(&BytesStart::new("trkseg"), &start)?,
+                    },
+                    Event::End(end) => match end.name().as_ref() {
+                        b"trkseg" => {
+                            let extensions = extensions.take();
+                            self.state = State::InTrack(None);
+                            return Ok(Some(GpxItem::SegmentEnd { extensions }));
+                        }
+                        b"trkpt" | b"extensions" => {}
+                        e => return Err(GpxError::bad_end(e, &self.xml_reader)),
+                    },
+                    Event::Empty(empty) => self
+                        .xml_reader
+                        .unexpected_empty(&BytesStart::new("trkseg"), &empty)?,
+                    // Ignore spurious Event::Text, I think they are newlines.
+                    Event::Text(_) => {}
+                    event => return Err(GpxError::bad_event(Ok(event))),
+                },
+                State::AfterGpx => match event {
+                    Event::Start(start) => {
+                        return Err(GpxError::bad_start(start.name().as_ref(), &self.xml_reader))
+                    }
+                    Event::Eof => {
+                        self.state = State::Finished;
+                        return Ok(None);
+                    }
+                    _ => {}
+                },
+                State::Finished => unreachable!("handled before reading the event"),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for GpxStreamReader<R> {
+    type Item = Result<GpxItem, GpxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_item();
+        if item.is_err() {
+            self.state = State::Finished;
+            self.pending.clear();
+        }
+        item.transpose()
+    }
+}
+
+/// Reads a GPX from a file, calling `f` with each trackpoint instead of
+/// storing it. See [GpxStreamReader::for_each_trackpoint].
+pub fn read_gpx_trackpoints_from_file<P: AsRef<Path>, F: FnMut(Waypoint)>(
+    input_file: P,
+    f: F,
+) -> Result<Gpx, GpxError> {
+    GpxStreamReader::from_file(input_file)?.for_each_trackpoint(f)
+}
+
+/// Reads a GPX from any buffered reader, calling `f` with each trackpoint
+/// instead of storing it. See [GpxStreamReader::for_each_trackpoint].
+pub fn read_gpx_trackpoints_from_reader<R: BufRead, F: FnMut(Waypoint)>(
+    reader: R,
+    f: F,
+) -> Result<Gpx, GpxError> {
+    GpxStreamReader::new(reader).for_each_trackpoint(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::read_gpx_from_slice;
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="StravaGPX" version="1.1">
+  <metadata>
+    <name>Ride</name>
+  </metadata>
+  <wpt lat="53.1" lon="-2.1"><name>Control</name></wpt>
+  <trk>
+    <name>Morning Ride</name>
+    <type>cycling</type>
+    <trkseg>
+      <trkpt lat="53.0" lon="-2.5"><ele>100.0</ele></trkpt>
+      <trkpt lat="53.001" lon="-2.5"><ele>101.0</ele></trkpt>
+    </trkseg>
+    <trkseg>
+      <trkpt lat="53.002" lon="-2.5"><ele>102.0</ele></trkpt>
+      <extensions><foo>bar</foo></extensions>
+    </trkseg>
+  </trk>
+  <extensions><file>ext</file></extensions>
+</gpx>"#;
+
+    #[test]
+    fn items_are_produced_in_document_order() {
+        let items: Vec<_> = GpxStreamReader::new(GPX.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let kinds: Vec<_> = items
+            .iter()
+            .map(|item| match item {
+                GpxItem::Start(_) => "start",
+                GpxItem::Metadata(_) => "metadata",
+                GpxItem::Waypoint(_) => "wpt",
+                GpxItem::Route(_) => "rte",
+                GpxItem::TrackStart(_) => "trk",
+                GpxItem::SegmentStart => "seg",
+                GpxItem::TrackPoint(_) => "pt",
+                GpxItem::SegmentEnd { .. } => "/seg",
+                GpxItem::TrackEnd => "/trk",
+                GpxItem::Extensions(_) => "ext",
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                "start", "metadata", "wpt", "trk", "seg", "pt", "pt", "/seg", "seg", "pt", "/seg",
+                "/trk", "ext"
+            ]
+        );
+
+        match &items[0] {
+            GpxItem::Start(gpx) => {
+                assert_eq!(gpx.creator, "StravaGPX");
+                assert_eq!(gpx.declaration.version, "1.0");
+            }
+            _ => unreachable!(),
+        }
+        match &items[3] {
+            GpxItem::TrackStart(track) => {
+                assert_eq!(track.name.as_deref(), Some("Morning Ride"));
+                assert_eq!(track.r#type.as_deref(), Some("cycling"));
+                assert!(track.segments.is_empty());
+            }
+            _ => panic!("Expected TrackStart, got {:?}", items[3]),
+        }
+        match &items[10] {
+            GpxItem::SegmentEnd { extensions } => {
+                assert_eq!(extensions.as_ref().unwrap().raw_xml, "<foo>bar</foo>");
+            }
+            _ => panic!("Expected SegmentEnd, got {:?}", items[10]),
+        }
+    }
+
+    #[test]
+    fn trackpoints_iterator() {
+        let elevations: Vec<_> = GpxStreamReader::new(GPX.as_bytes())
+            .trackpoints()
+            .map(|p| p.unwrap().ele.unwrap())
+            .collect();
+        assert_eq!(elevations, [100.0, 101.0, 102.0]);
+    }
+
+    #[test]
+    fn for_each_trackpoint_matches_full_parse() {
+        let mut points = Vec::new();
+        let skeleton = GpxStreamReader::new(GPX.as_bytes())
+            .for_each_trackpoint(|p| points.push(p))
+            .unwrap();
+        let full = read_gpx_from_slice(GPX.as_bytes()).unwrap();
+
+        assert_eq!(skeleton.creator, full.creator);
+        assert_eq!(skeleton.declaration, full.declaration);
+        assert_eq!(skeleton.metadata.name, full.metadata.name);
+        assert_eq!(skeleton.waypoints.len(), full.waypoints.len());
+        assert_eq!(skeleton.extensions, full.extensions);
+        assert_eq!(skeleton.tracks.len(), 1);
+        assert_eq!(skeleton.tracks[0].name, full.tracks[0].name);
+        assert_eq!(skeleton.tracks[0].segments.len(), 2);
+        assert!(skeleton.tracks[0]
+            .segments
+            .iter()
+            .all(|s| s.points.is_empty()));
+        assert_eq!(
+            skeleton.tracks[0].segments[1].extensions,
+            full.tracks[0].segments[1].extensions
+        );
+
+        let full_points: Vec<_> = full.tracks[0]
+            .segments
+            .iter()
+            .flat_map(|s| s.points.iter())
+            .collect();
+        assert_eq!(points.len(), full_points.len());
+        for (p, f) in points.iter().zip(full_points) {
+            assert_eq!(p.lat, f.lat);
+            assert_eq!(p.lon, f.lon);
+            assert_eq!(p.ele, f.ele);
+        }
+    }
+
+    #[test]
+    fn track_without_segments() {
+        let gpx = r#"<?xml version="1.0"?><gpx creator="x" version="1.1"><trk><name>T</name></trk></gpx>"#;
+        let items: Vec<_> = GpxStreamReader::new(gpx.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(items.len(), 3);
+        assert!(matches!(&items[1], GpxItem::TrackStart(t) if t.name.as_deref() == Some("T")));
+        assert!(matches!(items[2], GpxItem::TrackEnd));
+    }
+
+    #[test]
+    fn errors_end_the_iteration() {
+        let gpx = r#"<?xml version="1.0"?><gpx creator="x" version="1.1"><trk><trkseg><trkpt lat="99" lon="0"></trkpt></trkseg></trk></gpx>"#;
+        let mut stream = GpxStreamReader::new(gpx.as_bytes());
+        assert!(matches!(stream.next(), Some(Ok(GpxItem::Start(_)))));
+        assert!(matches!(stream.next(), Some(Ok(GpxItem::TrackStart(_)))));
+        assert!(matches!(stream.next(), Some(Ok(GpxItem::SegmentStart))));
+        assert!(matches!(
+            stream.next(),
+            Some(Err(e)) if matches!(e.inner(), GpxError::InvalidLatitude(_))
+        ));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn elements_out_of_order_are_errors_unless_lenient() {
+        let gpx = r#"<?xml version="1.0"?><gpx creator="x" version="1.1"><rte></rte><metadata></metadata></gpx>"#;
+        let mut stream = GpxStreamReader::new(gpx.as_bytes());
+        assert!(matches!(stream.next(), Some(Ok(GpxItem::Start(_)))));
+        assert!(matches!(stream.next(), Some(Ok(GpxItem::Route(_)))));
+        assert!(matches!(
+            stream.next(),
+            Some(Err(e)) if matches!(e.inner(), GpxError::ElementOutOfOrder { .. })
+        ));
+
+        let mut stream = GpxStreamReader::new(gpx.as_bytes()).lenient();
+        let items: Vec<_> = stream.by_ref().collect::<Result<_, _>>().unwrap();
+        assert!(matches!(items[2], GpxItem::Metadata(_)));
+        assert!(matches!(
+            stream.warnings(),
+            [ReadWarning::ElementOutOfOrder { element, .. }] if element == "metadata"
+        ));
+    }
+
+    #[test]
+    fn missing_declaration() {
+        let gpx = r#"<gpx creator="x" version="1.1"></gpx>"#;
+        let mut stream = GpxStreamReader::new(gpx.as_bytes());
+        assert!(matches!(
+            stream.next(),
+            Some(Err(e)) if matches!(e.inner(), GpxError::ElementNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn lenient_stream_ends_at_truncation() {
+        let gpx = r#"<?xml version="1.0"?><gpx creator="x" version="1.1"><trk><trkseg>
+            <trkpt lat="53.0" lon="-2.5"><foo/></trkpt>
+            <trkpt lat="53.1" lon="-2.5"><ele>10"#;
+        let mut stream = GpxStreamReader::new(gpx.as_bytes()).lenient();
+        let items: Vec<_> = stream.by_ref().collect::<Result<_, _>>().unwrap();
+        let points = items
+            .iter()
+            .filter(|item| matches!(item, GpxItem::TrackPoint(_)))
+            .count();
+        assert_eq!(points, 1);
+        assert!(matches!(
+            stream.warnings(),
+            [
+                ReadWarning::SkippedElement { .. },
+                ReadWarning::Truncated { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn errors_have_a_location() {
+        let gpx = r#"<?xml version="1.0"?><gpx creator="x" version="1.1"><trk><trkseg><trkpt lat="1" lon="0"></trkpt><trkpt lat="99" lon="0"></trkpt></trkseg></trk></gpx>"#;
+        let err = GpxStreamReader::new(gpx.as_bytes())
+            .trackpoints()
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(
+            err.location().unwrap().path,
+            "gpx/trk[0]/trkseg[0]/trkpt[1]"
+        );
+    }
+}
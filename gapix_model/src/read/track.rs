@@ -0,0 +1,225 @@
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{error::GpxError, model::Track};
+
+use super::{
+    attributes::Attributes,
+    extensions::parse_extensions,
+    gpx_1_0::{is_foreign_element, parse_foreign_element, parse_url, parse_urlname},
+    link::parse_link,
+    track_segment::parse_track_segment,
+    xml_reader::XmlReader,
+    xml_reader_extensions::XmlReaderExtensions,
+};
+
+pub(crate) fn parse_track<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Track, GpxError> {
+    Attributes::check_is_empty(start_element, xml_reader)?;
+
+    // In lenient mode a truncated track keeps everything read before the
+    // point of truncation.
+    let mut track = Track::default();
+    let result = read_track(start_element, &mut track, xml_reader);
+    xml_reader.recover(result)?;
+    Ok(track)
+}
+
+fn read_track<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    track: &mut Track,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let mut buf = Vec::new();
+    loop {
+        if xml_reader.is_truncated() {
+            return Ok(());
+        }
+
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
+            Ok(Event::Start(start)) => match start.name().as_ref() {
+                b"trkseg" => {
+                    track
+                        .segments
+                        .push(parse_track_segment(&start, xml_reader)?);
+                }
+                _ => {
+                    if !parse_track_detail(&start, track, xml_reader)? {
+                        xml_reader.unexpected_start(start_element, &start)?;
+                    }
+                }
+            },
+            Ok(Event::End(e)) => {
+                let n = e.name();
+                let n = n.as_ref();
+                if n == start_element.name().as_ref() {
+                    return Ok(());
+                } else if n == b"trkseg" || is_track_detail(n) {
+                    // These are expected endings, do nothing.
+                } else {
+                    return Err(GpxError::bad_end(n, xml_reader));
+                }
+            }
+            Ok(Event::Empty(empty))
+                if xml_reader.is_gpx_1_0() && is_foreign_element(empty.name().as_ref()) =>
+            {
+                parse_foreign_element(&empty, true, &mut track.extensions, xml_reader)?;
+            }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
+            // Ignore spurious Event::Text, I think they are newlines.
+            Ok(Event::Text(_)) => {}
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+/// Parses one of the elements of a 'trk' that describe the track, which is
+/// everything apart from the segments. Returns false if `start_element` is not
+/// one of them.
+pub(crate) fn parse_track_detail<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    track: &mut Track,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<bool, GpxError> {
+    match start_element.name().as_ref() {
+        b"name" => {
+            track.name = Some(xml_reader.read_inner_as()?);
+        }
+        b"cmt" => {
+            track.comment = Some(xml_reader.read_inner_as()?);
+        }
+        b"desc" => {
+            track.description = Some(xml_reader.read_inner_as()?);
+        }
+        b"src" => {
+            track.source = Some(xml_reader.read_inner_as()?);
+        }
+        b"link" => {
+            let link = parse_link(start_element, xml_reader)?;
+            track.links.push(link);
+        }
+        b"number" => {
+            track.number = Some(xml_reader.read_inner_as()?);
+        }
+        b"type" => {
+            track.r#type = Some(xml_reader.read_inner_as_string()?);
+        }
+        b"extensions" => {
+            track.extensions = Some(parse_extensions(start_element, xml_reader)?);
+        }
+        b"url" if xml_reader.is_gpx_1_0() => parse_url(&mut track.links, xml_reader)?,
+        b"urlname" if xml_reader.is_gpx_1_0() => parse_urlname(&mut track.links, xml_reader)?,
+        name if xml_reader.is_gpx_1_0() && is_foreign_element(name) => {
+            parse_foreign_element(start_element, false, &mut track.extensions, xml_reader)?;
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Returns true if `name` is one of the elements handled by
+/// [parse_track_detail]. Their end tags are seen by the caller.
+pub(crate) fn is_track_detail(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"name"
+            | b"cmt"
+            | b"desc"
+            | b"src"
+            | b"link"
+            | b"url"
+            | b"urlname"
+            | b"number"
+            | b"type"
+            | b"extensions"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::read::xml_reader_extensions::start_parse;
+
+    use super::*;
+
+    #[test]
+    fn valid_track_all_fields() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trk>
+                 <name>Route name</name>
+                 <cmt>Route comment</cmt>
+                 <desc>Route description</desc>
+                 <src>Route source</src>
+                 <link href="http://example.com">
+                    <text>Some text here</text>
+                    <type>jpeg</type>
+                 </link>
+                 <link href="http://example2.com">
+                    <text>Some text here2</text>
+                    <type>jpeg2</type>
+                 </link>
+                 <number>42</number>
+                 <type>Route type</type>
+                 <extensions><foo><ex:ex1>extended data</ex:ex1></foo></extensions>
+                 <trkseg>
+                    <trkpt lat="55.20625" lon="-11.450350">
+                    </trkpt>
+                    <trkpt lat="55.20625" lon="-11.450350">
+                    </trkpt>
+                 </trkseg>
+                 <trkseg>
+                    <trkpt lat="55.20625" lon="-11.450350">
+                    </trkpt>
+                 </trkseg>
+               </trk>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_track(&start, &mut xml_reader).unwrap();
+        assert_eq!(result.name, Some("Route name".to_string()));
+        assert_eq!(result.comment, Some("Route comment".to_string()));
+        assert_eq!(result.description, Some("Route description".to_string()));
+        assert_eq!(result.source, Some("Route source".to_string()));
+        assert_eq!(result.links.len(), 2);
+        assert_eq!(result.links[0].href, "http://example.com");
+        assert_eq!(result.links[1].href, "http://example2.com");
+        assert_eq!(result.number, Some(42));
+        assert_eq!(result.r#type, Some("Route type".to_string()));
+        let ext = result.extensions.unwrap();
+        assert_eq!(ext.raw_xml, "<foo><ex:ex1>extended data</ex:ex1></foo>");
+        assert_eq!(result.segments.len(), 2);
+    }
+
+    #[test]
+    fn extra_elements() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trk>
+                 <foo>bar</foo>
+               </trk>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        match parse_track(&start, &mut xml_reader) {
+            Err(GpxError::UnexpectedStartElement(_)) => {}
+            x => panic!("Unexpected result from parse(): {:?}", x),
+        };
+    }
+
+    #[test]
+    fn extra_attributes() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trk foo="bar">
+               </trk>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        match parse_track(&start, &mut xml_reader) {
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
+            x => panic!("Unexpected result from parse(): {:?}", x),
+        };
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{error::GpxError, model::TrackSegment};
+
+use super::{
+    attributes::Attributes, extensions::parse_extensions, waypoint::parse_waypoint,
+    xml_reader::XmlReader,
+};
+
+pub(crate) fn parse_track_segment<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<TrackSegment, GpxError> {
+    Attributes::check_is_empty(start_element, xml_reader)?;
+
+    // In lenient mode a truncated segment keeps everything read before the
+    // point of truncation.
+    let mut segment = TrackSegment::default();
+    let result = read_track_segment(start_element, &mut segment, xml_reader);
+    xml_reader.recover(result)?;
+    Ok(segment)
+}
+
+fn read_track_segment<R: BufRead>(
+    start_element: &BytesStart<'_>,
+    segment: &mut TrackSegment,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let mut buf = Vec::new();
+    loop {
+        if xml_reader.is_truncated() {
+            return Ok(());
+        }
+
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
+            Ok(Event::Start(start)) => match start.name().as_ref() {
+                b"trkpt" => {
+                    let point = parse_waypoint(&start, xml_reader)?;
+                    segment.points.push(point);
+                }
+                b"extensions" => {
+                    segment.extensions = Some(parse_extensions(&start, xml_reader)?);
+                }
+                _ => xml_reader.unexpected_start(start_element, &start)?,
+            },
+            Ok(Event::End(e)) => {
+                let n = e.name();
+                let n = n.as_ref();
+                if n == start_element.name().as_ref() {
+                    return Ok(());
+                } else if n == b"trkpt" || n == b"extensions" {
+                    // These are expected endings, do nothing.
+                } else {
+                    return Err(GpxError::bad_end(n, xml_reader));
+                }
+            }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
+            // Ignore spurious Event::Text, I think they are newlines.
+            Ok(Event::Text(_)) => {}
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ReadWarning, read::xml_reader_extensions::start_parse};
+
+    use super::*;
+
+    #[test]
+    fn valid_track_segment_all_fields() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trkseg>
+                 <extensions><foo><ex:ex1>extended data</ex:ex1></foo></extensions>
+               </trkseg>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_track_segment(&start, &mut xml_reader).unwrap();
+        let ext = result.extensions.unwrap();
+        assert_eq!(ext.raw_xml, "<foo><ex:ex1>extended data</ex:ex1></foo>");
+    }
+
+    #[test]
+    fn extra_elements() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trkseg>
+                 <foo>bar</foo>
+               </trkseg>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        match parse_track_segment(&start, &mut xml_reader) {
+            Err(GpxError::UnexpectedStartElement(_)) => {}
+            x => panic!("Unexpected result from parse(): {:?}", x),
+        };
+    }
+
+    #[test]
+    fn extra_attributes() {
+        let mut xml_reader = XmlReader::from_str(
+            r#"<trkseg foo="bar">
+               </trkseg>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        match parse_track_segment(&start, &mut xml_reader) {
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
+            x => panic!("Unexpected result from parse(): {:?}", x),
+        };
+    }
+
+    #[test]
+    fn lenient_skips_extra_elements_and_attributes() {
+        let mut xml_reader = XmlReader::lenient_from_str(
+            r#"<trkseg foo="bar">
+                 <foo><bar>baz</bar></foo>
+                 <trkpt lat="53.0" lon="-2.5"></trkpt>
+                 <empty/>
+               </trkseg>"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_track_segment(&start, &mut xml_reader).unwrap();
+        assert_eq!(result.points.len(), 1);
+        assert_eq!(
+            xml_reader.warnings(),
+            [
+                ReadWarning::SkippedAttributes {
+                    element: "trkseg".to_string(),
+                    attributes: "foo".to_string()
+                },
+                ReadWarning::SkippedElement {
+                    parent: "trkseg".to_string(),
+                    element: "foo".to_string()
+                },
+                ReadWarning::SkippedElement {
+                    parent: "trkseg".to_string(),
+                    element: "empty".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient_keeps_complete_points_of_truncated_segment() {
+        let mut xml_reader = XmlReader::lenient_from_str(
+            r#"<trkseg>
+                 <trkpt lat="53.0" lon="-2.5"><ele>100</ele></trkpt>
+                 <trkpt lat="53.1" lon="-2.5"><ele>10"#,
+        );
+
+        let start = start_parse(&mut xml_reader);
+        let result = parse_track_segment(&start, &mut xml_reader).unwrap();
+        assert_eq!(result.points.len(), 1);
+        assert!(xml_reader.is_truncated());
+        assert!(matches!(
+            xml_reader.warnings(),
+            [ReadWarning::Truncated { .. }]
+        ));
+    }
+}
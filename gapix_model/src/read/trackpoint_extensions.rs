@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    name::PrefixDeclaration,
+    Reader,
+};
+
+use crate::{
+    error::{GpxError, ReadWarning},
+    extension_schemas::{find_extension_element, ExtensionElement, TrackpointField},
+    model::{Degrees, GarminTrackpointExtensions},
+};
+
+use super::xml_reader::XmlReader;
+
+/// The namespaces declared by an element, as (prefix, URI) pairs. The prefix
+/// is empty for the default namespace.
+type Declarations = Vec<(Vec<u8>, String)>;
+
+/// This function is a little different to the other parse() functions - it expects
+/// to be fed the inner Xml from an &lt;extensions&gt; tag, so it won't find the usual
+/// &lt;/extensions&gt; ending tag.
+///
+/// Elements from any of the known extension schemas are read, see
+/// [crate::extension_schemas]. Values that cannot be parsed or are out of
+/// range are an error, unless `xml_reader` is lenient, in which case they are
+/// skipped with a warning.
+pub(crate) fn parse_garmin_trackpoint_extensions<R>(
+    s: &str,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Option<GarminTrackpointExtensions>, GpxError> {
+    let mut gext = GarminTrackpointExtensions::default();
+    let mut found = false;
+    let mut reader = Reader::from_str(s);
+    // The declarations of the elements that enclose the current one.
+    let mut scopes: Vec<Declarations> = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                // Ignore any other elements, there can be ANYTHING in an extensions tag.
+                let declarations = declarations(&e);
+                let element =
+                    resolve_namespace(&e, &declarations, &scopes, xml_reader.namespaces())
+                        .and_then(|namespace| {
+                            find_extension_element(namespace.as_deref(), e.local_name().as_ref())
+                        });
+
+                let Some(element) = element else {
+                    scopes.push(declarations);
+                    continue;
+                };
+
+                let text = reader.read_text(e.name())?;
+                match parse_field(&mut gext, element, &text) {
+                    Ok(()) => found = true,
+                    Err(err) if xml_reader.is_lenient() => {
+                        xml_reader.warn(ReadWarning::SkippedExtensionValue {
+                            element: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+                            value: text.into_owned(),
+                            error: err.to_string(),
+                        });
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(Event::End(_)) => {
+                scopes.pop();
+            }
+            // Ignore spurious Event::Text, I think they are newlines.
+            Ok(Event::Text(_)) => {}
+            Ok(Event::Empty(_)) => {}
+            Ok(Event::Eof) => {
+                return Ok(found.then_some(gext));
+            }
+            e => return Err(GpxError::bad_event(e)),
+        }
+    }
+}
+
+/// Returns the namespaces declared by the 'xmlns' attributes of 'e'.
+fn declarations(e: &BytesStart<'_>) -> Declarations {
+    e.attributes()
+        .flatten()
+        .filter_map(|attr| {
+            let prefix = match attr.key.as_namespace_binding()? {
+                PrefixDeclaration::Default => Vec::new(),
+                PrefixDeclaration::Named(prefix) => prefix.to_vec(),
+            };
+            Some((prefix, String::from_utf8_lossy(&attr.value).into_owned()))
+        })
+        .collect()
+}
+
+/// Returns the namespace of 'e', looking for its prefix in its own
+/// declarations, then those of the elements that enclose it and finally those
+/// of the 'gpx' element. An element without a prefix is in no namespace
+/// unless the extensions declare a default one. Returns None if the prefix is
+/// not declared anywhere.
+fn resolve_namespace(
+    e: &BytesStart<'_>,
+    declarations: &Declarations,
+    scopes: &[Declarations],
+    gpx_namespaces: &HashMap<String, String>,
+) -> Option<Option<String>> {
+    let name = e.name();
+    let prefix = name
+        .prefix()
+        .map(|p| p.as_ref().to_vec())
+        .unwrap_or_default();
+
+    let declared = std::iter::once(declarations)
+        .chain(scopes.iter().rev())
+        .flatten()
+        .find(|(declared, _)| *declared == prefix);
+    if let Some((_, uri)) = declared {
+        return Some(Some(uri.clone()));
+    }
+
+    if prefix.is_empty() {
+        return Some(None);
+    }
+
+    let prefix = String::from_utf8_lossy(&prefix);
+    gpx_namespaces
+        .get(prefix.as_ref())
+        .map(|uri| Some(uri.clone()))
+}
+
+/// Parses 'text' and sets the field that 'element' is read into.
+fn parse_field(
+    gext: &mut GarminTrackpointExtensions,
+    element: &ExtensionElement,
+    text: &str,
+) -> Result<(), GpxError> {
+    let value = text.parse::<f64>().map_err(|_| GpxError::ParseFailure {
+        from: text.to_string(),
+        dest_type: std::any::type_name::<f64>().to_string(),
+    })?;
+    set_field(gext, element, value)
+}
+
+/// Sets the field that 'element' is read into, converting 'value' to its
+/// units and type.
+fn set_field(
+    gext: &mut GarminTrackpointExtensions,
+    element: &ExtensionElement,
+    value: f64,
+) -> Result<(), GpxError> {
+    let value = value * element.scale;
+    match element.field {
+        TrackpointField::AirTemp => gext.air_temp = Some(value),
+        TrackpointField::WaterTemp => gext.water_temp = Some(value),
+        TrackpointField::Depth => gext.depth = Some(value),
+        TrackpointField::HeartRate => gext.heart_rate = Some(to_integer(value)?),
+        TrackpointField::Cadence => gext.cadence = Some(to_integer(value)?),
+        TrackpointField::Speed => gext.speed = Some(value),
+        TrackpointField::Course => gext.course = Some(Degrees::new(value)?),
+        TrackpointField::Bearing => gext.bearing = Some(Degrees::new(value)?),
+        TrackpointField::Power => gext.power = Some(to_integer(value)?),
+    }
+
+    Ok(())
+}
+
+/// Rounds 'value' to an integer of type T, which it must fit in.
+fn to_integer<T: TryFrom<i64>>(value: f64) -> Result<T, GpxError> {
+    let rounded = value.round();
+    if rounded.is_finite() && rounded.abs() < i64::MAX as f64 {
+        if let Ok(value) = T::try_from(rounded as i64) {
+            return Ok(value);
+        }
+    }
+
+    Err(GpxError::ParseFailure {
+        from: value.to_string(),
+        dest_type: std::any::type_name::<T>().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses 'xml' with the namespaces that a 'gpx' element would declare.
+    fn parse(xml: &str, lenient: bool) -> (Option<GarminTrackpointExtensions>, Vec<ReadWarning>) {
+        try_parse(xml, lenient).unwrap()
+    }
+
+    fn try_parse(
+        xml: &str,
+        lenient: bool,
+    ) -> Result<(Option<GarminTrackpointExtensions>, Vec<ReadWarning>), GpxError> {
+        let attributes = [
+            ("xmlns", "http://www.topografix.com/GPX/1/1"),
+            (
+                "xmlns:ns3",
+                "http://www.garmin.com/xmlschemas/TrackPointExtension/v1",
+            ),
+            (
+                "xmlns:gpxtpx",
+                "http://www.garmin.com/xmlschemas/TrackPointExtension/v2",
+            ),
+            (
+                "xmlns:pwr",
+                "http://www.garmin.com/xmlschemas/PowerExtension/v1",
+            ),
+            ("xmlns:gpxdata", "http://www.cluetrust.com/XML/GPXDATA/1/0"),
+            ("xmlns:foo", "http://example.com/foo"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let mut xml_reader = XmlReader::from_str("");
+        xml_reader.set_lenient(lenient);
+        xml_reader.set_namespaces(&attributes);
+        let ext = parse_garmin_trackpoint_extensions(xml, &mut xml_reader)?;
+        Ok((ext, xml_reader.into_warnings()))
+    }
+
+    #[test]
+    fn version_1_extensions() {
+        let xml = "<ns3:TrackPointExtension><ns3:atemp>15.5</ns3:atemp>\
+                   <ns3:hr>120</ns3:hr><ns3:cad>85</ns3:cad></ns3:TrackPointExtension>";
+        let ext = parse(xml, false).0.unwrap();
+        assert_eq!(ext.air_temp, Some(15.5));
+        assert_eq!(ext.heart_rate, Some(120));
+        assert_eq!(ext.cadence, Some(85));
+        assert_eq!(ext.speed, None);
+    }
+
+    #[test]
+    fn version_2_extensions() {
+        let xml = "<gpxtpx:TrackPointExtension><gpxtpx:hr>130</gpxtpx:hr>\
+                   <gpxtpx:speed>7.25</gpxtpx:speed><gpxtpx:course>90.5</gpxtpx:course>\
+                   <gpxtpx:bearing>92</gpxtpx:bearing></gpxtpx:TrackPointExtension>";
+        let ext = parse(xml, false).0.unwrap();
+        assert_eq!(ext.heart_rate, Some(130));
+        assert_eq!(ext.speed, Some(7.25));
+        assert_eq!(ext.course.unwrap().value(), 90.5);
+        assert_eq!(ext.bearing.unwrap().value(), 92.0);
+    }
+
+    #[test]
+    fn namespaces_declared_within_the_extensions_are_used() {
+        let xml = r#"<TrackPointExtension xmlns="http://www.garmin.com/xmlschemas/TrackPointExtension/v2">
+                       <hr>125</hr></TrackPointExtension>
+                     <x:cad xmlns:x="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">80</x:cad>
+                     <power>99</power>"#;
+        let ext = parse(xml, false).0.unwrap();
+        assert_eq!(ext.heart_rate, Some(125));
+        assert_eq!(ext.cadence, Some(80));
+        assert_eq!(ext.power, Some(99));
+    }
+
+    #[test]
+    fn power_and_other_devices_extensions() {
+        let xml = "<power>250</power><gpxdata:hr>140</gpxdata:hr><gpxdata:temp>18.5</gpxdata:temp>\
+                   <gpxdata:cadence>88</gpxdata:cadence>";
+        let ext = parse(xml, false).0.unwrap();
+        assert_eq!(ext.power, Some(250));
+        assert_eq!(ext.heart_rate, Some(140));
+        assert_eq!(ext.air_temp, Some(18.5));
+        assert_eq!(ext.cadence, Some(88));
+    }
+
+    #[test]
+    fn power_in_milliwatts_is_converted_to_watts() {
+        let xml = "<pwr:PowerInMilliwatts>250400</pwr:PowerInMilliwatts>";
+        let ext = parse(xml, false).0.unwrap();
+        assert_eq!(ext.power, Some(250));
+    }
+
+    #[test]
+    fn elements_from_other_namespaces_are_ignored() {
+        let xml = "<foo:course>-10</foo:course><foo:speed>fast</foo:speed>\
+                   <foo:power><foo:avg>3</foo:avg></foo:power><bar:hr>1000</bar:hr>";
+        let (ext, warnings) = parse(xml, true);
+        assert!(ext.is_none());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn invalid_values_are_errors_unless_lenient() {
+        let xml = "<ns3:TrackPointExtension><ns3:course>400</ns3:course><ns3:hr>300</ns3:hr>\
+                   <ns3:cad>fast</ns3:cad><ns3:atemp>12</ns3:atemp></ns3:TrackPointExtension>\
+                   <power><avg>3</avg></power>";
+        assert!(matches!(
+            try_parse(xml, false),
+            Err(GpxError::InvalidDegrees(_))
+        ));
+        assert!(matches!(
+            try_parse(
+                "<ns3:TrackPointExtension><ns3:cad>fast</ns3:cad></ns3:TrackPointExtension>",
+                false
+            ),
+            Err(GpxError::ParseFailure { .. })
+        ));
+
+        let (ext, warnings) = parse(xml, true);
+        let ext = ext.unwrap();
+        assert_eq!(ext.course, None);
+        assert_eq!(ext.heart_rate, None);
+        assert_eq!(ext.cadence, None);
+        assert_eq!(ext.power, None);
+        assert_eq!(ext.air_temp, Some(12.0));
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(
+            warnings[2],
+            ReadWarning::SkippedExtensionValue {
+                element: "ns3:cad".to_string(),
+                value: "fast".to_string(),
+                error: "Could not parse fast into type f64".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_extensions_are_none() {
+        let xml = "<foo:bar>1</foo:bar><foo:empty/>";
+        assert!(parse(xml, false).0.is_none());
+    }
+}
@@ -1,34 +1,40 @@
-use quick_xml::{
-    events::{BytesStart, Event},
-    Reader,
-};
+use std::io::BufRead;
+
+use quick_xml::events::{BytesStart, Event};
 
 use crate::{
-    error::GapixError,
-    model::Waypoint,
-    model_impls::{validate_degrees, validate_dgps_station_id},
+    error::GpxError,
+    model::{Degrees, DgpsStationId, Waypoint},
 };
 
 use super::{
-    attributes::Attributes, extensions::parse_extensions, link::parse_link,
-    trackpoint_extensions::parse_garmin_trackpoint_extensions, xml_reader_extensions::XmlReaderExtensions,
+    attributes::Attributes,
+    extensions::parse_extensions,
+    gpx_1_0::{is_foreign_element, parse_foreign_element, parse_url, parse_urlname},
+    gpx_extensions::{parse_garmin_route_point_extensions, parse_garmin_waypoint_extensions},
+    link::parse_link,
+    trackpoint_extensions::parse_garmin_trackpoint_extensions,
+    xml_reader::XmlReader,
+    xml_reader_extensions::XmlReaderExtensions,
 };
 
 /// Parses a waypoint. Waypoints can appear under the 'gpx' tag, as part of a
 /// route or as part of a track.
-pub(crate) fn parse_waypoint(
+pub(crate) fn parse_waypoint<R: BufRead>(
     start_element: &BytesStart<'_>,
-    xml_reader: &mut Reader<&[u8]>,
-) -> Result<Waypoint, GapixError> {
+    xml_reader: &mut XmlReader<R>,
+) -> Result<Waypoint, GpxError> {
     let mut attributes = Attributes::new(start_element, xml_reader)?;
     let lat = attributes.get("lat")?;
     let lon = attributes.get("lon")?;
-    attributes.check_is_empty_now()?;
+    attributes.check_is_empty_now(xml_reader)?;
 
     let mut wp = Waypoint::with_lat_lon(lat, lon)?;
 
+    let mut buf = Vec::new();
     loop {
-        match xml_reader.read_event() {
+        buf.clear();
+        match xml_reader.read_event_into(&mut buf) {
             Ok(Event::Start(start)) => match start.name().as_ref() {
                 b"ele" => {
                     wp.ele = Some(xml_reader.read_inner_as()?);
@@ -36,8 +42,15 @@ pub(crate) fn parse_waypoint(
                 b"time" => {
                     wp.time = Some(xml_reader.read_inner_as_time()?);
                 }
+                b"course" if xml_reader.is_gpx_1_0() => {
+                    let degrees = Degrees::new(xml_reader.read_inner_as()?)?;
+                    wp.course = Some(degrees);
+                }
+                b"speed" if xml_reader.is_gpx_1_0() => {
+                    wp.speed = Some(xml_reader.read_inner_as()?);
+                }
                 b"magvar" => {
-                    let degrees = validate_degrees(xml_reader.read_inner_as()?)?;
+                    let degrees = Degrees::new(xml_reader.read_inner_as()?)?;
                     wp.magvar = Some(degrees);
                 }
                 b"geoidheight" => {
@@ -85,29 +98,34 @@ pub(crate) fn parse_waypoint(
                     wp.age_of_dgps_data = Some(xml_reader.read_inner_as()?);
                 }
                 b"dgpsid" => {
-                    let id = validate_dgps_station_id(xml_reader.read_inner_as()?)?;
+                    let id = DgpsStationId::try_from(xml_reader.read_inner_as::<i64>()?)?;
                     wp.dgps_id = Some(id);
                 }
                 b"extensions" => {
                     wp.extensions = Some(parse_extensions(&start, xml_reader)?);
-                    if start_element.name().as_ref() == b"trkpt" {
-                        // TP Extensions only exist on waypoints within tracks.
-                        // Even then, they are optional.
-                        let s = &wp.extensions.as_ref()
-                            .expect("unwrap is safe due to just setting extensions to Some in line above")
-                            .raw_xml;
-                        wp.garmin_extensions = parse_garmin_trackpoint_extensions(s)?;
-                    }
                 }
-                e => return Err(GapixError::bad_start(e, xml_reader)),
+                b"url" if xml_reader.is_gpx_1_0() => parse_url(&mut wp.links, xml_reader)?,
+                b"urlname" if xml_reader.is_gpx_1_0() => {
+                    parse_urlname(&mut wp.links, xml_reader)?;
+                }
+                name if xml_reader.is_gpx_1_0() && is_foreign_element(name) => {
+                    parse_foreign_element(&start, false, &mut wp.extensions, xml_reader)?;
+                }
+                _ => xml_reader.unexpected_start(start_element, &start)?,
             },
             Ok(Event::End(e)) => {
                 let n = e.name();
                 let n = n.as_ref();
                 if n == start_element.name().as_ref() {
+                    // The End has taken the point off the path, so put it
+                    // back on for any errors in its extensions.
+                    parse_garmin_extensions(n, &mut wp, xml_reader)
+                        .map_err(|err| xml_reader.locate_ended(err, n))?;
                     return Ok(wp);
                 } else if n == b"ele"
                     || n == b"time"
+                    || n == b"course"
+                    || n == b"speed"
                     || n == b"magvar"
                     || n == b"geoidheight"
                     || n == b"name"
@@ -115,6 +133,8 @@ pub(crate) fn parse_waypoint(
                     || n == b"desc"
                     || n == b"src"
                     || n == b"link"
+                    || n == b"url"
+                    || n == b"urlname"
                     || n == b"sym"
                     || n == b"type"
                     || n == b"fix"
@@ -128,25 +148,55 @@ pub(crate) fn parse_waypoint(
                 {
                     // These are expected endings, do nothing.
                 } else {
-                    return Err(GapixError::bad_end(n, xml_reader));
+                    return Err(GpxError::bad_end(n, xml_reader));
                 }
             }
+            Ok(Event::Empty(empty))
+                if xml_reader.is_gpx_1_0() && is_foreign_element(empty.name().as_ref()) =>
+            {
+                parse_foreign_element(&empty, true, &mut wp.extensions, xml_reader)?;
+            }
+            Ok(Event::Empty(empty)) => xml_reader.unexpected_empty(start_element, &empty)?,
             // Ignore spurious Event::Text, I think they are newlines.
             Ok(Event::Text(_)) => {}
-            e => return Err(GapixError::bad_event(e)),
+            e => return Err(GpxError::bad_event(e)),
         }
     }
 }
 
+/// Parses the Garmin extensions out of the raw extensions. Each kind of
+/// Garmin extension only exists on one kind of waypoint. Even then, they are
+/// optional.
+fn parse_garmin_extensions<R>(
+    element_name: &[u8],
+    wp: &mut Waypoint,
+    xml_reader: &mut XmlReader<R>,
+) -> Result<(), GpxError> {
+    let Some(extensions) = &wp.extensions else {
+        return Ok(());
+    };
+
+    let s = &extensions.raw_xml;
+    match element_name {
+        b"trkpt" => {
+            wp.garmin_extensions = parse_garmin_trackpoint_extensions(s, xml_reader)?;
+        }
+        b"wpt" => wp.garmin_waypoint_extensions = parse_garmin_waypoint_extensions(s)?,
+        b"rtept" => wp.garmin_route_point_extensions = parse_garmin_route_point_extensions(s)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{model::FixType, read::xml_reader_extensions::start_parse};
-    use quick_xml::Reader;
 
     #[test]
     fn valid_waypoint_all_fields() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350">
                  <ele>158.399993896484375</ele>
                  <time>2024-02-02T10:10:54.000Z</time>
@@ -181,7 +231,7 @@ mod tests {
         let result = parse_waypoint(&start, &mut xml_reader).unwrap();
         assert_eq!(result.lat, 55.20625);
         assert_eq!(result.lon, -11.450350);
-        assert_eq!(result.magvar, Some(52.3));
+        assert_eq!(result.magvar.map(Degrees::value), Some(52.3));
         assert_eq!(result.geoid_height, Some(100.7));
         assert_eq!(result.name, Some("Waypoint name".to_string()));
         assert_eq!(result.comment, Some("Waypoint comment".to_string()));
@@ -199,7 +249,7 @@ mod tests {
         assert_eq!(result.vdop, Some(130.3));
         assert_eq!(result.pdop, Some(140.4));
         assert_eq!(result.age_of_dgps_data, Some(1234.1234));
-        assert_eq!(result.dgps_id, Some(89));
+        assert_eq!(result.dgps_id.map(DgpsStationId::value), Some(89));
 
         let ext = result.extensions.unwrap();
         assert_eq!(ext.raw_xml, "<foo><ex:ex1>extended data</ex:ex1></foo>");
@@ -207,7 +257,7 @@ mod tests {
 
     #[test]
     fn invalid_negative_dgps_id() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350">
                  <dgpsid>-1</dgpsid>
                </trkpt>"#,
@@ -215,14 +265,14 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::InvalidDGPSStationId(-1)) => {}
+            Err(GpxError::InvalidDGPSStationId(-1)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn invalid_positive_dgps_id() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350">
                  <dgpsid>1024</dgpsid>
                </trkpt>"#,
@@ -230,14 +280,14 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::InvalidDGPSStationId(1024)) => {}
+            Err(GpxError::InvalidDGPSStationId(1024)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn invalid_magvar() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350">
                  <magvar>360.1</magvar>
                </trkpt>"#,
@@ -245,42 +295,42 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::InvalidDegrees(360.1)) => {}
+            Err(GpxError::InvalidDegrees(360.1)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn invalid_latitude() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="1234" lon="-11.450350">
                </trkpt>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::InvalidLatitude(1234.0)) => {}
+            Err(GpxError::InvalidLatitude(1234.0)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn invalid_longitude() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="34" lon="-1111.45">
                </trkpt>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::InvalidLongitude(-1111.45)) => {}
+            Err(GpxError::InvalidLongitude(-1111.45)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_elements() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350">
                  <foo>bar</foo>
                </trkpt>"#,
@@ -288,21 +338,21 @@ mod tests {
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedStartElement(_)) => {}
+            Err(GpxError::UnexpectedStartElement(_)) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
 
     #[test]
     fn extra_attributes() {
-        let mut xml_reader = Reader::from_str(
+        let mut xml_reader = XmlReader::from_str(
             r#"<trkpt lat="55.20625" lon="-11.450350" foo="bar">
                </trkpt>"#,
         );
 
         let start = start_parse(&mut xml_reader);
         match parse_waypoint(&start, &mut xml_reader) {
-            Err(GapixError::UnexpectedAttributes { .. }) => {}
+            Err(GpxError::UnexpectedAttributes { .. }) => {}
             x => panic!("Unexpected result from parse(): {:?}", x),
         };
     }
@@ -0,0 +1,376 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::BufRead,
+    ops::{Deref, DerefMut},
+};
+
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::error::{ErrorLocation, GpxError, ReadWarning};
+
+use super::xml_reader_extensions::XmlReaderConversions;
+
+/// Wraps a quick_xml Reader with the state needed while parsing a GPX: whether
+/// the parse is lenient, the warnings recorded so far and the path to the
+/// current element, which is used to say where errors happened. It derefs to
+/// the Reader, so the parsers can use it in the same way.
+///
+/// In strict mode (the default) anything unexpected is an error. In lenient
+/// mode unexpected elements and attributes are skipped, and if the document is
+/// truncated the parsers return what they had read up to that point. Each of
+/// these is recorded as a [ReadWarning].
+pub(crate) struct XmlReader<R> {
+    reader: Reader<R>,
+    lenient: bool,
+    gpx_1_0: bool,
+    /// The namespace prefixes declared on the 'gpx' element, mapped to their
+    /// URIs. Used to resolve the prefixes within extensions.
+    namespaces: HashMap<String, String>,
+    truncated: bool,
+    warnings: Vec<ReadWarning>,
+    path: Vec<PathElement>,
+}
+
+/// The elements that can repeat within their parent, these have an index in
+/// the path.
+const REPEATING_ELEMENTS: [&[u8]; 7] = [
+    b"wpt", b"rte", b"rtept", b"trk", b"trkseg", b"trkpt", b"link",
+];
+
+/// An element in the path to the current element.
+struct PathElement {
+    name: Vec<u8>,
+    index: Option<usize>,
+    /// The number of each repeating element seen so far within this element.
+    child_counts: HashMap<Vec<u8>, usize>,
+}
+
+impl<R> XmlReader<R> {
+    /// Creates a strict reader.
+    pub(crate) fn new(reader: Reader<R>) -> Self {
+        Self {
+            reader,
+            lenient: false,
+            gpx_1_0: false,
+            namespaces: HashMap::new(),
+            truncated: false,
+            warnings: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Creates a lenient reader.
+    pub(crate) fn lenient(reader: Reader<R>) -> Self {
+        Self {
+            lenient: true,
+            ..Self::new(reader)
+        }
+    }
+
+    pub(crate) fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Called when the 'gpx' element says the document is GPX 1.0, which has
+    /// a few elements that 1.1 does not.
+    pub(crate) fn set_gpx_1_0(&mut self, gpx_1_0: bool) {
+        self.gpx_1_0 = gpx_1_0;
+    }
+
+    pub(crate) fn is_gpx_1_0(&self) -> bool {
+        self.gpx_1_0
+    }
+
+    /// Called with the attributes of the 'gpx' element, to record the
+    /// namespace prefixes that they declare. The default namespace is not
+    /// recorded, because it is the GPX one.
+    pub(crate) fn set_namespaces(&mut self, attributes: &HashMap<String, String>) {
+        self.namespaces = attributes
+            .iter()
+            .filter_map(|(key, uri)| {
+                let prefix = key.strip_prefix("xmlns:")?;
+                Some((prefix.to_string(), uri.clone()))
+            })
+            .collect();
+    }
+
+    pub(crate) fn namespaces(&self) -> &HashMap<String, String> {
+        &self.namespaces
+    }
+
+    /// Returns true if a lenient parse has hit the end of the readable part of
+    /// the document. The parsers for the container elements check this each
+    /// time round their loop and return what they have so far.
+    pub(crate) fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub(crate) fn warn(&mut self, warning: ReadWarning) {
+        self.warnings.push(warning);
+    }
+
+    pub(crate) fn warnings(&self) -> &[ReadWarning] {
+        &self.warnings
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<ReadWarning> {
+        self.warnings
+    }
+
+    /// Called when the attributes of an element include some that are not
+    /// allowed. 'attributes' is a comma-separated list of their names.
+    pub(crate) fn unexpected_attributes(
+        &mut self,
+        element: String,
+        attributes: String,
+    ) -> Result<(), GpxError> {
+        if !self.lenient {
+            return Err(GpxError::UnexpectedAttributes {
+                element,
+                attributes,
+            });
+        }
+
+        self.warn(ReadWarning::SkippedAttributes {
+            element,
+            attributes,
+        });
+        Ok(())
+    }
+
+    /// Called when an empty element such as `<foo/>` is not allowed within
+    /// 'parent'. There is nothing to skip over.
+    pub(crate) fn unexpected_empty(
+        &mut self,
+        parent: &BytesStart<'_>,
+        element: &BytesStart<'_>,
+    ) -> Result<(), GpxError> {
+        if !self.lenient {
+            return Err(GpxError::bad_start(element.name().as_ref(), self));
+        }
+
+        let warning = ReadWarning::SkippedElement {
+            parent: self.bytes_to_string(parent.name().as_ref())?,
+            element: self.bytes_to_string(element.name().as_ref())?,
+        };
+        self.warn(warning);
+        Ok(())
+    }
+
+    /// Called when 'element' comes after 'after' within 'parent', but the XSD
+    /// requires it to come before. In lenient mode the element is read anyway.
+    pub(crate) fn out_of_order(
+        &mut self,
+        parent: &[u8],
+        element: &[u8],
+        after: &[u8],
+    ) -> Result<(), GpxError> {
+        let element = self.bytes_to_string(element)?;
+        let after = self.bytes_to_string(after)?;
+        if !self.lenient {
+            return Err(GpxError::ElementOutOfOrder { element, after });
+        }
+
+        let warning = ReadWarning::ElementOutOfOrder {
+            parent: self.bytes_to_string(parent)?,
+            element,
+            after,
+        };
+        self.warn(warning);
+        Ok(())
+    }
+
+    /// If the error means the rest of the document cannot be read, and this
+    /// is a lenient parse, records a warning, marks the reader as truncated
+    /// and returns Ok. Otherwise returns the error.
+    pub(crate) fn recover_err(&mut self, err: GpxError) -> Result<(), GpxError> {
+        if !self.lenient || !err.is_truncation() {
+            return Err(err);
+        }
+
+        if !self.truncated {
+            self.truncated = true;
+            let warning = ReadWarning::Truncated {
+                position: self.reader.buffer_position(),
+                error: err.to_string(),
+            };
+            self.warn(warning);
+        }
+        Ok(())
+    }
+
+    /// Adds the current position and element path to an error.
+    pub(crate) fn locate(&self, err: GpxError) -> GpxError {
+        self.locate_with(err, None)
+    }
+
+    /// Like [XmlReader::locate], but for an error found after the End of the
+    /// element called 'name' has been read, when it is no longer in the path.
+    /// It is put back on the end of the path, with its index.
+    pub(crate) fn locate_ended(&self, err: GpxError, name: &[u8]) -> GpxError {
+        self.locate_with(err, Some(name))
+    }
+
+    fn locate_with(&self, err: GpxError, ended: Option<&[u8]>) -> GpxError {
+        if err.location().is_some() {
+            return err;
+        }
+
+        let position = match err {
+            GpxError::XmlError(_) => self.reader.error_position(),
+            _ => self.reader.buffer_position(),
+        };
+
+        let mut path = String::new();
+        for element in &self.path {
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&String::from_utf8_lossy(&element.name));
+            if let Some(index) = element.index {
+                path.push_str(&format!("[{index}]"));
+            }
+        }
+
+        if let Some(name) = ended {
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&String::from_utf8_lossy(name));
+            let count = self
+                .path
+                .last()
+                .and_then(|parent| parent.child_counts.get(name));
+            if let Some(count) = count {
+                path.push_str(&format!("[{}]", count - 1));
+            }
+        }
+
+        GpxError::Located {
+            location: Box::new(ErrorLocation {
+                position,
+                line_column: None,
+                path,
+            }),
+            source: Box::new(err),
+        }
+    }
+
+    fn push_element(&mut self, name: &[u8]) {
+        let index = match self.path.last_mut() {
+            Some(parent) if REPEATING_ELEMENTS.contains(&name) => {
+                let count = parent.child_counts.entry(name.to_vec()).or_default();
+                *count += 1;
+                Some(*count - 1)
+            }
+            None if REPEATING_ELEMENTS.contains(&name) => Some(0),
+            _ => None,
+        };
+
+        self.path.push(PathElement {
+            name: name.to_vec(),
+            index,
+            child_counts: HashMap::new(),
+        });
+    }
+
+    /// Like [XmlReader::recover_err], but for the result of parsing a child
+    /// element. Returns None if the child could not be read because the
+    /// document is truncated.
+    pub(crate) fn recover<T>(
+        &mut self,
+        result: Result<T, GpxError>,
+    ) -> Result<Option<T>, GpxError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) => {
+                self.recover_err(err)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<R: BufRead> XmlReader<R> {
+    /// Reads the next event, keeping track of the path to the current element.
+    /// This hides [Reader::read_event_into], so the parsers use it without
+    /// knowing.
+    pub(crate) fn read_event_into<'b>(
+        &mut self,
+        buf: &'b mut Vec<u8>,
+    ) -> quick_xml::Result<Event<'b>> {
+        let event = self.reader.read_event_into(buf);
+        match &event {
+            Ok(Event::Start(start)) => self.push_element(start.name().as_ref()),
+            Ok(Event::End(_)) => {
+                self.path.pop();
+            }
+            _ => {}
+        }
+        event
+    }
+
+    /// Called when an element is not allowed within 'parent'. In lenient mode
+    /// the element and everything inside it is skipped.
+    pub(crate) fn unexpected_start(
+        &mut self,
+        parent: &BytesStart<'_>,
+        element: &BytesStart<'_>,
+    ) -> Result<(), GpxError> {
+        self.unexpected_empty(parent, element)?;
+        let mut buf = Vec::new();
+        self.reader.read_to_end_into(element.name(), &mut buf)?;
+        self.path.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<'a> XmlReader<&'a [u8]> {
+    pub(crate) fn from_str(s: &'a str) -> Self {
+        Self::new(Reader::from_str(s))
+    }
+
+    pub(crate) fn lenient_from_str(s: &'a str) -> Self {
+        Self::lenient(Reader::from_str(s))
+    }
+}
+
+impl<R> Deref for XmlReader<R> {
+    type Target = Reader<R>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.reader
+    }
+}
+
+impl<R> DerefMut for XmlReader<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.reader
+    }
+}
+
+impl<R> XmlReaderConversions for XmlReader<R> {
+    #[inline]
+    fn bytes_to_cow<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, GpxError> {
+        self.reader.bytes_to_cow(bytes)
+    }
+
+    #[inline]
+    fn bytes_to_string(&self, bytes: &[u8]) -> Result<String, GpxError> {
+        self.reader.bytes_to_string(bytes)
+    }
+
+    #[inline]
+    fn cow_to_string(&self, bytes: Cow<'_, [u8]>) -> Result<String, GpxError> {
+        self.reader.cow_to_string(bytes)
+    }
+}
@@ -0,0 +1,643 @@
+//! Validation of a [Gpx] against the rules of the GPX 1.1 XSD that the model
+//! does not enforce by itself, plus some checks for data that is valid XML but
+//! probably not what was intended (such as times that go backwards).
+//!
+//! The order of the elements is fixed by the model and the writer, so it is
+//! checked where the data can still get it wrong: the times of the
+//! trackpoints, segments and tracks, and the raw XML of extensions, which is
+//! written as it is.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use quick_xml::{escape::unescape, events::Event, Reader};
+
+use crate::{
+    model::{Bounds, Copyright, Extensions, Gpx, Link, Metadata, Person, Waypoint},
+    model_impls::{
+        validate_degrees, validate_dgps_station_id, validate_latitude, validate_longitude,
+    },
+};
+
+/// How serious a [ValidationIssue] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The GPX is valid, but the data is suspect.
+    Warning,
+    /// The GPX would not pass validation against the XSD.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found by [Gpx::validate].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// The path to the element with the problem, in the same form as
+    /// [ErrorLocation::path](crate::ErrorLocation::path), such as
+    /// `gpx/trk[0]/trkseg[2]/trkpt[10453]`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.path, self.message)
+    }
+}
+
+/// The result of validating a [Gpx]. The issues are in document order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Returns true if there are no errors. There may still be warnings.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Warning)
+    }
+
+    fn error<S: Into<String>>(&mut self, path: &str, message: S) {
+        self.push(Severity::Error, path, message);
+    }
+
+    fn warning<S: Into<String>>(&mut self, path: &str, message: S) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    fn push<S: Into<String>>(&mut self, severity: Severity, path: &str, message: S) {
+        self.issues.push(ValidationIssue {
+            severity,
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    /// Writes one line per issue followed by a line with the totals.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+
+        write!(
+            f,
+            "{} errors, {} warnings",
+            self.errors().count(),
+            self.warnings().count()
+        )
+    }
+}
+
+impl Gpx {
+    /// Checks the Gpx and returns a report of the errors, which would make the
+    /// written file fail validation against the GPX 1.1 XSD, and the warnings,
+    /// which are valid but suspect data.
+    ///
+    /// Errors:
+    ///
+    /// - the 'version' is not "1.1" or "1.0", or the 'creator' is empty
+    /// - the mandatory parts of links, emails and the copyright are empty
+    /// - latitudes, longitudes, degrees or DGPS station ids are out of range
+    /// - decimal values such as the elevation are NaN or infinite
+    /// - the minimums of the bounds are greater than the maximums
+    /// - the raw XML of extensions is not well-formed
+    /// - text, such as a name, contains an unescaped '&' or '<'. The model
+    ///   holds text escaped, see [crate::builders]
+    ///
+    /// Warnings:
+    ///
+    /// - points that lie outside the metadata bounds
+    /// - trackpoint times that go backwards, including between segments and
+    ///   tracks
+    /// - trackpoints that are duplicates of the previous point
+    ///
+    /// The order of the elements is not checked here because the model cannot
+    /// hold it, and the writer always writes them in the order the XSD
+    /// requires. Reading a document whose elements are out of order is an
+    /// error, or a [crate::ReadWarning] in lenient mode.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if self.version != "1.1" && self.version != "1.0" {
+            report.error(
+                "gpx",
+                format!(
+                    "The version is \"{}\", it must be \"1.1\" or \"1.0\"",
+                    self.version
+                ),
+            );
+        }
+        if self.creator.is_empty() {
+            report.error("gpx", "The mandatory 'creator' attribute is empty");
+        }
+        validate_text(&mut report, "gpx", "creator", Some(&self.creator));
+
+        validate_metadata(&mut report, &self.metadata);
+
+        for (idx, wp) in self.waypoints.iter().enumerate() {
+            validate_waypoint(&mut report, &format!("gpx/wpt[{idx}]"), wp);
+        }
+
+        for (idx, route) in self.routes.iter().enumerate() {
+            let path = format!("gpx/rte[{idx}]");
+            for (name, text) in [
+                ("name", &route.name),
+                ("cmt", &route.comment),
+                ("desc", &route.description),
+                ("src", &route.source),
+                ("type", &route.r#type),
+            ] {
+                validate_text(&mut report, &path, name, text.as_deref());
+            }
+            validate_links(&mut report, &path, &route.links);
+            validate_extensions(&mut report, &path, &route.extensions);
+            for (pt_idx, wp) in route.points.iter().enumerate() {
+                validate_waypoint(&mut report, &format!("{path}/rtept[{pt_idx}]"), wp);
+            }
+        }
+
+        // The time of the last trackpoint seen, so that times can be checked
+        // across segments and tracks.
+        let mut last_time = None;
+        for (idx, track) in self.tracks.iter().enumerate() {
+            let path = format!("gpx/trk[{idx}]");
+            for (name, text) in [
+                ("name", &track.name),
+                ("cmt", &track.comment),
+                ("desc", &track.description),
+                ("src", &track.source),
+                ("type", &track.r#type),
+            ] {
+                validate_text(&mut report, &path, name, text.as_deref());
+            }
+            validate_links(&mut report, &path, &track.links);
+            validate_extensions(&mut report, &path, &track.extensions);
+            for (seg_idx, segment) in track.segments.iter().enumerate() {
+                let seg_path = format!("{path}/trkseg[{seg_idx}]");
+                validate_extensions(&mut report, &seg_path, &segment.extensions);
+                let mut previous: Option<&Waypoint> = None;
+                for (pt_idx, wp) in segment.points.iter().enumerate() {
+                    let pt_path = format!("{seg_path}/trkpt[{pt_idx}]");
+                    validate_waypoint(&mut report, &pt_path, wp);
+                    if previous.is_some_and(|p| is_duplicate(p, wp)) {
+                        report.warning(&pt_path, "The point is a duplicate of the previous point");
+                    }
+                    validate_time(&mut report, &pt_path, wp.time, &mut last_time);
+                    previous = Some(wp);
+                }
+            }
+        }
+
+        if let Some(bounds) = &self.metadata.bounds {
+            validate_points_within_bounds(&mut report, self, bounds);
+        }
+
+        validate_extensions(&mut report, "gpx", &self.extensions);
+
+        report
+    }
+}
+
+fn validate_metadata(report: &mut ValidationReport, metadata: &Metadata) {
+    let path = "gpx/metadata";
+    for (name, text) in [
+        ("name", &metadata.name),
+        ("desc", &metadata.description),
+        ("keywords", &metadata.keywords),
+    ] {
+        validate_text(report, path, name, text.as_deref());
+    }
+    if let Some(author) = &metadata.author {
+        validate_person(report, &format!("{path}/author"), author);
+    }
+    if let Some(copyright) = &metadata.copyright {
+        validate_copyright(report, &format!("{path}/copyright"), copyright);
+    }
+    validate_links(report, path, &metadata.links);
+    if let Some(bounds) = &metadata.bounds {
+        validate_bounds(report, &format!("{path}/bounds"), bounds);
+    }
+    validate_extensions(report, path, &metadata.extensions);
+}
+
+fn validate_person(report: &mut ValidationReport, path: &str, person: &Person) {
+    validate_text(report, path, "name", person.name.as_deref());
+    if let Some(email) = &person.email {
+        if email.id.is_empty() || email.domain.is_empty() {
+            report.error(
+                &format!("{path}/email"),
+                "The mandatory 'id' and 'domain' attributes must not be empty",
+            );
+        }
+        validate_text(report, &format!("{path}/email"), "id", Some(&email.id));
+        validate_text(
+            report,
+            &format!("{path}/email"),
+            "domain",
+            Some(&email.domain),
+        );
+    }
+    if let Some(link) = &person.link {
+        validate_link(report, &format!("{path}/link"), link);
+    }
+}
+
+fn validate_copyright(report: &mut ValidationReport, path: &str, copyright: &Copyright) {
+    if copyright.author.is_empty() {
+        report.error(path, "The mandatory 'author' attribute is empty");
+    }
+    validate_text(report, path, "author", Some(&copyright.author));
+    validate_text(report, path, "license", copyright.license.as_deref());
+}
+
+fn validate_links(report: &mut ValidationReport, path: &str, links: &[Link]) {
+    for (idx, link) in links.iter().enumerate() {
+        validate_link(report, &format!("{path}/link[{idx}]"), link);
+    }
+}
+
+fn validate_link(report: &mut ValidationReport, path: &str, link: &Link) {
+    if link.href.is_empty() {
+        report.error(path, "The mandatory 'href' attribute is empty");
+    }
+    validate_text(report, path, "href", Some(&link.href));
+    validate_text(report, path, "text", link.text.as_deref());
+    validate_text(report, path, "type", link.r#type.as_deref());
+}
+
+fn validate_bounds(report: &mut ValidationReport, path: &str, bounds: &Bounds) {
+    for value in [bounds.min_lat.value(), bounds.max_lat.value()] {
+        if let Err(err) = validate_latitude(value) {
+            report.error(path, err.to_string());
+        }
+    }
+    for value in [bounds.min_lon.value(), bounds.max_lon.value()] {
+        if let Err(err) = validate_longitude(value) {
+            report.error(path, err.to_string());
+        }
+    }
+    if bounds.min_lat.value() > bounds.max_lat.value() {
+        report.error(path, "'minlat' is greater than 'maxlat'");
+    }
+    if bounds.min_lon.value() > bounds.max_lon.value() {
+        report.error(path, "'minlon' is greater than 'maxlon'");
+    }
+}
+
+/// Checks that every point lies within the bounds. Reports a single warning
+/// with the number of points outside them, rather than one per point.
+fn validate_points_within_bounds(report: &mut ValidationReport, gpx: &Gpx, bounds: &Bounds) {
+    let lat_range = bounds.min_lat.value()..=bounds.max_lat.value();
+    let lon_range = bounds.min_lon.value()..=bounds.max_lon.value();
+
+    let outside = gpx
+        .all_points()
+        .filter(|wp| !lat_range.contains(&wp.lat.value()) || !lon_range.contains(&wp.lon.value()))
+        .count();
+
+    if outside > 0 {
+        report.warning(
+            "gpx/metadata/bounds",
+            format!("{outside} points lie outside the bounds"),
+        );
+    }
+}
+
+fn validate_waypoint(report: &mut ValidationReport, path: &str, wp: &Waypoint) {
+    if let Err(err) = validate_latitude(wp.lat.value()) {
+        report.error(path, err.to_string());
+    }
+    if let Err(err) = validate_longitude(wp.lon.value()) {
+        report.error(path, err.to_string());
+    }
+
+    for (name, value) in [("magvar", wp.magvar), ("course", wp.course)] {
+        if let Some(Err(err)) = value.map(|v| validate_degrees(v.value())) {
+            report.error(&format!("{path}/{name}"), err.to_string());
+        }
+    }
+
+    if let Some(Err(err)) = wp
+        .dgps_id
+        .map(|id| validate_dgps_station_id(id.value().into()))
+    {
+        report.error(&format!("{path}/dgpsid"), err.to_string());
+    }
+
+    for (name, value) in [
+        ("ele", wp.ele),
+        ("speed", wp.speed),
+        ("geoidheight", wp.geoid_height),
+        ("hdop", wp.hdop),
+        ("vdop", wp.vdop),
+        ("pdop", wp.pdop),
+        ("ageofdgpsdata", wp.age_of_dgps_data),
+    ] {
+        if value.is_some_and(|v| !v.is_finite()) {
+            report.error(
+                &format!("{path}/{name}"),
+                "The value is not a finite decimal number",
+            );
+        }
+    }
+
+    for (name, text) in [
+        ("name", &wp.name),
+        ("cmt", &wp.comment),
+        ("desc", &wp.description),
+        ("src", &wp.source),
+        ("sym", &wp.symbol),
+        ("type", &wp.r#type),
+    ] {
+        validate_text(report, path, name, text.as_deref());
+    }
+
+    validate_links(report, path, &wp.links);
+    validate_extensions(report, path, &wp.extensions);
+}
+
+/// Checks that the text of the element or attribute 'name' is escaped, since
+/// it is written to the output as it is. A '<', or an '&' that does not start
+/// an entity or character reference, would make the output malformed.
+fn validate_text(report: &mut ValidationReport, path: &str, name: &str, text: Option<&str>) {
+    if text.is_some_and(|text| text.contains('<') || unescape(text).is_err()) {
+        report.error(
+            path,
+            format!("The '{name}' contains an unescaped '&' or '<'"),
+        );
+    }
+}
+
+/// Checks that 'time' is not before 'last_time', then makes it the new
+/// 'last_time'.
+fn validate_time(
+    report: &mut ValidationReport,
+    path: &str,
+    time: Option<DateTime<Utc>>,
+    last_time: &mut Option<DateTime<Utc>>,
+) {
+    let Some(time) = time else {
+        return;
+    };
+
+    if let Some(last) = *last_time {
+        if time < last {
+            report.warning(
+                &format!("{path}/time"),
+                format!(
+                    "The time {} is before the time of the previous point {}",
+                    time.to_rfc3339(),
+                    last.to_rfc3339()
+                ),
+            );
+        }
+    }
+
+    *last_time = Some(time);
+}
+
+/// Returns true if the points have the same position, elevation and time.
+fn is_duplicate(a: &Waypoint, b: &Waypoint) -> bool {
+    a.lat == b.lat && a.lon == b.lon && a.ele == b.ele && a.time == b.time
+}
+
+/// Checks that the raw XML of the extensions is well-formed, since it is
+/// written to the output as it is.
+fn validate_extensions(report: &mut ValidationReport, path: &str, extensions: &Option<Extensions>) {
+    let Some(extensions) = extensions else {
+        return;
+    };
+
+    let mut reader = Reader::from_str(extensions.as_str());
+    let mut depth = 0_usize;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::Eof) if depth == 0 => return,
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => {
+                report.error(
+                    &format!("{path}/extensions"),
+                    format!("The extensions are not well-formed XML: {err}"),
+                );
+                return;
+            }
+        }
+    }
+
+    report.error(
+        &format!("{path}/extensions"),
+        "The extensions are not well-formed XML: an element is not closed",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::model::{Track, TrackSegment};
+
+    use super::*;
+
+    fn point(lat: f64, lon: f64, seconds: i64) -> Waypoint {
+        let mut wp = Waypoint::with_lat_lon(lat, lon).unwrap();
+        wp.time = Some(Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap());
+        wp
+    }
+
+    fn gpx_with_segments(segments: Vec<Vec<Waypoint>>) -> Gpx {
+        let mut gpx = Gpx::default();
+        gpx.tracks.push(Track {
+            segments: segments
+                .into_iter()
+                .map(|points| TrackSegment {
+                    points,
+                    extensions: None,
+                })
+                .collect(),
+            ..Default::default()
+        });
+        gpx
+    }
+
+    #[test]
+    fn valid_gpx_has_no_issues() {
+        let gpx = gpx_with_segments(vec![vec![point(53.0, -2.0, 0), point(53.1, -2.1, 1)]]);
+        let report = gpx.validate();
+        assert!(report.issues.is_empty(), "{report}");
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn mandatory_values_are_errors() {
+        let mut gpx = Gpx::default();
+        gpx.creator.clear();
+        gpx.version = "2.0".to_string();
+        gpx.metadata.links.push(Link::new(""));
+        gpx.metadata.copyright = Some(Copyright::default());
+
+        let report = gpx.validate();
+        assert!(!report.is_valid());
+        let paths: Vec<_> = report.errors().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "gpx",
+                "gpx",
+                "gpx/metadata/copyright",
+                "gpx/metadata/link[0]"
+            ]
+        );
+    }
+
+    #[test]
+    fn out_of_range_and_non_finite_values_are_errors() {
+        let mut wp = point(53.0, -2.0, 0);
+        wp.ele = Some(f64::NAN);
+        // Only possible within the crate, the constructors and serde both
+        // reject it.
+        wp.lat.0 = 91.0;
+        let mut gpx = Gpx::default();
+        gpx.waypoints.push(wp);
+
+        let report = gpx.validate();
+        let issues: Vec<_> = report.errors().map(|e| e.path.as_str()).collect();
+        assert_eq!(issues, ["gpx/wpt[0]", "gpx/wpt[0]/ele"]);
+    }
+
+    #[test]
+    fn bounds_are_checked() {
+        let mut gpx = gpx_with_segments(vec![vec![point(53.0, -2.0, 0), point(54.0, -2.0, 1)]]);
+        gpx.metadata.bounds = Some(Bounds::new(52.0, -3.0, 53.5, -1.0).unwrap());
+        let report = gpx.validate();
+        assert!(report.is_valid());
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].message, "1 points lie outside the bounds");
+
+        gpx.metadata.bounds = Some(Bounds::new(55.0, -3.0, 53.5, -1.0).unwrap());
+        let report = gpx.validate();
+        assert_eq!(report.errors().count(), 1);
+    }
+
+    #[test]
+    fn times_going_backwards_are_warnings() {
+        let gpx = gpx_with_segments(vec![
+            vec![
+                point(53.0, -2.0, 0),
+                point(53.1, -2.0, 10),
+                point(53.2, -2.0, 5),
+            ],
+            vec![point(53.3, -2.0, 3)],
+        ]);
+
+        let report = gpx.validate();
+        assert!(report.is_valid());
+        let paths: Vec<_> = report.warnings().map(|w| w.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "gpx/trk[0]/trkseg[0]/trkpt[2]/time",
+                "gpx/trk[0]/trkseg[1]/trkpt[0]/time"
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_points_are_warnings() {
+        let gpx = gpx_with_segments(vec![vec![point(53.0, -2.0, 0), point(53.0, -2.0, 0)]]);
+        let report = gpx.validate();
+        assert_eq!(report.warnings().count(), 1);
+        assert_eq!(report.issues[0].path, "gpx/trk[0]/trkseg[0]/trkpt[1]");
+    }
+
+    #[test]
+    fn malformed_extensions_are_errors() {
+        let mut gpx = Gpx {
+            extensions: Some(Extensions::new("<a><b></a>")),
+            ..Default::default()
+        };
+        gpx.metadata.extensions = Some(Extensions::new("<a>"));
+        let mut wp = point(53.0, -2.0, 0);
+        wp.extensions = Some(Extensions::new("<a/><b>1</b>"));
+        gpx.waypoints.push(wp);
+
+        let report = gpx.validate();
+        let paths: Vec<_> = report.errors().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["gpx/metadata/extensions", "gpx/extensions"]);
+    }
+
+    #[test]
+    fn unescaped_text_is_an_error() {
+        let mut gpx = Gpx::builder()
+            .name("Fish & Chips <ride>")
+            .track(Track::builder().name("Out &amp; back &#38; &#x26;").build())
+            .build();
+        assert!(gpx.validate().is_valid());
+
+        gpx.creator = "Fish & Chips".to_string();
+        gpx.metadata.description = Some("a < b".to_string());
+        gpx.tracks[0].name = Some("Out &nbsp; back".to_string());
+        let mut wp = point(53.0, -2.0, 0);
+        wp.links.push(Link {
+            href: "http://example.com/?a=1&b=2".to_string(),
+            ..Default::default()
+        });
+        gpx.waypoints.push(wp);
+
+        let report = gpx.validate();
+        let issues: Vec<_> = report
+            .errors()
+            .map(|e| (e.path.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                ("gpx", "The 'creator' contains an unescaped '&' or '<'"),
+                (
+                    "gpx/metadata",
+                    "The 'desc' contains an unescaped '&' or '<'"
+                ),
+                (
+                    "gpx/wpt[0]/link[0]",
+                    "The 'href' contains an unescaped '&' or '<'"
+                ),
+                ("gpx/trk[0]", "The 'name' contains an unescaped '&' or '<'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn report_display() {
+        let mut gpx = Gpx::default();
+        gpx.creator.clear();
+        assert_eq!(
+            gpx.validate().to_string(),
+            "error: gpx: The mandatory 'creator' attribute is empty\n1 errors, 0 warnings"
+        );
+    }
+}